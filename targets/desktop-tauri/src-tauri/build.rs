@@ -1,3 +1,64 @@
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::path::Path;
+
+// Keep in step with `bundle.resources` in `tauri.conf.json`.
+const BUNDLED_RESOURCE_DIRS: &[&str] = &["resources/locales"];
+
+// Checksums of the bundled resources, for `resources::verify`.
+fn write_resource_checksums() {
+    let mut source = String::from("pub const RESOURCE_CHECKSUMS: &[(&str, &str)] = &[\n");
+    for dir in BUNDLED_RESOURCE_DIRS {
+        println!("cargo:rerun-if-changed={}", dir);
+        let mut files: Vec<_> = std::fs::read_dir(dir)
+            .unwrap_or_else(|e| panic!("Failed to read {}: {}", dir, e))
+            .map(|entry| entry.expect("Failed to list resources").path())
+            .filter(|path| path.is_file())
+            .collect();
+        files.sort();
+        for path in files {
+            let bytes = std::fs::read(&path)
+                .unwrap_or_else(|e| panic!("Failed to read {}: {}", path.display(), e));
+            let name = format!("{}/{}", dir, path.file_name().unwrap().to_string_lossy());
+            let sha256 = hex::encode(Sha256::digest(&bytes));
+            writeln!(source, "    ({:?}, {:?}),", name, sha256).unwrap();
+        }
+    }
+    source.push_str("];\n");
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR is not set");
+    std::fs::write(Path::new(&out_dir).join("resource_checksums.rs"), source)
+        .expect("Failed to write resource checksums");
+}
+
+// The updater and `check_for_updates` both verify against this key, so a
+// release without it could never be updated. `TAURI_CONFIG` overrides the
+// file, as it does for `tauri_build`.
+fn require_update_key() {
+    println!("cargo:rerun-if-changed=tauri.conf.json");
+    println!("cargo:rerun-if-env-changed=TAURI_CONFIG");
+    if std::env::var("PROFILE").as_deref() != Ok("release") {
+        return;
+    }
+    let pubkey = |json: &str| -> Option<String> {
+        let config: serde_json::Value = serde_json::from_str(json).ok()?;
+        let key = config["tauri"]["updater"]["pubkey"].as_str()?.trim().to_string();
+        (!key.is_empty()).then_some(key)
+    };
+    let file = std::fs::read_to_string("tauri.conf.json").expect("Failed to read tauri.conf.json");
+    let key = std::env::var("TAURI_CONFIG")
+        .ok()
+        .and_then(|json| pubkey(&json))
+        .or_else(|| pubkey(&file));
+    if key.is_none() {
+        panic!(
+            "Release builds need the update signing key: set tauri.updater.pubkey in \
+             tauri.conf.json (or through TAURI_CONFIG) to the minisign public key"
+        );
+    }
+}
+
 fn main() {
-  tauri_build::build()
-}
\ No newline at end of file
+    write_resource_checksums();
+    require_update_key();
+    tauri_build::build()
+}
@@ -0,0 +1,138 @@
+// Accessibility preferences applied by the frontend. The shell persists them
+// and re-announces them on every page load so they survive restarts. Reduced
+// motion follows the system setting unless the user has overridden it.
+
+use crate::config::{self, ConfigState};
+use crate::error::AppError;
+use crate::validate;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Runtime, Window};
+
+pub const UI_SCALE_CHANGED_EVENT: &str = "ui-scale-changed";
+pub const REDUCED_MOTION_CHANGED_EVENT: &str = "reduced-motion-changed";
+
+pub const MIN_UI_SCALE: f64 = 0.8;
+pub const MAX_UI_SCALE: f64 = 2.0;
+
+fn normalize_scale(scale: f64) -> f64 {
+    if !scale.is_finite() {
+        return 1.0;
+    }
+    (scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE) * 100.0).round() / 100.0
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ReducedMotion {
+    // Whether the frontend should turn animations off.
+    pub enabled: bool,
+    // `None` where the platform doesn't report a setting.
+    pub system: Option<bool>,
+    // Whether `enabled` comes from the config rather than the system.
+    pub overridden: bool,
+}
+
+impl ReducedMotion {
+    fn resolve(stored: Option<bool>) -> Self {
+        let system = system_reduced_motion();
+        ReducedMotion {
+            enabled: stored.or(system).unwrap_or(false),
+            system,
+            overridden: stored.is_some(),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn system_reduced_motion() -> Option<bool> {
+    use cocoa::base::{id, BOOL, YES};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let reduce: BOOL = msg_send![workspace, accessibilityDisplayShouldReduceMotion];
+        Some(reduce == YES)
+    }
+}
+
+// "Show animations in Windows" in the accessibility settings.
+#[cfg(target_os = "windows")]
+fn system_reduced_motion() -> Option<bool> {
+    use windows::Win32::Foundation::BOOL;
+    use windows::Win32::UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+    };
+
+    let mut animate = BOOL::default();
+    let read = unsafe {
+        SystemParametersInfoW(
+            SPI_GETCLIENTAREAANIMATION,
+            0,
+            &mut animate as *mut BOOL as *mut _,
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    };
+    read.as_bool().then(|| !animate.as_bool())
+}
+
+// GNOME's setting, which other GTK desktops also honor; elsewhere there's
+// nothing to read.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn system_reduced_motion() -> Option<bool> {
+    let output = std::process::Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "enable-animations"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "true" => Some(false),
+        "false" => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+fn system_reduced_motion() -> Option<bool> {
+    None
+}
+
+pub fn on_page_load<R: Runtime>(window: &Window<R>) {
+    if let Some(config) = window.try_state::<ConfigState>() {
+        let config = config.get();
+        let _ = window.emit(UI_SCALE_CHANGED_EVENT, normalize_scale(config.ui_scale));
+        let _ = window.emit(
+            REDUCED_MOTION_CHANGED_EVENT,
+            ReducedMotion::resolve(config.reduced_motion),
+        );
+    }
+}
+
+#[tauri::command]
+pub fn get_ui_scale(config: tauri::State<ConfigState>) -> f64 {
+    normalize_scale(config.get().ui_scale)
+}
+
+// Returns the scale actually applied after clamping.
+#[tauri::command]
+pub fn set_ui_scale(app: AppHandle, scale: f64) -> Result<f64, AppError> {
+    let scale = normalize_scale(validate::finite("UI scale", scale)?);
+    config::update(&app, |config| config.ui_scale = scale)?;
+    let _ = app.emit_all(UI_SCALE_CHANGED_EVENT, scale);
+    Ok(scale)
+}
+
+#[tauri::command]
+pub fn get_reduced_motion(config: tauri::State<ConfigState>) -> ReducedMotion {
+    ReducedMotion::resolve(config.get().reduced_motion)
+}
+
+// `None` drops the override and follows the system setting again.
+#[tauri::command]
+pub fn set_reduced_motion(
+    app: AppHandle,
+    enabled: Option<bool>,
+) -> Result<ReducedMotion, AppError> {
+    config::update(&app, |config| config.reduced_motion = enabled)?;
+    let reduced_motion = ReducedMotion::resolve(enabled);
+    let _ = app.emit_all(REDUCED_MOTION_CHANGED_EVENT, reduced_motion);
+    Ok(reduced_motion)
+}
@@ -0,0 +1,156 @@
+// Quick progress stats computed from the local store, so the dashboard's
+// mood chart doesn't need the core.
+
+use crate::error::AppError;
+use crate::storage::{MoodStats, StorageState};
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrendRange {
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl TrendRange {
+    fn days(self) -> i64 {
+        match self {
+            TrendRange::Week => 7,
+            TrendRange::Month => 30,
+            TrendRange::Quarter => 90,
+            TrendRange::Year => 365,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrendBucket {
+    Daily,
+    Weekly,
+}
+
+// Every bucket in the range is present; one without scores has
+// `no_data: true` and no statistics, so a chart shows a gap rather than
+// joining its neighbours.
+#[derive(Debug, Serialize)]
+pub struct TrendPoint {
+    pub start: String,
+    pub no_data: bool,
+    pub average: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub count: u32,
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+// Buckets from the one containing `first` up to the one containing `today`.
+fn fill_gaps(
+    stats: Vec<MoodStats>,
+    first: NaiveDate,
+    today: NaiveDate,
+    bucket: TrendBucket,
+) -> Vec<TrendPoint> {
+    let (mut start, step) = match bucket {
+        TrendBucket::Daily => (first, Duration::days(1)),
+        TrendBucket::Weekly => (week_start(first), Duration::days(7)),
+    };
+    let mut by_bucket: HashMap<String, MoodStats> =
+        stats.into_iter().map(|s| (s.bucket.clone(), s)).collect();
+
+    let mut points = Vec::new();
+    while start <= today {
+        let key = start.format("%Y-%m-%d").to_string();
+        points.push(match by_bucket.remove(&key) {
+            Some(s) => TrendPoint {
+                start: key,
+                no_data: false,
+                average: Some(s.average),
+                min: Some(s.min),
+                max: Some(s.max),
+                count: s.count,
+            },
+            None => TrendPoint {
+                start: key,
+                no_data: true,
+                average: None,
+                min: None,
+                max: None,
+                count: 0,
+            },
+        });
+        start += step;
+    }
+    points
+}
+
+// Oldest bucket first, ending with the current day or week.
+#[tauri::command]
+pub fn mood_trend(
+    state: tauri::State<StorageState>,
+    range: TrendRange,
+    bucket: TrendBucket,
+) -> Result<Vec<TrendPoint>, AppError> {
+    let today = Local::now().date_naive();
+    let first = today - Duration::days(range.days() - 1);
+    let since = match bucket {
+        TrendBucket::Daily => first,
+        TrendBucket::Weekly => week_start(first),
+    };
+    let stats = state.with(|storage| {
+        storage.mood_stats(bucket == TrendBucket::Weekly, &since.format("%Y-%m-%d").to_string())
+    })?;
+    Ok(fill_gaps(stats, first, today, bucket))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 5, day).unwrap()
+    }
+
+    fn stats(bucket: &str, average: f64, count: u32) -> MoodStats {
+        MoodStats {
+            bucket: bucket.to_string(),
+            average,
+            min: average - 1.0,
+            max: average + 1.0,
+            count,
+        }
+    }
+
+    #[test]
+    fn missing_days_are_filled_with_empty_points() {
+        let found = vec![stats("2026-05-09", 6.0, 2), stats("2026-05-11", 4.0, 1)];
+        let points = fill_gaps(found, date(8), date(12), TrendBucket::Daily);
+
+        let starts: Vec<_> = points.iter().map(|p| p.start.as_str()).collect();
+        assert_eq!(starts, ["2026-05-08", "2026-05-09", "2026-05-10", "2026-05-11", "2026-05-12"]);
+        let empty: Vec<_> = points.iter().map(|p| p.no_data).collect();
+        assert_eq!(empty, [true, false, true, false, true]);
+        let day = &points[1];
+        assert_eq!((day.average, day.min, day.max, day.count), (Some(6.0), Some(5.0), Some(7.0), 2));
+        assert_eq!((points[2].average, points[2].count), (None, 0));
+    }
+
+    #[test]
+    fn weeks_start_on_the_monday_before_the_first_day() {
+        // Wednesday 6 May to Wednesday 20 May covers three Monday-based weeks;
+        // a bucket from before the range is dropped.
+        let found = vec![stats("2026-04-27", 9.0, 1), stats("2026-05-11", 5.0, 3)];
+        let points = fill_gaps(found, date(6), date(20), TrendBucket::Weekly);
+
+        let starts: Vec<_> = points.iter().map(|p| (p.start.as_str(), p.no_data)).collect();
+        assert_eq!(starts, [("2026-05-04", true), ("2026-05-11", false), ("2026-05-18", true)]);
+        assert_eq!((points[1].average, points[1].count), (Some(5.0), 3));
+    }
+}
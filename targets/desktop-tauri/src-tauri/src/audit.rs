@@ -0,0 +1,391 @@
+// Append-only log of when local data was unlocked, re-keyed, exported,
+// imported, backed up or wiped, of core processes killed by hand, and of guest
+// sessions. Each record carries the hash of the one before it, so editing or
+// deleting a line breaks the chain. Only the action and metadata such as
+// counts and paths are recorded, never entry contents.
+//
+// Once `audit.log` reaches `MAX_SEGMENT_BYTES` it is renamed to a segment
+// named after its last record and a new file is started, opening with a
+// `LogRotated` record that carries the chain on. Only the newest
+// `KEEP_SEGMENTS` segments are kept, so the oldest one left starts partway
+// through the chain; a `LogRotated` record is the only one allowed to.
+
+use crate::error::AppError;
+use crate::guest::GuestState;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+pub const AUDIT_FILE_NAME: &str = "audit.log";
+const SEGMENT_PREFIX: &str = "audit-";
+const MAX_SEGMENT_BYTES: u64 = 1024 * 1024;
+const KEEP_SEGMENTS: usize = 4;
+
+// `prev_hash` of the first record.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Unlock,
+    RotateKey,
+    Export,
+    Import,
+    Wipe,
+    Backup,
+    KillProcess,
+    GuestSessionStarted,
+    GuestSessionEnded,
+    LogRotated,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub seq: u64,
+    pub at: String,
+    pub action: AuditAction,
+    pub details: serde_json::Value,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditRecord {
+    fn compute_hash(&self) -> String {
+        let body = serde_json::json!({
+            "seq": self.seq,
+            "at": self.at,
+            "action": self.action,
+            "details": self.details,
+            "prev_hash": self.prev_hash,
+        });
+        hex::encode(Sha256::digest(body.to_string().as_bytes()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditLog {
+    pub records: Vec<AuditRecord>,
+    pub chain_ok: bool,
+    // Human-readable descriptions of where the chain breaks.
+    pub problems: Vec<String>,
+}
+
+struct Chain {
+    path: PathBuf,
+    last_hash: String,
+    next_seq: u64,
+}
+
+#[derive(Default)]
+pub struct AuditState {
+    chain: Mutex<Option<Chain>>,
+    // Set for the length of a guest session, which leaves no record beyond
+    // its start and end.
+    suspended: AtomicBool,
+}
+
+// The rotated segments of the log at `path`, oldest first, then `path`
+// itself if it exists.
+pub fn log_files(path: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = path
+        .parent()
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|file| {
+            file.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(SEGMENT_PREFIX) && name.ends_with(".log"))
+        })
+        .collect();
+    files.sort();
+    files.extend(path.exists().then(|| path.to_path_buf()));
+    files
+}
+
+// Reads every segment in order and checks the chain across them.
+fn read(path: &Path) -> Result<AuditLog, String> {
+    let mut records = Vec::new();
+    let mut problems = Vec::new();
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for file in log_files(path) {
+        let name = file.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let contents = match std::fs::read_to_string(&file) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("Failed to read audit log {}: {}", name, e)),
+        };
+        check_segment(&contents, &name, &mut prev_hash, &mut records, &mut problems);
+    }
+
+    Ok(AuditLog {
+        chain_ok: problems.is_empty(),
+        records,
+        problems,
+    })
+}
+
+fn check_segment(
+    contents: &str,
+    name: &str,
+    prev_hash: &mut String,
+    records: &mut Vec<AuditRecord>,
+    problems: &mut Vec<String>,
+) {
+    for (i, line) in contents.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let record: AuditRecord = match serde_json::from_str(line) {
+            Ok(record) => record,
+            Err(e) => {
+                problems.push(format!(
+                    "Line {} of {} is not a valid audit record: {}",
+                    i + 1,
+                    name,
+                    e
+                ));
+                continue;
+            }
+        };
+        let continues_pruned =
+            records.is_empty() && matches!(record.action, AuditAction::LogRotated);
+        if record.prev_hash != *prev_hash && !continues_pruned {
+            problems.push(format!("Record {} does not follow the previous record", record.seq));
+        }
+        if record.compute_hash() != record.hash {
+            problems.push(format!("Record {} has been modified", record.seq));
+        }
+        *prev_hash = record.hash.clone();
+        records.push(record);
+    }
+}
+
+// Moves a full log aside as a segment and drops the oldest beyond
+// `KEEP_SEGMENTS`. The next record written starts the new file.
+fn rotate(chain: &Chain) -> Result<String, String> {
+    let name = format!("{}{:012}.log", SEGMENT_PREFIX, chain.next_seq - 1);
+    std::fs::rename(&chain.path, chain.path.with_file_name(&name))
+        .map_err(|e| format!("Failed to rotate audit log: {}", e))?;
+    let segments = log_files(&chain.path);
+    let excess = segments.len().saturating_sub(KEEP_SEGMENTS);
+    for old in &segments[..excess] {
+        if let Err(e) = std::fs::remove_file(old) {
+            eprintln!("Failed to remove old audit log {}: {}", old.display(), e);
+        }
+    }
+    Ok(name)
+}
+
+fn write_record(
+    chain: &mut Chain,
+    action: AuditAction,
+    details: serde_json::Value,
+) -> Result<(), String> {
+    let mut record = AuditRecord {
+        seq: chain.next_seq,
+        at: chrono::Utc::now().to_rfc3339(),
+        action,
+        details,
+        prev_hash: chain.last_hash.clone(),
+        hash: String::new(),
+    };
+    record.hash = record.compute_hash();
+
+    let line = serde_json::to_string(&record)
+        .map_err(|e| format!("Failed to serialize audit record: {}", e))?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&chain.path)
+        .and_then(|mut file| writeln!(file, "{}", line))
+        .map_err(|e| format!("Failed to write audit log: {}", e))?;
+
+    chain.last_hash = record.hash;
+    chain.next_seq += 1;
+    Ok(())
+}
+
+impl AuditState {
+    pub fn open(path: PathBuf) -> Result<Self, String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let log = read(&path)?;
+        let (last_hash, next_seq) = log
+            .records
+            .last()
+            .map(|r| (r.hash.clone(), r.seq + 1))
+            .unwrap_or_else(|| (GENESIS_HASH.to_string(), 1));
+        Ok(Self {
+            chain: Mutex::new(Some(Chain {
+                path,
+                last_hash,
+                next_seq,
+            })),
+            suspended: AtomicBool::new(false),
+        })
+    }
+
+    // Point the log at another file, e.g. after a profile switch.
+    pub fn reopen(&self, path: PathBuf) -> Result<(), String> {
+        let opened = Self::open(path)?;
+        let chain = opened.chain.into_inner().unwrap_or_else(|p| p.into_inner());
+        *self.chain.lock().unwrap_or_else(|p| p.into_inner()) = chain;
+        Ok(())
+    }
+
+    fn append(&self, action: AuditAction, details: serde_json::Value) -> Result<(), String> {
+        let mut guard = self.chain.lock().unwrap_or_else(|p| p.into_inner());
+        let chain = guard.as_mut().ok_or("Audit log is not open")?;
+        let size = std::fs::metadata(&chain.path).map_or(0, |metadata| metadata.len());
+        if size >= MAX_SEGMENT_BYTES {
+            let previous = rotate(chain)?;
+            write_record(
+                chain,
+                AuditAction::LogRotated,
+                serde_json::json!({ "previous_segment": previous }),
+            )?;
+        }
+        write_record(chain, action, details)
+    }
+
+    pub fn set_suspended(&self, suspended: bool) {
+        self.suspended.store(suspended, Ordering::SeqCst);
+    }
+
+    // Auditing must never block the action it describes, so failures are
+    // only logged.
+    pub fn record(&self, action: AuditAction, details: serde_json::Value) {
+        if self.suspended.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Err(e) = self.append(action, details) {
+            eprintln!("Failed to record {:?} in audit log: {}", action, e);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_audit_log(
+    state: tauri::State<AuditState>,
+    guest: tauri::State<GuestState>,
+) -> Result<AuditLog, AppError> {
+    guest.refuse("The audit log")?;
+    let guard = state.chain.lock().unwrap_or_else(|p| p.into_inner());
+    let chain = guard.as_ref().ok_or("Audit log is not open")?;
+    Ok(read(&chain.path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A log with three records, as its lines.
+    fn log_with_three_records() -> (PathBuf, Vec<String>) {
+        let dir = std::env::temp_dir().join(format!("smartbot-audit-{}", uuid::Uuid::new_v4()));
+        let path = dir.join(AUDIT_FILE_NAME);
+        let state = AuditState::open(path.clone()).unwrap();
+        for n in 1..=3 {
+            state.record(AuditAction::Backup, serde_json::json!({ "entries": n }));
+        }
+        let lines = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        (path, lines)
+    }
+
+    fn read_lines(path: &Path, lines: &[String]) -> AuditLog {
+        std::fs::write(path, lines.join("\n") + "\n").unwrap();
+        let log = read(path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        log
+    }
+
+    #[test]
+    fn untouched_log_verifies() {
+        let (path, lines) = log_with_three_records();
+        let log = read_lines(&path, &lines);
+        assert!(log.chain_ok);
+        assert_eq!(log.records.iter().map(|r| r.seq).collect::<Vec<_>>(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn edited_details_are_caught() {
+        let (path, mut lines) = log_with_three_records();
+        let mut record: serde_json::Value = serde_json::from_str(&lines[1]).unwrap();
+        record["details"]["entries"] = 200.into();
+        lines[1] = record.to_string();
+        let log = read_lines(&path, &lines);
+        assert!(!log.chain_ok);
+        assert_eq!(log.problems, ["Record 2 has been modified"]);
+    }
+
+    #[test]
+    fn deleted_line_is_caught() {
+        let (path, mut lines) = log_with_three_records();
+        lines.remove(1);
+        let log = read_lines(&path, &lines);
+        assert!(!log.chain_ok);
+        assert_eq!(log.problems, ["Record 3 does not follow the previous record"]);
+    }
+
+    #[test]
+    fn reordered_lines_are_caught() {
+        let (path, mut lines) = log_with_three_records();
+        lines.swap(1, 2);
+        let log = read_lines(&path, &lines);
+        assert!(!log.chain_ok);
+        assert_eq!(
+            log.problems,
+            [
+                "Record 3 does not follow the previous record",
+                "Record 2 does not follow the previous record",
+            ]
+        );
+    }
+
+    #[test]
+    fn reopening_continues_the_chain() {
+        let (path, _) = log_with_three_records();
+        let state = AuditState::default();
+        state.reopen(path.clone()).unwrap();
+        state.record(AuditAction::Wipe, serde_json::json!({}));
+
+        let log = read(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(log.chain_ok);
+        assert_eq!(log.records.len(), 4);
+        assert_eq!(log.records[3].seq, 4);
+        assert_eq!(log.records[3].prev_hash, log.records[2].hash);
+    }
+
+    #[test]
+    fn full_log_rotates_and_the_chain_carries_across_segments() {
+        let dir = std::env::temp_dir().join(format!("smartbot-audit-{}", uuid::Uuid::new_v4()));
+        let path = dir.join(AUDIT_FILE_NAME);
+        let state = AuditState::open(path.clone()).unwrap();
+        let padding = "x".repeat(MAX_SEGMENT_BYTES as usize);
+        for _ in 0..KEEP_SEGMENTS + 2 {
+            state.record(AuditAction::Backup, serde_json::json!({ "padding": padding }));
+        }
+
+        // Each record after the first filled the log and started a new one.
+        assert_eq!(log_files(&path).len(), KEEP_SEGMENTS + 1);
+        let log = read(&path).unwrap();
+        assert!(log.chain_ok, "{:?}", log.problems);
+        assert!(matches!(log.records[0].action, AuditAction::LogRotated));
+        assert_eq!(log.records.last().unwrap().seq, 2 * (KEEP_SEGMENTS as u64 + 2) - 1);
+
+        state.reopen(path.clone()).unwrap();
+        state.record(AuditAction::Wipe, serde_json::json!({}));
+        assert!(read(&path).unwrap().chain_ok);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
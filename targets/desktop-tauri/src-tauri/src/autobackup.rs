@@ -0,0 +1,273 @@
+// Scheduled backups to a folder the user picks, so there is a recent copy
+// even for people who never think to make one. Backups use the regular
+// `.smartbak` format; the password is held in memory only, so after a restart
+// the scheduler waits until it is entered again rather than keeping it on
+// disk next to the data it protects.
+
+use crate::audit::{AuditAction, AuditState};
+use crate::background::Workers;
+use crate::backup::{self, BackupError, BackupInfo, BackupPayload, BACKUP_EXTENSION};
+use crate::config::{self, AppConfig, ConfigState};
+use crate::disk;
+use crate::error::AppError;
+use crate::guest::GuestState;
+use crate::storage::StorageState;
+use crate::validate;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const TICK: Duration = Duration::from_secs(5 * 60);
+// A failed backup (full drive, unplugged USB stick) is retried this often
+// rather than on every tick or only at the next interval.
+const RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+pub const INTERVAL_HOURS_RANGE: std::ops::RangeInclusive<u64> = 1..=30 * 24;
+pub const KEEP_RANGE: std::ops::RangeInclusive<usize> = 1..=100;
+
+// Only files with this prefix are ever pruned, so manual backups saved to the
+// same folder are left alone.
+const FILE_PREFIX: &str = "smartbot-auto-";
+
+pub const AUTO_BACKUP_COMPLETED_EVENT: &str = "auto-backup-completed";
+pub const AUTO_BACKUP_FAILED_EVENT: &str = "auto-backup-failed";
+
+#[derive(Default)]
+pub struct AutoBackupState {
+    password: Mutex<Option<String>>,
+    retry_after: Mutex<Option<Instant>>,
+}
+
+impl AutoBackupState {
+    fn password(&self) -> Option<String> {
+        self.password.lock().unwrap_or_else(|p| p.into_inner()).clone()
+    }
+
+    fn set_password(&self, password: Option<String>) {
+        *self.password.lock().unwrap_or_else(|p| p.into_inner()) = password;
+    }
+
+    fn retry_after(&self) -> Option<Instant> {
+        *self.retry_after.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    fn set_retry_after(&self, at: Option<Instant>) {
+        *self.retry_after.lock().unwrap_or_else(|p| p.into_inner()) = at;
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AutoBackupStatus {
+    pub destination: Option<String>,
+    pub interval_hours: u64,
+    pub keep: usize,
+    pub last_backup_at: Option<String>,
+    // Set after a restart until `set_auto_backup_destination` is called again.
+    pub needs_password: bool,
+}
+
+// Hashes the data rather than the file: every backup gets a fresh salt and
+// nonce, so identical data never encrypts to identical bytes.
+pub fn content_hash(payload: &BackupPayload) -> Result<String, String> {
+    let entries = serde_json::to_vec(&payload.entries)
+        .map_err(|e| format!("Failed to serialize entries: {}", e))?;
+    Ok(hex::encode(Sha256::digest(&entries)))
+}
+
+// Oldest first; the timestamp in the name sorts chronologically.
+pub fn auto_backups(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let suffix = format!(".{}", BACKUP_EXTENSION);
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read backup folder {}: {}", dir.display(), e))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(FILE_PREFIX) && name.ends_with(&suffix))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn prune(dir: &Path, keep: usize) -> Result<(), String> {
+    let files = auto_backups(dir)?;
+    let excess = files.len().saturating_sub(keep);
+    for old in &files[..excess] {
+        std::fs::remove_file(old)
+            .map_err(|e| format!("Failed to remove old backup {}: {}", old.display(), e))?;
+    }
+    Ok(())
+}
+
+fn is_due(config: &AppConfig) -> bool {
+    let last = config.auto_backup_last_at.as_deref();
+    let last = last.and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok());
+    let hours = config
+        .auto_backup_interval_hours
+        .clamp(*INTERVAL_HOURS_RANGE.start(), *INTERVAL_HOURS_RANGE.end());
+    let interval = chrono::Duration::hours(hours as i64);
+    last.is_none_or(|at| chrono::Utc::now().signed_duration_since(at) >= interval)
+}
+
+// Returns `None` when nothing changed since the last backup, which still
+// counts as this interval's backup.
+fn run(app: &AppHandle, dir: &str, password: &str) -> Result<Option<BackupInfo>, BackupError> {
+    let dir = validate::user_path(app, "Backup folder", dir)?;
+    let config = app.state::<ConfigState>().get();
+    let payload = backup::snapshot(&app.state::<StorageState>())?;
+    let hash = content_hash(&payload)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create backup folder {}: {}", dir.display(), e))?;
+
+    // Only skip while the previous backup is still there to fall back on.
+    let unchanged = config.auto_backup_last_sha256.as_deref() == Some(hash.as_str());
+    if unchanged && !auto_backups(&dir)?.is_empty() {
+        config::update(app, |config| config.auto_backup_last_at = Some(now))?;
+        return Ok(None);
+    }
+
+    let name = format!(
+        "{}{}.{}",
+        FILE_PREFIX,
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        BACKUP_EXTENSION
+    );
+    let reserve = disk::reserve_bytes(&app.state::<ConfigState>());
+    let info = backup::write(&payload, &dir.join(name), password, reserve)?;
+    config::update(app, |config| {
+        config.auto_backup_last_at = Some(now);
+        config.auto_backup_last_sha256 = Some(hash);
+    })?;
+    app.state::<AuditState>().record(
+        AuditAction::Backup,
+        serde_json::json!({ "path": info.path, "size_bytes": info.size_bytes, "automatic": true }),
+    );
+    prune(&dir, config.auto_backup_keep.clamp(*KEEP_RANGE.start(), *KEEP_RANGE.end()))?;
+    Ok(Some(info))
+}
+
+fn tick(app: &AppHandle) {
+    if app.state::<GuestState>().active() {
+        return;
+    }
+    let config = app.state::<ConfigState>().get();
+    let Some(dir) = config.auto_backup_dir.as_deref() else {
+        return;
+    };
+    let state = app.state::<AutoBackupState>();
+    let Some(password) = state.password() else {
+        return;
+    };
+    if !is_due(&config) || state.retry_after().is_some_and(|at| Instant::now() < at) {
+        return;
+    }
+
+    match run(app, dir, &password) {
+        Ok(info) => {
+            state.set_retry_after(None);
+            if let Some(info) = info {
+                let _ = app.emit_all(AUTO_BACKUP_COMPLETED_EVENT, &info);
+            }
+        }
+        Err(e) => {
+            eprintln!("Automatic backup failed: {}", e);
+            state.set_retry_after(Some(Instant::now() + RETRY_DELAY));
+            let _ = app.emit_all(AUTO_BACKUP_FAILED_EVENT, &e);
+        }
+    }
+}
+
+pub fn start(app: &AppHandle) {
+    let handle = app.clone();
+    app.state::<Workers>().spawn(TICK, move || tick(&handle));
+}
+
+#[tauri::command]
+pub fn get_auto_backup_status(
+    config: tauri::State<ConfigState>,
+    state: tauri::State<AutoBackupState>,
+) -> AutoBackupStatus {
+    let config = config.get();
+    AutoBackupStatus {
+        needs_password: config.auto_backup_dir.is_some() && state.password().is_none(),
+        destination: config.auto_backup_dir,
+        interval_hours: config.auto_backup_interval_hours,
+        keep: config.auto_backup_keep,
+        last_backup_at: config.auto_backup_last_at,
+    }
+}
+
+// After the store's passphrase changes: re-encrypts the automatic backups
+// that opened with the old one, and moves the backup password over if it was
+// the same. Backups under some other password are left as they are. Returns
+// how many were re-encrypted.
+pub fn rekey(app: &AppHandle, old: &str, new: &str) -> Result<usize, BackupError> {
+    let state = app.state::<AutoBackupState>();
+    if state.password().as_deref() == Some(old) {
+        state.set_password(Some(new.to_string()));
+    }
+    let Some(dir) = app.state::<ConfigState>().get().auto_backup_dir else {
+        return Ok(0);
+    };
+    let dir = PathBuf::from(dir);
+    if !dir.is_dir() {
+        return Ok(0);
+    }
+    let mut rekeyed = 0;
+    for path in auto_backups(&dir)? {
+        if backup::reseal(&path, old, new)? {
+            rekeyed += 1;
+        }
+    }
+    Ok(rekeyed)
+}
+
+// `None` turns automatic backups off. Otherwise the password is required
+// every time, including to resume after a restart.
+#[tauri::command]
+pub fn set_auto_backup_destination(
+    app: AppHandle,
+    state: tauri::State<AutoBackupState>,
+    dir: Option<String>,
+    password: Option<String>,
+) -> Result<(), BackupError> {
+    let Some(dir) = dir else {
+        state.set_password(None);
+        config::update(&app, |config| config.auto_backup_dir = None)?;
+        return Ok(());
+    };
+
+    let dir = validate::user_path(&app, "Backup folder", &dir)?;
+    if dir.exists() && !dir.is_dir() {
+        return Err(BackupError::InvalidInput(format!("{} is not a folder", dir.display())));
+    }
+    let password = password.unwrap_or_default();
+    validate::text("Password", &password, backup::MAX_PASSWORD_LEN)?;
+    backup::check_password(&password)?;
+
+    state.set_password(Some(password));
+    state.set_retry_after(None);
+    config::update(&app, |config| config.auto_backup_dir = Some(dir.display().to_string()))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_auto_backup_interval(app: AppHandle, hours: u64) -> Result<u64, AppError> {
+    validate::number("The backup interval (hours)", hours, INTERVAL_HOURS_RANGE)?;
+    config::update(&app, |config| config.auto_backup_interval_hours = hours)?;
+    Ok(hours)
+}
+
+// Takes effect at the next automatic backup, which is when pruning happens.
+#[tauri::command]
+pub fn set_auto_backup_retention(app: AppHandle, keep: usize) -> Result<usize, AppError> {
+    validate::number("The number of backups to keep", keep, KEEP_RANGE)?;
+    config::update(&app, |config| config.auto_backup_keep = keep)?;
+    Ok(keep)
+}
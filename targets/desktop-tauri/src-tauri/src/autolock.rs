@@ -0,0 +1,77 @@
+// Idle auto-lock: after a period without activity the main window is
+// minimized and a `locked` event tells the frontend to show its passphrase
+// gate, so an unattended session doesn't leave recovery data on screen.
+
+use crate::background::Workers;
+use crate::config::ConfigState;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const TICK: Duration = Duration::from_secs(1);
+
+pub struct AutoLockState {
+    last_activity: Mutex<Instant>,
+    locked: Mutex<bool>,
+}
+
+impl Default for AutoLockState {
+    fn default() -> Self {
+        Self {
+            last_activity: Mutex::new(Instant::now()),
+            locked: Mutex::new(false),
+        }
+    }
+}
+
+impl AutoLockState {
+    pub fn record_activity(&self) {
+        *self.last_activity.lock().unwrap_or_else(|p| p.into_inner()) = Instant::now();
+        *self.locked.lock().unwrap_or_else(|p| p.into_inner()) = false;
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_activity
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .elapsed()
+    }
+}
+
+pub fn lock(app: &AppHandle) {
+    if let Some(window) = app.get_window("main") {
+        let _ = window.minimize();
+    }
+    let _ = app.emit_all("locked", ());
+}
+
+fn tick(app: &AppHandle) {
+    let config = app.state::<ConfigState>().get();
+    if !config.auto_lock_enabled {
+        return;
+    }
+
+    let state = app.state::<AutoLockState>();
+    if state.idle_for() < Duration::from_secs(config.auto_lock_timeout_secs) {
+        return;
+    }
+    let mut locked = state.locked.lock().unwrap_or_else(|p| p.into_inner());
+    if !*locked {
+        *locked = true;
+        drop(locked);
+        lock(app);
+    }
+}
+
+// Settings are re-read every tick so enabling auto-lock or changing the
+// timeout takes effect without a restart.
+pub fn start(app: &AppHandle) {
+    let handle = app.clone();
+    app.state::<Workers>().spawn(TICK, move || tick(&handle));
+}
+
+// Called by the frontend on user interaction to reset the idle timer.
+#[tauri::command]
+pub fn record_activity(state: tauri::State<AutoLockState>) {
+    state.record_activity();
+}
@@ -0,0 +1,42 @@
+// Periodic background tasks that are stopped and joined on exit, so nothing
+// is still running when the app tears down its state.
+
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+struct Worker {
+    stop: Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct Workers(Mutex<Vec<Worker>>);
+
+impl Workers {
+    // Run `tick` every `interval` on its own thread until `stop_all`.
+    pub fn spawn(&self, interval: Duration, mut tick: impl FnMut() + Send + 'static) {
+        let (stop, stop_rx) = mpsc::channel::<()>();
+        // Runs until a stop is requested or the sender is dropped.
+        let thread = std::thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                tick();
+            }
+        });
+        self.0
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .push(Worker { stop, thread });
+    }
+
+    pub fn stop_all(&self) {
+        let workers = std::mem::take(&mut *self.0.lock().unwrap_or_else(|p| p.into_inner()));
+        for worker in &workers {
+            let _ = worker.stop.send(());
+        }
+        for worker in workers {
+            let _ = worker.thread.join();
+        }
+    }
+}
@@ -0,0 +1,644 @@
+// Password-protected backups of the local store.
+//
+// A `.smartbak` file is a small binary header followed by AES-256-GCM
+// ciphertext of the JSON-serialized entries and their edit history. Future
+// messages and installed programs aren't included, and a restore leaves them
+// as they are: programs are imported again from their signed bundles, and
+// future messages are held back until their date. The header carries everything
+// needed to decrypt (KDF parameters, salt, nonce) and is authenticated as
+// associated data, so tampering with it fails decryption like tampering with
+// the body would.
+//
+// Header layout (integers little-endian):
+//   magic     8 bytes  "SMARTBAK"
+//   version   u16      format version
+//   kdf       u8       1 = Argon2id
+//   m_cost    u32      Argon2 memory cost (KiB)
+//   t_cost    u32      Argon2 iterations
+//   p_cost    u32      Argon2 parallelism
+//   salt_len  u8
+//   salt      salt_len bytes
+//   nonce     12 bytes
+
+use crate::audit::{AuditAction, AuditState};
+use crate::config::ConfigState;
+use crate::disk::{self, DiskError};
+use crate::error;
+use crate::guest::GuestState;
+use crate::storage::{Entry, HistoryRecord, ReplacePlan, StorageError, StorageState};
+use crate::validate::{self, ValidationError};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+pub const BACKUP_EXTENSION: &str = "smartbak";
+pub const MIN_PASSWORD_LEN: usize = 10;
+
+const MAGIC: &[u8; 8] = b"SMARTBAK";
+const FORMAT_VERSION: u16 = 1;
+const KDF_ARGON2ID: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+// OWASP-recommended Argon2id baseline: 19 MiB, 2 iterations, 1 lane.
+const DEFAULT_M_COST: u32 = 19 * 1024;
+const DEFAULT_T_COST: u32 = 2;
+const DEFAULT_P_COST: u32 = 1;
+
+// Upper bounds accepted when reading a header, so a crafted file can't make
+// key derivation allocate gigabytes or spin for minutes.
+const MAX_M_COST: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 16;
+const MAX_P_COST: u32 = 8;
+pub const MAX_PASSWORD_LEN: usize = 1024;
+
+// Upgrades for payloads written by older schema versions. Entry `i` turns a
+// version `i + 1` payload into version `i + 2`; append one whenever
+// `storage::SCHEMA_VERSION` is bumped.
+const PAYLOAD_UPGRADES: &[fn(&mut serde_json::Value)] =
+    &[add_history, unchanged, unchanged, unchanged];
+
+// Version 2 added edit history; a version 1 store had none to carry.
+fn add_history(value: &mut serde_json::Value) {
+    value["history"] = serde_json::json!([]);
+}
+
+// For schema versions that don't change the payload: version 3 added future
+// messages and version 4 installed programs, neither carried in backups;
+// version 5 marked merged history, which defaults to unmarked.
+fn unchanged(_: &mut serde_json::Value) {}
+
+#[derive(Debug)]
+pub enum BackupError {
+    WeakPassword(String),
+    // The GCM tag didn't verify: wrong password, or the file was altered.
+    WrongPassword,
+    InvalidBackup(String),
+    UnsupportedVersion(String),
+    LowDiskSpace(String),
+    InvalidInput(String),
+    Failed(String),
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::WeakPassword(msg)
+            | BackupError::InvalidBackup(msg)
+            | BackupError::UnsupportedVersion(msg)
+            | BackupError::LowDiskSpace(msg)
+            | BackupError::InvalidInput(msg)
+            | BackupError::Failed(msg) => write!(f, "{}", msg),
+            BackupError::WrongPassword => write!(f, "Incorrect password, or the backup has been modified"),
+        }
+    }
+}
+
+impl BackupError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            BackupError::WeakPassword(_) => "WeakPassword",
+            BackupError::WrongPassword => "WrongPassword",
+            BackupError::InvalidBackup(_) => "InvalidBackup",
+            BackupError::UnsupportedVersion(_) => "UnsupportedVersion",
+            BackupError::LowDiskSpace(_) => "LowDiskSpace",
+            BackupError::InvalidInput(_) => "InvalidInput",
+            BackupError::Failed(_) => "Failed",
+        }
+    }
+}
+
+impl Serialize for BackupError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        error::serialize(serializer, self.code(), &self.to_string(), None)
+    }
+}
+
+impl From<DiskError> for BackupError {
+    fn from(e: DiskError) -> Self {
+        match e {
+            DiskError::LowDiskSpace(msg) => BackupError::LowDiskSpace(msg),
+            DiskError::InvalidInput(msg) => BackupError::InvalidInput(msg),
+            DiskError::Failed(msg) => BackupError::Failed(msg),
+        }
+    }
+}
+
+impl From<StorageError> for BackupError {
+    fn from(e: StorageError) -> Self {
+        BackupError::Failed(e.to_string())
+    }
+}
+
+impl From<ValidationError> for BackupError {
+    fn from(e: ValidationError) -> Self {
+        BackupError::InvalidInput(e.to_string())
+    }
+}
+
+impl From<String> for BackupError {
+    fn from(msg: String) -> Self {
+        BackupError::Failed(msg)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Header {
+    version: u16,
+    kdf: u8,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    salt: Vec<u8>,
+    nonce: [u8; NONCE_LEN],
+}
+
+impl Header {
+    fn new() -> Self {
+        let mut salt = vec![0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        Self {
+            version: FORMAT_VERSION,
+            kdf: KDF_ARGON2ID,
+            m_cost: DEFAULT_M_COST,
+            t_cost: DEFAULT_T_COST,
+            p_cost: DEFAULT_P_COST,
+            salt,
+            nonce,
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(8 + 2 + 1 + 12 + 1 + self.salt.len() + NONCE_LEN);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.push(self.kdf);
+        out.extend_from_slice(&self.m_cost.to_le_bytes());
+        out.extend_from_slice(&self.t_cost.to_le_bytes());
+        out.extend_from_slice(&self.p_cost.to_le_bytes());
+        out.push(self.salt.len() as u8);
+        out.extend_from_slice(&self.salt);
+        out.extend_from_slice(&self.nonce);
+        out
+    }
+
+    // Parse a header from the start of `bytes`, returning it along with its
+    // encoded length.
+    fn parse(bytes: &[u8]) -> Result<(Self, usize), BackupError> {
+        let truncated = || BackupError::InvalidBackup("Backup file is truncated".to_string());
+        let take = |offset: usize, len: usize| bytes.get(offset..offset + len).ok_or_else(truncated);
+        let u32_at = |offset: usize| -> Result<u32, BackupError> {
+            Ok(u32::from_le_bytes(take(offset, 4)?.try_into().unwrap()))
+        };
+
+        if take(0, MAGIC.len())? != MAGIC {
+            return Err(BackupError::InvalidBackup("Not a Smartbot backup file".to_string()));
+        }
+        let version = u16::from_le_bytes(take(8, 2)?.try_into().unwrap());
+        if version != FORMAT_VERSION {
+            return Err(BackupError::UnsupportedVersion(format!(
+                "Backup format version {} is not supported by this version of Smartbot",
+                version
+            )));
+        }
+        let kdf = take(10, 1)?[0];
+        if kdf != KDF_ARGON2ID {
+            return Err(BackupError::UnsupportedVersion(format!(
+                "Unsupported key derivation function {}",
+                kdf
+            )));
+        }
+        let (m_cost, t_cost, p_cost) = (u32_at(11)?, u32_at(15)?, u32_at(19)?);
+        if m_cost > MAX_M_COST || t_cost > MAX_T_COST || p_cost > MAX_P_COST {
+            return Err(BackupError::InvalidBackup(
+                "Backup key derivation parameters are out of range".to_string(),
+            ));
+        }
+        let salt_len = take(23, 1)?[0] as usize;
+        let salt = take(24, salt_len)?.to_vec();
+        let nonce: [u8; NONCE_LEN] = take(24 + salt_len, NONCE_LEN)?.try_into().unwrap();
+
+        let header = Self {
+            version,
+            kdf,
+            m_cost,
+            t_cost,
+            p_cost,
+            salt,
+            nonce,
+        };
+        Ok((header, 24 + salt_len + NONCE_LEN))
+    }
+
+    fn derive_key(&self, password: &str) -> Result<[u8; 32], String> {
+        if self.kdf != KDF_ARGON2ID {
+            return Err(format!("Unsupported key derivation function {}", self.kdf));
+        }
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|e| format!("Invalid key derivation parameters: {}", e))?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), &self.salt, &mut key)
+            .map_err(|e| format!("Key derivation failed: {}", e))?;
+        Ok(key)
+    }
+}
+
+// Everything a backup carries. Bump `storage::SCHEMA_VERSION` rather than
+// this struct's shape when the store changes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupPayload {
+    pub schema_version: i64,
+    pub app_version: String,
+    pub created_at: String,
+    pub entries: Vec<Entry>,
+    // Backups written at schema versions 2 to 4 before history was carried
+    // have none, rather than an empty list.
+    #[serde(default)]
+    pub history: Vec<HistoryRecord>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupInfo {
+    pub path: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreReport {
+    pub entries_restored: usize,
+    pub backup_created_at: String,
+    pub backup_schema_version: i64,
+    pub migrated: bool,
+    pub changes: ReplacePlan,
+    // What backups don't carry, which the restore left as it was.
+    pub left_unchanged: &'static [&'static str],
+    // Nothing was changed; the report shows what a real restore would do.
+    pub dry_run: bool,
+}
+
+const NOT_IN_BACKUPS: &[&str] = &["future_messages", "programs"];
+
+pub fn check_password(password: &str) -> Result<(), BackupError> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(BackupError::WeakPassword(format!(
+            "Backup password must be at least {} characters long",
+            MIN_PASSWORD_LEN
+        )));
+    }
+    Ok(())
+}
+
+// The backup container around any bytes, for other files that need a
+// password too.
+pub fn seal(plaintext: &[u8], password: &str) -> Result<Vec<u8>, BackupError> {
+    let header = Header::new();
+    let key = header.derive_key(password)?;
+    let header_bytes = header.to_bytes();
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&header.nonce),
+            Payload {
+                msg: plaintext,
+                aad: &header_bytes,
+            },
+        )
+        .map_err(|_| "Failed to encrypt backup".to_string())?;
+
+    let mut out = header_bytes;
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+pub fn unseal(bytes: &[u8], password: &str) -> Result<Vec<u8>, BackupError> {
+    let (header, header_len) = Header::parse(bytes)?;
+    let key = header.derive_key(password)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    cipher
+        .decrypt(
+            Nonce::from_slice(&header.nonce),
+            Payload {
+                msg: &bytes[header_len..],
+                aad: &bytes[..header_len],
+            },
+        )
+        .map_err(|_| BackupError::WrongPassword)
+}
+
+pub fn encrypt(payload: &BackupPayload, password: &str) -> Result<Vec<u8>, BackupError> {
+    let plaintext =
+        serde_json::to_vec(payload).map_err(|e| format!("Failed to serialize backup: {}", e))?;
+    seal(&plaintext, password)
+}
+
+// Decrypt and authenticate a backup, then bring its payload up to the
+// current schema. Nothing is imported here, so a failure at any step leaves
+// the store untouched.
+pub fn decrypt(bytes: &[u8], password: &str) -> Result<(BackupPayload, bool), BackupError> {
+    let plaintext = unseal(bytes, password)?;
+    let value: serde_json::Value = serde_json::from_slice(&plaintext)
+        .map_err(|e| BackupError::InvalidBackup(format!("Backup contents are malformed: {}", e)))?;
+    upgrade_payload(value)
+}
+
+// Bring a payload written by any supported schema version up to the current
+// one. Returns whether anything needed upgrading.
+pub fn upgrade_payload(mut value: serde_json::Value) -> Result<(BackupPayload, bool), BackupError> {
+    let version = value
+        .get("schema_version")
+        .and_then(|v| v.as_i64())
+        .ok_or_else(|| BackupError::InvalidBackup("Backup has no schema version".to_string()))?;
+    if version < 1 || version > crate::storage::SCHEMA_VERSION {
+        return Err(BackupError::UnsupportedVersion(format!(
+            "Backup schema version {} is not supported (this app supports up to {})",
+            version,
+            crate::storage::SCHEMA_VERSION
+        )));
+    }
+
+    let migrated = version < crate::storage::SCHEMA_VERSION;
+    for upgrade in &PAYLOAD_UPGRADES[(version - 1) as usize..] {
+        upgrade(&mut value);
+    }
+    value["schema_version"] = crate::storage::SCHEMA_VERSION.into();
+
+    let payload: BackupPayload = serde_json::from_value(value)
+        .map_err(|e| BackupError::InvalidBackup(format!("Backup contents are malformed: {}", e)))?;
+    Ok((payload, migrated))
+}
+
+fn with_backup_extension(path: &Path) -> PathBuf {
+    if path.extension().is_some() {
+        path.to_path_buf()
+    } else {
+        path.with_extension(BACKUP_EXTENSION)
+    }
+}
+
+// Write via a temporary sibling and rename so a crash never leaves a
+// half-written backup under the real name.
+pub fn write_atomically(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+    std::fs::write(&tmp, bytes)
+        .map_err(|e| format!("Failed to write backup to {}: {}", tmp.display(), e))?;
+    std::fs::rename(&tmp, path).map_err(|e| {
+        let _ = std::fs::remove_file(&tmp);
+        format!("Failed to move backup into place at {}: {}", path.display(), e)
+    })
+}
+
+// Re-encrypts the backup at `path` under `new` without touching its
+// contents. Returns false, leaving the file alone, when `old` doesn't open it.
+pub fn reseal(path: &Path, old: &str, new: &str) -> Result<bool, BackupError> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let plaintext = match unseal(&bytes, old) {
+        Ok(plaintext) => plaintext,
+        Err(BackupError::WrongPassword) => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    write_atomically(path, &seal(&plaintext, new)?)?;
+    Ok(true)
+}
+
+pub fn snapshot(state: &StorageState) -> Result<BackupPayload, BackupError> {
+    Ok(state.with(|storage| {
+        Ok(BackupPayload {
+            schema_version: crate::storage::SCHEMA_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            entries: storage.all_entries()?,
+            history: storage.all_history()?,
+        })
+    })?)
+}
+
+pub fn create(
+    state: &StorageState,
+    dest: &Path,
+    password: &str,
+    reserve_bytes: u64,
+) -> Result<BackupInfo, BackupError> {
+    check_password(password)?;
+    write(&snapshot(state)?, dest, password, reserve_bytes)
+}
+
+pub fn write(
+    payload: &BackupPayload,
+    dest: &Path,
+    password: &str,
+    reserve_bytes: u64,
+) -> Result<BackupInfo, BackupError> {
+    let bytes = encrypt(payload, password)?;
+
+    let dest = with_backup_extension(dest);
+    disk::ensure_space(&dest, bytes.len() as u64, reserve_bytes)?;
+    write_atomically(&dest, &bytes)?;
+    Ok(BackupInfo {
+        path: dest.display().to_string(),
+        size_bytes: bytes.len() as u64,
+        sha256: hex::encode(Sha256::digest(&bytes)),
+    })
+}
+
+pub fn restore(
+    state: &StorageState,
+    src: &Path,
+    password: &str,
+    dry_run: bool,
+) -> Result<RestoreReport, BackupError> {
+    let bytes = std::fs::read(src)
+        .map_err(|e| BackupError::Failed(format!("Failed to read backup {}: {}", src.display(), e)))?;
+    let (payload, migrated) = decrypt(&bytes, password)?;
+    let entries_restored = payload.entries.len();
+    let changes = state.with(|storage| {
+        let changes = storage.plan_replace(&payload.entries)?;
+        if !dry_run {
+            storage.replace_all(&payload.entries, &payload.history)?;
+        }
+        Ok(changes)
+    })?;
+    Ok(RestoreReport {
+        entries_restored,
+        backup_created_at: payload.created_at,
+        backup_schema_version: payload.schema_version,
+        migrated,
+        changes,
+        left_unchanged: NOT_IN_BACKUPS,
+        dry_run,
+    })
+}
+
+// Serialize and encrypt the entries and their history into a single
+// `.smartbak` file.
+#[tauri::command]
+pub async fn create_backup(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, StorageState>,
+    config: tauri::State<'_, ConfigState>,
+    audit: tauri::State<'_, AuditState>,
+    dest_path: String,
+    password: String,
+) -> Result<BackupInfo, BackupError> {
+    app.state::<GuestState>().refuse("Backups")?;
+    let dest_path = validate::user_path(&app, "Backup destination", &dest_path)?;
+    validate::text("Password", &password, MAX_PASSWORD_LEN)?;
+    let info = create(&state, &dest_path, &password, disk::reserve_bytes(&config))?;
+    audit.record(
+        AuditAction::Backup,
+        serde_json::json!({ "path": info.path, "size_bytes": info.size_bytes }),
+    );
+    Ok(info)
+}
+
+// Replace the local data with the contents of a `.smartbak` file. The store
+// is only touched once the backup has decrypted and validated in full, and
+// not at all with `dry_run`.
+#[tauri::command]
+pub async fn restore_backup(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, StorageState>,
+    audit: tauri::State<'_, AuditState>,
+    src_path: String,
+    password: String,
+    dry_run: Option<bool>,
+) -> Result<RestoreReport, BackupError> {
+    app.state::<GuestState>().refuse("Restoring a backup")?;
+    let src_path = validate::user_path(&app, "Backup file", &src_path)?;
+    validate::text("Password", &password, MAX_PASSWORD_LEN)?;
+    let report = restore(&state, &src_path, &password, dry_run.unwrap_or(false))?;
+    if report.dry_run {
+        return Ok(report);
+    }
+    audit.record(
+        AuditAction::Import,
+        serde_json::json!({
+            "source": "backup",
+            "path": src_path,
+            "entries": report.entries_restored,
+        }),
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSWORD: &str = "correct horse battery";
+
+    fn payload() -> BackupPayload {
+        BackupPayload {
+            schema_version: crate::storage::SCHEMA_VERSION,
+            app_version: "test".to_string(),
+            created_at: "2026-05-01T12:00:00+00:00".to_string(),
+            entries: vec![Entry {
+                id: 1,
+                kind: "journal".to_string(),
+                content: "Called my sponsor".to_string(),
+                mood: Some(6.0),
+                created_at: "2026-05-01T11:00:00+00:00".to_string(),
+                updated_at: "2026-05-01T11:30:00+00:00".to_string(),
+            }],
+            history: vec![HistoryRecord {
+                entry_id: 1,
+                content: "Called".to_string(),
+                written_at: "2026-05-01T11:00:00+00:00".to_string(),
+                replaced_at: "2026-05-01T11:30:00+00:00".to_string(),
+                merged: false,
+            }],
+        }
+    }
+
+    #[test]
+    fn payload_round_trips() {
+        let sealed = encrypt(&payload(), PASSWORD).unwrap();
+        assert!(sealed.starts_with(MAGIC));
+        let (opened, migrated) = decrypt(&sealed, PASSWORD).unwrap();
+        assert!(!migrated);
+        assert_eq!(opened.entries[0].content, "Called my sponsor");
+        assert_eq!(opened.history[0].content, "Called");
+    }
+
+    #[test]
+    fn wrong_password_is_reported_as_such() {
+        let sealed = seal(b"secret", PASSWORD).unwrap();
+        assert!(matches!(
+            unseal(&sealed, "not the password"),
+            Err(BackupError::WrongPassword)
+        ));
+    }
+
+    #[test]
+    fn altered_header_fails_authentication() {
+        let mut sealed = seal(b"secret", PASSWORD).unwrap();
+        let (_, header_len) = Header::parse(&sealed).unwrap();
+        sealed[header_len - 1] ^= 1;
+        assert!(matches!(unseal(&sealed, PASSWORD), Err(BackupError::WrongPassword)));
+    }
+
+    #[test]
+    fn out_of_range_kdf_costs_are_refused_before_deriving() {
+        for (offset, cost) in [(11, MAX_M_COST), (15, MAX_T_COST), (19, MAX_P_COST)] {
+            let mut sealed = seal(b"secret", PASSWORD).unwrap();
+            // Large enough that deriving with it would take minutes.
+            sealed[offset..offset + 4].copy_from_slice(&(cost + 1).to_le_bytes());
+            assert!(matches!(
+                unseal(&sealed, PASSWORD),
+                Err(BackupError::InvalidBackup(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn truncated_files_are_refused() {
+        let sealed = seal(b"secret", PASSWORD).unwrap();
+        let (_, header_len) = Header::parse(&sealed).unwrap();
+        assert!(matches!(
+            unseal(&sealed[..header_len - 1], PASSWORD),
+            Err(BackupError::InvalidBackup(_))
+        ));
+        // A cut-off body fails the GCM tag.
+        assert!(matches!(
+            unseal(&sealed[..sealed.len() - 1], PASSWORD),
+            Err(BackupError::WrongPassword)
+        ));
+    }
+
+    #[test]
+    fn version_1_payloads_gain_an_empty_history() {
+        let mut value = serde_json::to_value(payload()).unwrap();
+        value["schema_version"] = 1.into();
+        value.as_object_mut().unwrap().remove("history");
+        let (upgraded, migrated) = upgrade_payload(value).unwrap();
+        assert!(migrated);
+        assert_eq!(upgraded.schema_version, crate::storage::SCHEMA_VERSION);
+        assert!(upgraded.history.is_empty());
+    }
+
+    #[test]
+    fn reseal_moves_a_backup_to_the_new_password() {
+        let path = std::env::temp_dir()
+            .join(format!("smartbot-reseal-{}.smartbak", uuid::Uuid::new_v4()));
+        std::fs::write(&path, seal(b"secret", PASSWORD).unwrap()).unwrap();
+
+        assert!(!reseal(&path, "not the password", "new password here").unwrap());
+        assert!(reseal(&path, PASSWORD, "new password here").unwrap());
+        let sealed = std::fs::read(&path).unwrap();
+        assert!(matches!(unseal(&sealed, PASSWORD), Err(BackupError::WrongPassword)));
+        assert_eq!(unseal(&sealed, "new password here").unwrap(), b"secret");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+// A count on the app icon: the dock badge on macOS, an overlay on the
+// taskbar button on Windows. Windows overlays are icons rather than text, so
+// there the overlay only marks that something is waiting and the count goes
+// in its accessible description. Other platforms have no badge to set.
+
+use crate::error::AppError;
+use crate::validate;
+use serde::Serialize;
+use std::sync::mpsc;
+use tauri::AppHandle;
+
+pub const SUPPORTED: bool = cfg!(any(target_os = "macos", target_os = "windows"));
+const MAX_COUNT: u32 = 9999;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BadgeStatus {
+    pub count: u32,
+    // Whether the icon actually shows it.
+    pub applied: bool,
+}
+
+#[cfg(target_os = "macos")]
+fn apply(_app: &AppHandle, count: u32) -> Result<(), String> {
+    use cocoa::appkit::NSApp;
+    use cocoa::base::{id, nil};
+    use cocoa::foundation::{NSAutoreleasePool, NSString};
+    use objc::{msg_send, sel, sel_impl};
+
+    unsafe {
+        let label = match count {
+            0 => nil,
+            n => NSString::alloc(nil).init_str(&n.to_string()).autorelease(),
+        };
+        let dock_tile: id = msg_send![NSApp(), dockTile];
+        let _: () = msg_send![dock_tile, setBadgeLabel: label];
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn apply(app: &AppHandle, count: u32) -> Result<(), String> {
+    use tauri::Manager;
+    use windows::core::HSTRING;
+    use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+    use windows::Win32::UI::Shell::{ITaskbarList3, TaskbarList};
+    use windows::Win32::UI::WindowsAndMessaging::{LoadIconW, HICON, IDI_INFORMATION};
+
+    let window = app
+        .get_window("main")
+        .ok_or("The main window is not open")?;
+    let hwnd = window.hwnd().map_err(|e| e.to_string())?;
+    unsafe {
+        let taskbar: ITaskbarList3 = CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER)
+            .map_err(|e| e.to_string())?;
+        taskbar.HrInit().map_err(|e| e.to_string())?;
+        let (icon, description) = match count {
+            0 => (HICON::default(), HSTRING::new()),
+            1 => (
+                LoadIconW(None, IDI_INFORMATION).map_err(|e| e.to_string())?,
+                HSTRING::from("1 pending check-in"),
+            ),
+            n => (
+                LoadIconW(None, IDI_INFORMATION).map_err(|e| e.to_string())?,
+                HSTRING::from(format!("{} pending check-ins", n)),
+            ),
+        };
+        taskbar
+            .SetOverlayIcon(hwnd, icon, &description)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn apply(_app: &AppHandle, _count: u32) -> Result<(), String> {
+    Ok(())
+}
+
+// Both badge APIs have to be called on the main thread, so this blocks until
+// it has run there and must not be called from the main thread itself.
+pub fn set(app: &AppHandle, count: u32) -> BadgeStatus {
+    let count = count.min(MAX_COUNT);
+    let (tx, rx) = mpsc::channel();
+    let handle = app.clone();
+    let result = app
+        .run_on_main_thread(move || {
+            let _ = tx.send(apply(&handle, count));
+        })
+        .map_err(|e| e.to_string())
+        .and_then(|()| rx.recv().map_err(|e| e.to_string())?);
+    if let Err(e) = &result {
+        eprintln!("Failed to set the badge count: {}", e);
+    }
+    BadgeStatus {
+        count,
+        applied: SUPPORTED && result.is_ok(),
+    }
+}
+
+// The reminder scheduler sets the count too, whenever a reminder fires or is
+// acknowledged, so this is for counts the frontend tracks itself.
+#[tauri::command]
+pub async fn set_badge_count(app: AppHandle, n: u32) -> Result<BadgeStatus, AppError> {
+    let n = validate::number("Badge count", n, 0..=MAX_COUNT)?;
+    Ok(set(&app, n))
+}
@@ -0,0 +1,127 @@
+// Timing cold starts of the core, so a report that the app is slow to start
+// comes with numbers. Each run stops the core and starts it again, which
+// fails any request made meanwhile, so it is behind `diagnostics_enabled`.
+
+use crate::config::ConfigState;
+use crate::core::{self, CoreState, STARTUP_PROGRESS_EVENT};
+use crate::error::AppError;
+use crate::validate;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+
+const RUNS_RANGE: std::ops::RangeInclusive<u32> = 1..=10;
+const DEFAULT_RUNS: u32 = 3;
+
+#[derive(Debug, Serialize)]
+pub struct StartupRun {
+    pub duration_ms: u128,
+    // The `core-startup-progress` payloads this start sent, in order.
+    pub stages: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StartupBenchmark {
+    pub min_ms: u128,
+    pub median_ms: u128,
+    pub max_ms: u128,
+    pub runs: Vec<StartupRun>,
+}
+
+// Durations must be non-empty; an even count takes the mean of the middle two.
+fn summarize(durations: &[u128]) -> (u128, u128, u128) {
+    let mut sorted = durations.to_vec();
+    sorted.sort_unstable();
+    let middle = sorted.len() / 2;
+    let median = if sorted.len() % 2 == 0 {
+        (sorted[middle - 1] + sorted[middle]) / 2
+    } else {
+        sorted[middle]
+    };
+    (sorted[0], median, sorted[sorted.len() - 1])
+}
+
+fn cold_start(app: &AppHandle, state: &CoreState) -> Result<StartupRun, String> {
+    core::shutdown(state);
+    let stages = Arc::new(Mutex::new(Vec::new()));
+    let recorded = stages.clone();
+    let listener = app.listen_global(STARTUP_PROGRESS_EVENT, move |event| {
+        if let Some(progress) = event.payload().and_then(|p| serde_json::from_str(p).ok()) {
+            recorded
+                .lock()
+                .unwrap_or_else(|p| p.into_inner())
+                .push(progress);
+        }
+    });
+    let started = Instant::now();
+    let result = core::ensure(app);
+    let duration_ms = started.elapsed().as_millis();
+    app.unlisten(listener);
+    result.map_err(|e| e.to_string())?;
+    let stages = std::mem::take(&mut *stages.lock().unwrap_or_else(|p| p.into_inner()));
+    Ok(StartupRun {
+        duration_ms,
+        stages,
+    })
+}
+
+// Only a core this app spawned can be measured: one started elsewhere would
+// just be waited on. The core is left running afterwards, and started again
+// if a run failed.
+#[tauri::command]
+pub async fn benchmark_startup(
+    app: AppHandle,
+    runs: Option<u32>,
+) -> Result<StartupBenchmark, AppError> {
+    if !app.state::<ConfigState>().get().diagnostics_enabled {
+        return Err(AppError::InvalidInput(
+            "Turn on `diagnostics_enabled` in the config to benchmark startup".to_string(),
+        ));
+    }
+    let runs = runs.unwrap_or(DEFAULT_RUNS);
+    validate::number("Runs", runs, RUNS_RANGE)?;
+    let benchmark = tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<CoreState>();
+        if state.child_pid().is_none() && core::is_ready(&app, &state) {
+            return Err(
+                "The core was started outside the app, so its startup can't be timed".to_string(),
+            );
+        }
+        let mut results = Vec::new();
+        for _ in 0..runs {
+            match cold_start(&app, &state) {
+                Ok(run) => results.push(run),
+                Err(e) => {
+                    if let Err(restart) = core::ensure(&app) {
+                        eprintln!("Core didn't restart after the benchmark: {}", restart);
+                    }
+                    return Err(format!("Startup run {} failed: {}", results.len() + 1, e));
+                }
+            }
+        }
+        let durations: Vec<_> = results.iter().map(|run| run.duration_ms).collect();
+        let (min_ms, median_ms, max_ms) = summarize(&durations);
+        Ok(StartupBenchmark {
+            min_ms,
+            median_ms,
+            max_ms,
+            runs: results,
+        })
+    })
+    .await
+    .map_err(|e| format!("Startup benchmark failed: {}", e))??;
+    Ok(benchmark)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_takes_the_middle_run() {
+        assert_eq!(summarize(&[900, 300, 500]), (300, 500, 900));
+        assert_eq!(summarize(&[400, 200, 800, 600]), (200, 500, 800));
+        assert_eq!(summarize(&[700]), (700, 700, 700));
+    }
+}
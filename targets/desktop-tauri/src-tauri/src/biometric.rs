@@ -0,0 +1,70 @@
+// Optional Touch ID gate on launch and after auto-lock, for shared Macs.
+// Platforms without biometric support are treated as unlocked.
+
+use crate::audit::{AuditAction, AuditState};
+use crate::config::ConfigState;
+use crate::error::AppError;
+use tauri::{AppHandle, Manager};
+
+#[cfg(target_os = "macos")]
+fn authenticate() -> bool {
+    use robius_authentication::{
+        AndroidText, BiometricStrength, Context, Error, PolicyBuilder, Text, WindowsText,
+    };
+
+    let Some(policy) = PolicyBuilder::new()
+        .biometrics(Some(BiometricStrength::Strong))
+        .password(false)
+        .watch(true)
+        .build()
+    else {
+        return true;
+    };
+    let text = Text {
+        android: AndroidText {
+            title: "Unlock Smartbot",
+            subtitle: None,
+            description: None,
+        },
+        apple: "unlock Smartbot",
+        windows: WindowsText::new("Unlock Smartbot", "Confirm it's you to continue"),
+    };
+    match Context::new(()).blocking_authenticate(text, &policy) {
+        Ok(()) => true,
+        // No Touch ID on this Mac, or no fingerprints enrolled.
+        Err(Error::Unavailable) | Err(Error::NotEnrolled) => true,
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn authenticate() -> bool {
+    true
+}
+
+// Prompts when the gate is enabled and reveals the main window on success.
+// Returns whether the user is now unlocked.
+#[tauri::command]
+pub async fn biometric_unlock(app: AppHandle) -> Result<bool, AppError> {
+    let unlocked = if app.state::<ConfigState>().get().biometric_unlock_enabled {
+        let unlocked = tauri::async_runtime::spawn_blocking(authenticate)
+            .await
+            .map_err(|e| format!("Biometric prompt failed: {}", e))?;
+        if unlocked {
+            app.state::<AuditState>()
+                .record(AuditAction::Unlock, serde_json::json!({ "method": "biometric" }));
+        }
+        unlocked
+    } else {
+        true
+    };
+
+    if unlocked {
+        if let Some(window) = app.get_window("main") {
+            window.show().map_err(|e| format!("Failed to show window: {}", e))?;
+            let _ = window.unminimize();
+            let _ = window.set_focus();
+        }
+    }
+    Ok(unlocked)
+}
@@ -0,0 +1,137 @@
+// Circuit breaker for calls to the core. After enough consecutive failures it
+// opens and calls are refused without touching the network; once the cooldown
+// has passed it half-opens, and the next call decides whether it closes again
+// or stays open for another cooldown.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const FAILURE_THRESHOLD: u32 = 5;
+pub const COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    // The cooldown is over; the next call is let through as a trial.
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    // Until the breaker half-opens; `None` unless it is open.
+    pub retry_in_ms: Option<u128>,
+}
+
+#[derive(Default)]
+struct Counts {
+    failures: u32,
+    opened_at: Option<Instant>,
+}
+
+pub struct CircuitBreaker {
+    counts: Mutex<Counts>,
+    threshold: u32,
+    cooldown: Duration,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(FAILURE_THRESHOLD, COOLDOWN)
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            counts: Mutex::new(Counts::default()),
+            threshold,
+            cooldown,
+        }
+    }
+
+    fn counts(&self) -> std::sync::MutexGuard<'_, Counts> {
+        self.counts.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.state_of(&self.counts())
+    }
+
+    // Whether a call may go ahead right now.
+    pub fn allows(&self) -> bool {
+        self.state() != BreakerState::Open
+    }
+
+    // A failed trial reopens the breaker straight away, since the failure
+    // count is still at the threshold.
+    pub fn record(&self, ok: bool) {
+        let mut counts = self.counts();
+        if ok {
+            *counts = Counts::default();
+            return;
+        }
+        counts.failures = counts.failures.saturating_add(1);
+        if counts.failures >= self.threshold {
+            counts.opened_at = Some(Instant::now());
+        }
+    }
+
+    // For a core that is known to be back, e.g. after a successful start.
+    pub fn reset(&self) {
+        *self.counts() = Counts::default();
+    }
+
+    pub fn status(&self) -> BreakerStatus {
+        let counts = self.counts();
+        let retry_in = counts
+            .opened_at
+            .map(|at| self.cooldown.saturating_sub(at.elapsed()))
+            .filter(|remaining| !remaining.is_zero());
+        BreakerStatus {
+            state: self.state_of(&counts),
+            consecutive_failures: counts.failures,
+            retry_in_ms: retry_in.map(|remaining| remaining.as_millis()),
+        }
+    }
+
+    fn state_of(&self, counts: &Counts) -> BreakerState {
+        match counts.opened_at {
+            None => BreakerState::Closed,
+            Some(at) if at.elapsed() < self.cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_threshold_and_half_opens_after_cooldown() {
+        let breaker = CircuitBreaker::new(3, Duration::from_millis(50));
+        breaker.record(false);
+        breaker.record(false);
+        assert!(breaker.allows());
+        breaker.record(false);
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(!breaker.allows());
+        assert!(breaker.status().retry_in_ms.is_some());
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        // A failed trial reopens it for another full cooldown.
+        breaker.record(false);
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        std::thread::sleep(Duration::from_millis(60));
+        breaker.record(true);
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert_eq!(breaker.status().consecutive_failures, 0);
+    }
+}
@@ -0,0 +1,144 @@
+// In-memory TTL cache for idempotent core responses. Only endpoints listed in
+// `core_cache_endpoints` are cached, since most core data changes with use.
+
+use crate::http::Response;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct Cached {
+    response: Response,
+    stored_at: Instant,
+}
+
+#[derive(Default)]
+pub struct ResponseCache(Mutex<HashMap<String, Cached>>);
+
+pub fn key(method: &str, path: &str) -> String {
+    format!("{} {}", method.to_uppercase(), path)
+}
+
+// "/stats/weekly?x=1" -> "/stats": a write anywhere under a resource
+// invalidates every cached read of it.
+fn resource_prefix(path: &str) -> &str {
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    match path.char_indices().skip(1).find(|&(_, c)| c == '/') {
+        Some((i, _)) => &path[..i],
+        None => path,
+    }
+}
+
+fn path_of(key: &str) -> &str {
+    key.split_once(' ').map(|(_, path)| path).unwrap_or(key)
+}
+
+impl ResponseCache {
+    pub fn get(&self, key: &str, ttl: Duration) -> Option<Response> {
+        let mut entries = self.0.lock().unwrap_or_else(|p| p.into_inner());
+        match entries.get(key) {
+            Some(cached) if cached.stored_at.elapsed() < ttl => Some(cached.response.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: String, response: Response, max_entries: usize) {
+        if max_entries == 0 {
+            return;
+        }
+        let mut entries = self.0.lock().unwrap_or_else(|p| p.into_inner());
+        while entries.len() >= max_entries && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, cached)| cached.stored_at)
+                .map(|(k, _)| k.clone());
+            match oldest {
+                Some(oldest) => entries.remove(&oldest),
+                None => break,
+            };
+        }
+        entries.insert(
+            key,
+            Cached {
+                response,
+                stored_at: Instant::now(),
+            },
+        );
+    }
+
+    pub fn invalidate_resource(&self, path: &str) {
+        let prefix = resource_prefix(path);
+        self.0
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .retain(|key, _| resource_prefix(path_of(key)) != prefix);
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap_or_else(|p| p.into_inner()).clear();
+    }
+}
+
+#[tauri::command]
+pub fn clear_core_cache(cache: tauri::State<ResponseCache>) {
+    cache.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn response(body: &str) -> Response {
+        Response {
+            status: 200,
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn entries_expire_after_their_ttl() {
+        let cache = ResponseCache::default();
+        cache.insert(key("get", "/stats"), response("weekly"), 8);
+        assert_eq!(cache.get("GET /stats", TTL).unwrap().body, "weekly");
+        assert!(cache.get("GET /stats", Duration::ZERO).is_none());
+        // An expired entry is dropped, not kept for a longer TTL.
+        assert!(cache.get("GET /stats", TTL).is_none());
+    }
+
+    #[test]
+    fn the_oldest_entry_makes_room_at_max_entries() {
+        let cache = ResponseCache::default();
+        for path in ["/a", "/b", "/c"] {
+            cache.insert(key("GET", path), response(path), 2);
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        assert!(cache.get("GET /a", TTL).is_none());
+        assert!(cache.get("GET /b", TTL).is_some());
+        assert!(cache.get("GET /c", TTL).is_some());
+
+        // Replacing a cached key doesn't push anything else out.
+        cache.insert(key("GET", "/b"), response("newer"), 2);
+        assert_eq!(cache.get("GET /b", TTL).unwrap().body, "newer");
+        assert!(cache.get("GET /c", TTL).is_some());
+        cache.insert(key("GET", "/d"), response("/d"), 0);
+        assert!(cache.get("GET /d", TTL).is_none());
+    }
+
+    #[test]
+    fn a_write_invalidates_reads_of_the_same_resource() {
+        let cache = ResponseCache::default();
+        for path in ["/stats", "/stats/weekly?x=1", "/statsx", "/mood"] {
+            cache.insert(key("GET", path), response(path), 8);
+        }
+        cache.invalidate_resource("/stats/daily#today");
+        assert!(cache.get("GET /stats", TTL).is_none());
+        assert!(cache.get("GET /stats/weekly?x=1", TTL).is_none());
+        assert!(cache.get("GET /statsx", TTL).is_some());
+        assert!(cache.get("GET /mood", TTL).is_some());
+    }
+}
@@ -0,0 +1,109 @@
+// Command-line flags, for scripts and users who want the core without the
+// window or the window without the core.
+
+use crate::core::{self, CoreState};
+use tauri::{AppHandle, Manager};
+
+pub const HELP: &str = "\
+Smartbot Desktop
+
+Usage: smartbot [OPTIONS]
+
+Options:
+  --minimized   Start with the window minimized
+  --no-core     Don't start the core; connect to one started separately
+  --core-only   Start the core, print its URL and exit, leaving it running
+  -h, --help    Print this help and exit
+
+--minimized and --no-core can be combined. --core-only opens no window, so
+--minimized has no effect with it, and it can't be combined with --no-core.";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Args {
+    pub minimized: bool,
+    pub no_core: bool,
+    pub core_only: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Run(Args),
+    Help,
+}
+
+// `args` excludes the program name.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut parsed = Args::default();
+    for arg in args {
+        match arg.as_str() {
+            "--minimized" => parsed.minimized = true,
+            "--no-core" => parsed.no_core = true,
+            "--core-only" => parsed.core_only = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            // Added by Finder on older macOS when launching an app bundle.
+            _ if arg.starts_with("-psn_") => {}
+            _ => return Err(format!("Unknown option {:?}", arg)),
+        }
+    }
+    if parsed.core_only && parsed.no_core {
+        return Err("--core-only and --no-core can't be used together".to_string());
+    }
+    Ok(Command::Run(parsed))
+}
+
+// A release build on Windows has no console of its own, so output would be
+// lost without borrowing the one it was started from.
+pub fn attach_console() {
+    #[cfg(all(target_os = "windows", not(debug_assertions)))]
+    unsafe {
+        use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+// For `--core-only`: needs the config to be managed already, and never
+// returns.
+pub fn run_core_only(app: &AppHandle) -> ! {
+    let state = app.state::<CoreState>();
+    state.detach();
+    let url = core::ensure(app).and_then(|_| {
+        state
+            .url()
+            .ok_or_else(|| "The core started but its address is unknown".to_string().into())
+    });
+    match url {
+        Ok(url) => {
+            println!("{}", url);
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("Core startup failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Command, String> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn flags_combine_and_conflicts_are_rejected() {
+        assert_eq!(parse_args(&[]), Ok(Command::Run(Args::default())));
+        assert_eq!(
+            parse_args(&["--no-core", "--minimized"]),
+            Ok(Command::Run(Args {
+                minimized: true,
+                no_core: true,
+                core_only: false,
+            }))
+        );
+        assert_eq!(parse_args(&["--core-only", "--help"]), Ok(Command::Help));
+        assert!(parse_args(&["--core-only", "--no-core"]).is_err());
+        assert!(parse_args(&["--headless"]).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+// Clipboard writes from the frontend, with an optional auto-clear so copied
+// recovery details don't linger for other apps to read.
+
+use crate::config::ConfigState;
+use crate::error::AppError;
+use crate::validate;
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, ClipboardManager, Manager};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SensitiveCopy {
+    pub copied: bool,
+    pub clears_at: String,
+}
+
+fn write(app: &AppHandle, text: String) -> Result<(), String> {
+    app.clipboard_manager()
+        .write_text(text)
+        .map_err(|e| format!("Clipboard is unavailable: {}", e))
+}
+
+// Clear after `delay`, unless the user has copied something else meanwhile.
+fn schedule_clear(app: &AppHandle, text: String, delay: Duration) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(delay);
+        let mut clipboard = app.clipboard_manager();
+        if clipboard.read_text().ok().flatten().as_deref() == Some(text.as_str()) {
+            let _ = clipboard.write_text(String::new());
+        }
+    });
+}
+
+const MAX_TEXT_LEN: usize = 64 * 1024;
+const MAX_CLEAR_AFTER_SECS: u64 = 60 * 60;
+
+#[tauri::command]
+pub fn copy_text(app: AppHandle, text: String, clear_after_secs: Option<u64>) -> Result<(), AppError> {
+    validate::text("Text", &text, MAX_TEXT_LEN)?;
+    write(&app, text.clone())?;
+    if let Some(secs) = clear_after_secs {
+        let secs = validate::number("Clear delay", secs, 1..=MAX_CLEAR_AFTER_SECS)?;
+        schedule_clear(&app, text, Duration::from_secs(secs));
+    }
+    Ok(())
+}
+
+// For crisis numbers, passwords and the like: always cleared, after
+// `clear_after_secs` or the configured default.
+#[tauri::command]
+pub fn copy_sensitive(
+    app: AppHandle,
+    text: String,
+    clear_after_secs: Option<u64>,
+) -> Result<SensitiveCopy, AppError> {
+    validate::text("Text", &text, MAX_TEXT_LEN)?;
+    let secs = clear_after_secs
+        .unwrap_or_else(|| app.state::<ConfigState>().get().clipboard_clear_after_secs);
+    let secs = validate::number("Clear delay", secs, 1..=MAX_CLEAR_AFTER_SECS)?;
+    write(&app, text.clone())?;
+    schedule_clear(&app, text, Duration::from_secs(secs));
+    let clears_at = chrono::Utc::now() + chrono::Duration::seconds(secs as i64);
+    Ok(SensitiveCopy {
+        copied: true,
+        clears_at: clears_at.to_rfc3339(),
+    })
+}
@@ -0,0 +1,117 @@
+// Internet reachability for the optional online features (meeting links,
+// resource pages). The app itself never needs the network.
+
+use crate::config::ConfigState;
+use serde::Serialize;
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+const TICK: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Connectivity {
+    Unknown,
+    Online,
+    Offline,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectivityStatus {
+    pub state: Connectivity,
+    pub checked_at: Option<String>,
+}
+
+pub struct ConnectivityState {
+    status: Mutex<ConnectivityStatus>,
+    last_probe: Mutex<Option<Instant>>,
+}
+
+impl Default for ConnectivityState {
+    fn default() -> Self {
+        Self {
+            status: Mutex::new(ConnectivityStatus {
+                state: Connectivity::Unknown,
+                checked_at: None,
+            }),
+            last_probe: Mutex::new(None),
+        }
+    }
+}
+
+// Any HTTP response counts as online; curl fails on DNS, connect or timeout.
+fn probe(url: &str) -> Connectivity {
+    let output = Command::new("curl")
+        .args(["-s", "-I", "--max-time", &PROBE_TIMEOUT.as_secs().to_string(), url])
+        .output();
+    match output {
+        Ok(result) if result.status.success() => Connectivity::Online,
+        _ => Connectivity::Offline,
+    }
+}
+
+// Nobody can click a link in a window they can't see, so don't spend
+// battery probing while it is hidden or minimized.
+fn window_visible(app: &AppHandle) -> bool {
+    app.get_window("main")
+        .map(|w| w.is_visible().unwrap_or(true) && !w.is_minimized().unwrap_or(false))
+        .unwrap_or(false)
+}
+
+fn tick(app: &AppHandle) {
+    let config = app.state::<ConfigState>().get();
+    if !config.features.connectivity_checks
+        || config.connectivity_probe_url.is_empty()
+        || !window_visible(app)
+    {
+        return;
+    }
+
+    let state = app.state::<ConnectivityState>();
+    {
+        let mut last_probe = state.last_probe.lock().unwrap_or_else(|p| p.into_inner());
+        let interval = Duration::from_secs(config.connectivity_probe_interval_secs);
+        if last_probe.is_some_and(|at| at.elapsed() < interval) {
+            return;
+        }
+        *last_probe = Some(Instant::now());
+    }
+
+    let result = probe(&config.connectivity_probe_url);
+    let changed = {
+        let mut status = state.status.lock().unwrap_or_else(|p| p.into_inner());
+        let changed = status.state != result;
+        status.state = result;
+        status.checked_at = Some(chrono::Utc::now().to_rfc3339());
+        changed
+    };
+    if changed {
+        let event = match result {
+            Connectivity::Online => "online",
+            _ => "offline",
+        };
+        let _ = app.emit_all(event, ());
+    }
+}
+
+// Probe again on the next tick instead of waiting out the interval.
+pub fn refresh(app: &AppHandle) {
+    *app.state::<ConnectivityState>()
+        .last_probe
+        .lock()
+        .unwrap_or_else(|p| p.into_inner()) = None;
+}
+
+pub fn start(app: &AppHandle) {
+    let handle = app.clone();
+    app.state::<crate::background::Workers>()
+        .spawn(TICK, move || tick(&handle));
+}
+
+#[tauri::command]
+pub fn get_connectivity(state: tauri::State<ConnectivityState>) -> ConnectivityStatus {
+    state.status.lock().unwrap_or_else(|p| p.into_inner()).clone()
+}
@@ -0,0 +1,1388 @@
+// Lifecycle of the Python FastAPI core: starting it on demand and stopping it
+// with the app so it is never left running in the background, or sooner when
+// it sits idle and `core_idle_shutdown_mins` is set.
+
+use crate::background::Workers;
+use crate::breaker::{BreakerStatus, CircuitBreaker};
+use crate::cache::{self, ResponseCache};
+use crate::config::{self, AppConfig, ConfigState, CORE_LOG_LEVELS, STARTUP_TIMEOUT_RANGE};
+use crate::error::{self, AppError};
+use crate::http;
+use crate::i18n;
+use crate::paths;
+use crate::singleflight::InFlight;
+use crate::tls::CoreCert;
+use crate::validate;
+use crate::warmup::{WarmupReport, WarmupState};
+use semver::Version;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+// Preferred, not guaranteed: when something else holds it the core is
+// started on a free port instead, and `core-endpoint-changed` goes out.
+pub const CORE_PORT: u16 = 8000;
+// Tried in order. Some systems resolve `localhost` to `::1` first or have no
+// IPv4 loopback at all, so the core may only be reachable over IPv6.
+const LOOPBACK_ADDRS: [IpAddr; 2] = [
+    IpAddr::V4(Ipv4Addr::LOCALHOST),
+    IpAddr::V6(Ipv6Addr::LOCALHOST),
+];
+
+// Core versions this app can talk to: MIN inclusive, MAX exclusive.
+const MIN_CORE_VERSION: &str = "0.1.0";
+const MAX_CORE_VERSION: &str = "0.2.0";
+
+const CORE_PACKAGE: &str = "template";
+// The ASGI app uvicorn is pointed at; also how our cores are recognized in
+// the process list.
+pub const CORE_APP: &str = "template.core.main:app";
+// Relative to the package directory; must exist before we try to spawn.
+const CORE_MODULE_FILE: &str = "core/main.py";
+
+// How long the core gets to exit on SIGTERM before it is killed.
+#[cfg(unix)]
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+const HEALTH_POLL: Duration = Duration::from_millis(250);
+
+// Most recent core output kept for diagnosing failed starts.
+const OUTPUT_LIMIT: usize = 64 * 1024;
+
+const PROXY_METHODS: [&str; 6] = ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"];
+const MAX_PROXY_PATH_LEN: usize = 2048;
+const MAX_PROXY_BODY_LEN: usize = 1024 * 1024;
+// Summaries from a slow local model can legitimately take minutes.
+pub const REQUEST_TIMEOUT_RANGE: std::ops::RangeInclusive<u64> = 1..=600;
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+pub const STARTUP_PROGRESS_EVENT: &str = "core-startup-progress";
+
+// `core_idle_shutdown_mins`; 0 keeps the core running for the whole session.
+pub const IDLE_SHUTDOWN_MINS_RANGE: std::ops::RangeInclusive<u64> = 0..=24 * 60;
+const IDLE_TICK: Duration = Duration::from_secs(30);
+// Sent when a request finds the core asleep and has to start it again, so
+// the UI can show it reconnecting rather than starting from scratch.
+pub const RECONNECTING_EVENT: &str = "core-reconnecting";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoreStatus {
+    Stopped,
+    Starting,
+    Running,
+    // Ready, but outside the range of core versions this app supports.
+    VersionMismatch,
+    // Failed too many probes in a row; it isn't probed again until the
+    // breaker's cooldown has passed.
+    Unreachable,
+    // Stopped after `core_idle_shutdown_mins` without a request; the next
+    // one starts it again.
+    Sleeping,
+}
+
+#[derive(Debug)]
+pub enum CoreError {
+    // The core didn't answer its health check in time; the UI offers a retry.
+    StartupTimeout(String),
+    // A proxied request gave up waiting for a starting core, or for the
+    // core's answer.
+    Timeout(String),
+    // `cancel_request` aborted a tracked request.
+    Cancelled(String),
+    // Refused without trying, because the core keeps failing; see
+    // `get_core_status` for when it will be tried again.
+    Unreachable(String),
+    CoreNotFound { path: String },
+    VersionMismatch(String),
+    InvalidInput(String),
+    Failed(String),
+}
+
+impl fmt::Display for CoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoreError::StartupTimeout(msg)
+            | CoreError::Timeout(msg)
+            | CoreError::Cancelled(msg)
+            | CoreError::Unreachable(msg)
+            | CoreError::VersionMismatch(msg)
+            | CoreError::InvalidInput(msg)
+            | CoreError::Failed(msg) => write!(f, "{}", msg),
+            CoreError::CoreNotFound { path } => write!(
+                f,
+                "The Smartbot core was not found at {}; set `core_path` in the config to its location",
+                path
+            ),
+        }
+    }
+}
+
+impl CoreError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            CoreError::StartupTimeout(_) => "StartupTimeout",
+            CoreError::Timeout(_) => "Timeout",
+            CoreError::Cancelled(_) => "Cancelled",
+            CoreError::Unreachable(_) => "Unreachable",
+            CoreError::CoreNotFound { .. } => "CoreNotFound",
+            CoreError::VersionMismatch(_) => "VersionMismatch",
+            CoreError::InvalidInput(_) => "InvalidInput",
+            CoreError::Failed(_) => "Failed",
+        }
+    }
+
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            CoreError::CoreNotFound { path } => Some(serde_json::json!({ "path": path })),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for CoreError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        error::serialize(serializer, self.code(), &self.to_string(), self.details())
+    }
+}
+
+impl From<String> for CoreError {
+    fn from(msg: String) -> Self {
+        CoreError::Failed(msg)
+    }
+}
+
+impl From<validate::ValidationError> for CoreError {
+    fn from(e: validate::ValidationError) -> Self {
+        CoreError::InvalidInput(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupProgress {
+    pub stage: &'static str,
+    // Health-check attempt number while `waiting_for_health`.
+    pub attempt: Option<u32>,
+    pub message: String,
+    // Rough overall progress for a progress bar; not a time estimate.
+    pub percent: u8,
+    pub elapsed_ms: u128,
+}
+
+pub struct CoreState {
+    child: Mutex<Option<Child>>,
+    status: Mutex<CoreStatus>,
+    status_changed: Condvar,
+    output: Arc<Mutex<String>>,
+    // Held for the whole of `ensure`, so startup at launch, a retry from the
+    // UI and a resume probe can't each spawn their own core.
+    startup: Mutex<()>,
+    // Reported by the core's `/version` once it is ready.
+    version: Mutex<Option<String>>,
+    version_problem: Mutex<Option<String>>,
+    // The loopback address the core last answered its health check on.
+    address: Mutex<Option<IpAddr>>,
+    // The port the core was last started on; `CORE_PORT` until it has had to
+    // move.
+    port: Mutex<u16>,
+    // The base URL last announced to the frontend, to tell when it moves.
+    endpoint: Mutex<Option<String>>,
+    // Decided once per launch from `core_tls_enabled`, so the scheme can't
+    // change under a core that is already running.
+    tls: OnceLock<Option<CoreCert>>,
+    // Set once `core-unavailable` has gone out, until the core recovers.
+    unavailable: AtomicBool,
+    // Proxied requests the frontend gave an id, so `cancel_request` can
+    // abort them.
+    requests: Mutex<HashMap<String, Arc<http::Cancel>>>,
+    // Trips on status probes and proxied requests, not on the startup wait,
+    // where failed health checks are expected.
+    breaker: CircuitBreaker,
+    // For `--core-only`: the core is left running when the app exits.
+    detached: AtomicBool,
+    // When a request to the core last started or finished, and how many are
+    // still waiting on it, for the idle shutdown.
+    last_request: Mutex<Instant>,
+    pending: AtomicUsize,
+}
+
+impl Default for CoreState {
+    fn default() -> Self {
+        Self {
+            child: Mutex::new(None),
+            status: Mutex::new(CoreStatus::Stopped),
+            status_changed: Condvar::new(),
+            output: Arc::new(Mutex::new(String::new())),
+            startup: Mutex::new(()),
+            version: Mutex::new(None),
+            version_problem: Mutex::new(None),
+            address: Mutex::new(None),
+            port: Mutex::new(CORE_PORT),
+            endpoint: Mutex::new(None),
+            tls: OnceLock::new(),
+            unavailable: AtomicBool::new(false),
+            requests: Mutex::new(HashMap::new()),
+            breaker: CircuitBreaker::default(),
+            detached: AtomicBool::new(false),
+            last_request: Mutex::new(Instant::now()),
+            pending: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl CoreState {
+    fn child(&self) -> std::sync::MutexGuard<'_, Option<Child>> {
+        self.child.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Must be called before `ensure`. A detached core's output isn't captured,
+    // since nothing would be left to read it once the app is gone.
+    pub fn detach(&self) {
+        self.detached.store(true, Ordering::SeqCst);
+    }
+
+    pub fn status(&self) -> CoreStatus {
+        *self.status.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    fn set_status(&self, status: CoreStatus) {
+        *self.status.lock().unwrap_or_else(|p| p.into_inner()) = status;
+        self.status_changed.notify_all();
+    }
+
+    // Blocks while the core is starting, for at most `timeout`; returns the
+    // status it settled on (still `Starting` if it timed out).
+    pub fn wait_while_starting(&self, timeout: Duration) -> CoreStatus {
+        let status = self.status.lock().unwrap_or_else(|p| p.into_inner());
+        let (status, _) = self
+            .status_changed
+            .wait_timeout_while(status, timeout, |status| *status == CoreStatus::Starting)
+            .unwrap_or_else(|p| p.into_inner());
+        *status
+    }
+
+    pub fn version(&self) -> Option<String> {
+        self.version.lock().unwrap_or_else(|p| p.into_inner()).clone()
+    }
+
+    fn version_problem(&self) -> Option<String> {
+        self.version_problem.lock().unwrap_or_else(|p| p.into_inner()).clone()
+    }
+
+    pub fn address(&self) -> Option<IpAddr> {
+        *self.address.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    pub fn port(&self) -> u16 {
+        *self.port.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    fn set_port(&self, port: u16) {
+        *self.port.lock().unwrap_or_else(|p| p.into_inner()) = port;
+    }
+
+    fn touch(&self) {
+        *self.last_request.lock().unwrap_or_else(|p| p.into_inner()) = Instant::now();
+    }
+
+    // Keeps the idle shutdown off for as long as the guard is held, waking
+    // included, and restarts its clock when dropped.
+    fn begin_request(&self) -> PendingRequest<'_> {
+        self.touch();
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        PendingRequest(self)
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_request.lock().unwrap_or_else(|p| p.into_inner()).elapsed()
+    }
+
+    fn tls(&self) -> Option<&CoreCert> {
+        self.tls.get().and_then(Option::as_ref)
+    }
+
+    // Where the core last answered its health check; `None` before it has.
+    pub fn url(&self) -> Option<String> {
+        self.address()
+            .map(|ip| base_url(ip, self.port(), self.tls().is_some()))
+    }
+
+    // The current URL if it differs from the one last announced. The first
+    // URL is only recorded: the frontend asks for it with `get_core_base_url`.
+    fn endpoint_change(&self) -> Option<String> {
+        let url = self.url()?;
+        let mut endpoint = self.endpoint.lock().unwrap_or_else(|p| p.into_inner());
+        let changed = endpoint.as_ref().is_some_and(|old| *old != url);
+        *endpoint = Some(url.clone());
+        changed.then_some(url)
+    }
+
+    // Every call to the core goes through here so the scheme and pinned
+    // certificate match how it was started.
+    fn send(
+        &self,
+        ip: IpAddr,
+        method: &str,
+        path: &str,
+        body: Option<&str>,
+        timeout: Duration,
+        cancel: Option<&http::Cancel>,
+    ) -> Result<http::Response, String> {
+        let url = format!("{}{}", base_url(ip, self.port(), self.tls().is_some()), path);
+        let cert = self.tls().map(|tls| tls.cert.as_path());
+        http::send(method, &url, body, timeout, cert, cancel)
+    }
+
+    // Requests go wherever the core last answered, defaulting to IPv4.
+    fn send_to_core(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&str>,
+        timeout: Duration,
+        cancel: Option<&http::Cancel>,
+    ) -> Result<http::Response, String> {
+        let ip = self.address().unwrap_or(LOOPBACK_ADDRS[0]);
+        self.send(ip, method, path, body, timeout, cancel)
+    }
+
+    fn track(&self, id: &str) -> Result<Arc<http::Cancel>, CoreError> {
+        let mut requests = self.requests.lock().unwrap_or_else(|p| p.into_inner());
+        if requests.contains_key(id) {
+            return Err(CoreError::InvalidInput(format!(
+                "A request with id {:?} is already running",
+                id
+            )));
+        }
+        let cancel = Arc::new(http::Cancel::default());
+        requests.insert(id.to_string(), cancel.clone());
+        Ok(cancel)
+    }
+
+    fn untrack(&self, id: &str) {
+        self.requests.lock().unwrap_or_else(|p| p.into_inner()).remove(id);
+    }
+
+    // The pid of the core this app spawned, if it is still ours to manage.
+    pub fn child_pid(&self) -> Option<u32> {
+        self.child().as_ref().map(Child::id)
+    }
+
+    pub fn output(&self) -> String {
+        self.output.lock().unwrap_or_else(|p| p.into_inner()).clone()
+    }
+}
+
+struct PendingRequest<'a>(&'a CoreState);
+
+impl Drop for PendingRequest<'_> {
+    fn drop(&mut self) {
+        self.0.pending.fetch_sub(1, Ordering::SeqCst);
+        self.0.touch();
+    }
+}
+
+// Keep draining the core's stdout/stderr for its whole life (a full pipe
+// would stall it), retaining only the tail.
+fn capture_output(stream: impl Read + Send + 'static, output: Arc<Mutex<String>>) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(stream).lines().map_while(Result::ok) {
+            let mut output = output.lock().unwrap_or_else(|p| p.into_inner());
+            output.push_str(&line);
+            output.push('\n');
+            if output.len() > OUTPUT_LIMIT {
+                let mut cut = output.len() - OUTPUT_LIMIT;
+                while !output.is_char_boundary(cut) {
+                    cut += 1;
+                }
+                output.drain(..cut);
+            }
+        }
+    });
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CoreHealth {
+    pub status: CoreStatus,
+    // Something is listening on the core port (or our child is running).
+    pub alive: bool,
+    // `/health` returned 200: the core has finished loading and can serve.
+    pub ready: bool,
+    pub core_version: Option<String>,
+    // Which component to update when the status is `version_mismatch`.
+    pub message: Option<String>,
+    // Loopback address the health check succeeded on, e.g. "::1".
+    pub address: Option<String>,
+    pub breaker: BreakerStatus,
+    // The last `warm_core`, including the one at startup.
+    pub warmup: Option<WarmupReport>,
+}
+
+fn base_url(ip: IpAddr, port: u16, tls: bool) -> String {
+    let scheme = if tls { "https" } else { "http" };
+    // SocketAddr brackets IPv6 addresses as URLs require.
+    format!("{}://{}", scheme, SocketAddr::new(ip, port))
+}
+
+// The first loopback address for which `probe` succeeds.
+fn first_loopback(probe: impl Fn(IpAddr) -> bool) -> Option<IpAddr> {
+    LOOPBACK_ADDRS.into_iter().find(|&ip| probe(ip))
+}
+
+// uvicorn binds the first address this machine's loopback supports, which is
+// also the first one the health check tries.
+fn bind_address(can_bind: impl Fn(IpAddr) -> bool) -> IpAddr {
+    first_loopback(can_bind).unwrap_or(LOOPBACK_ADDRS[0])
+}
+
+// `CORE_PORT` if it is free on `ip`, otherwise any port the system says is.
+// Something else could still take it before uvicorn binds; the startup wait
+// then fails and the next start picks again.
+fn pick_port(ip: IpAddr, preferred: u16) -> u16 {
+    if TcpListener::bind((ip, preferred)).is_ok() {
+        return preferred;
+    }
+    TcpListener::bind((ip, 0))
+        .and_then(|listener| listener.local_addr())
+        .map_or(preferred, |addr| addr.port())
+}
+
+fn is_listening(port: u16) -> bool {
+    first_loopback(|ip| {
+        TcpStream::connect_timeout(&SocketAddr::new(ip, port), HEALTH_TIMEOUT).is_ok()
+    })
+    .is_some()
+}
+
+// Any HTTP answer on the health path, ready or not. A listener that gives
+// none isn't a core.
+fn answers_http(app: &AppHandle, state: &CoreState) -> bool {
+    let health = HealthCheck::from_config(&app.state::<ConfigState>().get());
+    first_loopback(|ip| {
+        state
+            .send(ip, "GET", &health.path, None, HEALTH_TIMEOUT, None)
+            .is_ok()
+    })
+    .is_some()
+}
+
+fn child_running(state: &CoreState) -> bool {
+    state
+        .child()
+        .as_mut()
+        .is_some_and(|child| matches!(child.try_wait(), Ok(None)))
+}
+
+pub fn is_alive(state: &CoreState) -> bool {
+    child_running(state) || is_listening(state.port())
+}
+
+// What counts as "ready", from the config: any 2xx from `health_path`,
+// optionally with a JSON body whose `health_required_field` is set (for cores
+// that answer 200 while still loading).
+struct HealthCheck {
+    path: String,
+    required_field: Option<String>,
+}
+
+impl HealthCheck {
+    fn from_config(config: &AppConfig) -> Self {
+        // A hand-edited path that could leave the core's origin is ignored.
+        let path = if validate_proxy_request("GET", &config.health_path, None).is_ok() {
+            config.health_path.clone()
+        } else {
+            config::DEFAULT_HEALTH_PATH.to_string()
+        };
+        Self {
+            path,
+            required_field: config.health_required_field.clone().filter(|field| !field.is_empty()),
+        }
+    }
+
+    fn accepts(&self, response: &http::Response) -> bool {
+        if !(200..300).contains(&response.status) {
+            return false;
+        }
+        let Some(field) = &self.required_field else {
+            return true;
+        };
+        serde_json::from_str::<serde_json::Value>(&response.body)
+            .ok()
+            .and_then(|body| body.get(field).cloned())
+            .is_some_and(|value| !value.is_null() && value != serde_json::Value::Bool(false))
+    }
+
+    // For error messages, so someone running a custom core knows what the
+    // shell was waiting for.
+    fn expectation(&self) -> String {
+        match &self.required_field {
+            Some(field) => format!(
+                "GET {} to return a 2xx JSON response with a {:?} field that isn't false or null",
+                self.path, field
+            ),
+            None => format!("GET {} to return a 2xx response", self.path),
+        }
+    }
+}
+
+fn health_ok(state: &CoreState, health: &HealthCheck, ip: IpAddr) -> bool {
+    matches!(
+        state.send(ip, "GET", &health.path, None, HEALTH_TIMEOUT, None),
+        Ok(response) if health.accepts(&response)
+    )
+}
+
+// Records the address that answered so later requests go straight to it.
+pub fn is_ready(app: &AppHandle, state: &CoreState) -> bool {
+    let health = HealthCheck::from_config(&app.state::<ConfigState>().get());
+    let address = first_loopback(|ip| health_ok(state, &health, ip));
+    if address.is_some() {
+        *state.address.lock().unwrap_or_else(|p| p.into_inner()) = address;
+    }
+    address.is_some()
+}
+
+pub const STATUS_EVENT: &str = "core-status";
+// Sent with the new base URL when a restarted core comes up somewhere else,
+// for frontend code that fetches from it directly.
+pub const ENDPOINT_CHANGED_EVENT: &str = "core-endpoint-changed";
+
+fn publish_status(app: &AppHandle, state: &CoreState, status: CoreStatus) {
+    state.set_status(status);
+    let _ = app.emit_all(STATUS_EVENT, status);
+}
+
+fn stage_percent(stage: &str) -> u8 {
+    match stage {
+        "checking_existing" => 5,
+        "selecting_port" => 15,
+        "spawning" => 25,
+        "waiting_for_health" => 30,
+        _ => 100,
+    }
+}
+
+fn emit(app: &AppHandle, progress: StartupProgress) {
+    crate::splash::on_progress(app, &progress);
+    let _ = app.emit_all(STARTUP_PROGRESS_EVENT, progress);
+}
+
+fn emit_progress(app: &AppHandle, stage: &'static str, started: Instant) {
+    emit(
+        app,
+        StartupProgress {
+            stage,
+            attempt: None,
+            message: i18n::t(app, &format!("startup.{}", stage)),
+            percent: stage_percent(stage),
+            elapsed_ms: started.elapsed().as_millis(),
+        },
+    );
+}
+
+// Waiting fills the bar from 30% towards 95% as the timeout runs down, so it
+// keeps moving without ever claiming to be done.
+fn emit_health_attempt(app: &AppHandle, attempt: u32, started: Instant, timeout: Duration) {
+    let fraction = started.elapsed().as_secs_f64() / timeout.as_secs_f64().max(1.0);
+    let percent = 30.0 + 65.0 * fraction.min(1.0);
+    emit(
+        app,
+        StartupProgress {
+            stage: "waiting_for_health",
+            attempt: Some(attempt),
+            message: i18n::t(app, "startup.waiting_for_health")
+                .replace("{attempt}", &attempt.to_string()),
+            percent: percent as u8,
+            elapsed_ms: started.elapsed().as_millis(),
+        },
+    );
+}
+
+fn fetch_version(state: &CoreState) -> Result<Version, String> {
+    let response = state.send_to_core("GET", "/version", None, HEALTH_TIMEOUT, None)?;
+    if response.status != 200 {
+        // Cores from before `/version` existed predate every supported range.
+        return Ok(Version::new(0, 0, 0));
+    }
+    let body: serde_json::Value = serde_json::from_str(&response.body)
+        .map_err(|e| format!("Invalid core version response: {}", e))?;
+    let version = body
+        .get("version")
+        .and_then(|v| v.as_str())
+        .ok_or("Core version response has no version")?;
+    Version::parse(version).map_err(|e| format!("Invalid core version {:?}: {}", version, e))
+}
+
+// None when compatible, otherwise a message naming what to update.
+pub fn version_problem(version: &Version) -> Option<String> {
+    let min = Version::parse(MIN_CORE_VERSION).expect("MIN_CORE_VERSION is valid semver");
+    let max = Version::parse(MAX_CORE_VERSION).expect("MAX_CORE_VERSION is valid semver");
+    if *version < min {
+        Some(format!(
+            "The Smartbot core is version {}, but this app needs at least {}. Please update the core.",
+            version, min
+        ))
+    } else if *version >= max {
+        Some(format!(
+            "The Smartbot core is version {}, which is newer than this app supports (below {}). Please update the desktop app.",
+            version, max
+        ))
+    } else {
+        None
+    }
+}
+
+fn on_ready(
+    app: &AppHandle,
+    state: &CoreState,
+    started: Instant,
+    message: &str,
+) -> Result<String, CoreError> {
+    let version = fetch_version(state)?;
+    let problem = version_problem(&version);
+    *state.version.lock().unwrap_or_else(|p| p.into_inner()) = Some(version.to_string());
+    *state.version_problem.lock().unwrap_or_else(|p| p.into_inner()) = problem.clone();
+    if let Some(problem) = problem {
+        publish_status(app, state, CoreStatus::VersionMismatch);
+        emit_progress(app, "failed", started);
+        return Err(CoreError::VersionMismatch(problem));
+    }
+    state.breaker.reset();
+    publish_status(app, state, CoreStatus::Running);
+    if let Some(url) = state.endpoint_change() {
+        let _ = app.emit_all(ENDPOINT_CHANGED_EVENT, serde_json::json!({ "base_url": url }));
+    }
+    emit_progress(app, "ready", started);
+    Ok(message.to_string())
+}
+
+pub const UNAVAILABLE_EVENT: &str = "core-unavailable";
+pub const AVAILABLE_EVENT: &str = "core-available";
+
+// What the app can still do on its own, announced with `core-unavailable` so
+// the UI can switch to an offline mode instead of looking broken. None of
+// these commands talk to the core.
+pub const LOCAL_CAPABILITIES: &[&str] = &[
+    "journal",
+    "mood_trend",
+    "reminders",
+    "backup",
+    "export",
+    "crisis_resources",
+];
+
+// `core-unavailable` goes out on every failed start, since the reason can
+// change between retries; `core-available` only once the core comes back.
+fn announce_availability(app: &AppHandle, state: &CoreState, result: &Result<String, CoreError>) {
+    match result {
+        Ok(_) => {
+            if state.unavailable.swap(false, Ordering::SeqCst) {
+                let _ = app.emit_all(AVAILABLE_EVENT, state.version());
+            }
+        }
+        Err(e) => {
+            state.unavailable.store(true, Ordering::SeqCst);
+            let _ = app.emit_all(
+                UNAVAILABLE_EVENT,
+                serde_json::json!({
+                    "reason": e.to_string(),
+                    "error": e,
+                    "capabilities": LOCAL_CAPABILITIES,
+                }),
+            );
+        }
+    }
+}
+
+pub fn ensure(app: &AppHandle) -> Result<String, CoreError> {
+    let state = app.state::<CoreState>();
+    let result = start(app, &state);
+    announce_availability(app, &state, &result);
+    result
+}
+
+fn start(app: &AppHandle, state: &CoreState) -> Result<String, CoreError> {
+    let _startup = state.startup.lock().unwrap_or_else(|p| p.into_inner());
+    if state.tls.get().is_none() {
+        let tls = if app.state::<ConfigState>().get().core_tls_enabled {
+            Some(CoreCert::in_dir(&paths::data_dir(app)?))
+        } else {
+            None
+        };
+        let _ = state.tls.set(tls);
+    }
+    let started = Instant::now();
+    emit_progress(app, "checking_existing", started);
+
+    if is_ready(app, state) {
+        return on_ready(app, state, started, "Core already running");
+    }
+
+    // A core that is up but still loading (ours or one started elsewhere)
+    // only needs waiting for; spawning another would fight it for the port.
+    // Anything else holding the port just means ours starts on another.
+    if child_running(state) || (is_listening(state.port()) && answers_http(app, state)) {
+        publish_status(app, state, CoreStatus::Starting);
+    } else {
+        spawn(app, state, started)?;
+    }
+
+    let timeout = app.state::<ConfigState>().get().startup_timeout();
+    wait_until_ready(app, state, started, timeout)
+}
+
+// The `template` package directory, checked in order: the configured
+// `core_path`, the bundled resources, then the dev checkout layout.
+fn core_dir_candidates(app: &AppHandle) -> Result<Vec<PathBuf>, CoreError> {
+    let mut candidates = Vec::new();
+    if let Some(path) = app.state::<ConfigState>().get().core_path {
+        candidates.push(PathBuf::from(path));
+    }
+    if let Some(dir) = app.path_resolver().resource_dir() {
+        candidates.push(dir.join(CORE_PACKAGE));
+    }
+    candidates.push(
+        std::env::current_dir()
+            .map_err(|e| format!("Failed to get current directory: {}", e))?
+            .join("../../../template"),
+    );
+    Ok(candidates)
+}
+
+pub fn resolve_core_dir(candidates: &[PathBuf]) -> Result<PathBuf, CoreError> {
+    for candidate in candidates {
+        if let Ok(dir) = candidate.canonicalize() {
+            if dir.join(CORE_MODULE_FILE).is_file() {
+                return Ok(dir);
+            }
+        }
+    }
+    let reported = candidates.first().cloned().unwrap_or_default();
+    let path = std::path::absolute(&reported).unwrap_or(reported);
+    Err(CoreError::CoreNotFound {
+        path: path.display().to_string(),
+    })
+}
+
+fn spawn(app: &AppHandle, state: &CoreState, started: Instant) -> Result<(), CoreError> {
+    let core_dir = resolve_core_dir(&core_dir_candidates(app)?)?;
+    // `template.core.main` is importable from the package's parent.
+    let core_path = core_dir.parent().unwrap_or(&core_dir).to_path_buf();
+
+    publish_status(app, state, CoreStatus::Starting);
+    emit_progress(app, "selecting_port", started);
+    let ip = bind_address(|ip| TcpListener::bind((ip, 0)).is_ok());
+    let port = pick_port(ip, CORE_PORT);
+    if port != CORE_PORT {
+        eprintln!("Port {} is in use; starting the core on {}", CORE_PORT, port);
+    }
+    state.set_port(port);
+    let host = ip.to_string();
+    let port = port.to_string();
+    emit_progress(app, "spawning", started);
+    state.output.lock().unwrap_or_else(|p| p.into_inner()).clear();
+    let log_level = app.state::<ConfigState>().get().core_log_level().to_string();
+    let mut command = Command::new("python");
+    command
+        .args(["-m", "uvicorn", CORE_APP, "--host", &host, "--port", &port])
+        .args(["--log-level", &log_level])
+        .current_dir(&core_path);
+    if state.detached.load(Ordering::SeqCst) {
+        command.stdout(Stdio::null()).stderr(Stdio::null());
+    } else {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    if let Some(tls) = state.tls() {
+        tls.ensure()?;
+        command
+            .arg("--ssl-keyfile")
+            .arg(&tls.key)
+            .arg("--ssl-certfile")
+            .arg(&tls.cert);
+    }
+    // Keep the core's state alongside ours when the data dir is redirected.
+    if paths::is_overridden() {
+        command.env(paths::DATA_DIR_ENV, paths::data_dir(app)?);
+    }
+    let mut child = command.spawn().map_err(|e| {
+        publish_status(app, state, CoreStatus::Stopped);
+        format!("Failed to start core: {}. Please ensure Python and dependencies are installed.", e)
+    })?;
+    if let Some(stdout) = child.stdout.take() {
+        capture_output(stdout, state.output.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        capture_output(stderr, state.output.clone());
+    }
+    *state.child() = Some(child);
+    Ok(())
+}
+
+fn wait_until_ready(
+    app: &AppHandle,
+    state: &CoreState,
+    started: Instant,
+    timeout: Duration,
+) -> Result<String, CoreError> {
+    let deadline = started + timeout;
+    let mut attempt = 0;
+    while Instant::now() < deadline {
+        attempt += 1;
+        emit_health_attempt(app, attempt, started, timeout);
+        if is_ready(app, state) {
+            return on_ready(app, state, started, "Core started");
+        }
+        let spawned = state.child().is_some();
+        if spawned && !child_running(state) {
+            state.child().take();
+            publish_status(app, state, CoreStatus::Stopped);
+            emit_progress(app, "failed", started);
+            eprintln!("Core exited during startup. Output:\n{}", state.output());
+            return Err(CoreError::Failed(
+                "The core exited during startup; see the log for its output".to_string(),
+            ));
+        }
+        std::thread::sleep(HEALTH_POLL);
+    }
+
+    // Don't leave a half-started core holding the port for the retry.
+    if let Some(mut child) = state.child().take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+    publish_status(app, state, CoreStatus::Stopped);
+    emit_progress(app, "failed", started);
+    eprintln!(
+        "Core did not become ready within {}s. Output:\n{}",
+        timeout.as_secs(),
+        state.output()
+    );
+    let health = HealthCheck::from_config(&app.state::<ConfigState>().get());
+    Err(CoreError::StartupTimeout(format!(
+        "The core did not start within {} seconds (waiting for {}; set `health_path` in the config if your core uses a different endpoint)",
+        timeout.as_secs(),
+        health.expectation()
+    )))
+}
+
+// Ask the core to exit cleanly so uvicorn can finish in-flight requests and
+// the bandit state is flushed, then force it if it doesn't stop in time.
+pub fn shutdown(state: &CoreState) {
+    state.set_status(CoreStatus::Stopped);
+    stop_child(state);
+}
+
+fn stop_child(state: &CoreState) {
+    let Some(mut child) = state.child().take() else {
+        return;
+    };
+    if state.detached.load(Ordering::SeqCst) {
+        return;
+    }
+
+    #[cfg(unix)]
+    {
+        let _ = Command::new("kill")
+            .args(["-TERM", &child.id().to_string()])
+            .status();
+        let deadline = Instant::now() + SHUTDOWN_GRACE;
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = child.try_wait() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+// Only a core this app spawned is put to sleep: one started elsewhere isn't
+// ours to stop, and a detached one is meant to outlive the app. The startup
+// lock keeps a wake-up from racing the shutdown.
+fn idle_tick(app: &AppHandle) {
+    let mins = app.state::<ConfigState>().get().core_idle_shutdown_mins;
+    let state = app.state::<CoreState>();
+    let idle = || {
+        state.pending.load(Ordering::SeqCst) == 0
+            && state.idle_for() >= Duration::from_secs(mins * 60)
+    };
+    if mins == 0
+        || state.detached.load(Ordering::SeqCst)
+        || !matches!(state.status(), CoreStatus::Running | CoreStatus::VersionMismatch)
+        || !idle()
+    {
+        return;
+    }
+    let Ok(_startup) = state.startup.try_lock() else {
+        return;
+    };
+    // Checked again now that a start can't be under way.
+    if !child_running(&state) || !idle() {
+        return;
+    }
+    // Published before stopping, so requests arriving meanwhile wake it
+    // rather than going to a core that is on its way out.
+    publish_status(app, &state, CoreStatus::Sleeping);
+    stop_child(&state);
+}
+
+// The setting is re-read every tick, so changing it needs no restart.
+pub fn watch_idle(app: &AppHandle) {
+    let handle = app.clone();
+    app.state::<Workers>().spawn(IDLE_TICK, move || idle_tick(&handle));
+}
+
+// Starts a core the idle shutdown stopped. Waiting for the startup lock also
+// covers a shutdown still in progress.
+fn wake(app: &AppHandle, state: &CoreState) -> Result<(), String> {
+    if state.status() != CoreStatus::Sleeping {
+        return Ok(());
+    }
+    let _ = app.emit_all(RECONNECTING_EVENT, ());
+    ensure(app).map(|_| ()).map_err(|e| e.to_string())
+}
+
+// Tauri command to ensure the FastAPI core is running
+#[tauri::command]
+pub async fn ensure_core(app: AppHandle) -> Result<String, CoreError> {
+    tauri::async_runtime::spawn_blocking(move || ensure(&app))
+        .await
+        .map_err(|e| CoreError::Failed(format!("Core startup failed: {}", e)))?
+}
+
+// Where the core can be reached directly, for code that doesn't go through
+// `core_request`; `None` until it has answered once. Listen for
+// `core-endpoint-changed` rather than caching it for the whole session.
+#[tauri::command]
+pub fn get_core_base_url(state: tauri::State<CoreState>) -> Option<String> {
+    state.url()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Versions {
+    pub app: String,
+    pub core: Option<String>,
+    pub warning: Option<String>,
+}
+
+fn core_version(app: &AppHandle) -> Result<String, String> {
+    let state = app.state::<CoreState>();
+    let version = fetch_version(&state)?.to_string();
+    *state.version.lock().unwrap_or_else(|p| p.into_inner()) = Some(version.clone());
+    Ok(version)
+}
+
+// Only the major versions are compared; finer-grained compatibility is
+// enforced by the supported range checked at startup.
+fn major_divergence(app_version: &str, core_version: &str) -> Option<String> {
+    let app = Version::parse(app_version).ok()?;
+    let core = Version::parse(core_version).ok()?;
+    (app.major != core.major).then(|| {
+        format!(
+            "The desktop app ({}) and the core ({}) are from different major versions; update whichever is older.",
+            app, core
+        )
+    })
+}
+
+#[tauri::command]
+pub async fn get_core_version(app: AppHandle) -> Result<String, AppError> {
+    let version = tauri::async_runtime::spawn_blocking(move || core_version(&app))
+        .await
+        .map_err(|e| format!("Core version check failed: {}", e))??;
+    Ok(version)
+}
+
+// The core version is `None` (with a warning) when the core can't be reached.
+#[tauri::command]
+pub async fn get_versions(app: AppHandle) -> Result<Versions, AppError> {
+    let core = tauri::async_runtime::spawn_blocking(move || core_version(&app))
+        .await
+        .map_err(|e| format!("Core version check failed: {}", e))?;
+    let app_version = env!("CARGO_PKG_VERSION").to_string();
+    let (core, warning) = match core {
+        Ok(core) => {
+            let warning = major_divergence(&app_version, &core);
+            (Some(core), warning)
+        }
+        Err(e) => (None, Some(format!("Couldn't read the core version: {}", e))),
+    };
+    Ok(Versions {
+        app: app_version,
+        core,
+        warning,
+    })
+}
+
+// Live adjustment for slow machines; applies to the next startup wait and
+// proxied request.
+#[tauri::command]
+pub fn set_startup_timeout(app: AppHandle, secs: u64) -> Result<u64, AppError> {
+    validate::number("The startup timeout (seconds)", secs, STARTUP_TIMEOUT_RANGE)?;
+    config::update(&app, |config| config.startup_timeout_secs = secs)?;
+    Ok(secs)
+}
+
+// Restarts the core so the new level applies; returns the status it ends up
+// in.
+#[tauri::command]
+pub async fn set_core_log_level(app: AppHandle, level: String) -> Result<CoreStatus, CoreError> {
+    let level = level.to_lowercase();
+    if !CORE_LOG_LEVELS.contains(&level.as_str()) {
+        return Err(CoreError::InvalidInput(format!(
+            "Unknown log level {:?}; expected one of {}",
+            level,
+            CORE_LOG_LEVELS.join(", ")
+        )));
+    }
+    config::update(&app, |config| config.core_log_level = level)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<CoreState>();
+        shutdown(&state);
+        ensure(&app)?;
+        Ok(state.status())
+    })
+    .await
+    .map_err(|e| CoreError::Failed(format!("Core restart failed: {}", e)))?
+}
+
+fn unreachable_message(state: &CoreState) -> String {
+    let retry_in = state.breaker.status().retry_in_ms.unwrap_or(0);
+    format!(
+        "The core isn't responding; it will be tried again in {} seconds",
+        retry_in.div_ceil(1000)
+    )
+}
+
+// Probes run off the main thread since an unresponsive core can take up to
+// the health timeout to answer. While the breaker is open nothing is probed
+// and the status is `unreachable`.
+#[tauri::command]
+pub async fn get_core_status(app: AppHandle) -> Result<CoreHealth, AppError> {
+    let health = tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<CoreState>();
+        if !state.breaker.allows() {
+            return CoreHealth {
+                status: CoreStatus::Unreachable,
+                alive: false,
+                ready: false,
+                core_version: state.version(),
+                message: Some(unreachable_message(&state)),
+                address: None,
+                breaker: state.breaker.status(),
+                warmup: app.state::<WarmupState>().last(),
+            };
+        }
+        let ready = is_ready(&app, &state);
+        // Only a core we expect to answer counts against it; probing one
+        // that is stopped or still starting says nothing new.
+        if matches!(state.status(), CoreStatus::Running | CoreStatus::VersionMismatch) {
+            state.breaker.record(ready);
+        }
+        CoreHealth {
+            status: state.status(),
+            alive: is_alive(&state),
+            ready,
+            core_version: state.version(),
+            message: state.version_problem(),
+            address: ready.then(|| state.address()).flatten().map(|ip| ip.to_string()),
+            breaker: state.breaker.status(),
+            warmup: app.state::<WarmupState>().last(),
+        }
+    })
+    .await
+    .map_err(|e| format!("Core status check failed: {}", e))?;
+    Ok(health)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CoreResponse {
+    pub status: u16,
+    pub body: serde_json::Value,
+}
+
+impl From<http::Response> for CoreResponse {
+    // The core speaks JSON; anything else is passed through as a string.
+    fn from(response: http::Response) -> Self {
+        let body = serde_json::from_str(&response.body)
+            .unwrap_or(serde_json::Value::String(response.body));
+        Self {
+            status: response.status,
+            body,
+        }
+    }
+}
+
+pub fn request(
+    app: &AppHandle,
+    method: &str,
+    path: &str,
+    body: Option<&serde_json::Value>,
+    timeout: Duration,
+    cancel: Option<&http::Cancel>,
+) -> Result<CoreResponse, String> {
+    let method = method.to_uppercase();
+    let config = app.state::<ConfigState>().get();
+    let cache = app.state::<ResponseCache>();
+    let key = cache::key(&method, path);
+    let ttl = Duration::from_secs(config.core_cache_ttl_secs);
+    let cacheable = method == "GET"
+        && config
+            .core_cache_endpoints
+            .iter()
+            .any(|endpoint| path.starts_with(endpoint.as_str()));
+
+    if cacheable {
+        if let Some(cached) = cache.get(&key, ttl) {
+            return Ok(cached.into());
+        }
+    } else if method != "GET" && method != "HEAD" {
+        cache.invalidate_resource(path);
+    }
+
+    let state = app.state::<CoreState>();
+    if !state.breaker.allows() {
+        return Err(unreachable_message(&state));
+    }
+    let _pending = state.begin_request();
+    wake(app, &state)?;
+    let body = body.map(|b| b.to_string());
+    let send = || state.send_to_core(&method, path, body.as_deref(), timeout, cancel);
+    // Reads carry no body, so method and path identify them completely. A
+    // cancellable read runs on its own: cancelling it mustn't fail callers
+    // that were only sharing its result.
+    let response = if method == "GET" && cancel.is_none() {
+        app.state::<InFlight>().run(&key, send)
+    } else {
+        send()
+    };
+    // Only a transport failure counts: an error status is still an answer,
+    // and a cancelled request never got the chance.
+    if !cancel.is_some_and(http::Cancel::is_cancelled) {
+        state.breaker.record(response.is_ok());
+    }
+    let response = response?;
+
+    // Only successful reads are worth replaying.
+    if cacheable && (200..300).contains(&response.status) {
+        cache.insert(key, response.clone(), config.core_cache_max_entries);
+    }
+    Ok(response.into())
+}
+
+// The path is appended to the core's origin, so anything but a plain
+// absolute path (`//host`, `..`) could point the request somewhere else.
+pub fn validate_proxy_request(
+    method: &str,
+    path: &str,
+    body: Option<&serde_json::Value>,
+) -> Result<(), validate::ValidationError> {
+    if !PROXY_METHODS.contains(&method.to_uppercase().as_str()) {
+        return Err(validate::ValidationError::Invalid(format!("Unsupported method {:?}", method)));
+    }
+    validate::text("Path", path, MAX_PROXY_PATH_LEN)?;
+    let bad_path = !path.starts_with('/')
+        || path.starts_with("//")
+        || path.chars().any(|c| c.is_whitespace() || c.is_control() || c == '\\')
+        || path.split(['/', '?', '#']).any(|segment| segment == "..");
+    if bad_path {
+        return Err(validate::ValidationError::InvalidPath(format!("Invalid core path {:?}", path)));
+    }
+    if let Some(body) = body {
+        validate::text("Request body", &body.to_string(), MAX_PROXY_BODY_LEN)?;
+    }
+    Ok(())
+}
+
+// Proxy a request to the core so the webview never talks to it directly.
+// Requests made while the core is still starting are held until it is ready
+// rather than failing, so early frontend calls don't each need a retry. One
+// that finds it asleep starts it again and waits the same way.
+#[tauri::command]
+pub async fn core_request(
+    app: AppHandle,
+    method: String,
+    path: String,
+    body: Option<serde_json::Value>,
+    timeout_secs: Option<u64>,
+    request_id: Option<String>,
+) -> Result<CoreResponse, CoreError> {
+    validate_proxy_request(&method, &path, body.as_ref())?;
+    if let Some(secs) = timeout_secs {
+        validate::number("Timeout", secs, REQUEST_TIMEOUT_RANGE)?;
+    }
+    if let Some(id) = &request_id {
+        validate::identifier("Request id", id, MAX_REQUEST_ID_LEN)?;
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let startup_timeout = app.state::<ConfigState>().get().startup_timeout();
+        let state = app.state::<CoreState>();
+        if !state.breaker.allows() {
+            return Err(CoreError::Unreachable(unreachable_message(&state)));
+        }
+        if state.wait_while_starting(startup_timeout) == CoreStatus::Starting {
+            return Err(CoreError::Timeout(format!(
+                "The core was still starting after {} seconds",
+                startup_timeout.as_secs()
+            )));
+        }
+
+        let timeout = timeout_secs.map_or(startup_timeout, Duration::from_secs);
+        let cancel = request_id.as_deref().map(|id| state.track(id)).transpose()?;
+        let started = Instant::now();
+        let result = request(&app, &method, &path, body.as_ref(), timeout, cancel.as_deref());
+        if let Some(id) = &request_id {
+            state.untrack(id);
+        }
+        result.map_err(|e| {
+            if cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled()) {
+                CoreError::Cancelled(format!("{} {} was cancelled", method, path))
+            } else if started.elapsed() >= timeout {
+                CoreError::Timeout(format!(
+                    "The core didn't answer {} {} within {} seconds",
+                    method,
+                    path,
+                    timeout.as_secs()
+                ))
+            } else {
+                CoreError::Failed(e)
+            }
+        })
+    })
+    .await
+    .map_err(|e| CoreError::Failed(format!("Core request failed: {}", e)))?
+}
+
+// Returns whether a request with that id was still running.
+#[tauri::command]
+pub fn cancel_request(state: tauri::State<CoreState>, id: String) -> bool {
+    let requests = state.requests.lock().unwrap_or_else(|p| p.into_inner());
+    match requests.get(&id) {
+        Some(cancel) => {
+            cancel.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_core_dir_reports_absolute_path() {
+        let missing = PathBuf::from("definitely/not/a/core/dir");
+        let expected = std::env::current_dir().unwrap().join(&missing);
+
+        match resolve_core_dir(&[missing]) {
+            Err(CoreError::CoreNotFound { path }) => assert_eq!(path, expected.display().to_string()),
+            other => panic!("expected CoreNotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn proxy_rejects_paths_that_leave_the_core_api() {
+        for path in ["@evil.example/", "//evil.example/", "/../../etc/passwd", "/a b", ""] {
+            assert!(
+                matches!(
+                    validate_proxy_request("GET", path, None),
+                    Err(validate::ValidationError::InvalidPath(_))
+                ),
+                "{:?} was accepted",
+                path
+            );
+        }
+        assert!(validate_proxy_request("TRACE", "/health", None).is_err());
+        assert!(validate_proxy_request("get", "/recommendations?limit=5", None).is_ok());
+    }
+
+    #[test]
+    fn health_check_accepts_any_2xx_and_can_require_a_field() {
+        let response = |status, body: &str| http::Response {
+            status,
+            body: body.to_string(),
+        };
+        let mut config = AppConfig::default();
+        let health = HealthCheck::from_config(&config);
+        assert!(health.accepts(&response(204, "")));
+        assert!(!health.accepts(&response(503, r#"{"ready": false}"#)));
+
+        config.health_required_field = Some("ready".to_string());
+        config.health_path = "//evil.example/".to_string();
+        let health = HealthCheck::from_config(&config);
+        assert_eq!(health.path, config::DEFAULT_HEALTH_PATH);
+        assert!(health.accepts(&response(200, r#"{"ready": true}"#)));
+        assert!(!health.accepts(&response(200, r#"{"ready": false}"#)));
+        assert!(!health.accepts(&response(200, "not json")));
+    }
+
+    #[test]
+    fn ipv6_only_loopback_falls_back_to_ipv6() {
+        let ipv6_only = |ip: IpAddr| ip.is_ipv6();
+
+        let answered = first_loopback(ipv6_only);
+        assert_eq!(answered, Some(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+        assert_eq!(base_url(answered.unwrap(), CORE_PORT, false), "http://[::1]:8000");
+        assert_eq!(bind_address(ipv6_only), IpAddr::V6(Ipv6Addr::LOCALHOST));
+        assert_eq!(bind_address(|_| true), IpAddr::V4(Ipv4Addr::LOCALHOST));
+    }
+
+    #[test]
+    fn restarted_core_on_a_new_port_is_followed() {
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let state = CoreState::default();
+        *state.address.lock().unwrap() = Some(ip);
+        assert_eq!(state.endpoint_change(), None);
+
+        // Something that isn't the core takes the port it was on.
+        let taken = TcpListener::bind((ip, 0)).unwrap();
+        let taken_port = taken.local_addr().unwrap().port();
+        let port = pick_port(ip, taken_port);
+        assert_ne!(port, taken_port);
+
+        // A stand-in core answering its health check on the new port.
+        let core = TcpListener::bind((ip, port)).unwrap();
+        std::thread::spawn(move || {
+            for mut stream in core.incoming().map_while(Result::ok) {
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request);
+                let _ = std::io::Write::write_all(
+                    &mut stream,
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}",
+                );
+            }
+        });
+        state.set_port(port);
+
+        let health = HealthCheck::from_config(&AppConfig::default());
+        assert!(health_ok(&state, &health, ip));
+        let url = format!("http://127.0.0.1:{}", port);
+        assert_eq!(state.url().as_deref(), Some(url.as_str()));
+        assert_eq!(state.endpoint_change(), Some(url));
+        assert_eq!(state.endpoint_change(), None);
+    }
+
+    #[test]
+    fn requests_in_flight_hold_off_the_idle_shutdown() {
+        let state = CoreState::default();
+        {
+            let _first = state.begin_request();
+            let _second = state.begin_request();
+            assert_eq!(state.pending.load(Ordering::SeqCst), 2);
+        }
+        assert_eq!(state.pending.load(Ordering::SeqCst), 0);
+        assert!(state.idle_for() < Duration::from_secs(1));
+    }
+}
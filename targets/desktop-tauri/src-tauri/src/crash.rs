@@ -0,0 +1,196 @@
+// Panic reports written to the app log directory. They never leave the
+// device; users can attach them when asking for help. Old ones are pruned at
+// launch, and `purge_logs` deletes the rest on request.
+//
+// The only other log the shell writes is each profile's audit log
+// (`audit.rs`), which `purge_logs` leaves alone on purpose: a record of
+// exports and wipes that can be deleted on request proves nothing. It caps
+// its own size by rotating instead.
+
+use crate::disk;
+use crate::error::AppError;
+use crate::i18n;
+use serde::Serialize;
+use std::backtrace::Backtrace;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+
+const REPORT_PREFIX: &str = "crash-";
+const REPORT_EXTENSION: &str = "txt";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PurgeReport {
+    pub files_removed: usize,
+    pub bytes_freed: u64,
+    pub freed: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashReport {
+    pub path: String,
+    pub created_at: String,
+    pub size_bytes: u64,
+    // The panic message line, for listing reports without opening them.
+    pub summary: String,
+}
+
+fn report_dir(app: &AppHandle) -> Option<PathBuf> {
+    crate::paths::log_dir(app).map(|dir| dir.join("crashes"))
+}
+
+fn is_report(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == REPORT_EXTENSION)
+        && path
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with(REPORT_PREFIX))
+}
+
+fn reports(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| is_report(p))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn write_report(dir: &Path, info: &std::panic::PanicHookInfo<'_>) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let now = chrono::Utc::now();
+    let path = dir.join(format!(
+        "{}{}.{}",
+        REPORT_PREFIX,
+        now.format("%Y%m%dT%H%M%S%.3fZ"),
+        REPORT_EXTENSION
+    ));
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Unknown panic".to_string());
+    let location = info
+        .location()
+        .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+        .unwrap_or_else(|| "unknown".to_string());
+    let report = format!(
+        "panic: {}\nlocation: {}\nthread: {}\ntime: {}\nversion: {}\nos: {}\n\nbacktrace:\n{}\n",
+        message,
+        location,
+        std::thread::current().name().unwrap_or("unnamed"),
+        now.to_rfc3339(),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        Backtrace::force_capture()
+    );
+    std::fs::write(&path, report)?;
+    Ok(path)
+}
+
+// Keeps the default hook so panics still reach stderr during development.
+pub fn install(app: &AppHandle) {
+    let Some(dir) = report_dir(app) else {
+        return;
+    };
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Err(e) = write_report(&dir, info) {
+            eprintln!("Failed to write crash report: {}", e);
+        }
+        default_hook(info);
+    }));
+}
+
+pub fn prune(app: &AppHandle, max_age: Duration) {
+    let Some(dir) = report_dir(app) else {
+        return;
+    };
+    let Some(cutoff) = SystemTime::now().checked_sub(max_age) else {
+        return;
+    };
+    for path in reports(&dir) {
+        let expired = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| modified < cutoff);
+        if expired {
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
+
+// Newest first.
+#[tauri::command]
+pub fn get_crash_reports(app: AppHandle) -> Result<Vec<CrashReport>, AppError> {
+    let dir = report_dir(&app).ok_or("Could not resolve the app log directory")?;
+    let mut reports: Vec<(SystemTime, CrashReport)> = reports(&dir)
+        .into_iter()
+        .filter_map(|path| {
+            let metadata = std::fs::metadata(&path).ok()?;
+            let modified = metadata.modified().ok()?;
+            let summary = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|c| c.lines().next().map(|l| l.to_string()))
+                .unwrap_or_default();
+            Some((
+                modified,
+                CrashReport {
+                    path: path.display().to_string(),
+                    created_at: chrono::DateTime::<chrono::Utc>::from(modified).to_rfc3339(),
+                    size_bytes: metadata.len(),
+                    summary,
+                },
+            ))
+        })
+        .collect();
+    reports.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    Ok(reports.into_iter().map(|(_, report)| report).collect())
+}
+
+// Deletes every crash report once the user confirms. Returns `None` if they
+// decline. Each report is written in one go when it is created, so there is
+// never one half-written to lose.
+#[tauri::command]
+pub async fn purge_logs(app: AppHandle) -> Result<Option<PurgeReport>, AppError> {
+    let dir = report_dir(&app).ok_or("Could not resolve the app log directory")?;
+    let files: Vec<(PathBuf, u64)> = reports(&dir)
+        .into_iter()
+        .map(|path| {
+            let size = std::fs::metadata(&path).map_or(0, |m| m.len());
+            (path, size)
+        })
+        .collect();
+    if !files.is_empty() {
+        let window = app.get_window("main");
+        let total: u64 = files.iter().map(|(_, size)| size).sum();
+        let confirmed = tauri::api::dialog::blocking::confirm(
+            window.as_ref(),
+            i18n::t(&app, "purge_logs.title"),
+            i18n::t(&app, "purge_logs.body")
+                .replace("{count}", &files.len().to_string())
+                .replace("{size}", &disk::format_bytes(total)),
+        );
+        if !confirmed {
+            return Ok(None);
+        }
+    }
+
+    let mut files_removed = 0;
+    let mut bytes_freed = 0;
+    for (path, size) in files {
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                files_removed += 1;
+                bytes_freed += size;
+            }
+            Err(e) => eprintln!("Failed to delete {}: {}", path.display(), e),
+        }
+    }
+    Ok(Some(PurgeReport {
+        files_removed,
+        bytes_freed,
+        freed: disk::format_bytes(bytes_freed),
+    }))
+}
@@ -0,0 +1,97 @@
+// What happens when a folder the app keeps its files in can't be used, e.g.
+// after its permissions changed or on a read-only volume. Instead of every
+// storage command failing with "Storage is not open", the user is told which
+// folder and why, with a dialog and `storage-unavailable`, and can pick
+// another one, which is remembered for later launches. Turning that down
+// leaves the app running without local storage: the window and the core still
+// start, so crisis resources stay reachable. The exception is a core with
+// `core_tls_enabled`, whose certificate lives in the data folder; the fallback
+// page covers that case.
+
+use crate::paths::{self, DirUnavailable};
+use std::sync::Mutex;
+use tauri::api::dialog::blocking::{self as dialog, FileDialogBuilder};
+use tauri::{AppHandle, Manager, Runtime, Window};
+
+pub const STORAGE_UNAVAILABLE_EVENT: &str = "storage-unavailable";
+
+// Set in `setup` when the app carries on without a usable folder.
+#[derive(Default)]
+pub struct StorageUnavailableState(Mutex<Option<DirUnavailable>>);
+
+impl StorageUnavailableState {
+    fn get(&self) -> Option<DirUnavailable> {
+        self.0.lock().unwrap_or_else(|p| p.into_inner()).clone()
+    }
+}
+
+fn offer_another(problem: &DirUnavailable) -> Option<std::path::PathBuf> {
+    let accepted = dialog::confirm(
+        None::<&Window>,
+        "Smartbot can't use its data folder",
+        format!(
+            "{}.\n\nChoose another folder for Smartbot's data? Otherwise Smartbot opens without saving anything.",
+            problem
+        ),
+    );
+    if !accepted {
+        return None;
+    }
+    FileDialogBuilder::new()
+        .set_title("Choose a folder for Smartbot's data")
+        .pick_folder()
+}
+
+// Runs in `setup` before anything resolves a path, so everything opened
+// afterwards uses the folder chosen here. Returns whether the app has a
+// usable folder.
+pub fn check(app: &AppHandle) -> bool {
+    paths::load_chosen(app);
+    let mut problem = match paths::check_dirs(app) {
+        Ok(()) => return true,
+        Err(problem) => problem,
+    };
+    loop {
+        eprintln!("{}", problem);
+        // No page is listening yet; `on_page_load` repeats it.
+        let _ = app.emit_all(STORAGE_UNAVAILABLE_EVENT, &problem);
+        let Some(dir) = offer_another(&problem) else {
+            *app.state::<StorageUnavailableState>()
+                .0
+                .lock()
+                .unwrap_or_else(|p| p.into_inner()) = Some(problem);
+            return false;
+        };
+        match paths::choose(&dir) {
+            Ok(()) => break,
+            Err(e) => problem = e,
+        }
+    }
+    if let Err(e) = paths::remember_chosen(app) {
+        eprintln!("Failed to remember the data folder: {}", e);
+        dialog::message(
+            None::<&Window>,
+            "Smartbot will ask again next time",
+            format!(
+                "Smartbot will use the folder you chose until it closes, but couldn't save that choice: {}",
+                e
+            ),
+        );
+    }
+    true
+}
+
+// Sent on every load of the main window, not once, since the state lasts
+// until the app restarts.
+pub fn on_page_load<R: Runtime>(window: &Window<R>) {
+    if let Some(problem) = window.state::<StorageUnavailableState>().get() {
+        let _ = window.emit(STORAGE_UNAVAILABLE_EVENT, &problem);
+    }
+}
+
+#[tauri::command]
+pub fn get_storage_unavailable(
+    state: tauri::State<StorageUnavailableState>,
+) -> Option<DirUnavailable> {
+    state.get()
+}
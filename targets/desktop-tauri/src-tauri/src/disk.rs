@@ -0,0 +1,143 @@
+// Free-space checks before writes, so a nearly full disk fails cleanly
+// instead of leaving a half-written database, backup or export.
+
+use crate::config::ConfigState;
+use crate::error;
+use crate::validate::{self, ValidationError};
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+const MB: u64 = 1024 * 1024;
+
+#[derive(Debug)]
+pub enum DiskError {
+    LowDiskSpace(String),
+    InvalidInput(String),
+    Failed(String),
+}
+
+impl fmt::Display for DiskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiskError::LowDiskSpace(msg)
+            | DiskError::InvalidInput(msg)
+            | DiskError::Failed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl DiskError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            DiskError::LowDiskSpace(_) => "LowDiskSpace",
+            DiskError::InvalidInput(_) => "InvalidInput",
+            DiskError::Failed(_) => "Failed",
+        }
+    }
+}
+
+impl Serialize for DiskError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        error::serialize(serializer, self.code(), &self.to_string(), None)
+    }
+}
+
+impl From<ValidationError> for DiskError {
+    fn from(e: ValidationError) -> Self {
+        DiskError::InvalidInput(e.to_string())
+    }
+}
+
+impl From<DiskError> for String {
+    fn from(e: DiskError) -> Self {
+        e.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskSpace {
+    pub path: String,
+    pub available_bytes: u64,
+    pub reserve_bytes: u64,
+    pub low: bool,
+}
+
+fn format_mb(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / MB as f64)
+}
+
+// In the largest unit that keeps the number at or above 1, e.g. "3.4 MB".
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+pub fn reserve_bytes(config: &ConfigState) -> u64 {
+    config.get().min_free_disk_mb * MB
+}
+
+// The target file (or even its directory) may not exist yet; measure the
+// nearest ancestor that does, which is on the same filesystem.
+pub fn available(path: &Path) -> Result<u64, DiskError> {
+    let dir = path
+        .ancestors()
+        .find(|p| p.is_dir())
+        .unwrap_or_else(|| Path::new("."));
+    fs2::available_space(dir)
+        .map_err(|e| DiskError::Failed(format!("Failed to check free space at {}: {}", dir.display(), e)))
+}
+
+// Refuse a write of `write_bytes` to `path` if it would leave less than
+// `reserve_bytes` free.
+pub fn ensure_space(path: &Path, write_bytes: u64, reserve_bytes: u64) -> Result<(), DiskError> {
+    let available = available(path)?;
+    let needed = write_bytes.saturating_add(reserve_bytes);
+    if available < needed {
+        return Err(DiskError::LowDiskSpace(format!(
+            "Not enough free disk space at {}: {} free, {} needed. Free up some space and try again.",
+            path.display(),
+            format_mb(available),
+            format_mb(needed)
+        )));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn check_disk_space(
+    app: tauri::AppHandle,
+    config: tauri::State<ConfigState>,
+    path: String,
+) -> Result<DiskSpace, DiskError> {
+    let reserve_bytes = reserve_bytes(&config);
+    let available_bytes = available(&validate::user_path(&app, "Path", &path)?)?;
+    Ok(DiskSpace {
+        path,
+        available_bytes,
+        reserve_bytes,
+        low: available_bytes < reserve_bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_are_shown_in_the_largest_whole_unit() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(3 * MB + MB / 2), "3.5 MB");
+        assert_eq!(format_bytes(u64::MAX), "16777216.0 TB");
+    }
+}
@@ -0,0 +1,231 @@
+// Finding entries that were saved twice, e.g. by a double-clicked submit,
+// and folding them into one. Entries are only grouped when they are of the
+// same kind and were created close together, so a thought that genuinely
+// recurs on different days is never flagged.
+
+use crate::storage::{Entry, StorageError, StorageState};
+use crate::validate;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+const DEFAULT_WINDOW_SECS: u64 = 5 * 60;
+const MAX_WINDOW_SECS: u64 = 24 * 60 * 60;
+const MAX_DISTANCE: usize = 50;
+// Fuzzy matching is quadratic in the text length, so longer entries only
+// match exactly.
+const MAX_FUZZY_LEN: usize = 2000;
+const MAX_MERGE: usize = 100;
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateGroup {
+    // Oldest first; `merge_entries` keeps the first id it is given.
+    pub entries: Vec<Entry>,
+    // Every entry has the same text once whitespace and case are ignored.
+    pub exact: bool,
+}
+
+fn normalize(content: &str) -> String {
+    content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+fn similar(a: &str, b: &str, max_distance: usize) -> bool {
+    if a == b {
+        return true;
+    }
+    if max_distance == 0 {
+        return false;
+    }
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    a.len().abs_diff(b.len()) <= max_distance
+        && a.len().max(b.len()) <= MAX_FUZZY_LEN
+        && levenshtein(&a, &b) <= max_distance
+}
+
+fn find(parents: &[usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    root
+}
+
+// Groups are connected: A and C land together if each is close to B, even
+// if they aren't close to each other.
+pub fn group(entries: Vec<Entry>, window_secs: u64, max_distance: usize) -> Vec<DuplicateGroup> {
+    let mut entries: Vec<(Entry, Option<DateTime<Utc>>, String)> = entries
+        .into_iter()
+        .map(|entry| {
+            let at = DateTime::parse_from_rfc3339(&entry.created_at)
+                .ok()
+                .map(|at| at.with_timezone(&Utc));
+            let normalized = normalize(&entry.content);
+            (entry, at, normalized)
+        })
+        .collect();
+    entries.sort_by(|a, b| (a.1, a.0.id).cmp(&(b.1, b.0.id)));
+
+    let window = chrono::Duration::seconds(window_secs as i64);
+    let mut parents: Vec<usize> = (0..entries.len()).collect();
+    for i in 0..entries.len() {
+        let (entry, Some(at), text) = &entries[i] else {
+            continue;
+        };
+        for j in i + 1..entries.len() {
+            let (other, Some(other_at), other_text) = &entries[j] else {
+                continue;
+            };
+            if *other_at - *at > window {
+                break;
+            }
+            if other.kind == entry.kind && similar(text, other_text, max_distance) {
+                let (root, other_root) = (find(&parents, i), find(&parents, j));
+                parents[other_root] = root;
+            }
+        }
+    }
+
+    let mut groups: Vec<(usize, Vec<usize>)> = Vec::new();
+    for i in 0..entries.len() {
+        let root = find(&parents, i);
+        match groups.iter_mut().find(|(r, _)| *r == root) {
+            Some((_, members)) => members.push(i),
+            None => groups.push((root, vec![i])),
+        }
+    }
+    let mut slots: Vec<Option<(Entry, Option<DateTime<Utc>>, String)>> =
+        entries.into_iter().map(Some).collect();
+    groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(_, members)| {
+            let members: Vec<_> = members
+                .into_iter()
+                .filter_map(|i| slots[i].take())
+                .collect();
+            let exact = members.windows(2).all(|pair| pair[0].2 == pair[1].2);
+            DuplicateGroup {
+                entries: members.into_iter().map(|(entry, _, _)| entry).collect(),
+                exact,
+            }
+        })
+        .collect()
+}
+
+// `max_distance` allows that many single-character edits between two entries'
+// normalized text; leave it out to match only identical text.
+#[tauri::command]
+pub async fn find_duplicate_entries(
+    state: tauri::State<'_, StorageState>,
+    window_secs: Option<u64>,
+    max_distance: Option<usize>,
+) -> Result<Vec<DuplicateGroup>, StorageError> {
+    let window_secs = window_secs.unwrap_or(DEFAULT_WINDOW_SECS);
+    let window_secs = validate::number("Window (seconds)", window_secs, 1..=MAX_WINDOW_SECS)?;
+    let max_distance = max_distance.unwrap_or(0);
+    let max_distance = validate::number("Edit distance", max_distance, 0..=MAX_DISTANCE)?;
+    let entries = state.with(|storage| storage.all_entries())?;
+    Ok(group(entries, window_secs, max_distance))
+}
+
+// Keeps the first id and folds the rest into it; their text is kept in its
+// edit history.
+#[tauri::command]
+pub fn merge_entries(
+    state: tauri::State<StorageState>,
+    ids: Vec<i64>,
+) -> Result<Entry, StorageError> {
+    validate::number("Number of entries", ids.len(), 2..=MAX_MERGE)?;
+    let (keep, others) = ids.split_first().ok_or("No entries to merge")?;
+    if others.contains(keep) || (1..others.len()).any(|i| others[..i].contains(&others[i])) {
+        return Err(StorageError::InvalidInput(
+            "Each entry can only be listed once".to_string(),
+        ));
+    }
+    state.with(|storage| storage.merge_entries(*keep, others))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: i64, kind: &str, content: &str, created_at: &str) -> Entry {
+        Entry {
+            id,
+            kind: kind.to_string(),
+            content: content.to_string(),
+            mood: None,
+            created_at: created_at.to_string(),
+            updated_at: created_at.to_string(),
+        }
+    }
+
+    fn ids(groups: &[DuplicateGroup]) -> Vec<Vec<i64>> {
+        groups
+            .iter()
+            .map(|group| group.entries.iter().map(|entry| entry.id).collect())
+            .collect()
+    }
+
+    #[test]
+    fn groups_same_kind_text_within_the_window() {
+        let entries = || {
+            vec![
+                entry(
+                    1,
+                    "journal",
+                    "Felt an urge  at lunch",
+                    "2026-05-01T12:00:00Z",
+                ),
+                entry(
+                    2,
+                    "journal",
+                    "felt an urge at lunch",
+                    "2026-05-01T12:00:03Z",
+                ),
+                // Same text, but a different kind or much later.
+                entry(3, "urge", "Felt an urge at lunch", "2026-05-01T12:00:04Z"),
+                entry(
+                    4,
+                    "journal",
+                    "Felt an urge at lunch",
+                    "2026-05-02T12:00:00Z",
+                ),
+                entry(5, "journal", "Felt an urge at lunh", "2026-05-01T12:01:00Z"),
+            ]
+        };
+
+        let exact = group(entries(), 300, 0);
+        assert_eq!(ids(&exact), [[1, 2]]);
+        assert!(exact[0].exact);
+
+        let fuzzy = group(entries(), 300, 1);
+        assert_eq!(ids(&fuzzy), [[1, 2, 5]]);
+        assert!(!fuzzy[0].exact);
+    }
+
+    #[test]
+    fn levenshtein_counts_single_character_edits() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(levenshtein(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(levenshtein(&chars(""), &chars("abc")), 3);
+        assert_eq!(levenshtein(&chars("same"), &chars("same")), 0);
+    }
+}
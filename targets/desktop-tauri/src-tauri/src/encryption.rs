@@ -0,0 +1,229 @@
+// Encryption of the local store. Builds from before SQLCipher wrote
+// plaintext databases, which the default profile may still have (other
+// profiles are encrypted when created), so the user is warned at startup
+// when one holds data and can convert it with `encrypt_existing_store`. An
+// encrypted store stays closed after launch or a profile switch until
+// `unlock_storage` is given its passphrase, which `rotate_storage_key`
+// changes.
+
+use crate::audit::{AuditAction, AuditState};
+use crate::autobackup;
+use crate::backup::{self, BACKUP_EXTENSION};
+use crate::config::ConfigState;
+use crate::disk;
+use crate::guest::GuestState;
+use crate::i18n;
+use crate::profiles::ProfileState;
+use crate::storage::{self, EncryptReport, Storage, StorageError, StorageState, DB_FILE_NAME};
+use crate::sync;
+use crate::validate;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+// Sent when the user accepts the startup offer, so the frontend can ask for
+// a passphrase; the native dialog can't.
+pub const ENCRYPTION_REQUESTED_EVENT: &str = "storage-encryption-requested";
+
+#[derive(Debug, Serialize)]
+pub struct EncryptionStatus {
+    pub encrypted: bool,
+    // Encrypted and waiting for `unlock_storage`.
+    pub locked: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EncryptionReport {
+    #[serde(flatten)]
+    pub store: EncryptReport,
+    // Taken just before converting, encrypted with the same passphrase.
+    pub backup_path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RotationReport {
+    pub entry_count: i64,
+    // Backups written under the old passphrase and now under the new one:
+    // pre-encryption and automatic backups.
+    pub backups_rekeyed: usize,
+    pub sync_snapshot_rekeyed: bool,
+    // What couldn't be re-encrypted, and why.
+    pub problems: Vec<String>,
+}
+
+fn db_path(app: &AppHandle) -> Result<PathBuf, StorageError> {
+    Ok(app.state::<ProfileState>().active_dir()?.join(DB_FILE_NAME))
+}
+
+pub fn check_passphrase(passphrase: &str) -> Result<(), StorageError> {
+    validate::text("Passphrase", passphrase, backup::MAX_PASSWORD_LEN)?;
+    backup::check_password(passphrase).map_err(|e| StorageError::InvalidInput(e.to_string()))
+}
+
+// Only asks once; after a "no" the status command still reports the store as
+// plaintext, for settings to show.
+pub fn offer(app: &AppHandle) {
+    if app.state::<ProfileState>().settings().storage_encryption_declined {
+        return;
+    }
+    let plaintext = db_path(app)
+        .and_then(|path| storage::is_encrypted(&path))
+        .is_ok_and(|encrypted| !encrypted);
+    let has_data = app
+        .state::<StorageState>()
+        .with(|storage| storage.entry_count())
+        .is_ok_and(|count| count > 0);
+    if !plaintext || !has_data {
+        return;
+    }
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let window = app.get_window("main");
+        let accepted = tauri::api::dialog::blocking::confirm(
+            window.as_ref(),
+            i18n::t(&app, "storage_encryption.title"),
+            i18n::t(&app, "storage_encryption.body"),
+        );
+        if accepted {
+            let _ = app.emit_all(ENCRYPTION_REQUESTED_EVENT, ());
+        } else if let Err(e) = app
+            .state::<ProfileState>()
+            .update_settings(|settings| settings.storage_encryption_declined = true)
+        {
+            eprintln!("Failed to save the encryption offer: {}", e);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn storage_encryption_status(
+    app: AppHandle,
+    state: tauri::State<StorageState>,
+) -> Result<EncryptionStatus, StorageError> {
+    let encrypted = storage::is_encrypted(&db_path(&app)?)?;
+    let open = state.0.lock().unwrap_or_else(|p| p.into_inner()).is_some();
+    Ok(EncryptionStatus {
+        encrypted,
+        locked: encrypted && !open,
+    })
+}
+
+#[tauri::command]
+pub async fn unlock_storage(app: AppHandle, passphrase: String) -> Result<(), StorageError> {
+    app.state::<GuestState>().refuse("Unlocking storage")?;
+    validate::text("Passphrase", &passphrase, backup::MAX_PASSWORD_LEN)?;
+    let state = app.state::<StorageState>();
+    let mut guard = state.0.lock().unwrap_or_else(|p| p.into_inner());
+    if guard.is_some() {
+        return Ok(());
+    }
+    *guard = Some(Storage::open_encrypted(&db_path(&app)?, &passphrase)?);
+    drop(guard);
+    app.state::<AuditState>()
+        .record(AuditAction::Unlock, serde_json::json!({ "method": "passphrase" }));
+    Ok(())
+}
+
+// Backs the store up first, as a `.smartbak` next to it with the same
+// passphrase, since the conversion replaces the database file.
+#[tauri::command]
+pub async fn encrypt_existing_store(
+    app: AppHandle,
+    passphrase: String,
+) -> Result<EncryptionReport, StorageError> {
+    app.state::<GuestState>().refuse("Encrypting storage")?;
+    check_passphrase(&passphrase)?;
+    let path = db_path(&app)?;
+    let state = app.state::<StorageState>();
+    let reserve = disk::reserve_bytes(&app.state::<ConfigState>());
+    // The encrypted copy sits next to the original until the swap.
+    let size = std::fs::metadata(&path).map_or(0, |metadata| metadata.len());
+    disk::ensure_space(&path, size, reserve)?;
+
+    let backup_path = path.with_file_name(format!(
+        "pre-encryption-{}.{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        BACKUP_EXTENSION
+    ));
+    let backup = backup::create(&state, &backup_path, &passphrase, reserve)
+        .map_err(|e| format!("Couldn't back up before encrypting, nothing was changed: {}", e))?;
+    let store = storage::encrypt_in_place(&state, &passphrase)?;
+    Ok(EncryptionReport {
+        store,
+        backup_path: backup.path,
+    })
+}
+
+// The backups taken by `encrypt_existing_store`, next to the database.
+fn pre_encryption_backups(path: &Path) -> Vec<PathBuf> {
+    let suffix = format!(".{}", BACKUP_EXTENSION);
+    let Some(dir) = path.parent().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    dir.filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("pre-encryption-") && name.ends_with(&suffix))
+        })
+        .collect()
+}
+
+// Re-encrypts the store under a new passphrase, then the backups and sync
+// snapshot the old one opens, so it stops opening anything. Those already
+// under some other password are left alone. There's no keychain copy of the
+// passphrase to update; the next unlock asks for the new one.
+#[tauri::command]
+pub async fn rotate_storage_key(
+    app: AppHandle,
+    old_passphrase: String,
+    new_passphrase: String,
+) -> Result<RotationReport, StorageError> {
+    app.state::<GuestState>().refuse("Changing the passphrase")?;
+    validate::text("Current passphrase", &old_passphrase, backup::MAX_PASSWORD_LEN)?;
+    check_passphrase(&new_passphrase)?;
+    if new_passphrase == old_passphrase {
+        return Err(StorageError::InvalidInput(
+            "The new passphrase is the same as the current one".to_string(),
+        ));
+    }
+    let path = db_path(&app)?;
+    let reserve = disk::reserve_bytes(&app.state::<ConfigState>());
+    // The re-encrypted copy sits next to the original until the swap.
+    let size = std::fs::metadata(&path).map_or(0, |metadata| metadata.len());
+    disk::ensure_space(&path, size, reserve)?;
+
+    let state = app.state::<StorageState>();
+    let entry_count = storage::rotate_key(&state, &old_passphrase, &new_passphrase)?;
+    app.state::<AuditState>()
+        .record(AuditAction::RotateKey, serde_json::json!({ "entry_count": entry_count }));
+
+    // The store is already re-keyed, so from here failures are reported
+    // rather than returned.
+    let mut problems = Vec::new();
+    let mut backups_rekeyed = 0;
+    for backup in pre_encryption_backups(&path) {
+        match backup::reseal(&backup, &old_passphrase, &new_passphrase) {
+            Ok(true) => backups_rekeyed += 1,
+            Ok(false) => {}
+            Err(e) => problems.push(format!("{}: {}", backup.display(), e)),
+        }
+    }
+    match autobackup::rekey(&app, &old_passphrase, &new_passphrase) {
+        Ok(count) => backups_rekeyed += count,
+        Err(e) => problems.push(format!("Automatic backups: {}", e)),
+    }
+    let sync_snapshot_rekeyed = sync::rekey(&app, &old_passphrase, &new_passphrase)
+        .unwrap_or_else(|e| {
+            problems.push(format!("Sync snapshot: {}", e));
+            false
+        });
+    Ok(RotationReport {
+        entry_count,
+        backups_rekeyed,
+        sync_snapshot_rekeyed,
+        problems,
+    })
+}
@@ -0,0 +1,216 @@
+// The error commands return when they have no error type of their own, so
+// the frontend gets `{ code, message, details }` to branch on instead of
+// prose. The module errors (`StorageError`, `BackupError`, `CoreError`, ...)
+// keep their finer codes where commands already return them, in the same
+// shape through `serialize`; converted into an `AppError` they map onto the
+// nearest code here.
+
+use crate::backup::BackupError;
+use crate::core::CoreError;
+use crate::disk::DiskError;
+use crate::storage::StorageError;
+use crate::validate::ValidationError;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum AppError {
+    // The core isn't running or didn't answer.
+    CoreUnreachable(String),
+    // The store is encrypted and waiting for its passphrase.
+    AppLocked(String),
+    WrongPassword(String),
+    LowDiskSpace(String),
+    InvalidInput(String),
+    // A file operation failed; `path` is the file it was on, if known.
+    Io {
+        message: String,
+        path: Option<String>,
+    },
+    Failed(String),
+}
+
+impl AppError {
+    pub fn io(path: &Path, message: String) -> Self {
+        AppError::Io {
+            message,
+            path: Some(path.display().to_string()),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::CoreUnreachable(_) => "CoreUnreachable",
+            AppError::AppLocked(_) => "AppLocked",
+            AppError::WrongPassword(_) => "WrongPassword",
+            AppError::LowDiskSpace(_) => "LowDiskSpace",
+            AppError::InvalidInput(_) => "InvalidInput",
+            AppError::Io { .. } => "Io",
+            AppError::Failed(_) => "Failed",
+        }
+    }
+
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            AppError::Io {
+                path: Some(path), ..
+            } => Some(serde_json::json!({ "path": path })),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::CoreUnreachable(msg)
+            | AppError::AppLocked(msg)
+            | AppError::WrongPassword(msg)
+            | AppError::LowDiskSpace(msg)
+            | AppError::InvalidInput(msg)
+            | AppError::Io { message: msg, .. }
+            | AppError::Failed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+// The shape every command error is sent in: the variant name as `code`, its
+// `Display` text as `message`, and `details` (`null` when there are none).
+pub fn serialize<S: Serializer>(
+    serializer: S,
+    code: &str,
+    message: &str,
+    details: Option<serde_json::Value>,
+) -> Result<S::Ok, S::Error> {
+    let mut out = serializer.serialize_struct("Error", 3)?;
+    out.serialize_field("code", code)?;
+    out.serialize_field("message", message)?;
+    out.serialize_field("details", &details)?;
+    out.end()
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(serializer, self.code(), &self.to_string(), self.details())
+    }
+}
+
+impl From<String> for AppError {
+    fn from(msg: String) -> Self {
+        AppError::Failed(msg)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(msg: &str) -> Self {
+        AppError::Failed(msg.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io {
+            message: e.to_string(),
+            path: None,
+        }
+    }
+}
+
+impl From<ValidationError> for AppError {
+    fn from(e: ValidationError) -> Self {
+        AppError::InvalidInput(e.to_string())
+    }
+}
+
+impl From<DiskError> for AppError {
+    fn from(e: DiskError) -> Self {
+        match e {
+            DiskError::LowDiskSpace(msg) => AppError::LowDiskSpace(msg),
+            DiskError::InvalidInput(msg) => AppError::InvalidInput(msg),
+            DiskError::Failed(msg) => AppError::Failed(msg),
+        }
+    }
+}
+
+impl From<StorageError> for AppError {
+    fn from(e: StorageError) -> Self {
+        match e {
+            StorageError::Locked(msg) => AppError::AppLocked(msg),
+            StorageError::InvalidInput(msg) => AppError::InvalidInput(msg),
+            e => AppError::Failed(e.to_string()),
+        }
+    }
+}
+
+impl From<BackupError> for AppError {
+    fn from(e: BackupError) -> Self {
+        match e {
+            BackupError::WrongPassword => AppError::WrongPassword(e.to_string()),
+            BackupError::LowDiskSpace(msg) => AppError::LowDiskSpace(msg),
+            BackupError::WeakPassword(msg) | BackupError::InvalidInput(msg) => {
+                AppError::InvalidInput(msg)
+            }
+            e => AppError::Failed(e.to_string()),
+        }
+    }
+}
+
+impl From<CoreError> for AppError {
+    fn from(e: CoreError) -> Self {
+        match e {
+            CoreError::Unreachable(msg) => AppError::CoreUnreachable(msg),
+            CoreError::InvalidInput(msg) => AppError::InvalidInput(msg),
+            e => AppError::Failed(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_serialize_with_a_code_and_details() {
+        let error = AppError::io(
+            Path::new("/tmp/smartbot/config.json"),
+            "Failed to save /tmp/smartbot/config.json: permission denied".to_string(),
+        );
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["code"], "Io");
+        assert_eq!(json["details"]["path"], "/tmp/smartbot/config.json");
+        assert_eq!(
+            json["message"],
+            "Failed to save /tmp/smartbot/config.json: permission denied"
+        );
+
+        let json = serde_json::to_value(AppError::from(BackupError::WrongPassword)).unwrap();
+        assert_eq!(json["code"], "WrongPassword");
+        assert!(json["details"].is_null());
+    }
+
+    #[test]
+    fn module_errors_take_the_same_shape() {
+        let json = serde_json::to_value(CoreError::CoreNotFound {
+            path: "/opt/smartbot/core".to_string(),
+        })
+        .unwrap();
+        assert_eq!(json["code"], "CoreNotFound");
+        assert!(json["message"]
+            .as_str()
+            .unwrap()
+            .contains("/opt/smartbot/core"));
+        assert_eq!(json["details"]["path"], "/opt/smartbot/core");
+
+        // A unit variant still gets its message.
+        let json = serde_json::to_value(BackupError::WrongPassword).unwrap();
+        assert_eq!(json["code"], "WrongPassword");
+        assert!(json["message"].is_string());
+        assert!(json["details"].is_null());
+
+        let invalid = CoreError::from(ValidationError::OutOfRange("Timeout".to_string()));
+        assert_eq!(invalid.code(), "InvalidInput");
+        assert!(matches!(AppError::from(invalid), AppError::InvalidInput(_)));
+    }
+}
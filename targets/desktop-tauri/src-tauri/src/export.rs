@@ -0,0 +1,663 @@
+// Exports of user content to files the user picks.
+//
+// Dates and numbers in exported entries follow the user's locale unless the
+// caller asks for a specific format; either way the format used is stated in
+// the file, so a reader never has to guess whether 03/04 is March or April.
+//
+// Entry exports run as background jobs that report progress by event and
+// can be cancelled; a file only appears under its final name once it has
+// been written in full.
+
+use crate::audit::{AuditAction, AuditState};
+use crate::config::ConfigState;
+use crate::disk;
+use crate::error::AppError;
+use crate::i18n;
+use crate::storage::{Entry, StorageState};
+use crate::validate;
+use chrono::format::{Item, StrftimeItems};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::api::dialog::blocking::FileDialogBuilder;
+use tauri::{AppHandle, Manager};
+
+const TRANSCRIPT_EXTENSION: &str = "md";
+const MAX_TRANSCRIPT_LEN: usize = 10 * 1024 * 1024;
+const MAX_FILE_NAME_LEN: usize = 255;
+const MAX_DATE_FORMAT_LEN: usize = 64;
+const DECIMAL_SEPARATORS: [char; 2] = ['.', ','];
+// Progress is reported every this many entries, and once at the end.
+const PROGRESS_EVERY: usize = 100;
+
+pub const EXPORT_PROGRESS_EVENT: &str = "export-progress";
+pub const EXPORT_COMPLETE_EVENT: &str = "export-complete";
+pub const EXPORT_ERROR_EVENT: &str = "export-error";
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryFormat {
+    Csv,
+    Markdown,
+}
+
+impl EntryFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            EntryFormat::Csv => "csv",
+            EntryFormat::Markdown => "md",
+        }
+    }
+
+    fn filter_key(self) -> &'static str {
+        match self {
+            EntryFormat::Csv => "export_entries.filter_csv",
+            EntryFormat::Markdown => "export_entries.filter_markdown",
+        }
+    }
+}
+
+// What the caller asked for; anything left out follows the locale.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct FormatOptions {
+    // A chrono/strftime pattern such as "%d.%m.%Y %H:%M".
+    pub date_format: Option<String>,
+    pub decimal_separator: Option<char>,
+}
+
+// The format actually applied.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportFormat {
+    pub locale: String,
+    pub date_format: String,
+    pub decimal_separator: char,
+    // Set when a requested option was invalid and the locale default was
+    // used in its place.
+    pub fell_back: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportReport {
+    pub path: String,
+    pub entries: usize,
+    pub format: ExportFormat,
+}
+
+// Returned by `export_entries`; `id` tags the job's events.
+#[derive(Debug, Serialize)]
+pub struct ExportJob {
+    pub id: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportProgress {
+    pub id: String,
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportComplete {
+    pub id: String,
+    pub report: ExportReport,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportFailed {
+    pub id: String,
+    pub message: String,
+    // Stopped by `cancel_export` rather than by an error.
+    pub cancelled: bool,
+}
+
+// Running entry exports, by job id, so `cancel_export` can stop them.
+#[derive(Default)]
+pub struct ExportJobs(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+impl ExportJobs {
+    fn start(&self, id: &str) -> Arc<AtomicBool> {
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.jobs().insert(id.to_string(), cancel.clone());
+        cancel
+    }
+
+    fn finish(&self, id: &str) {
+        self.jobs().remove(id);
+    }
+
+    // Returns whether a job with that id was still running.
+    fn cancel(&self, id: &str) -> bool {
+        match self.jobs().get(id) {
+            Some(cancel) => {
+                cancel.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Cancels every running export and waits up to `timeout` for them to
+    // remove their partial files.
+    pub fn cancel_all(&self, timeout: Duration) {
+        for cancel in self.jobs().values() {
+            cancel.store(true, Ordering::SeqCst);
+        }
+        let started = Instant::now();
+        while !self.jobs().is_empty() && started.elapsed() < timeout {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    fn jobs(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<AtomicBool>>> {
+        self.0.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+// Defaults by language, with US English the one region that changes them.
+// Unlisted languages get ISO 8601 dates, which nobody misreads.
+fn locale_defaults(locale: &str) -> (&'static str, char) {
+    let tag = locale.split('.').next().unwrap_or(locale).replace('_', "-");
+    let (language, region) = tag.split_once('-').unwrap_or((&tag, ""));
+    match language.to_ascii_lowercase().as_str() {
+        "en" if region.is_empty() || region.eq_ignore_ascii_case("us") => ("%m/%d/%Y %H:%M", '.'),
+        "en" => ("%d/%m/%Y %H:%M", '.'),
+        "de" | "da" | "fi" | "nb" | "no" | "pl" | "ru" | "tr" => ("%d.%m.%Y %H:%M", ','),
+        "es" | "fr" | "it" | "pt" => ("%d/%m/%Y %H:%M", ','),
+        "nl" => ("%d-%m-%Y %H:%M", ','),
+        "ja" | "zh" | "ko" => ("%Y/%m/%d %H:%M", '.'),
+        _ => ("%Y-%m-%d %H:%M", '.'),
+    }
+}
+
+// chrono panics when formatting with a pattern it can't parse, so patterns
+// are checked up front.
+fn valid_date_format(pattern: &str) -> bool {
+    !pattern.trim().is_empty()
+        && pattern.len() <= MAX_DATE_FORMAT_LEN
+        && !pattern.chars().any(char::is_control)
+        && !StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error))
+}
+
+pub fn resolve_format(app: &AppHandle, options: &FormatOptions) -> ExportFormat {
+    resolve(i18n::requested_locale(app), options)
+}
+
+fn resolve(locale: String, options: &FormatOptions) -> ExportFormat {
+    let (default_date, default_decimal) = locale_defaults(&locale);
+    let mut fell_back = false;
+
+    let date_format = match &options.date_format {
+        Some(pattern) if valid_date_format(pattern) => pattern.clone(),
+        Some(_) => {
+            fell_back = true;
+            default_date.to_string()
+        }
+        None => default_date.to_string(),
+    };
+    let decimal_separator = match options.decimal_separator {
+        Some(separator) if DECIMAL_SEPARATORS.contains(&separator) => separator,
+        Some(_) => {
+            fell_back = true;
+            default_decimal
+        }
+        None => default_decimal,
+    };
+    ExportFormat {
+        locale,
+        date_format,
+        decimal_separator,
+        fell_back,
+    }
+}
+
+impl ExportFormat {
+    // Stored timestamps are RFC 3339 UTC; exports show local time. Anything
+    // that doesn't parse is passed through as stored.
+    fn date(&self, timestamp: &str) -> String {
+        match chrono::DateTime::parse_from_rfc3339(timestamp) {
+            Ok(at) => at
+                .with_timezone(&chrono::Local)
+                .format(&self.date_format)
+                .to_string(),
+            Err(_) => timestamp.to_string(),
+        }
+    }
+
+    fn number(&self, value: f64) -> String {
+        value.to_string().replace('.', &self.decimal_separator.to_string())
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "dates {} (e.g. {}), decimal separator \"{}\"",
+            self.date_format,
+            chrono::Local::now().format(&self.date_format),
+            self.decimal_separator
+        )
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+// Every field is quoted, so a decimal comma never splits a column. The
+// header row names the formats, since CSV has no standard place for notes.
+fn csv_header(format: &ExportFormat) -> String {
+    let header = [
+        "id".to_string(),
+        "kind".to_string(),
+        format!("created_at ({})", format.date_format),
+        format!("updated_at ({})", format.date_format),
+        format!("mood (decimal \"{}\")", format.decimal_separator),
+        "content".to_string(),
+    ];
+    let mut out = header.iter().map(|h| csv_field(h)).collect::<Vec<_>>().join(",");
+    out.push_str("\r\n");
+    out
+}
+
+fn csv_row(entry: &Entry, format: &ExportFormat) -> String {
+    let row = [
+        entry.id.to_string(),
+        entry.kind.clone(),
+        format.date(&entry.created_at),
+        format.date(&entry.updated_at),
+        entry.mood.map(|mood| format.number(mood)).unwrap_or_default(),
+        entry.content.clone(),
+    ];
+    let mut out = row.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(",");
+    out.push_str("\r\n");
+    out
+}
+
+fn markdown_header(format: &ExportFormat) -> String {
+    format!(
+        "# Smartbot entries\n\n_Exported {}. Formats: {}._\n",
+        format.date(&chrono::Utc::now().to_rfc3339()),
+        format.describe()
+    )
+}
+
+fn markdown_entry(entry: &Entry, format: &ExportFormat) -> String {
+    let mut out = format!("\n## {} · {}\n\n", format.date(&entry.created_at), entry.kind);
+    if let Some(mood) = entry.mood {
+        out.push_str(&format!("Mood: {}\n\n", format.number(mood)));
+    }
+    out.push_str(entry.content.trim_end());
+    out.push('\n');
+    out
+}
+
+fn with_default_extension(path: &Path, extension: &str) -> PathBuf {
+    if path.extension().is_some() {
+        path.to_path_buf()
+    } else {
+        path.with_extension(extension)
+    }
+}
+
+// Returns `None` if the user cancelled the dialog.
+async fn choose_path(
+    app: &AppHandle,
+    title_key: &str,
+    filter_key: &str,
+    suggested_name: &str,
+    extension: &'static str,
+) -> Result<Option<PathBuf>, String> {
+    validate::file_name("Suggested name", suggested_name, MAX_FILE_NAME_LEN)?;
+    let file_name = with_default_extension(Path::new(suggested_name), extension);
+    let window = app.get_window("main");
+    let title = i18n::t(app, title_key);
+    let filter = i18n::t(app, filter_key);
+
+    // The blocking dialog must stay off the main thread.
+    let chosen = tauri::async_runtime::spawn_blocking(move || {
+        let mut dialog = FileDialogBuilder::new()
+            .set_title(&title)
+            .set_file_name(&file_name.to_string_lossy())
+            .add_filter(&filter, &[extension]);
+        if let Some(window) = &window {
+            dialog = dialog.set_parent(window);
+        }
+        dialog.save_file()
+    })
+    .await
+    .map_err(|e| format!("Save dialog failed: {}", e))?;
+    Ok(chosen.map(|path| with_default_extension(&path, extension)))
+}
+
+// Ask where to save a session transcript or progress summary and write it
+// there. Returns the saved path, or `None` if the user cancelled the dialog.
+#[tauri::command]
+pub async fn save_transcript(
+    app: AppHandle,
+    contents: String,
+    suggested_name: String,
+) -> Result<Option<String>, AppError> {
+    validate::text("Transcript", &contents, MAX_TRANSCRIPT_LEN)?;
+    let Some(path) = choose_path(
+        &app,
+        "save_transcript.title",
+        "save_transcript.filter",
+        &suggested_name,
+        TRANSCRIPT_EXTENSION,
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+    let reserve = disk::reserve_bytes(&app.state::<ConfigState>());
+    disk::ensure_space(&path, contents.len() as u64, reserve)?;
+    std::fs::write(&path, contents)
+        .map_err(|e| {
+            AppError::io(
+                &path,
+                format!("Failed to save transcript to {}: {}", path.display(), e),
+            )
+        })?;
+    app.state::<AuditState>().record(
+        AuditAction::Export,
+        serde_json::json!({ "kind": "transcript", "path": path.display().to_string() }),
+    );
+    Ok(Some(path.display().to_string()))
+}
+
+// The format `export_entries` would use, so transcripts rendered by the
+// frontend can match it.
+#[tauri::command]
+pub fn get_export_format(app: AppHandle, options: Option<FormatOptions>) -> ExportFormat {
+    resolve_format(&app, &options.unwrap_or_default())
+}
+
+enum JobError {
+    Cancelled,
+    Failed(String),
+}
+
+impl From<String> for JobError {
+    fn from(msg: String) -> Self {
+        JobError::Failed(msg)
+    }
+}
+
+// Written to a `.part` sibling, which the caller renames into place or
+// removes. `progress` is told how many entries are written out of how many.
+fn write_entries(
+    entries: &[Entry],
+    part: &Path,
+    format: EntryFormat,
+    export_format: &ExportFormat,
+    cancel: &AtomicBool,
+    progress: &impl Fn(usize, usize),
+) -> Result<(), JobError> {
+    let failed = |e: std::io::Error| {
+        JobError::Failed(format!("Failed to export entries to {}: {}", part.display(), e))
+    };
+    let file = std::fs::File::create(part).map_err(failed)?;
+    let mut out = BufWriter::new(file);
+    let header = match format {
+        EntryFormat::Csv => csv_header(export_format),
+        EntryFormat::Markdown => markdown_header(export_format),
+    };
+    out.write_all(header.as_bytes()).map_err(failed)?;
+
+    let total = entries.len();
+    for (index, entry) in entries.iter().enumerate() {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(JobError::Cancelled);
+        }
+        let chunk = match format {
+            EntryFormat::Csv => csv_row(entry, export_format),
+            EntryFormat::Markdown => markdown_entry(entry, export_format),
+        };
+        out.write_all(chunk.as_bytes()).map_err(failed)?;
+        let processed = index + 1;
+        if processed % PROGRESS_EVERY == 0 && processed < total {
+            progress(processed, total);
+        }
+    }
+    out.flush().map_err(failed)?;
+    out.get_ref().sync_all().map_err(failed)?;
+    progress(total, total);
+    Ok(())
+}
+
+// Through the `.part` sibling, so `path` only appears once complete; the
+// partial file is removed if the export fails or is cancelled.
+fn write_into_place(
+    entries: &[Entry],
+    path: &Path,
+    format: EntryFormat,
+    export_format: &ExportFormat,
+    cancel: &AtomicBool,
+    progress: &impl Fn(usize, usize),
+) -> Result<(), JobError> {
+    let mut part_name = path.file_name().unwrap_or_default().to_os_string();
+    part_name.push(".part");
+    let part = path.with_file_name(part_name);
+    let written = write_entries(entries, &part, format, export_format, cancel, progress)
+        .and_then(|()| {
+            if cancel.load(Ordering::SeqCst) {
+                return Err(JobError::Cancelled);
+            }
+            std::fs::rename(&part, path).map_err(|e| {
+                JobError::Failed(format!("Failed to export entries to {}: {}", path.display(), e))
+            })
+        });
+    if written.is_err() {
+        let _ = std::fs::remove_file(&part);
+    }
+    written
+}
+
+fn run_export(
+    app: &AppHandle,
+    id: &str,
+    path: &Path,
+    format: EntryFormat,
+    export_format: ExportFormat,
+    cancel: &AtomicBool,
+) -> Result<ExportReport, JobError> {
+    let entries = app
+        .state::<StorageState>()
+        .with(|storage| storage.all_entries())
+        .map_err(String::from)?;
+    // Only a floor: quoting and formatting add a little on top.
+    let estimate: usize = entries.iter().map(|entry| entry.content.len()).sum();
+    let reserve = disk::reserve_bytes(&app.state::<ConfigState>());
+    disk::ensure_space(path, estimate as u64, reserve).map_err(String::from)?;
+
+    let progress = |processed, total| {
+        let progress = ExportProgress {
+            id: id.to_string(),
+            processed,
+            total,
+        };
+        let _ = app.emit_all(EXPORT_PROGRESS_EVENT, progress);
+    };
+    write_into_place(&entries, path, format, &export_format, cancel, &progress)?;
+    let entries = entries.len();
+
+    app.state::<AuditState>().record(
+        AuditAction::Export,
+        serde_json::json!({
+            "kind": "entries",
+            "format": format.extension(),
+            "path": path.display().to_string(),
+            "entries": entries,
+        }),
+    );
+    Ok(ExportReport {
+        path: path.display().to_string(),
+        entries,
+        format: export_format,
+    })
+}
+
+// Export every entry as CSV or Markdown. The export runs in the background
+// once a file is chosen: follow `export-progress` and then `export-complete`
+// or `export-error` for the returned job id. Returns `None` if the user
+// cancelled the dialog.
+#[tauri::command]
+pub async fn export_entries(
+    app: AppHandle,
+    format: EntryFormat,
+    suggested_name: String,
+    options: Option<FormatOptions>,
+) -> Result<Option<ExportJob>, AppError> {
+    let export_format = resolve_format(&app, &options.unwrap_or_default());
+    let Some(path) = choose_path(
+        &app,
+        "export_entries.title",
+        format.filter_key(),
+        &suggested_name,
+        format.extension(),
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let cancel = app.state::<ExportJobs>().start(&id);
+    let job = ExportJob {
+        id: id.clone(),
+        path: path.display().to_string(),
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = run_export(&app, &id, &path, format, export_format, &cancel);
+        app.state::<ExportJobs>().finish(&id);
+        match result {
+            Ok(report) => {
+                let _ = app.emit_all(EXPORT_COMPLETE_EVENT, ExportComplete { id, report });
+            }
+            Err(e) => {
+                let (message, cancelled) = match e {
+                    JobError::Cancelled => ("Export cancelled".to_string(), true),
+                    JobError::Failed(message) => (message, false),
+                };
+                let _ = app.emit_all(
+                    EXPORT_ERROR_EVENT,
+                    ExportFailed {
+                        id,
+                        message,
+                        cancelled,
+                    },
+                );
+            }
+        }
+    });
+    Ok(Some(job))
+}
+
+// Returns whether an export with that id was still running. The partial file
+// is removed by the job itself.
+#[tauri::command]
+pub fn cancel_export(jobs: tauri::State<ExportJobs>, id: String) -> bool {
+    jobs.cancel(&id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locales_pick_their_date_and_decimal_conventions() {
+        assert_eq!(locale_defaults("en"), ("%m/%d/%Y %H:%M", '.'));
+        assert_eq!(locale_defaults("en_US.UTF-8"), ("%m/%d/%Y %H:%M", '.'));
+        assert_eq!(locale_defaults("en-GB"), ("%d/%m/%Y %H:%M", '.'));
+        assert_eq!(locale_defaults("de_DE"), ("%d.%m.%Y %H:%M", ','));
+        assert_eq!(locale_defaults("PT-br"), ("%d/%m/%Y %H:%M", ','));
+        assert_eq!(locale_defaults("ja-JP"), ("%Y/%m/%d %H:%M", '.'));
+        assert_eq!(locale_defaults("xx"), ("%Y-%m-%d %H:%M", '.'));
+    }
+
+    #[test]
+    fn date_formats_chrono_cant_use_are_rejected() {
+        assert!(valid_date_format("%d.%m.%Y %H:%M"));
+        assert!(!valid_date_format("%Y-%m-%d %Q"));
+        assert!(!valid_date_format("%Y-%m-%d %"));
+        assert!(!valid_date_format("%Y\n%m"));
+        assert!(!valid_date_format("  "));
+        assert!(!valid_date_format(&"%Y".repeat(MAX_DATE_FORMAT_LEN)));
+    }
+
+    #[test]
+    fn invalid_options_fall_back_to_the_locale() {
+        let options = FormatOptions {
+            date_format: Some("%Y-%m-%d %Q".to_string()),
+            decimal_separator: Some(';'),
+        };
+        let format = resolve("de-DE".to_string(), &options);
+        assert_eq!(format.date_format, "%d.%m.%Y %H:%M");
+        assert_eq!(format.decimal_separator, ',');
+        assert!(format.fell_back);
+
+        let options = FormatOptions {
+            date_format: Some("%Y-%m-%d".to_string()),
+            decimal_separator: Some('.'),
+        };
+        let format = resolve("de-DE".to_string(), &options);
+        assert_eq!(format.date_format, "%Y-%m-%d");
+        assert_eq!(format.decimal_separator, '.');
+        assert!(!format.fell_back);
+    }
+
+    #[test]
+    fn csv_fields_keep_commas_quotes_and_newlines_inside() {
+        assert_eq!(csv_field("plain"), "\"plain\"");
+        assert_eq!(csv_field("4,5"), "\"4,5\"");
+        assert_eq!(csv_field("said \"no\""), "\"said \"\"no\"\"\"");
+        assert_eq!(csv_field("line one\r\nline two"), "\"line one\r\nline two\"");
+    }
+
+    #[test]
+    fn a_cancelled_export_leaves_no_partial_file() {
+        let dir = std::env::temp_dir().join(format!("smartbot-export-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("entries.csv");
+        let entries: Vec<Entry> = (0..250)
+            .map(|id| Entry {
+                id,
+                kind: "journal".to_string(),
+                content: format!("Entry {}", id),
+                mood: Some(5.5),
+                created_at: "2026-05-01T11:00:00+00:00".to_string(),
+                updated_at: "2026-05-01T11:00:00+00:00".to_string(),
+            })
+            .collect();
+        let format = resolve("en".to_string(), &FormatOptions::default());
+        let jobs = ExportJobs::default();
+        let cancel = jobs.start("job");
+
+        // Cancelled as the first progress report goes out, partway through.
+        let progress = |processed: usize, _total: usize| {
+            if processed == PROGRESS_EVERY {
+                assert!(jobs.cancel("job"));
+            }
+        };
+        let result =
+            write_into_place(&entries, &path, EntryFormat::Csv, &format, &cancel, &progress);
+        assert!(matches!(result, Err(JobError::Cancelled)));
+        assert!(!path.exists());
+        assert!(!dir.join("entries.csv.part").exists());
+
+        jobs.finish("job");
+        assert!(!jobs.cancel("job"));
+        let cancel = jobs.start("job");
+        let result =
+            write_into_place(&entries, &path, EntryFormat::Csv, &format, &cancel, &|_, _| {});
+        assert!(matches!(result, Ok(())));
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written.lines().count(), entries.len() + 1);
+        assert!(!dir.join("entries.csv.part").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
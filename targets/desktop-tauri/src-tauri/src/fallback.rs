@@ -0,0 +1,92 @@
+// Static error page for when the core can't start and there is no splash to
+// report it. A frontend that relies on the core would otherwise sit on a
+// blank window; `fallback.html` ships with the app's assets, so it loads even
+// when nothing is listening on localhost. Like the splash it has no IPC: the
+// shell drives it with `eval`, and its buttons are links caught by the main
+// window's navigation filter.
+
+use crate::core::{self, CoreState};
+use crate::splash::SPLASH_LABEL;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Url, Window};
+
+pub const FALLBACK_PAGE: &str = "fallback.html";
+
+// Links on the fallback page point here to trigger an action.
+pub const ACTION_HOST: &str = "fallback.smartbot";
+
+// The error the page should show once it has loaded.
+#[derive(Default)]
+pub struct FallbackState(Mutex<Option<String>>);
+
+fn js_string(text: &str) -> String {
+    serde_json::Value::String(text.to_string()).to_string()
+}
+
+fn eval(app: &AppHandle, script: String) {
+    if let Some(window) = app.get_window("main") {
+        let _ = window.eval(&script);
+    }
+}
+
+pub fn is_fallback(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| url.path().ends_with(FALLBACK_PAGE))
+}
+
+// Relative URLs resolve against whichever origin the frontend was served
+// from, so the same swap works with both the bundled assets and a dev server.
+fn navigate(app: &AppHandle, path: &str) {
+    eval(app, format!("window.location.replace({})", js_string(path)));
+}
+
+// With the splash enabled its own failure screen covers this.
+pub fn on_startup_finished(app: &AppHandle, result: &Result<String, core::CoreError>) {
+    if app.get_window(SPLASH_LABEL).is_some() {
+        return;
+    }
+    let showing = app
+        .get_window("main")
+        .is_some_and(|window| is_fallback(window.url().as_str()));
+    match result {
+        Ok(_) if showing => navigate(app, "/"),
+        Ok(_) => {}
+        Err(e) => {
+            *app.state::<FallbackState>().0.lock().unwrap_or_else(|p| p.into_inner()) =
+                Some(e.to_string());
+            if showing {
+                eval(app, format!("window.fallback.failed({})", js_string(&e.to_string())));
+            } else {
+                navigate(app, &format!("/{}", FALLBACK_PAGE));
+            }
+        }
+    }
+}
+
+pub fn on_page_load(window: &Window) {
+    let error = window
+        .state::<FallbackState>()
+        .0
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .clone();
+    if let Some(error) = error {
+        let _ = window.eval(&format!("window.fallback.failed({})", js_string(&error)));
+    }
+}
+
+pub fn on_action(app: &AppHandle, url: &Url) {
+    match url.path() {
+        "/retry" => {
+            let app = app.clone();
+            std::thread::spawn(move || {
+                let result = core::ensure(&app);
+                on_startup_finished(&app, &result);
+            });
+        }
+        "/logs" => {
+            let output = app.state::<CoreState>().output();
+            eval(app, format!("window.fallback.showLog({})", js_string(&output)));
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,48 @@
+// Optional subsystems the user can turn off, kept together in the config's
+// `features` section. Each subsystem reads its flag whenever it runs (every
+// tick for the background ones) rather than only at launch, so a change
+// applies without a restart.
+
+use crate::config;
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+pub const FEATURES_CHANGED_EVENT: &str = "features-changed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Features {
+    // Keeping the store in step with `sync_dir`.
+    pub sync: bool,
+    // Every system notification, reminders included; due reminders are
+    // dropped as if paused.
+    pub notifications: bool,
+    // `check_for_updates`, and so `install_update`.
+    pub update_checks: bool,
+    // Probing `connectivity_probe_url` for internet access.
+    pub connectivity_checks: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Self {
+            sync: true,
+            notifications: true,
+            update_checks: true,
+            connectivity_checks: true,
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_features(state: tauri::State<config::ConfigState>) -> Features {
+    state.get().features
+}
+
+#[tauri::command]
+pub fn set_features(app: AppHandle, features: Features) -> Result<Features, AppError> {
+    let config = config::update(&app, |config| config.features = features)?;
+    let _ = app.emit_all(FEATURES_CHANGED_EVENT, config.features);
+    Ok(config.features)
+}
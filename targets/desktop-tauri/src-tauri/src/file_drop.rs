@@ -0,0 +1,73 @@
+// Files dropped onto the window, e.g. journals or CSVs exported from other
+// tools. Accepted files are handed to the frontend, which drives the import.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+const SUPPORTED_EXTENSIONS: &[&str] = &["csv", "json", "md", "txt"];
+const MAX_FILE_BYTES: u64 = 20 * 1024 * 1024;
+
+pub const FILE_DROPPED_EVENT: &str = "file-dropped";
+pub const FILE_REJECTED_EVENT: &str = "file-drop-rejected";
+
+#[derive(Debug, Clone, Serialize)]
+struct DroppedFile {
+    path: String,
+    extension: String,
+    size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct RejectedFile {
+    path: String,
+    reason: String,
+}
+
+fn check(path: &Path) -> Result<DroppedFile, String> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .filter(|e| SUPPORTED_EXTENSIONS.contains(&e.as_str()))
+        .ok_or_else(|| {
+            format!(
+                "Only {} files can be imported",
+                SUPPORTED_EXTENSIONS.join(", ")
+            )
+        })?;
+    let metadata = std::fs::metadata(path).map_err(|e| format!("Can't read the file: {}", e))?;
+    if !metadata.is_file() {
+        return Err("Folders can't be imported".to_string());
+    }
+    if metadata.len() > MAX_FILE_BYTES {
+        return Err(format!(
+            "The file is larger than the {} MB import limit",
+            MAX_FILE_BYTES / (1024 * 1024)
+        ));
+    }
+    Ok(DroppedFile {
+        path: path.display().to_string(),
+        extension,
+        size_bytes: metadata.len(),
+    })
+}
+
+// Called from the window event handler on the main thread, so the files are
+// checked on a worker thread (network drives can be slow to stat).
+pub fn on_drop(app: &AppHandle, paths: Vec<PathBuf>) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for path in paths {
+            let _ = match check(&path) {
+                Ok(file) => app.emit_all(FILE_DROPPED_EVENT, file),
+                Err(reason) => app.emit_all(
+                    FILE_REJECTED_EVENT,
+                    RejectedFile {
+                        path: path.display().to_string(),
+                        reason,
+                    },
+                ),
+            };
+        }
+    });
+}
@@ -0,0 +1,433 @@
+// Moving to a new computer in one step: a `.smartmove` file carries the
+// entries (journal, worksheet answers, meeting notes and the rest, as in a
+// backup), the settings and the active profile's reminders, which hold
+// meeting times too. Settings tied to this computer (`MACHINE_SETTINGS`)
+// stay behind. Installed programs and future messages aren't carried, as in
+// backups; programs are imported again from their signed bundles.
+//
+// The file is the backup container around JSON: a manifest listing each
+// section with its checksum, and the sections as JSON text, so a checksum
+// covers exactly the bytes it was taken over. Import checks all of it and
+// brings old data and settings up to date before changing anything.
+
+use crate::audit::{AuditAction, AuditState};
+use crate::backup::{self, BackupError, BackupPayload};
+use crate::config::{self, AppConfig, ConfigState};
+use crate::disk;
+use crate::guest::GuestState;
+use crate::i18n::I18nState;
+use crate::profiles::ProfileState;
+use crate::reminders::Reminder;
+use crate::storage::{ReplacePlan, StorageState};
+use crate::validate;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+const FULL_BUNDLE_EXTENSION: &str = "smartmove";
+
+const FORMAT_VERSION: u32 = 1;
+
+const DATA: &str = "data";
+const SETTINGS: &str = "settings";
+const REMINDERS: &str = "reminders";
+const SECTIONS: [&str; 3] = [DATA, SETTINGS, REMINDERS];
+
+// Left out of a bundle, and kept as they are on import: paths on this
+// computer, its hardware, bookkeeping about this install, and the program
+// signing keys it trusts, which a bundle from anywhere mustn't be able to add.
+const MACHINE_SETTINGS: &[&str] = &[
+    "auto_backup_dir",
+    "auto_backup_last_at",
+    "auto_backup_last_sha256",
+    "biometric_unlock_enabled",
+    "core_path",
+    "integrity_checked_at",
+    "legacy_import_offered",
+    "sync_dir",
+    "trusted_program_keys",
+    "widget_geometry",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionInfo {
+    pub name: String,
+    pub sha256: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format_version: u32,
+    pub app_version: String,
+    pub created_at: String,
+    pub sections: Vec<SectionInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FullBundle {
+    manifest: Manifest,
+    sections: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FullBundleInfo {
+    pub path: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+    pub manifest: Manifest,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FullImportReport {
+    pub manifest: Manifest,
+    pub entries_restored: usize,
+    pub reminders_restored: usize,
+    pub settings_restored: usize,
+    // The data came from an older schema version and was upgraded.
+    pub migrated: bool,
+    pub changes: ReplacePlan,
+    // Nothing was changed; the report shows what a real import would do.
+    pub dry_run: bool,
+}
+
+fn checksum(text: &str) -> String {
+    hex::encode(Sha256::digest(text.as_bytes()))
+}
+
+fn to_json(value: &impl Serialize) -> Result<String, BackupError> {
+    serde_json::to_string(value)
+        .map_err(|e| BackupError::Failed(format!("Failed to serialize the bundle: {}", e)))
+}
+
+fn build(
+    payload: &BackupPayload,
+    config: &AppConfig,
+    reminders: &[Reminder],
+) -> Result<FullBundle, BackupError> {
+    let mut settings = serde_json::to_value(config)
+        .map_err(|e| BackupError::Failed(format!("Failed to serialize settings: {}", e)))?;
+    if let Some(fields) = settings.as_object_mut() {
+        for field in MACHINE_SETTINGS {
+            fields.remove(*field);
+        }
+    }
+    let sections: BTreeMap<String, String> = [
+        (DATA, to_json(payload)?),
+        (SETTINGS, to_json(&settings)?),
+        (REMINDERS, to_json(&reminders)?),
+    ]
+    .into_iter()
+    .map(|(name, text)| (name.to_string(), text))
+    .collect();
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: payload.created_at.clone(),
+        sections: sections
+            .iter()
+            .map(|(name, text)| SectionInfo {
+                name: name.clone(),
+                sha256: checksum(text),
+                size_bytes: text.len() as u64,
+            })
+            .collect(),
+    };
+    Ok(FullBundle { manifest, sections })
+}
+
+// Every section the manifest lists must be there with its checksum, and
+// nothing else; the container's own authentication already rules out
+// tampering, so a mismatch means a broken writer.
+fn open(bytes: &[u8], password: &str) -> Result<FullBundle, BackupError> {
+    let json = backup::unseal(bytes, password)?;
+    let bundle: FullBundle = serde_json::from_slice(&json).map_err(|e| {
+        BackupError::InvalidBackup(format!("This isn't a Smartbot move bundle: {}", e))
+    })?;
+    if bundle.manifest.format_version != FORMAT_VERSION {
+        return Err(BackupError::UnsupportedVersion(format!(
+            "Bundle format version {} is not supported by this version of Smartbot",
+            bundle.manifest.format_version
+        )));
+    }
+    let listed: Vec<_> = bundle
+        .manifest
+        .sections
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect();
+    if listed.len() != bundle.sections.len() || SECTIONS.iter().any(|name| !listed.contains(name)) {
+        return Err(BackupError::InvalidBackup(
+            "The bundle's manifest doesn't match its contents".to_string(),
+        ));
+    }
+    for info in &bundle.manifest.sections {
+        let text = bundle.sections.get(&info.name).ok_or_else(|| {
+            BackupError::InvalidBackup(format!("The bundle is missing its {} section", info.name))
+        })?;
+        if checksum(text) != info.sha256 || text.len() as u64 != info.size_bytes {
+            return Err(BackupError::InvalidBackup(format!(
+                "The bundle's {} section doesn't match its checksum",
+                info.name
+            )));
+        }
+    }
+    Ok(bundle)
+}
+
+fn section<T: DeserializeOwned>(bundle: &FullBundle, name: &str) -> Result<T, BackupError> {
+    let text = bundle.sections.get(name).map_or("", String::as_str);
+    serde_json::from_str(text).map_err(|e| {
+        BackupError::InvalidBackup(format!("The bundle's {} section is malformed: {}", name, e))
+    })
+}
+
+// The bundle's settings with this computer's own kept from `current`. Goes
+// through the same checks and upgrades as a config file on disk. Returns the
+// merged config and how many settings came from the bundle.
+fn merged_settings(
+    current: &AppConfig,
+    bundle: &FullBundle,
+) -> Result<(AppConfig, usize), BackupError> {
+    let mut settings: serde_json::Map<String, serde_json::Value> = section(bundle, SETTINGS)?;
+    for field in MACHINE_SETTINGS {
+        settings.remove(*field);
+    }
+    let restored = settings.len();
+    let current = serde_json::to_value(current)
+        .map_err(|e| BackupError::Failed(format!("Failed to serialize settings: {}", e)))?;
+    for field in MACHINE_SETTINGS {
+        if let Some(value) = current.get(*field) {
+            settings.insert(field.to_string(), value.clone());
+        }
+    }
+    let merged = serde_json::Value::Object(settings);
+    let config = config::parse(&merged.to_string()).map_err(|issues| {
+        let issues: Vec<_> = issues
+            .iter()
+            .map(|issue| format!("{}: {}", issue.field, issue.reason))
+            .collect();
+        BackupError::InvalidBackup(format!(
+            "The bundle's settings can't be used: {}",
+            issues.join("; ")
+        ))
+    })?;
+    Ok((config, restored))
+}
+
+fn with_extension(path: &Path) -> PathBuf {
+    if path.extension().is_some() {
+        path.to_path_buf()
+    } else {
+        path.with_extension(FULL_BUNDLE_EXTENSION)
+    }
+}
+
+#[tauri::command]
+pub async fn export_full_bundle(
+    app: AppHandle,
+    state: tauri::State<'_, StorageState>,
+    config: tauri::State<'_, ConfigState>,
+    audit: tauri::State<'_, AuditState>,
+    dest_path: String,
+    password: String,
+) -> Result<FullBundleInfo, BackupError> {
+    app.state::<GuestState>()
+        .refuse("Moving to another computer")?;
+    let dest = with_extension(&validate::user_path(
+        &app,
+        "Bundle destination",
+        &dest_path,
+    )?);
+    validate::text("Password", &password, backup::MAX_PASSWORD_LEN)?;
+    backup::check_password(&password)?;
+
+    let reminders = app.state::<ProfileState>().settings().reminders;
+    let bundle = build(&backup::snapshot(&state)?, &config.get(), &reminders)?;
+    let bytes = backup::seal(to_json(&bundle)?.as_bytes(), &password)?;
+    disk::ensure_space(&dest, bytes.len() as u64, disk::reserve_bytes(&config))?;
+    backup::write_atomically(&dest, &bytes)?;
+    let info = FullBundleInfo {
+        path: dest.display().to_string(),
+        size_bytes: bytes.len() as u64,
+        sha256: hex::encode(Sha256::digest(&bytes)),
+        manifest: bundle.manifest,
+    };
+    audit.record(
+        AuditAction::Backup,
+        serde_json::json!({
+            "kind": "full_bundle",
+            "path": info.path,
+            "size_bytes": info.size_bytes,
+        }),
+    );
+    Ok(info)
+}
+
+// Replaces the entries, the settings (other than `MACHINE_SETTINGS`) and the
+// active profile's reminders. The settings and reminders are saved first and
+// put back if the entries then can't be replaced, so a failed import leaves
+// everything as it was.
+#[tauri::command]
+pub async fn import_full_bundle(
+    app: AppHandle,
+    state: tauri::State<'_, StorageState>,
+    audit: tauri::State<'_, AuditState>,
+    src_path: String,
+    password: String,
+    dry_run: Option<bool>,
+) -> Result<FullImportReport, BackupError> {
+    app.state::<GuestState>()
+        .refuse("Moving from another computer")?;
+    let src = validate::user_path(&app, "Bundle", &src_path)?;
+    validate::text("Password", &password, backup::MAX_PASSWORD_LEN)?;
+    let dry_run = dry_run.unwrap_or(false);
+
+    let bytes = std::fs::read(&src)
+        .map_err(|e| BackupError::Failed(format!("Failed to read {}: {}", src.display(), e)))?;
+    let bundle = open(&bytes, &password)?;
+    let (payload, migrated) = backup::upgrade_payload(section(&bundle, DATA)?)?;
+    let previous = app.state::<ConfigState>().get();
+    let (settings, settings_restored) = merged_settings(&previous, &bundle)?;
+    let reminders: Vec<Reminder> = section(&bundle, REMINDERS)?;
+    let reminders_restored = reminders.len();
+    let changes = state.with(|storage| storage.plan_replace(&payload.entries))?;
+
+    if !dry_run {
+        let profiles = app.state::<ProfileState>();
+        let previous_reminders = profiles.settings().reminders;
+        let put_back = || {
+            let reverted = config::update(&app, |config| *config = previous.clone()).and_then(|_| {
+                profiles.update_settings(|profile| profile.reminders = previous_reminders.clone())
+            });
+            if let Err(revert) = reverted {
+                eprintln!(
+                    "Failed to put the settings back after a failed import: {}",
+                    revert
+                );
+            }
+        };
+        config::update(&app, |config| *config = settings)?;
+        if let Err(e) = profiles.update_settings(|profile| profile.reminders = reminders) {
+            put_back();
+            return Err(e.into());
+        }
+        if let Err(e) = state.with(|storage| storage.replace_all(&payload.entries, &payload.history)) {
+            put_back();
+            return Err(e.into());
+        }
+        // The locale may have changed with the settings.
+        app.state::<I18nState>().reset();
+        audit.record(
+            AuditAction::Import,
+            serde_json::json!({
+                "source": "full_bundle",
+                "path": src,
+                "entries": payload.entries.len(),
+            }),
+        );
+    }
+    Ok(FullImportReport {
+        manifest: bundle.manifest,
+        entries_restored: payload.entries.len(),
+        reminders_restored,
+        settings_restored,
+        migrated,
+        changes,
+        dry_run,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Entry;
+
+    fn payload() -> BackupPayload {
+        BackupPayload {
+            schema_version: crate::storage::SCHEMA_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: "2026-05-01T12:00:00+00:00".to_string(),
+            entries: vec![Entry {
+                id: 1,
+                kind: "journal".to_string(),
+                content: "Made it to the Tuesday meeting".to_string(),
+                mood: Some(7.0),
+                created_at: "2026-05-01T11:00:00+00:00".to_string(),
+                updated_at: "2026-05-01T11:00:00+00:00".to_string(),
+            }],
+            history: Vec::new(),
+        }
+    }
+
+    fn sealed(bundle: &FullBundle) -> Vec<u8> {
+        backup::seal(to_json(bundle).unwrap().as_bytes(), "correct horse battery").unwrap()
+    }
+
+    #[test]
+    fn bundle_carries_settings_but_not_this_computers() {
+        let old = AppConfig {
+            zoom_level: 1.25,
+            sync_dir: Some("/Users/old/Dropbox/smartbot".to_string()),
+            ..AppConfig::default()
+        };
+        let bundle = open(
+            &sealed(&build(&payload(), &old, &[]).unwrap()),
+            "correct horse battery",
+        )
+        .unwrap();
+        assert_eq!(bundle.manifest.sections.len(), SECTIONS.len());
+
+        let new = AppConfig {
+            sync_dir: Some("D:\\Sync\\smartbot".to_string()),
+            ..AppConfig::default()
+        };
+        let (merged, _) = merged_settings(&new, &bundle).unwrap();
+        assert_eq!(merged.zoom_level, 1.25);
+        assert_eq!(merged.sync_dir, new.sync_dir);
+        let (data, migrated) = backup::upgrade_payload(section(&bundle, DATA).unwrap()).unwrap();
+        assert_eq!(data.entries.len(), 1);
+        assert!(!migrated);
+    }
+
+    #[test]
+    fn sections_that_dont_match_the_manifest_are_rejected() {
+        let mut bundle = build(&payload(), &AppConfig::default(), &[]).unwrap();
+        bundle
+            .sections
+            .insert(REMINDERS.to_string(), "[]  ".to_string());
+        assert!(matches!(
+            open(&sealed(&bundle), "correct horse battery"),
+            Err(BackupError::InvalidBackup(_))
+        ));
+
+        let mut bundle = build(&payload(), &AppConfig::default(), &[]).unwrap();
+        bundle
+            .manifest
+            .sections
+            .retain(|info| info.name != SETTINGS);
+        assert!(open(&sealed(&bundle), "correct horse battery").is_err());
+    }
+
+    #[test]
+    fn bundles_cant_add_trusted_program_keys() {
+        let old = AppConfig {
+            trusted_program_keys: vec!["counselor-key".to_string()],
+            ..AppConfig::default()
+        };
+        let mut bundle = build(&payload(), &old, &[]).unwrap();
+        assert!(!bundle.sections[SETTINGS].contains("trusted_program_keys"));
+
+        // A bundle put together by hand can still list some.
+        let mut settings: serde_json::Value = section(&bundle, SETTINGS).unwrap();
+        settings["trusted_program_keys"] = serde_json::json!(["someone-elses-key"]);
+        bundle
+            .sections
+            .insert(SETTINGS.to_string(), settings.to_string());
+        let (merged, _) = merged_settings(&AppConfig::default(), &bundle).unwrap();
+        assert!(merged.trusted_program_keys.is_empty());
+    }
+}
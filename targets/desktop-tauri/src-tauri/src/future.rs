@@ -0,0 +1,95 @@
+// Messages the user writes to their future self, delivered as a notification
+// at the chosen time and opened in the app from `future-message-delivered`.
+// They live in the profile's database rather than the config, so they
+// survive restarts and stay with the profile that wrote them. Unlike a
+// reminder, one that came due while the app was closed is delivered late
+// rather than dropped.
+
+use crate::background::Workers;
+use crate::guest::GuestState;
+use crate::i18n;
+use crate::notifications;
+use crate::storage::{FutureMessage, StorageError, StorageState};
+use crate::validate;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+// Also how long after launch overdue messages wait, so the frontend is
+// listening by then.
+const TICK: Duration = Duration::from_secs(30);
+const MAX_CONTENT_LEN: usize = 10_000;
+const MAX_DELAY: chrono::Duration = chrono::Duration::days(5 * 365);
+
+pub const FUTURE_MESSAGE_EVENT: &str = "future-message-delivered";
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn tick(app: &AppHandle) {
+    let now = timestamp(Utc::now());
+    let delivered = match app
+        .state::<StorageState>()
+        .with(|storage| storage.deliver_future_messages(&now))
+    {
+        Ok(delivered) => delivered,
+        Err(e) => {
+            eprintln!("Failed to deliver future messages: {}", e);
+            return;
+        }
+    };
+    for message in delivered {
+        // The text stays out of the notification, which may show on a lock
+        // screen.
+        let title = i18n::t(app, "future_message.title");
+        let body = i18n::t(app, "future_message.body");
+        if let Err(e) = notifications::notify(app, &title, &body, false) {
+            eprintln!("Failed to show future message {}: {}", message.id, e);
+        }
+        let _ = app.emit_all(FUTURE_MESSAGE_EVENT, &message);
+    }
+}
+
+pub fn start(app: &AppHandle) {
+    let handle = app.clone();
+    app.state::<Workers>().spawn(TICK, move || tick(&handle));
+}
+
+// `deliver_at` is RFC 3339 and may be up to five years out.
+#[tauri::command]
+pub fn schedule_future_message(
+    state: tauri::State<StorageState>,
+    guest: tauri::State<GuestState>,
+    content: String,
+    deliver_at: String,
+) -> Result<FutureMessage, StorageError> {
+    guest.refuse("Messages to your future self")?;
+    validate::non_empty("Message", &content, MAX_CONTENT_LEN)?;
+    let at = DateTime::parse_from_rfc3339(&deliver_at)
+        .map_err(|e| format!("Invalid delivery time {:?}: {}", deliver_at, e))?
+        .with_timezone(&Utc);
+    let now = Utc::now();
+    if at <= now {
+        return Err("Delivery times must be in the future".to_string().into());
+    }
+    if at - now > MAX_DELAY {
+        return Err("Delivery times can be at most five years away".to_string().into());
+    }
+    state.with(|storage| storage.add_future_message(&content, &timestamp(at)))
+}
+
+// Pending messages soonest first; with `delivered`, the ones already shown,
+// most recent first.
+#[tauri::command]
+pub fn list_future_messages(
+    state: tauri::State<StorageState>,
+    delivered: Option<bool>,
+) -> Result<Vec<FutureMessage>, StorageError> {
+    state.with(|storage| storage.future_messages(delivered.unwrap_or(false)))
+}
+
+#[tauri::command]
+pub fn cancel_future_message(state: tauri::State<StorageState>, id: i64) -> Result<(), StorageError> {
+    state.with(|storage| storage.cancel_future_message(id))
+}
@@ -0,0 +1,113 @@
+// Guest sessions, for a shared or public computer: entries go to an
+// in-memory database that is thrown away when the session ends or the app
+// closes. Backups, reminders and profile changes are refused meanwhile, and
+// the audit log only notes that a session started and ended.
+
+use crate::audit::{AuditAction, AuditState};
+use crate::error::AppError;
+use crate::i18n;
+use crate::profiles::{self, ProfileState};
+use crate::storage::{Storage, StorageState};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Manager};
+
+pub const GUEST_SESSION_EVENT: &str = "guest-session-changed";
+const WINDOW_TITLE: &str = "Smartbot Desktop";
+
+#[derive(Default)]
+pub struct GuestState(AtomicBool);
+
+impl GuestState {
+    pub fn active(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    // For actions that would write the guest's data somewhere lasting, or
+    // show them the regular user's.
+    pub fn refuse(&self, action: &str) -> Result<(), String> {
+        if self.active() {
+            return Err(format!("{} isn't available in a guest session", action));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GuestSession {
+    // While true, nothing entered is saved.
+    pub active: bool,
+}
+
+fn announce(app: &AppHandle, active: bool) -> GuestSession {
+    if let Some(window) = app.get_window("main") {
+        let title = if active {
+            i18n::t(app, "guest.window_title")
+        } else {
+            WINDOW_TITLE.to_string()
+        };
+        let _ = window.set_title(&title);
+    }
+    let session = GuestSession { active };
+    let _ = app.emit_all(GUEST_SESSION_EVENT, &session);
+    session
+}
+
+#[tauri::command]
+pub fn get_guest_session(state: tauri::State<GuestState>) -> GuestSession {
+    GuestSession {
+        active: state.active(),
+    }
+}
+
+// The regular store is closed, not just hidden, so nothing in the session
+// can reach it.
+#[tauri::command]
+pub fn start_guest_session(app: AppHandle) -> Result<GuestSession, AppError> {
+    let guest = app.state::<GuestState>();
+    if guest.active() {
+        return Err(AppError::InvalidInput(
+            "A guest session is already running".to_string(),
+        ));
+    }
+    let memory = Storage::open_in_memory()?;
+    let storage = app.state::<StorageState>();
+    storage.close();
+    *storage.0.lock().unwrap_or_else(|p| p.into_inner()) = Some(memory);
+    guest.0.store(true, Ordering::SeqCst);
+
+    let audit = app.state::<AuditState>();
+    audit.record(AuditAction::GuestSessionStarted, serde_json::json!({}));
+    audit.set_suspended(true);
+    Ok(announce(&app, true))
+}
+
+// Discards everything entered during the session and reopens the regular
+// store.
+#[tauri::command]
+pub fn end_guest_session(app: AppHandle) -> Result<GuestSession, AppError> {
+    let guest = app.state::<GuestState>();
+    if !guest.active() {
+        return Err(AppError::InvalidInput(
+            "No guest session is running".to_string(),
+        ));
+    }
+    let dir = app.state::<ProfileState>().active_dir()?;
+    profiles::open_stores(&app, &dir)?;
+    guest.0.store(false, Ordering::SeqCst);
+
+    let audit = app.state::<AuditState>();
+    audit.set_suspended(false);
+    audit.record(AuditAction::GuestSessionEnded, serde_json::json!({}));
+    Ok(announce(&app, false))
+}
+
+// Closing the app ends the session too; the in-memory store goes with the
+// process.
+pub fn on_exit(app: &AppHandle) {
+    if app.state::<GuestState>().active() {
+        let audit = app.state::<AuditState>();
+        audit.set_suspended(false);
+        audit.record(AuditAction::GuestSessionEnded, serde_json::json!({}));
+    }
+}
@@ -0,0 +1,141 @@
+// Minimal HTTP client built on the system `curl`, the same way the shell has
+// always probed the core, so no HTTP/TLS stack needs to be bundled.
+
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// How often a running request checks whether it was cancelled.
+const CANCEL_POLL: Duration = Duration::from_millis(25);
+
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+pub fn request(
+    method: &str,
+    url: &str,
+    body: Option<&str>,
+    timeout: Duration,
+) -> Result<Response, String> {
+    send(method, url, body, timeout, None, None)
+}
+
+// Read on its own thread so a large response can't fill the pipe while the
+// request is polled for cancellation.
+fn drain(stream: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut stream) = stream {
+            let _ = stream.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+// Set from another thread to abort a request in flight.
+#[derive(Default)]
+pub struct Cancel(AtomicBool);
+
+impl Cancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+// With a `cert`, only that certificate is trusted for https, never the
+// system store (for endpoints with a self-signed certificate). Cancelling or
+// running past `timeout` kills curl, so an abandoned request doesn't linger.
+pub fn send(
+    method: &str,
+    url: &str,
+    body: Option<&str>,
+    timeout: Duration,
+    cert: Option<&Path>,
+    cancel: Option<&Cancel>,
+) -> Result<Response, String> {
+    // `-X HEAD` would leave curl waiting for a body that never comes.
+    let head = method.eq_ignore_ascii_case("HEAD");
+    let mut cmd = Command::new("curl");
+    cmd.args(["-s", "-S"]);
+    if head {
+        cmd.arg("--head");
+    } else {
+        cmd.args(["-X", method]);
+    }
+    cmd.args(["--max-time", &timeout.as_secs().to_string()])
+        // Append the status code on its own line after the body.
+        .args(["-w", "\n%{http_code}"]);
+    if let Some(cert) = cert {
+        cmd.arg("--cacert").arg(cert);
+    }
+    if body.is_some() {
+        cmd.args(["-H", "Content-Type: application/json", "--data-binary", "@-"]);
+    }
+    cmd.arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to run curl: {}", e))?;
+    // Dropping stdin after writing closes it so curl sees the end of the body.
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Some(body) = body {
+            stdin
+                .write_all(body.as_bytes())
+                .map_err(|e| format!("Failed to send request body: {}", e))?;
+        }
+    }
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    // curl enforces `--max-time` itself; the deadline only catches a curl
+    // that doesn't.
+    let deadline = Instant::now() + timeout + Duration::from_secs(1);
+    let status = loop {
+        if let Some(status) = child
+            .try_wait()
+            .map_err(|e| format!("Failed to run curl: {}", e))?
+        {
+            break status;
+        }
+        let cancelled = cancel.is_some_and(Cancel::is_cancelled);
+        if cancelled || Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            let why = if cancelled { "was cancelled" } else { "timed out" };
+            return Err(format!("{} {} {}", method, url, why));
+        }
+        std::thread::sleep(CANCEL_POLL);
+    };
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        return Err(format!("{} {} failed: {}", method, url, stderr.trim()));
+    }
+
+    let stdout = String::from_utf8_lossy(&stdout);
+    let (body, status) = stdout.rsplit_once('\n').unwrap_or(("", &stdout));
+    let status = status
+        .trim()
+        .parse()
+        .map_err(|_| format!("{} {} returned no status code", method, url))?;
+    Ok(Response {
+        status,
+        // `--head` prints the headers where the body would go.
+        body: if head { String::new() } else { body.to_string() },
+    })
+}
@@ -0,0 +1,137 @@
+// Localized strings for text the shell shows itself (dialogs, and later
+// notifications). Translations are bundled as `resources/locales/<tag>.json`;
+// English is compiled in so a missing or broken file never leaves a dialog
+// blank.
+
+use crate::config::{self, ConfigState};
+use crate::error::AppError;
+use crate::validate;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Runtime};
+
+const FALLBACK_LOCALE: &str = "en";
+// Long enough for any BCP 47 tag in practice.
+const MAX_LOCALE_LEN: usize = 35;
+const FALLBACK_STRINGS: &str = include_str!("../resources/locales/en.json");
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocaleInfo {
+    pub system: Option<String>,
+    // The user's choice from config, if any.
+    pub preferred: Option<String>,
+    // The locale strings are actually being served from.
+    pub active: String,
+}
+
+struct Catalog {
+    locale: String,
+    strings: HashMap<String, String>,
+    fallback: HashMap<String, String>,
+}
+
+#[derive(Default)]
+pub struct I18nState(Mutex<Option<Catalog>>);
+
+impl I18nState {
+    // The catalog is built again on next use, for a changed locale.
+    pub fn reset(&self) {
+        *self.0.lock().unwrap_or_else(|p| p.into_inner()) = None;
+    }
+}
+
+pub fn system_locale() -> Option<String> {
+    sys_locale::get_locale()
+}
+
+fn parse(json: &str) -> Option<HashMap<String, String>> {
+    serde_json::from_str(json).ok()
+}
+
+fn load_locale<R: Runtime>(app: &AppHandle<R>, locale: &str) -> Option<HashMap<String, String>> {
+    let path = app
+        .path_resolver()
+        .resolve_resource(format!("resources/locales/{}.json", locale))?;
+    parse(&std::fs::read_to_string(path).ok()?)
+}
+
+// "pt_BR.UTF-8" -> ["pt-BR", "pt"]: try the full tag, then the language.
+fn candidates(locale: &str) -> Vec<String> {
+    let tag = locale.split('.').next().unwrap_or(locale).replace('_', "-");
+    let mut candidates = vec![tag.clone()];
+    if let Some((language, _)) = tag.split_once('-') {
+        candidates.push(language.to_string());
+    }
+    candidates
+}
+
+// The configured locale, else the system's, before any catalog lookup.
+pub fn requested_locale<R: Runtime>(app: &AppHandle<R>) -> String {
+    app.state::<ConfigState>()
+        .get()
+        .locale
+        .or_else(system_locale)
+        .unwrap_or_else(|| FALLBACK_LOCALE.to_string())
+}
+
+fn build<R: Runtime>(app: &AppHandle<R>) -> Catalog {
+    let fallback = parse(FALLBACK_STRINGS).unwrap_or_default();
+    let requested = requested_locale(app);
+
+    for candidate in candidates(&requested) {
+        if let Some(strings) = load_locale(app, &candidate) {
+            return Catalog {
+                locale: candidate,
+                strings,
+                fallback,
+            };
+        }
+    }
+    Catalog {
+        locale: FALLBACK_LOCALE.to_string(),
+        strings: HashMap::new(),
+        fallback,
+    }
+}
+
+// Look up `key` in the active locale, then English, then return the key
+// itself so a missing string is visible rather than empty.
+pub fn t<R: Runtime>(app: &AppHandle<R>, key: &str) -> String {
+    let state = app.state::<I18nState>();
+    let mut catalog = state.0.lock().unwrap_or_else(|p| p.into_inner());
+    let catalog = catalog.get_or_insert_with(|| build(app));
+    catalog
+        .strings
+        .get(key)
+        .or_else(|| catalog.fallback.get(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_string())
+}
+
+fn active_locale(app: &AppHandle) -> String {
+    let state = app.state::<I18nState>();
+    let mut catalog = state.0.lock().unwrap_or_else(|p| p.into_inner());
+    catalog.get_or_insert_with(|| build(app)).locale.clone()
+}
+
+#[tauri::command]
+pub fn get_locale(app: AppHandle) -> LocaleInfo {
+    LocaleInfo {
+        system: system_locale(),
+        preferred: app.state::<ConfigState>().get().locale,
+        active: active_locale(&app),
+    }
+}
+
+// `None` goes back to following the system locale.
+#[tauri::command]
+pub fn set_locale(app: AppHandle, locale: Option<String>) -> Result<LocaleInfo, AppError> {
+    // Locales name resource files, so nothing path-like gets through.
+    if let Some(locale) = &locale {
+        validate::identifier("Locale", locale, MAX_LOCALE_LEN)?;
+    }
+    config::update(&app, |config| config.locale = locale)?;
+    app.state::<I18nState>().reset();
+    Ok(get_locale(app))
+}
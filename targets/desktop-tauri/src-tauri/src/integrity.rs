@@ -0,0 +1,62 @@
+// Weekly integrity check of the local database. Corruption in a local-only
+// store is otherwise invisible until data fails to load, and by then the
+// newest backup may be older than it needs to be.
+
+use crate::background::Workers;
+use crate::config::{self, ConfigState};
+use crate::error::AppError;
+use crate::storage::StorageState;
+use serde_json::json;
+use tauri::{AppHandle, Manager};
+
+const CHECK_INTERVAL: chrono::Duration = chrono::Duration::days(7);
+const TICK: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+pub const INTEGRITY_FAILED_EVENT: &str = "integrity-check-failed";
+
+fn check(app: &AppHandle) -> Result<serde_json::Value, String> {
+    let report = app.state::<StorageState>().with(|storage| Ok(storage.verify()))?;
+    let ok = report.ok;
+    let checked_at = report.checked_at.clone();
+    config::update(app, |config| config.integrity_checked_at = Some(checked_at))?;
+
+    let recommendation = (!ok).then_some(
+        "Your data may be damaged. Restore your most recent backup, or run a repair if you don't have one.",
+    );
+    let result = json!({ "ok": ok, "report": report, "recommendation": recommendation });
+    if !ok {
+        let _ = app.emit_all(INTEGRITY_FAILED_EVENT, &result);
+    }
+    Ok(result)
+}
+
+fn is_due(app: &AppHandle) -> bool {
+    let last = app.state::<ConfigState>().get().integrity_checked_at;
+    let last = last.and_then(|at| chrono::DateTime::parse_from_rfc3339(&at).ok());
+    last.is_none_or(|at| chrono::Utc::now().signed_duration_since(at) >= CHECK_INTERVAL)
+}
+
+fn tick(app: &AppHandle) {
+    if is_due(app) {
+        if let Err(e) = check(app) {
+            eprintln!("Scheduled integrity check failed: {}", e);
+        }
+    }
+}
+
+// Checked hourly against the stored timestamp, so a week of uptime or a
+// launch after a week away both trigger it.
+pub fn start(app: &AppHandle) {
+    let handle = app.clone();
+    std::thread::spawn(move || tick(&handle));
+    let handle = app.clone();
+    app.state::<Workers>().spawn(TICK, move || tick(&handle));
+}
+
+#[tauri::command]
+pub async fn check_integrity(app: AppHandle) -> Result<serde_json::Value, AppError> {
+    let report = tauri::async_runtime::spawn_blocking(move || check(&app))
+        .await
+        .map_err(|e| format!("Integrity check failed: {}", e))??;
+    Ok(report)
+}
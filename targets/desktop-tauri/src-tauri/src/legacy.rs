@@ -0,0 +1,379 @@
+// Importing data left behind by pre-release builds, which kept everything in
+// `~/.smartbot` before the app moved to the platform data directory. A
+// legacy folder is recognized by its `smartbot.db` having an `entries` table;
+// older databases may lack later columns, which are filled in before the
+// data goes through the same payload upgrades as a restored backup.
+//
+// The legacy files are copied aside first and only ever read, and the import
+// is refused unless the current store is empty, so nothing is merged or
+// overwritten.
+
+use crate::audit::{AuditAction, AuditState};
+use crate::backup::{self, BackupError};
+use crate::config::{self, ConfigState, CONFIG_FILE_NAME};
+use crate::guest::GuestState;
+use crate::i18n;
+use crate::paths;
+use crate::profiles::ProfileState;
+use crate::storage::{Entry, ReplacePlan, StorageState, DB_FILE_NAME};
+use crate::validate;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+pub const LEGACY_DIR_NAME: &str = ".smartbot";
+
+pub const LEGACY_IMPORTED_EVENT: &str = "legacy-data-imported";
+pub const LEGACY_IMPORT_FAILED_EVENT: &str = "legacy-data-import-failed";
+
+const LEGACY_FILES: [&str; 5] = [
+    DB_FILE_NAME,
+    "smartbot.db-wal",
+    "smartbot.db-shm",
+    CONFIG_FILE_NAME,
+    "bandit_state.json",
+];
+
+// Preferences carried over from a legacy `config.json`; anything else keeps
+// the current defaults, since older builds had no notion of most settings.
+const LEGACY_SETTINGS: [&str; 4] = ["locale", "ui_scale", "zoom_level", "splash_enabled"];
+
+#[derive(Debug, Serialize)]
+pub struct MigrationReport {
+    pub legacy_dir: String,
+    pub legacy_backup_dir: String,
+    pub files_backed_up: Vec<String>,
+    pub legacy_schema_version: i64,
+    pub entries_imported: usize,
+    pub settings_imported: Vec<String>,
+    pub migrated: bool,
+    pub changes: ReplacePlan,
+    // Nothing was copied or imported; `legacy_backup_dir` is where the copy
+    // would go.
+    pub dry_run: bool,
+}
+
+fn unrecognized(dir: &Path) -> BackupError {
+    BackupError::InvalidBackup(format!(
+        "{} doesn't contain data from an earlier version of Smartbot",
+        dir.display()
+    ))
+}
+
+// Returns the database's schema version along with its entries, shaped like
+// schema version 1.
+fn read_entries(dir: &Path) -> Result<(i64, Vec<Entry>), BackupError> {
+    let db = dir.join(DB_FILE_NAME);
+    if !db.is_file() {
+        return Err(unrecognized(dir));
+    }
+    let failed = |e: rusqlite::Error| {
+        BackupError::InvalidBackup(format!("Failed to read {}: {}", db.display(), e))
+    };
+    let conn = Connection::open_with_flags(&db, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(failed)?;
+    let version: i64 = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(failed)?;
+
+    let columns: Vec<String> = {
+        let mut stmt = conn.prepare("PRAGMA table_info(entries)").map_err(failed)?;
+        let names = stmt.query_map([], |row| row.get(1)).map_err(failed)?;
+        names.collect::<Result<_, _>>().map_err(failed)?
+    };
+    let has = |column: &str| columns.iter().any(|c| c == column);
+    if !["id", "kind", "content", "created_at"].iter().all(|column| has(column)) {
+        return Err(unrecognized(dir));
+    }
+    let mood = if has("mood") { "mood" } else { "NULL" };
+    let updated_at = if has("updated_at") { "updated_at" } else { "created_at" };
+
+    let sql = format!(
+        "SELECT id, kind, content, {mood}, created_at, {updated_at} FROM entries ORDER BY id"
+    );
+    let mut stmt = conn.prepare(&sql).map_err(failed)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(Entry {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                content: row.get(2)?,
+                mood: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })
+        .map_err(failed)?;
+    let entries = rows.collect::<Result<_, _>>().map_err(failed)?;
+    Ok((version, entries))
+}
+
+fn existing_files(dir: &Path) -> Vec<&'static str> {
+    LEGACY_FILES
+        .into_iter()
+        .filter(|name| dir.join(name).exists())
+        .collect()
+}
+
+fn copy_aside(dir: &Path, backup_dir: &Path, files: &[&str]) -> Result<(), BackupError> {
+    std::fs::create_dir_all(backup_dir)
+        .map_err(|e| format!("Failed to create {}: {}", backup_dir.display(), e))?;
+    for name in files {
+        let src = dir.join(name);
+        std::fs::copy(&src, backup_dir.join(name))
+            .map_err(|e| format!("Failed to back up {}: {}", src.display(), e))?;
+    }
+    Ok(())
+}
+
+// Merged through JSON so each setting is validated by the config's own
+// deserialization; a legacy value of the wrong type is skipped. Returns
+// `current` with the legacy settings applied, or `None` if there is nothing to
+// import.
+fn merge_settings(
+    current: &config::AppConfig,
+    dir: &Path,
+) -> Result<(Option<config::AppConfig>, Vec<String>), String> {
+    let Ok(contents) = std::fs::read_to_string(dir.join(CONFIG_FILE_NAME)) else {
+        return Ok((None, Vec::new()));
+    };
+    let Ok(legacy) = serde_json::from_str::<serde_json::Value>(&contents) else {
+        return Ok((None, Vec::new()));
+    };
+
+    let mut imported = Vec::new();
+    let mut merged = serde_json::to_value(current)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    for key in LEGACY_SETTINGS {
+        let Some(value) = legacy.get(key) else {
+            continue;
+        };
+        let mut candidate = merged.clone();
+        candidate[key] = value.clone();
+        if serde_json::from_value::<config::AppConfig>(candidate.clone()).is_ok() {
+            merged = candidate;
+            imported.push(key.to_string());
+        }
+    }
+    let merged: config::AppConfig = serde_json::from_value(merged)
+        .map_err(|e| format!("Failed to apply legacy settings: {}", e))?;
+    Ok((Some(merged), imported))
+}
+
+// With `dry_run`, every check and conversion still runs so the report matches
+// what a real import would do, but nothing is written.
+pub fn migrate(app: &AppHandle, dir: &Path, dry_run: bool) -> Result<MigrationReport, BackupError> {
+    let current = app.state::<ProfileState>().active_dir()?;
+    if current.canonicalize().ok() == dir.canonicalize().ok() {
+        return Err(BackupError::InvalidInput(
+            "That folder is where Smartbot already keeps its data".to_string(),
+        ));
+    }
+    let (version, entries) = read_entries(dir)?;
+    let state = app.state::<StorageState>();
+    if state.with(|storage| storage.entry_count())? > 0 {
+        return Err(BackupError::InvalidInput(
+            "Data from an earlier version can only be imported while Smartbot has no entries of its own"
+                .to_string(),
+        ));
+    }
+    // Before anything is written, so settings that can't be merged leave
+    // nothing copied aside or imported.
+    let (settings, settings_imported) =
+        merge_settings(&app.state::<ConfigState>().get(), dir)?;
+
+    let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+    let legacy_backup_dir = paths::data_dir(app)?.join(format!("legacy-backup-{}", timestamp));
+    let files = existing_files(dir);
+    if !dry_run {
+        copy_aside(dir, &legacy_backup_dir, &files)?;
+    }
+    // Pre-release databases predate schema versioning (version 0), but their
+    // entries now have the version 1 shape.
+    let payload = serde_json::json!({
+        "schema_version": version.max(1),
+        "app_version": "legacy",
+        "created_at": chrono::Utc::now().to_rfc3339(),
+        "entries": entries,
+    });
+    let (payload, migrated) = backup::upgrade_payload(payload)?;
+    let changes = state.with(|storage| {
+        let changes = storage.plan_replace(&payload.entries)?;
+        if !dry_run {
+            storage.replace_all(&payload.entries, &payload.history)?;
+        }
+        Ok(changes)
+    })?;
+    let report = MigrationReport {
+        legacy_dir: dir.display().to_string(),
+        legacy_backup_dir: legacy_backup_dir.display().to_string(),
+        files_backed_up: files.iter().map(|name| name.to_string()).collect(),
+        legacy_schema_version: version,
+        entries_imported: payload.entries.len(),
+        settings_imported,
+        migrated,
+        changes,
+        dry_run,
+    };
+    if dry_run {
+        return Ok(report);
+    }
+    if let Some(settings) = settings {
+        config::update(app, |config| *config = settings)?;
+    }
+
+    app.state::<AuditState>().record(
+        AuditAction::Import,
+        serde_json::json!({
+            "source": "legacy",
+            "path": dir,
+            "entries": payload.entries.len(),
+        }),
+    );
+    Ok(report)
+}
+
+// Asked once: when a legacy folder with entries exists and the current store
+// is still empty. The answer is remembered either way.
+pub fn offer(app: &AppHandle) {
+    if app.state::<ConfigState>().get().legacy_import_offered {
+        return;
+    }
+    let Some(dir) = tauri::api::path::home_dir().map(|home| home.join(LEGACY_DIR_NAME)) else {
+        return;
+    };
+    let legacy_entries = read_entries(&dir).map(|(_, entries)| entries.len()).unwrap_or(0);
+    let store_empty = app
+        .state::<StorageState>()
+        .with(|storage| storage.entry_count())
+        .is_ok_and(|count| count == 0);
+    if legacy_entries == 0 || !store_empty {
+        return;
+    }
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = config::update(&app, |config| config.legacy_import_offered = true) {
+            eprintln!("Failed to save the legacy import offer: {}", e);
+        }
+        let window = app.get_window("main");
+        let accepted = tauri::api::dialog::blocking::confirm(
+            window.as_ref(),
+            i18n::t(&app, "legacy_import.title"),
+            i18n::t(&app, "legacy_import.body").replace("{count}", &legacy_entries.to_string()),
+        );
+        if !accepted {
+            return;
+        }
+        match migrate(&app, &dir, false) {
+            Ok(report) => {
+                let _ = app.emit_all(LEGACY_IMPORTED_EVENT, &report);
+            }
+            Err(e) => {
+                eprintln!("Legacy data import failed: {}", e);
+                let _ = app.emit_all(LEGACY_IMPORT_FAILED_EVENT, &e);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn migrate_legacy_data(
+    app: AppHandle,
+    legacy_dir: String,
+    dry_run: Option<bool>,
+) -> Result<MigrationReport, BackupError> {
+    app.state::<GuestState>().refuse("Importing earlier data")?;
+    let dir = validate::user_path(&app, "Legacy data folder", &legacy_dir)?;
+    let dry_run = dry_run.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || migrate(&app, &dir, dry_run))
+        .await
+        .map_err(|e| BackupError::Failed(format!("Legacy data import failed: {}", e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The shape of a pre-release `~/.smartbot`: no schema version, entries
+    // without `mood` or `updated_at`, and a config with only a few settings.
+    fn legacy_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("smartbot-legacy-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let conn = Connection::open(dir.join(DB_FILE_NAME)).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE entries (
+                 id INTEGER PRIMARY KEY,
+                 kind TEXT NOT NULL,
+                 content TEXT NOT NULL,
+                 created_at TEXT NOT NULL
+             );
+             INSERT INTO entries VALUES (7, 'journal', 'First entry', '2023-02-01T09:00:00+00:00');
+             INSERT INTO entries VALUES (3, 'gratitude', 'Sunny', '2023-01-15T18:30:00+00:00');",
+        )
+        .unwrap();
+        let config = serde_json::json!({
+            "locale": "es",
+            "ui_scale": 1.25,
+            "zoom_level": "large",
+            "always_on_top": true,
+        });
+        std::fs::write(dir.join(CONFIG_FILE_NAME), config.to_string()).unwrap();
+        dir
+    }
+
+    #[test]
+    fn old_schema_entries_are_read_in_the_current_shape() {
+        let dir = legacy_dir();
+        let (version, entries) = read_entries(&dir).unwrap();
+        assert_eq!(version, 0);
+
+        let read: Vec<_> = entries
+            .iter()
+            .map(|e| (e.id, e.kind.as_str(), e.content.as_str(), e.mood))
+            .collect();
+        assert_eq!(read, [(3, "gratitude", "Sunny", None), (7, "journal", "First entry", None)]);
+        assert_eq!(entries[0].updated_at, entries[0].created_at);
+
+        let payload = serde_json::json!({
+            "schema_version": version.max(1),
+            "app_version": "legacy",
+            "created_at": "2026-05-01T00:00:00+00:00",
+            "entries": entries,
+        });
+        let (payload, migrated) = backup::upgrade_payload(payload).unwrap();
+        assert!(migrated);
+        assert_eq!(payload.entries.len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_valid_legacy_settings_are_merged() {
+        let dir = legacy_dir();
+        let current = config::AppConfig::default();
+        let (merged, imported) = merge_settings(&current, &dir).unwrap();
+        let merged = merged.unwrap();
+
+        // `zoom_level` has the wrong type and `always_on_top` isn't carried
+        // over, so both keep the current values.
+        assert_eq!(imported, ["locale", "ui_scale"]);
+        assert_eq!(merged.locale.as_deref(), Some("es"));
+        assert_eq!(merged.ui_scale, 1.25);
+        assert_eq!(merged.zoom_level, current.zoom_level);
+        assert_eq!(merged.always_on_top, current.always_on_top);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn folders_without_an_entries_table_are_not_recognized() {
+        let dir = std::env::temp_dir().join(format!("smartbot-legacy-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(matches!(read_entries(&dir), Err(BackupError::InvalidBackup(_))));
+
+        let conn = Connection::open(dir.join(DB_FILE_NAME)).unwrap();
+        conn.execute_batch("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT);").unwrap();
+        assert!(matches!(read_entries(&dir), Err(BackupError::InvalidBackup(_))));
+        drop(conn);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
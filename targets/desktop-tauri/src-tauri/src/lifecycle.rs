@@ -0,0 +1,92 @@
+// App shutdown: confirm before quitting mid-write, and always stop the core
+// on the way out.
+
+use crate::core::{self, CoreState};
+use crate::error::AppError;
+use crate::export::ExportJobs;
+use crate::storage::StorageState;
+use crate::{guest, i18n, ui_state, validate};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, CloseRequestApi, Manager, Window};
+
+pub const APP_RESTARTING_EVENT: &str = "app-restarting";
+
+const MAX_REASON_LEN: usize = 500;
+// Exports check for cancellation between entries, so this is only a backstop.
+const EXPORT_CANCEL_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize)]
+struct Restarting {
+    // Shown by the frontend, e.g. "Restarting to use the new data folder".
+    reason: Option<String>,
+}
+
+// Set by the frontend while it is persisting data (e.g. saving a journal
+// entry) so closing the window can warn first.
+#[derive(Default)]
+pub struct BusyState(AtomicBool);
+
+#[tauri::command]
+pub fn set_busy(state: tauri::State<BusyState>, busy: bool) {
+    state.0.store(busy, Ordering::SeqCst);
+}
+
+// Full restart, for settings that only apply at launch (a new data folder,
+// encryption) and after an import or a profile switch. Refused while the
+// frontend is mid-write. Otherwise `app-restarting` goes out first, so the
+// frontend can say why the window is about to go, and saves its UI state.
+// Exports are cancelled, and background work, the core and the database are
+// all shut down before the new process starts, since `restart` exits without
+// running the usual exit handling. Async so joining workers and waiting on
+// the core never blocks the main thread.
+#[tauri::command]
+pub async fn restart_app(app: AppHandle, reason: Option<String>) -> Result<(), AppError> {
+    if app.state::<BusyState>().0.load(Ordering::SeqCst) {
+        return Err("Smartbot is still saving; try again in a moment".into());
+    }
+    if let Some(reason) = &reason {
+        validate::text("Reason", reason, MAX_REASON_LEN)?;
+    }
+    let _ = app.emit_all(APP_RESTARTING_EVENT, Restarting { reason });
+    ui_state::save_before_restart(&app);
+    app.state::<ExportJobs>().cancel_all(EXPORT_CANCEL_TIMEOUT);
+    app.state::<crate::background::Workers>().stop_all();
+    guest::on_exit(&app);
+    core::shutdown(&app.state::<CoreState>());
+    app.state::<StorageState>().close();
+    app.restart();
+    Ok(())
+}
+
+// The earlier name for `restart_app`, without a reason.
+#[tauri::command]
+pub async fn relaunch(app: AppHandle) -> Result<(), AppError> {
+    restart_app(app, None).await
+}
+
+pub fn on_close_requested(window: &Window, api: &CloseRequestApi) {
+    if !window.state::<BusyState>().0.load(Ordering::SeqCst) {
+        // Idle: the main window closes once the frontend has saved where the
+        // user left off; the core is stopped on RunEvent::Exit.
+        if window.label() == "main" {
+            crate::ui_state::on_close_requested(window, api);
+        }
+        return;
+    }
+
+    api.prevent_close();
+    let app = window.app_handle();
+    tauri::api::dialog::confirm(
+        Some(window),
+        i18n::t(&app, "quit_while_saving.title"),
+        i18n::t(&app, "quit_while_saving.body"),
+        move |confirmed| {
+            if confirmed {
+                core::shutdown(&app.state::<CoreState>());
+                app.exit(0);
+            }
+        },
+    );
+}
@@ -1,41 +1,74 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::process::Command;
+mod accessibility;
+mod analytics;
+mod audit;
+mod autobackup;
+mod autolock;
+mod background;
+mod badge;
+mod backup;
+mod benchmark;
+mod biometric;
+mod breaker;
+mod cache;
+mod cli;
+mod clipboard;
+mod config;
+mod connectivity;
+mod core;
+mod crash;
+mod data_location;
+mod disk;
+mod encryption;
+mod duplicates;
+mod error;
+mod export;
+mod fallback;
+mod features;
+mod file_drop;
+mod full_bundle;
+mod future;
+mod guest;
+mod http;
+mod i18n;
+mod integrity;
+mod legacy;
+mod lifecycle;
+mod meetings;
+mod navigation;
+mod notifications;
+mod on_top;
+mod onboarding;
+mod paths;
+mod power;
+mod privacy;
+mod processes;
+mod profiles;
+mod programs;
+mod reminders;
+mod resources;
+mod reveal;
+mod routes;
+mod share;
+mod singleflight;
+mod splash;
+mod storage;
+mod sync;
+mod theme;
+mod tls;
+mod ui_state;
+mod updates;
+mod usage;
+mod validate;
+mod warmup;
+mod widget;
+mod worksheets;
+mod zoom;
 
-// Tauri command to ensure the FastAPI core is running
-#[tauri::command]
-fn ensure_core() -> Result<String, String> {
-    // Check if core is already running
-    let output = Command::new("curl")
-        .args(&["-s", "http://localhost:8000/"])
-        .output();
-        
-    match output {
-        Ok(result) => {
-            if result.status.success() {
-                return Ok("Core already running".to_string());
-            }
-        }
-        Err(_) => {
-            // curl might not be available, try to start core anyway
-        }
-    }
-    
-    // Try to start the FastAPI core
-    // This is a simplified approach - in production you'd want better process management
-    let core_path = std::env::current_dir()
-        .map_err(|e| format!("Failed to get current directory: {}", e))?
-        .join("../../../template");
-    
-    let _child = Command::new("python")
-        .args(&["-m", "uvicorn", "template.core.main:app", "--host", "127.0.0.1", "--port", "8000"])
-        .current_dir(&core_path)
-        .spawn()
-        .map_err(|e| format!("Failed to start core: {}. Please ensure Python and dependencies are installed.", e))?;
-        
-    Ok("Attempted to start core".to_string())
-}
+use std::sync::Mutex;
+use tauri::{FileDropEvent, Manager, RunEvent, WindowBuilder, WindowEvent, WindowUrl};
 
 #[tauri::command]
 fn get_app_info() -> serde_json::Value {
@@ -49,11 +82,310 @@ fn get_app_info() -> serde_json::Value {
 }
 
 fn main() {
+    cli::attach_console();
+    let args = match cli::parse(std::env::args().skip(1)) {
+        Ok(cli::Command::Run(args)) => args,
+        Ok(cli::Command::Help) => {
+            println!("{}", cli::HELP);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::HELP);
+            std::process::exit(2);
+        }
+    };
+
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![ensure_core, get_app_info])
-        .setup(|app| {
-            // Try to ensure core is running on app startup
-            let _ = ensure_core();
+        .invoke_handler(tauri::generate_handler![
+            core::cancel_request,
+            core::ensure_core,
+            core::get_core_base_url,
+            core::core_request,
+            core::get_core_status,
+            core::get_core_version,
+            core::get_versions,
+            core::set_core_log_level,
+            core::set_startup_timeout,
+            config::get_effective_config,
+            config::validate_config,
+            crash::get_crash_reports,
+            crash::purge_logs,
+            get_app_info,
+            accessibility::get_reduced_motion,
+            accessibility::get_ui_scale,
+            accessibility::set_reduced_motion,
+            accessibility::set_ui_scale,
+            analytics::mood_trend,
+            audit::get_audit_log,
+            autobackup::get_auto_backup_status,
+            autobackup::set_auto_backup_destination,
+            autobackup::set_auto_backup_interval,
+            autobackup::set_auto_backup_retention,
+            autolock::record_activity,
+            backup::create_backup,
+            backup::restore_backup,
+            badge::set_badge_count,
+            benchmark::benchmark_startup,
+            biometric::biometric_unlock,
+            cache::clear_core_cache,
+            clipboard::copy_sensitive,
+            clipboard::copy_text,
+            connectivity::get_connectivity,
+            data_location::get_storage_unavailable,
+            disk::check_disk_space,
+            duplicates::find_duplicate_entries,
+            duplicates::merge_entries,
+            encryption::encrypt_existing_store,
+            encryption::rotate_storage_key,
+            encryption::storage_encryption_status,
+            encryption::unlock_storage,
+            export::cancel_export,
+            export::export_entries,
+            export::get_export_format,
+            export::save_transcript,
+            features::get_features,
+            features::set_features,
+            full_bundle::export_full_bundle,
+            full_bundle::import_full_bundle,
+            future::cancel_future_message,
+            future::list_future_messages,
+            future::schedule_future_message,
+            guest::end_guest_session,
+            guest::get_guest_session,
+            guest::start_guest_session,
+            i18n::get_locale,
+            i18n::set_locale,
+            integrity::check_integrity,
+            legacy::migrate_legacy_data,
+            lifecycle::relaunch,
+            lifecycle::restart_app,
+            lifecycle::set_busy,
+            meetings::parse_meeting_time,
+            on_top::get_always_on_top,
+            on_top::set_always_on_top,
+            navigation::open_external,
+            notifications::get_notification_stats,
+            notifications::test_notification,
+            onboarding::complete_onboarding,
+            onboarding::complete_onboarding_step,
+            onboarding::get_onboarding_state,
+            privacy::get_privacy_mode,
+            privacy::set_privacy_mode,
+            processes::kill_core_process,
+            processes::list_core_processes,
+            profiles::create_profile,
+            profiles::delete_profile,
+            profiles::list_profiles,
+            profiles::switch_profile,
+            programs::import_program,
+            programs::list_programs,
+            programs::remove_program,
+            reminders::acknowledge_reminders,
+            reminders::cancel_reminder,
+            reminders::list_reminders,
+            reminders::pending_reminders,
+            reminders::pause_reminders,
+            reminders::reminders_paused_state,
+            reminders::resume_reminders,
+            reminders::schedule_reminder,
+            reminders::set_reminder_adaptation,
+            reminders::set_reminder_timezone,
+            resources::verify_resources,
+            reveal::reveal_in_explorer,
+            routes::core_routes,
+            share::export_share_summary,
+            share::open_share_summary,
+            splash::frontend_ready,
+            storage::add_entry,
+            storage::force_unlock_storage,
+            storage::get_entry_history,
+            storage::list_entries,
+            storage::verify_storage,
+            storage::repair_storage,
+            storage::update_entry,
+            sync::get_sync_status,
+            sync::set_sync_folder,
+            theme::get_system_theme,
+            ui_state::load_ui_state,
+            ui_state::save_ui_state,
+            updates::check_for_updates,
+            updates::install_update,
+            usage::get_storage_stats,
+            warmup::warm_core,
+            widget::toggle_widget_mode,
+            widget::update_widget,
+            worksheets::get_worksheet,
+            worksheets::list_worksheets,
+            worksheets::save_worksheet_response,
+            zoom::get_zoom,
+            zoom::set_zoom
+        ])
+        .manage(core::CoreState::default())
+        .manage(cache::ResponseCache::default())
+        .manage(export::ExportJobs::default())
+        .manage(routes::RoutesCache::default())
+        .manage(singleflight::InFlight::default())
+        .manage(lifecycle::BusyState::default())
+        .manage(autolock::AutoLockState::default())
+        .manage(autobackup::AutoBackupState::default())
+        .manage(sync::SyncState::default())
+        .manage(background::Workers::default())
+        .manage(reminders::PendingReminders::default())
+        .manage(connectivity::ConnectivityState::default())
+        .manage(i18n::I18nState::default())
+        .manage(widget::WidgetState::default())
+        .manage(notifications::NotificationState::default())
+        .manage(ui_state::UiState::default())
+        .manage(fallback::FallbackState::default())
+        .manage(guest::GuestState::default())
+        .manage(data_location::StorageUnavailableState::default())
+        .manage(warmup::WarmupState::default())
+        .manage(splash::RevealState::default())
+        .manage(resources::ResourceState::default())
+        .setup(move |app| {
+            // An explicit data directory that can't be used is a setup error
+            // the user needs to fix, not something to silently work around.
+            // Any other folder that can't be used gets the user asked for
+            // another, before anything resolves a path.
+            let storage_available = if paths::is_set_by_env() {
+                let dir = paths::data_dir(&app.handle())?;
+                if let Err(e) = paths::ensure_writable(&dir) {
+                    tauri::api::dialog::blocking::message(
+                        None::<&tauri::Window>,
+                        "Smartbot can't use its data folder",
+                        format!("{}\n\nCheck the {} setting and try again.", e, paths::DATA_DIR_ENV),
+                    );
+                    return Err(e.into());
+                }
+                true
+            } else {
+                data_location::check(&app.handle())
+            };
+            // Crash reports go to the log folder, so only once it is settled.
+            crash::install(&app.handle());
+
+            // Before the config is loaded, which drops the settings the
+            // default profile takes over from it.
+            let config_path = config::config_path(&app.handle());
+            let profiles = paths::data_dir(&app.handle())
+                .map(|root| profiles::ProfileState::load(root, config_path.as_deref()))
+                .unwrap_or_default();
+            let profile_dir = profiles.active_dir();
+            app.manage(profiles);
+
+            // Open the local data store. A failure here shouldn't stop the app
+            // from launching; storage commands will report it instead. A
+            // folder that failed `data_location::check` isn't tried, since a
+            // read-only one could still open and then fail every save.
+            let storage = profile_dir
+                .clone()
+                .map_err(storage::StorageError::from)
+                .and_then(|dir| {
+                    if !storage_available {
+                        return Err(storage::StorageError::Failed(format!(
+                            "{} can't be used",
+                            dir.display()
+                        )));
+                    }
+                    storage::Storage::open(&dir.join(storage::DB_FILE_NAME))
+                });
+            match &storage {
+                // Waits for `unlock_storage`.
+                Err(storage::StorageError::Locked(_)) => {}
+                Err(e) => eprintln!("Failed to open local storage: {}", e),
+                Ok(_) => {}
+            }
+            app.manage(storage::StorageState(Mutex::new(storage.ok())));
+
+            let audit = profile_dir
+                .and_then(|dir| audit::AuditState::open(dir.join(audit::AUDIT_FILE_NAME)))
+                .unwrap_or_else(|e| {
+                    eprintln!("Failed to open audit log: {}", e);
+                    audit::AuditState::default()
+                });
+            app.manage(audit);
+
+            let first_run = !config_path.as_ref().is_some_and(|path| path.exists());
+            app.manage(onboarding::FirstRun(first_run.into()));
+            let (config, config_error) = config_path
+                .map(|path| config::load(&path))
+                .unwrap_or_default();
+            app.manage(config::ConfigErrorState(Mutex::new(config_error)));
+            if args.core_only {
+                app.manage(config::ConfigState(Mutex::new(config)));
+                cli::run_core_only(&app.handle());
+            }
+            // The splash waits on the core, and would cover a minimized
+            // window once the core is up.
+            let splash_enabled = config.splash_enabled && !args.minimized && !args.no_core;
+
+            // The main window is built here rather than in tauri.conf.json so
+            // navigation can be filtered before any page loads.
+            let handle = app.handle();
+            let internal_origins = config.internal_origins.clone();
+            let window = WindowBuilder::new(app, "main", WindowUrl::default())
+                .title("Smartbot Desktop")
+                .inner_size(1200.0, 800.0)
+                .min_inner_size(800.0, 600.0)
+                .resizable(true)
+                .always_on_top(config.always_on_top)
+                .content_protected(config.privacy_mode)
+                // Stays hidden behind the splash until the core is ready,
+                // and until `biometric_unlock` succeeds.
+                .visible(!splash_enabled && !config.biometric_unlock_enabled)
+                .initialization_script(navigation::LINK_INTERCEPT_SCRIPT)
+                .on_navigation(move |url| {
+                    if url.host_str() == Some(fallback::ACTION_HOST) {
+                        fallback::on_action(&handle, &url);
+                        return false;
+                    }
+                    navigation::handle(&handle, &url, &internal_origins)
+                })
+                .build()?;
+            if args.minimized {
+                window.minimize()?;
+            }
+            if splash_enabled {
+                splash::create(app, config.internal_origins.clone())?;
+            }
+
+            crash::prune(
+                &app.handle(),
+                std::time::Duration::from_secs(config.crash_report_max_age_days * 24 * 60 * 60),
+            );
+            app.manage(config::ConfigState(Mutex::new(config)));
+            autobackup::start(&app.handle());
+            sync::start(&app.handle());
+            autolock::start(&app.handle());
+            connectivity::start(&app.handle());
+            integrity::start(&app.handle());
+            resources::check(&app.handle());
+            legacy::offer(&app.handle());
+            encryption::offer(&app.handle());
+            notifications::start(&app.handle());
+            reminders::start(&app.handle());
+            future::start(&app.handle());
+            power::start(&app.handle());
+            core::watch_idle(&app.handle());
+
+            // Start the core in the background so the window paints right
+            // away; the frontend follows `core-status` and
+            // `core-startup-progress` instead of waiting on a command. With
+            // `--no-core` it is left to `ensure_core`.
+            if !args.no_core {
+                let handle = app.handle();
+                std::thread::spawn(move || {
+                    let result = core::ensure(&handle);
+                    if let Err(e) = &result {
+                        eprintln!("Core startup failed: {}", e);
+                    }
+                    splash::on_startup_finished(&handle, &result);
+                    fallback::on_startup_finished(&handle, &result);
+                    if result.is_ok() {
+                        warmup::on_startup(&handle);
+                    }
+                });
+            }
             
             // Set app menu (optional)
             #[cfg(target_os = "macos")]
@@ -97,6 +429,55 @@ fn main() {
             
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .on_page_load(|window, payload| {
+            if window.label() == widget::WIDGET_LABEL {
+                widget::on_page_load(&window);
+            }
+            // The splash and widget pages have no app UI to restore settings
+            // into.
+            if window.label() != "main" {
+                return;
+            }
+            if fallback::is_fallback(payload.url()) {
+                fallback::on_page_load(&window);
+                return;
+            }
+            config::on_page_load(&window);
+            data_location::on_page_load(&window);
+            zoom::on_page_load(&window);
+            accessibility::on_page_load(&window);
+            resources::on_page_load(&window);
+            onboarding::on_page_load(&window);
+        })
+        .on_window_event(|event| {
+            match event.event() {
+                WindowEvent::CloseRequested { .. }
+                    if event.window().label() == widget::WIDGET_LABEL =>
+                {
+                    widget::save_geometry(event.window());
+                }
+                WindowEvent::CloseRequested { api, .. } => {
+                    lifecycle::on_close_requested(event.window(), api);
+                }
+                WindowEvent::FileDrop(FileDropEvent::Dropped(paths)) => {
+                    file_drop::on_drop(&event.window().app_handle(), paths.clone());
+                }
+                WindowEvent::ThemeChanged(theme) => {
+                    theme::on_theme_changed(event.window(), theme);
+                }
+                WindowEvent::Focused(true) => {
+                    event.window().state::<autolock::AutoLockState>().record_activity();
+                }
+                _ => {}
+            }
+        })
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                app.state::<background::Workers>().stop_all();
+                guest::on_exit(app);
+                core::shutdown(&app.state::<core::CoreState>());
+            }
+        });
 }
\ No newline at end of file
@@ -0,0 +1,259 @@
+// Meeting times as users type them ("7pm", "tue 19:00", "7:00 PM"),
+// normalized to a 24-hour `HH:MM` and a weekday so calendar exports get
+// something they can use. Times without am/pm that could be either ("7")
+// are refused rather than guessed.
+
+use crate::error::AppError;
+use crate::reminders;
+use crate::validate;
+use chrono::{DateTime, Datelike, NaiveTime, Timelike, Utc, Weekday};
+use serde::Serialize;
+
+const MAX_INPUT_LEN: usize = 64;
+
+// English and Spanish, matching the bundled locales.
+const WEEKDAYS: &[(&str, Weekday)] = &[
+    ("monday", Weekday::Mon),
+    ("mon", Weekday::Mon),
+    ("lunes", Weekday::Mon),
+    ("lun", Weekday::Mon),
+    ("tuesday", Weekday::Tue),
+    ("tues", Weekday::Tue),
+    ("tue", Weekday::Tue),
+    ("martes", Weekday::Tue),
+    ("mar", Weekday::Tue),
+    ("wednesday", Weekday::Wed),
+    ("wed", Weekday::Wed),
+    ("miércoles", Weekday::Wed),
+    ("miercoles", Weekday::Wed),
+    ("mié", Weekday::Wed),
+    ("mie", Weekday::Wed),
+    ("thursday", Weekday::Thu),
+    ("thurs", Weekday::Thu),
+    ("thu", Weekday::Thu),
+    ("jueves", Weekday::Thu),
+    ("jue", Weekday::Thu),
+    ("friday", Weekday::Fri),
+    ("fri", Weekday::Fri),
+    ("viernes", Weekday::Fri),
+    ("vie", Weekday::Fri),
+    ("saturday", Weekday::Sat),
+    ("sat", Weekday::Sat),
+    ("sábado", Weekday::Sat),
+    ("sabado", Weekday::Sat),
+    ("sáb", Weekday::Sat),
+    ("sab", Weekday::Sat),
+    ("sunday", Weekday::Sun),
+    ("sun", Weekday::Sun),
+    ("domingo", Weekday::Sun),
+    ("dom", Weekday::Sun),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MeetingTime {
+    // 24-hour, zero-padded.
+    pub time: String,
+    // The English day name, e.g. "Tuesday".
+    pub weekday: String,
+    pub timezone: Option<String>,
+}
+
+fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+// Strips a trailing am/pm marker, in any of "pm", "p.m.", "p. m." and "p"
+// forms, returning whether it was pm.
+fn split_meridiem(text: &str) -> (&str, Option<bool>) {
+    let compact: String = text.chars().filter(|c| !matches!(c, '.' | ' ')).collect();
+    for (suffix, pm) in [("am", false), ("pm", true), ("a", false), ("p", true)] {
+        if compact.ends_with(suffix) {
+            // Walk back over the marker's letters and any dots or spaces
+            // around them.
+            let mut letters = suffix.len();
+            let mut cut = text.len();
+            for (i, c) in text.char_indices().rev() {
+                if letters == 0 && !matches!(c, '.' | ' ') {
+                    break;
+                }
+                if c.is_ascii_alphabetic() {
+                    letters -= 1;
+                }
+                cut = i;
+            }
+            return (text[..cut].trim_end(), Some(pm));
+        }
+    }
+    (text, None)
+}
+
+fn parse_clock(text: &str) -> Result<NaiveTime, String> {
+    let invalid = || format!("{:?} isn't a time. Try something like 7pm or 19:00", text);
+    let (clock, pm) = split_meridiem(text);
+    // "19:00", "19.00", "19h00" and "19h" are all common.
+    let (hour, minute) = match clock.find([':', '.', 'h']) {
+        Some(i) => (&clock[..i], &clock[i + 1..]),
+        None => (clock, ""),
+    };
+    if hour.is_empty() || hour.len() > 2 || !hour.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    if !minute.is_empty() && (minute.len() != 2 || !minute.bytes().all(|b| b.is_ascii_digit())) {
+        return Err(invalid());
+    }
+    let hour: u32 = hour.parse().map_err(|_| invalid())?;
+    let minute: u32 = if minute.is_empty() {
+        0
+    } else {
+        minute.parse().map_err(|_| invalid())?
+    };
+
+    let hour = match pm {
+        Some(pm) => {
+            if !(1..=12).contains(&hour) {
+                return Err(format!("{:?} mixes 24-hour time with am/pm", text));
+            }
+            match (hour, pm) {
+                (12, false) => 0,
+                (12, true) => 12,
+                (hour, false) => hour,
+                (hour, true) => hour + 12,
+            }
+        }
+        // A leading zero ("07:00") or an hour past 12 can only be 24-hour
+        // time, and so can an explicit "00"; otherwise it needs a minute part
+        // to count as 24-hour time.
+        None if clock.len() > 2 || hour > 12 || text.starts_with('0') => hour,
+        None => {
+            return Err(format!(
+                "{:?} could be morning or evening. Add am or pm, or use 24-hour time like {:02}:00",
+                text,
+                hour % 12 + 12
+            ))
+        }
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(invalid)
+}
+
+fn split_weekday(text: &str) -> (Option<Weekday>, &str) {
+    let (word, rest) = text.split_once(' ').unwrap_or((text, ""));
+    let word = word.trim_end_matches([',', '.']);
+    match WEEKDAYS.iter().find(|(name, _)| *name == word) {
+        Some((_, day)) => (Some(*day), rest.trim_start()),
+        None => (None, text),
+    }
+}
+
+// Without a weekday in `input`, the meeting is taken to be the next time that
+// clock time comes round after `now`.
+fn parse<Z: chrono::TimeZone>(
+    input: &str,
+    now: DateTime<Z>,
+) -> Result<(NaiveTime, Weekday), String> {
+    let text = input.trim().to_lowercase();
+    if text.is_empty() {
+        return Err("Enter a meeting time".to_string());
+    }
+    let (day, clock) = split_weekday(&text);
+    // "Tuesday at 7pm", "martes a las 19:00".
+    let clock = clock
+        .strip_prefix("at ")
+        .or_else(|| clock.strip_prefix("a las "))
+        .or_else(|| clock.strip_prefix("a la "))
+        .unwrap_or(clock);
+    let time = parse_clock(clock.trim())?;
+    let day = day.unwrap_or_else(|| {
+        let local = now.naive_local();
+        if time > local.time() {
+            local.weekday()
+        } else {
+            local.weekday().succ()
+        }
+    });
+    Ok((time, day))
+}
+
+// `timezone` (IANA, e.g. "Europe/Madrid") defaults to the system's zone and
+// only matters when the input has no weekday.
+#[tauri::command]
+pub fn parse_meeting_time(input: String, timezone: Option<String>) -> Result<MeetingTime, AppError> {
+    validate::text("Meeting time", &input, MAX_INPUT_LEN)?;
+    let timezone = match timezone {
+        Some(timezone) => Some(timezone),
+        None => iana_time_zone::get_timezone()
+            .inspect_err(|e| eprintln!("Couldn't read the system time zone: {}", e))
+            .ok(),
+    };
+    let (time, day) = match timezone
+        .as_deref()
+        .map(reminders::parse_timezone)
+        .transpose()?
+    {
+        Some(zone) => parse(&input, Utc::now().with_timezone(&zone))?,
+        None => parse(&input, Utc::now())?,
+    };
+    Ok(MeetingTime {
+        time: format!("{:02}:{:02}", time.hour(), time.minute()),
+        weekday: weekday_name(day).to_string(),
+        timezone,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // A Wednesday.
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 15, 12, 0, 0).unwrap()
+    }
+
+    fn clock(input: &str) -> Result<String, String> {
+        parse(input, now()).map(|(time, _)| time.format("%H:%M").to_string())
+    }
+
+    #[test]
+    fn twelve_and_twenty_four_hour_times_agree() {
+        for input in [
+            "7pm", "7 PM", "7:00 PM", "7:00pm", "7 p.m.", "7 p. m.", "19:00", "19.00", "19h",
+            "19h00",
+        ] {
+            assert_eq!(clock(input), Ok("19:00".to_string()), "{}", input);
+        }
+        assert_eq!(clock("07:30"), Ok("07:30".to_string()));
+        assert_eq!(clock("7:30 am"), Ok("07:30".to_string()));
+        assert_eq!(clock("12am"), Ok("00:00".to_string()));
+        assert_eq!(clock("12:15 pm"), Ok("12:15".to_string()));
+        assert_eq!(clock("00:00"), Ok("00:00".to_string()));
+    }
+
+    #[test]
+    fn ambiguous_and_malformed_times_are_rejected() {
+        for input in [
+            "7", "12", "", "19pm", "7:5pm", "25:00", "19:60", "noonish", "7:00:00",
+        ] {
+            assert!(clock(input).is_err(), "{}", input);
+        }
+        assert!(clock("7").unwrap_err().contains("19:00"));
+    }
+
+    #[test]
+    fn weekday_is_read_or_inferred() {
+        let day = |input: &str| parse(input, now()).map(|(_, day)| day);
+        assert_eq!(day("Tuesday 7pm"), Ok(Weekday::Tue));
+        assert_eq!(day("tue, 19:00"), Ok(Weekday::Tue));
+        assert_eq!(day("martes a las 19:00"), Ok(Weekday::Tue));
+        assert_eq!(day("sáb 10:00"), Ok(Weekday::Sat));
+        assert_eq!(day("7pm"), Ok(Weekday::Wed));
+        assert_eq!(day("9am"), Ok(Weekday::Thu));
+    }
+}
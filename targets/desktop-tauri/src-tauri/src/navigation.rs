@@ -0,0 +1,127 @@
+// Keeps the webview on the app's own origins and sends everything else to
+// the system browser, so recovery resources never open inside the app where
+// the address bar isn't visible.
+
+use crate::config::ConfigState;
+use crate::error::AppError;
+use crate::{i18n, validate};
+use tauri::{AppHandle, Manager, Url};
+
+// Schemes that can't reach a remote origin and are safe to stay in-app.
+const LOCAL_SCHEMES: &[&str] = &["about", "data", "blob"];
+
+// The only schemes `open_external` hands to the OS; anything else (file:,
+// custom app handlers) could launch arbitrary programs.
+const EXTERNAL_SCHEMES: &[&str] = &["https", "mailto", "tel"];
+const MAX_URL_LEN: usize = 2048;
+
+// `target="_blank"` links and `window.open` would otherwise bypass the
+// navigation handler, so funnel them through a normal navigation.
+pub const LINK_INTERCEPT_SCRIPT: &str = r#"
+(function () {
+  document.addEventListener('click', function (event) {
+    var link = event.target && event.target.closest ? event.target.closest('a[href]') : null;
+    if (link && link.target && link.target !== '_self') {
+      event.preventDefault();
+      window.location.assign(link.href);
+    }
+  }, true);
+  window.open = function (url) {
+    if (url) {
+      window.location.assign(new URL(url, window.location.href).href);
+    }
+    return null;
+  };
+})();
+"#;
+
+// `Url::origin` treats custom schemes like `tauri://` as opaque, so build
+// the comparable `scheme://host[:port]` form by hand.
+fn origin_of(url: &Url) -> String {
+    let mut origin = format!("{}://{}", url.scheme(), url.host_str().unwrap_or_default());
+    if let Some(port) = url.port() {
+        origin.push_str(&format!(":{}", port));
+    }
+    origin
+}
+
+pub fn is_internal(url: &Url, internal_origins: &[String]) -> bool {
+    if LOCAL_SCHEMES.contains(&url.scheme()) {
+        return true;
+    }
+    let origin = origin_of(url);
+    internal_origins.iter().any(|allowed| {
+        Url::parse(allowed)
+            .map(|allowed| origin_of(&allowed) == origin)
+            .unwrap_or(false)
+    })
+}
+
+// Navigation handler for app windows: returns whether the webview may load
+// `url`. External web links are opened in the default browser instead.
+pub fn handle(app: &tauri::AppHandle, url: &Url, internal_origins: &[String]) -> bool {
+    if is_internal(url, internal_origins) {
+        return true;
+    }
+    if matches!(url.scheme(), "http" | "https") {
+        if let Err(e) = tauri::api::shell::open(&app.shell_scope(), url.as_str(), None) {
+            eprintln!("Failed to open {} in the system browser: {}", url, e);
+        }
+    } else {
+        eprintln!("Blocked navigation to {}", url);
+    }
+    false
+}
+
+// Subdomains of a trusted host are trusted too.
+fn is_trusted_host(host: &str, trusted: &[String]) -> bool {
+    let host = host.to_ascii_lowercase();
+    trusted.iter().any(|trusted| {
+        let trusted = trusted.to_ascii_lowercase();
+        host == trusted || host.ends_with(&format!(".{}", trusted))
+    })
+}
+
+fn confirm_open(app: &AppHandle, url: &Url) -> bool {
+    let window = app.get_window("main");
+    tauri::api::dialog::blocking::confirm(
+        window.as_ref(),
+        i18n::t(app, "open_external.title"),
+        i18n::t(app, "open_external.body").replace("{url}", url.as_str()),
+    )
+}
+
+// For links in user content and resources. Web links to hosts outside
+// `trusted_link_hosts` are confirmed first; mail and phone links open
+// directly so crisis numbers are one click away. Returns false if the user
+// declined.
+#[tauri::command]
+pub async fn open_external(app: AppHandle, url: String) -> Result<bool, AppError> {
+    validate::text("URL", &url, MAX_URL_LEN)?;
+    let url = Url::parse(&url)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid URL {:?}: {}", url, e)))?;
+    if !EXTERNAL_SCHEMES.contains(&url.scheme()) {
+        return Err(AppError::InvalidInput(format!(
+            "Links using {}: can't be opened from Smartbot",
+            url.scheme()
+        )));
+    }
+
+    let trusted = app.state::<ConfigState>().get().trusted_link_hosts;
+    let needs_confirmation = url.scheme() == "https"
+        && !url.host_str().is_some_and(|host| is_trusted_host(host, &trusted));
+    if needs_confirmation {
+        let handle = app.clone();
+        let shown = url.clone();
+        let confirmed = tauri::async_runtime::spawn_blocking(move || confirm_open(&handle, &shown))
+            .await
+            .map_err(|e| format!("Link confirmation failed: {}", e))?;
+        if !confirmed {
+            return Ok(false);
+        }
+    }
+
+    tauri::api::shell::open(&app.shell_scope(), url.as_str(), None)
+        .map_err(|e| format!("Failed to open {}: {}", url, e))?;
+    Ok(true)
+}
@@ -0,0 +1,301 @@
+// The one path native notifications go through. A token bucket caps how many
+// are shown per window, so a bad reminder config or a burst of events can't
+// bury the desktop; whatever doesn't fit is folded into a single summary
+// shown once the bucket refills. Urgent notifications (crisis resources)
+// are never held back.
+
+use crate::background::Workers;
+use crate::config::{AppConfig, ConfigState};
+use crate::error::AppError;
+use crate::i18n;
+use serde::Serialize;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::api::notification::Notification;
+use tauri::{AppHandle, Manager};
+
+const TICK: Duration = Duration::from_secs(5);
+
+pub const BURST_RANGE: std::ops::RangeInclusive<u32> = 1..=20;
+pub const WINDOW_SECS_RANGE: std::ops::RangeInclusive<u64> = 10..=60 * 60;
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct NotificationStats {
+    pub shown: u64,
+    // Held back by the limiter and counted into a summary instead.
+    pub coalesced: u64,
+    pub summaries: u64,
+    // The OS refused to show them.
+    pub dropped: u64,
+    // Coalesced but not yet summarized.
+    pub pending: u64,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+// The bucket and the counts, kept apart from the AppHandle so the limiting
+// can be driven with explicit limits and times.
+#[derive(Default)]
+struct Limiter {
+    bucket: Option<Bucket>,
+    stats: NotificationStats,
+}
+
+impl Limiter {
+    // Refills at `burst` tokens per window, never above `burst`.
+    fn take(&mut self, (burst, window): (f64, Duration), now: Instant) -> bool {
+        let bucket = self.bucket.get_or_insert_with(|| Bucket {
+            tokens: burst,
+            refilled_at: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        let refill = elapsed.as_secs_f64() * burst / window.as_secs_f64();
+        bucket.tokens = (bucket.tokens + refill).min(burst);
+        bucket.refilled_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    // Whether a notification may be shown now; one that may not is counted
+    // toward the next summary.
+    fn admit(&mut self, limits: (f64, Duration), urgent: bool, now: Instant) -> bool {
+        if urgent {
+            return true;
+        }
+        // Once anything is pending, later notifications queue behind the
+        // summary rather than jumping ahead of it.
+        if self.stats.pending > 0 || !self.take(limits, now) {
+            self.stats.coalesced += 1;
+            self.stats.pending += 1;
+            return false;
+        }
+        true
+    }
+
+    // How many held-back notifications to summarize now, if any and the
+    // bucket has room for the summary.
+    fn summary_due(&mut self, limits: (f64, Duration), now: Instant) -> Option<u64> {
+        let pending = self.stats.pending;
+        (pending > 0 && self.take(limits, now)).then_some(pending)
+    }
+
+    fn shown(&mut self, ok: bool) {
+        if ok {
+            self.stats.shown += 1;
+        } else {
+            self.stats.dropped += 1;
+        }
+    }
+
+    fn summarized(&mut self, pending: u64, ok: bool) {
+        self.stats.pending -= pending;
+        if ok {
+            self.stats.summaries += 1;
+        } else {
+            self.stats.dropped += pending;
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct NotificationState(Mutex<Limiter>);
+
+fn limits(config: &AppConfig) -> (f64, Duration) {
+    let burst = config
+        .notification_burst
+        .clamp(*BURST_RANGE.start(), *BURST_RANGE.end());
+    let window = config
+        .notification_window_secs
+        .clamp(*WINDOW_SECS_RANGE.start(), *WINDOW_SECS_RANGE.end());
+    (f64::from(burst), Duration::from_secs(window))
+}
+
+impl NotificationState {
+    fn limiter(&self) -> MutexGuard<'_, Limiter> {
+        self.0.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    pub fn stats(&self) -> NotificationStats {
+        self.limiter().stats.clone()
+    }
+}
+
+fn display(app: &AppHandle, title: &str, body: &str) -> Result<(), String> {
+    Notification::new(&app.config().tauri.bundle.identifier)
+        .title(title)
+        .body(body)
+        .show()
+        .map_err(|e| format!("Failed to show notification: {}", e))
+}
+
+fn shown(state: &NotificationState, result: Result<(), String>) -> Result<(), String> {
+    state.limiter().shown(result.is_ok());
+    result
+}
+
+fn summary_body(template: &str, pending: u64) -> String {
+    template.replace("{count}", &pending.to_string())
+}
+
+// Returns `Ok` for a held-back notification too: it will be summarized.
+pub fn notify(app: &AppHandle, title: &str, body: &str, urgent: bool) -> Result<(), String> {
+    let config = app.state::<ConfigState>().get();
+    if !config.features.notifications {
+        return Ok(());
+    }
+    let state = app.state::<NotificationState>();
+    if !state.limiter().admit(limits(&config), urgent, Instant::now()) {
+        return Ok(());
+    }
+    shown(&state, display(app, title, body))
+}
+
+fn flush(app: &AppHandle) {
+    let state = app.state::<NotificationState>();
+    let limits = limits(&app.state::<ConfigState>().get());
+    let Some(pending) = state.limiter().summary_due(limits, Instant::now()) else {
+        return;
+    };
+    let body = summary_body(&i18n::t(app, "notifications.pending"), pending);
+    let result = display(app, &i18n::t(app, "notifications.pending_title"), &body);
+    if let Err(e) = &result {
+        eprintln!("{}", e);
+    }
+    state.limiter().summarized(pending, result.is_ok());
+}
+
+pub fn start(app: &AppHandle) {
+    let handle = app.clone();
+    app.state::<Workers>().spawn(TICK, move || flush(&handle));
+}
+
+#[tauri::command]
+pub fn get_notification_stats(state: tauri::State<NotificationState>) -> NotificationStats {
+    state.stats()
+}
+
+#[cfg(target_os = "macos")]
+const NOTIFICATION_SETTINGS_URL: &str =
+    "x-apple.systempreferences:com.apple.preference.notifications";
+
+// The OS reports a refusal the same way whether notifications are switched
+// off for the app or were never allowed, so both land here.
+fn not_permitted(app: &AppHandle, cause: String) -> String {
+    eprintln!("{}", cause);
+    #[cfg(target_os = "macos")]
+    {
+        let result = tauri::api::shell::open(&app.shell_scope(), NOTIFICATION_SETTINGS_URL, None);
+        if let Err(e) = result {
+            eprintln!("Failed to open Notifications settings: {}", e);
+        }
+    }
+    #[cfg(not(target_os = "macos"))]
+    let _ = app;
+    "Notifications are turned off for Smartbot. Allow them in your system settings, then try again."
+        .to_string()
+}
+
+// Shows a sample notification right away, past the limiter, so onboarding
+// can confirm reminders will get through. `Ok` means the OS accepted it.
+#[tauri::command]
+pub fn test_notification(app: AppHandle) -> Result<(), AppError> {
+    let state = app.state::<NotificationState>();
+    let title = i18n::t(&app, "notifications.test_title");
+    let body = i18n::t(&app, "notifications.test_body");
+    shown(&state, display(&app, &title, &body)).map_err(|e| not_permitted(&app, e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: (f64, Duration) = (3.0, Duration::from_secs(60));
+
+    #[test]
+    fn the_burst_is_shown_and_the_rest_held_back() {
+        let mut limiter = Limiter::default();
+        let now = Instant::now();
+        let admitted: Vec<_> = (0..5).map(|_| limiter.admit(LIMITS, false, now)).collect();
+        assert_eq!(admitted, [true, true, true, false, false]);
+        assert_eq!((limiter.stats.coalesced, limiter.stats.pending), (2, 2));
+    }
+
+    #[test]
+    fn tokens_refill_over_the_window_up_to_the_burst() {
+        let mut limiter = Limiter::default();
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.take(LIMITS, now));
+        }
+        assert!(!limiter.take(LIMITS, now));
+
+        // A third of the window brings back one token.
+        let later = now + Duration::from_secs(20);
+        assert!(limiter.take(LIMITS, later));
+        assert!(!limiter.take(LIMITS, later));
+
+        // A long quiet spell refills to the burst and no further.
+        let much_later = later + Duration::from_secs(60 * 60);
+        let taken = (0..5).filter(|_| limiter.take(LIMITS, much_later)).count();
+        assert_eq!(taken, 3);
+    }
+
+    #[test]
+    fn urgent_notifications_bypass_the_limit() {
+        let mut limiter = Limiter::default();
+        let now = Instant::now();
+        // Urgent ones use up no tokens...
+        for _ in 0..10 {
+            assert!(limiter.admit(LIMITS, true, now));
+        }
+        let admitted: Vec<_> = (0..4).map(|_| limiter.admit(LIMITS, false, now)).collect();
+        assert_eq!(admitted, [true, true, true, false]);
+        // ...and aren't held back once the bucket is empty.
+        assert!(limiter.admit(LIMITS, true, now));
+        assert_eq!((limiter.stats.coalesced, limiter.stats.pending), (1, 1));
+    }
+
+    #[test]
+    fn held_back_notifications_are_summarized_once_there_is_room() {
+        let mut limiter = Limiter::default();
+        let now = Instant::now();
+        for _ in 0..5 {
+            limiter.admit(LIMITS, false, now);
+        }
+        assert_eq!(limiter.summary_due(LIMITS, now), None);
+        // Later ones queue behind the summary even with a token to spare.
+        let later = now + Duration::from_secs(40);
+        assert!(!limiter.admit(LIMITS, false, later));
+
+        let pending = limiter.summary_due(LIMITS, later).unwrap();
+        assert_eq!(pending, 3);
+        limiter.summarized(pending, true);
+        assert_eq!((limiter.stats.pending, limiter.stats.summaries), (0, 1));
+        assert_eq!(limiter.summary_due(LIMITS, later), None);
+        assert!(limiter.admit(LIMITS, false, later));
+    }
+
+    #[test]
+    fn the_summary_counts_what_was_held_back() {
+        let locales = [
+            include_str!("../resources/locales/en.json"),
+            include_str!("../resources/locales/es.json"),
+        ];
+        let bodies: Vec<_> = locales
+            .iter()
+            .map(|json| {
+                let catalog: serde_json::Value = serde_json::from_str(json).unwrap();
+                summary_body(catalog["notifications.pending"].as_str().unwrap(), 12)
+            })
+            .collect();
+        assert_eq!(bodies, ["12 reminders pending", "12 recordatorios pendientes"]);
+    }
+}
@@ -0,0 +1,31 @@
+// Keeping the main window above other apps. The preference is applied when
+// the window is built, and here when it changes.
+
+use crate::config::{self, ConfigState};
+use crate::error::AppError;
+use tauri::{AppHandle, Manager};
+
+// A crisis window must never end up underneath the main one.
+const CRISIS_LABEL: &str = "crisis";
+
+#[tauri::command]
+pub fn get_always_on_top(config: tauri::State<ConfigState>) -> bool {
+    config.get().always_on_top
+}
+
+#[tauri::command]
+pub fn set_always_on_top(app: AppHandle, enabled: bool) -> Result<bool, AppError> {
+    let window = app.get_window("main").ok_or("The main window is not open")?;
+    window
+        .set_always_on_top(enabled)
+        .map_err(|e| format!("Failed to change always-on-top: {}", e))?;
+    config::update(&app, |config| config.always_on_top = enabled)?;
+
+    // Both windows pinned share the top level, so the crisis window is
+    // re-pinned and raised so it stays in front.
+    if let Some(crisis) = app.get_window(CRISIS_LABEL) {
+        let _ = crisis.set_always_on_top(true);
+        let _ = crisis.set_focus();
+    }
+    Ok(enabled)
+}
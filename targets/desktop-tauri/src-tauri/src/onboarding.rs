@@ -0,0 +1,88 @@
+// First-run welcome flow. Steps are tracked by id in the config, so new
+// steps can be added later without invalidating what existing users have
+// already done.
+
+use crate::config::{self, ConfigState};
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Manager, Runtime, Window};
+
+pub const ONBOARDING_NEEDED_EVENT: &str = "onboarding-needed";
+
+// Shown in this order; unknown ids in the config are kept but not listed.
+pub const STEPS: &[&str] = &["passphrase_set", "reminders_configured", "profile_created"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OnboardingProgress {
+    pub completed: bool,
+    pub steps: BTreeMap<String, bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OnboardingStep {
+    pub id: &'static str,
+    pub done: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OnboardingState {
+    pub completed: bool,
+    pub steps: Vec<OnboardingStep>,
+}
+
+impl From<&OnboardingProgress> for OnboardingState {
+    fn from(progress: &OnboardingProgress) -> Self {
+        Self {
+            completed: progress.completed,
+            steps: STEPS
+                .iter()
+                .map(|&id| OnboardingStep {
+                    id,
+                    done: progress.steps.get(id).copied().unwrap_or(false),
+                })
+                .collect(),
+        }
+    }
+}
+
+// Set in `setup` when there was no config file, i.e. this is a first run.
+#[derive(Default)]
+pub struct FirstRun(pub AtomicBool);
+
+// Events emitted during `setup` would be lost before the page is listening,
+// so announce it on the first page load instead.
+pub fn on_page_load<R: Runtime>(window: &Window<R>) {
+    if let Some(first_run) = window.try_state::<FirstRun>() {
+        if first_run.0.swap(false, Ordering::SeqCst) {
+            let _ = window.emit(ONBOARDING_NEEDED_EVENT, ());
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_onboarding_state(config: tauri::State<ConfigState>) -> OnboardingState {
+    OnboardingState::from(&config.get().onboarding)
+}
+
+#[tauri::command]
+pub fn complete_onboarding_step(app: AppHandle, step: String) -> Result<OnboardingState, AppError> {
+    if !STEPS.contains(&step.as_str()) {
+        return Err(AppError::InvalidInput(format!(
+            "Unknown onboarding step {:?}",
+            step
+        )));
+    }
+    let config = config::update(&app, |config| {
+        config.onboarding.steps.insert(step, true);
+    })?;
+    Ok(OnboardingState::from(&config.onboarding))
+}
+
+#[tauri::command]
+pub fn complete_onboarding(app: AppHandle) -> Result<OnboardingState, AppError> {
+    let config = config::update(&app, |config| config.onboarding.completed = true)?;
+    Ok(OnboardingState::from(&config.onboarding))
+}
@@ -0,0 +1,221 @@
+// Where the app keeps its files. `SMARTBOT_DATA_DIR` redirects everything
+// (data, settings, logs) to one folder, e.g. on an encrypted volume. A folder
+// the user picked when the default one couldn't be used does the same, and
+// is remembered in the default config folder.
+
+use serde::Serialize;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tauri::AppHandle;
+
+pub const DATA_DIR_ENV: &str = "SMARTBOT_DATA_DIR";
+
+const CHOSEN_DIR_FILE_NAME: &str = "data-location";
+
+// Read from `CHOSEN_DIR_FILE_NAME` at launch, or set by `choose`.
+static CHOSEN_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+// A folder the app needs but can't use, and why.
+#[derive(Debug, Clone, Serialize)]
+pub struct DirUnavailable {
+    // "data", "config" or "log".
+    pub kind: &'static str,
+    // None when the OS gave no such folder.
+    pub dir: Option<String>,
+    pub reason: String,
+}
+
+impl std::fmt::Display for DirUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.dir {
+            Some(dir) => write!(f, "The {} directory {} {}", self.kind, dir, self.reason),
+            None => write!(f, "The {} directory {}", self.kind, self.reason),
+        }
+    }
+}
+
+fn env_override() -> Option<PathBuf> {
+    std::env::var_os(DATA_DIR_ENV).and_then(non_empty)
+}
+
+fn chosen_dir() -> Option<PathBuf> {
+    CHOSEN_DIR.read().unwrap_or_else(|p| p.into_inner()).clone()
+}
+
+fn data_dir_override() -> Option<PathBuf> {
+    env_override().or_else(chosen_dir)
+}
+
+fn non_empty(value: OsString) -> Option<PathBuf> {
+    (!value.is_empty()).then(|| PathBuf::from(value))
+}
+
+pub fn resolve(override_dir: Option<PathBuf>, default: Option<PathBuf>) -> Option<PathBuf> {
+    override_dir.or(default)
+}
+
+pub fn is_overridden() -> bool {
+    data_dir_override().is_some()
+}
+
+pub fn is_set_by_env() -> bool {
+    env_override().is_some()
+}
+
+pub fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    resolve(data_dir_override(), app.path_resolver().app_data_dir())
+        .ok_or_else(|| "Could not resolve the app data directory".to_string())
+}
+
+pub fn config_dir(app: &AppHandle) -> Option<PathBuf> {
+    resolve(data_dir_override(), app.path_resolver().app_config_dir())
+}
+
+pub fn log_dir(app: &AppHandle) -> Option<PathBuf> {
+    resolve(
+        data_dir_override().map(|dir| dir.join("logs")),
+        app.path_resolver().app_log_dir(),
+    )
+}
+
+// Create the directory if needed and prove we can write to it, so a bad
+// folder fails at launch rather than on the first save.
+fn probe(kind: &'static str, dir: &Path) -> Result<(), DirUnavailable> {
+    let unavailable = |reason: String| DirUnavailable {
+        kind,
+        dir: Some(dir.display().to_string()),
+        reason,
+    };
+    std::fs::create_dir_all(dir).map_err(|e| unavailable(format!("can't be created: {}", e)))?;
+    let probe = dir.join(".smartbot-write-test");
+    std::fs::write(&probe, b"").map_err(|e| unavailable(format!("is not writable: {}", e)))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+pub fn ensure_writable(dir: &Path) -> Result<(), String> {
+    probe("data", dir).map_err(|e| e.to_string())
+}
+
+// The first of the data, config and log directories that can't be used.
+pub fn check_dirs(app: &AppHandle) -> Result<(), DirUnavailable> {
+    let dirs = [
+        ("data", data_dir(app).ok()),
+        ("config", config_dir(app)),
+        ("log", log_dir(app)),
+    ];
+    for (kind, dir) in dirs {
+        match dir {
+            Some(dir) => probe(kind, &dir)?,
+            None => {
+                return Err(DirUnavailable {
+                    kind,
+                    dir: None,
+                    reason: "could not be resolved".to_string(),
+                })
+            }
+        }
+    }
+    Ok(())
+}
+
+// Kept in the default config folder, since the point is to find the chosen
+// one.
+fn chosen_dir_file(app: &AppHandle) -> Option<PathBuf> {
+    app.path_resolver()
+        .app_config_dir()
+        .map(|dir| dir.join(CHOSEN_DIR_FILE_NAME))
+}
+
+// Must run before anything resolves a path.
+pub fn load_chosen(app: &AppHandle) {
+    let chosen = chosen_dir_file(app)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|contents| non_empty(contents.trim().into()));
+    *CHOSEN_DIR.write().unwrap_or_else(|p| p.into_inner()) = chosen;
+}
+
+// Switches every folder to `dir` if all of them can be used there, and
+// changes nothing otherwise. Only for `setup`: anything already opened stays
+// where it was.
+pub fn choose(dir: &Path) -> Result<(), DirUnavailable> {
+    let dirs = [("data", dir.to_path_buf()), ("log", dir.join("logs"))];
+    for (kind, dir) in dirs {
+        probe(kind, &dir)?;
+    }
+    *CHOSEN_DIR.write().unwrap_or_else(|p| p.into_inner()) = Some(dir.to_path_buf());
+    Ok(())
+}
+
+// Without this the choice only lasts until the app closes.
+pub fn remember_chosen(app: &AppHandle) -> Result<(), String> {
+    let path = chosen_dir_file(app).ok_or("Could not resolve the app config directory")?;
+    let dir = chosen_dir().ok_or("No data directory was chosen")?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Can't create {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(&path, dir.display().to_string())
+        .map_err(|e| format!("Can't write {}: {}", path.display(), e))
+}
+
+// Every file under `dir` with its size; empty if `dir` doesn't exist.
+pub fn files_under(dir: &Path) -> Result<Vec<(PathBuf, u64)>, String> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+        let path = entry.path();
+        let metadata = entry
+            .metadata()
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if metadata.is_dir() {
+            files.extend(files_under(&path)?);
+        } else {
+            files.push((path, metadata.len()));
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn override_is_preferred_over_default() {
+        let resolved = resolve(
+            non_empty(OsString::from("/mnt/secure/smartbot")),
+            Some(PathBuf::from("/home/user/.local/share/smartbot")),
+        );
+        assert_eq!(resolved, Some(PathBuf::from("/mnt/secure/smartbot")));
+    }
+
+    #[test]
+    fn empty_override_falls_back_to_default() {
+        let default = PathBuf::from("/home/user/.local/share/smartbot");
+        assert_eq!(resolve(non_empty(OsString::new()), Some(default.clone())), Some(default));
+    }
+
+    #[test]
+    fn unwritable_dir_names_the_folder_and_the_reason() {
+        let root = std::env::temp_dir().join(format!("smartbot-paths-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        // A file where the folder should be can't become one.
+        let blocked = root.join("blocked");
+        std::fs::write(&blocked, b"").unwrap();
+        let error = probe("data", &blocked.join("data")).unwrap_err();
+        assert_eq!(error.kind, "data");
+        assert!(error.to_string().starts_with(&format!(
+            "The data directory {} can't be created: ",
+            blocked.join("data").display()
+        )));
+        probe("data", &root.join("fine")).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
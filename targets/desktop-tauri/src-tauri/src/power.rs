@@ -0,0 +1,60 @@
+// Suspend/resume detection. Tauri 1 has no power events, so a sleep is
+// inferred from the wall clock jumping ahead of the monotonic clock, which
+// stops while the machine is suspended.
+
+use crate::background::Workers;
+use crate::{connectivity, core};
+use serde::Serialize;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Manager};
+
+const TICK: Duration = Duration::from_secs(5);
+// Gaps shorter than this are scheduler jitter or small clock adjustments.
+const MIN_SLEEP: Duration = Duration::from_secs(30);
+
+pub const RESUMED_EVENT: &str = "resumed";
+
+#[derive(Debug, Clone, Serialize)]
+struct Resumed {
+    slept_secs: u64,
+}
+
+fn on_resume(app: &AppHandle, slept: Duration) {
+    let _ = app.emit_all(
+        RESUMED_EVENT,
+        Resumed {
+            slept_secs: slept.as_secs(),
+        },
+    );
+
+    // The network has likely changed while asleep.
+    connectivity::refresh(app);
+
+    // The core may have been killed while asleep; `ensure` re-probes it and
+    // restarts it if needed. It can block for the startup timeout, so keep
+    // it off the timer thread.
+    // One put to sleep for idleness is left for the next request to wake.
+    if app.state::<core::CoreState>().status() == core::CoreStatus::Sleeping {
+        return;
+    }
+    let handle = app.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = core::ensure(&handle) {
+            eprintln!("Core unavailable after resume: {}", e);
+        }
+    });
+}
+
+pub fn start(app: &AppHandle) {
+    let handle = app.clone();
+    let mut last = (Instant::now(), SystemTime::now());
+    app.state::<Workers>().spawn(TICK, move || {
+        let now = (Instant::now(), SystemTime::now());
+        let awake = now.0.duration_since(last.0);
+        let wall = now.1.duration_since(last.1).unwrap_or_default();
+        last = now;
+        if let Some(slept) = wall.checked_sub(awake).filter(|gap| *gap >= MIN_SLEEP) {
+            on_resume(&handle, slept);
+        }
+    });
+}
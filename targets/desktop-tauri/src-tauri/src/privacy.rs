@@ -0,0 +1,44 @@
+// Privacy mode keeps the main window out of screenshots and screen
+// recordings. Only macOS and Windows let an app opt out of capture; elsewhere
+// the preference is still saved but reported as not in effect.
+
+use crate::config::{self, ConfigState};
+use crate::error::AppError;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+pub const SUPPORTED: bool = cfg!(any(target_os = "macos", target_os = "windows"));
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PrivacyMode {
+    pub enabled: bool,
+    // Whether the window is actually excluded from capture right now.
+    pub active: bool,
+}
+
+#[tauri::command]
+pub fn get_privacy_mode(config: tauri::State<ConfigState>) -> PrivacyMode {
+    let enabled = config.get().privacy_mode;
+    PrivacyMode {
+        enabled,
+        active: enabled && SUPPORTED,
+    }
+}
+
+#[tauri::command]
+pub fn set_privacy_mode(app: AppHandle, enabled: bool) -> Result<PrivacyMode, AppError> {
+    let window = app.get_window("main").ok_or("The main window is not open")?;
+    let applied = SUPPORTED
+        && match window.set_content_protected(enabled) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Failed to change content protection: {}", e);
+                false
+            }
+        };
+    config::update(&app, |config| config.privacy_mode = enabled)?;
+    Ok(PrivacyMode {
+        enabled,
+        active: enabled && applied,
+    })
+}
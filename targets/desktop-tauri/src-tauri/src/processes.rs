@@ -0,0 +1,93 @@
+// Finding and killing stray core processes, for people debugging a start
+// that fails because an old core still holds the port. Only processes whose
+// command line runs our core's app under uvicorn are listed or killed.
+
+use crate::audit::{AuditAction, AuditState};
+use crate::core::{CoreState, CORE_APP};
+use crate::error::AppError;
+use serde::Serialize;
+use sysinfo::{Pid, Process, ProcessRefreshKind, System, UpdateKind};
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Serialize)]
+pub struct CoreProcess {
+    pub pid: u32,
+    // RFC 3339; `None` if the OS didn't report it.
+    pub started_at: Option<String>,
+    pub command: String,
+    // Spawned by this app; killing it stops the core the app is using.
+    pub managed: bool,
+}
+
+fn is_core(process: &Process) -> bool {
+    let cmd = process.cmd();
+    cmd.iter().any(|arg| arg == CORE_APP)
+        && cmd.iter().any(|arg| arg == "uvicorn" || arg.ends_with("/uvicorn"))
+}
+
+fn processes() -> System {
+    let mut system = System::new();
+    system.refresh_processes_specifics(ProcessRefreshKind::new().with_cmd(UpdateKind::Always));
+    system
+}
+
+fn describe(pid: Pid, process: &Process, managed: Option<u32>) -> CoreProcess {
+    let started_at = chrono::DateTime::from_timestamp(process.start_time() as i64, 0)
+        .filter(|_| process.start_time() > 0)
+        .map(|at| at.to_rfc3339());
+    CoreProcess {
+        pid: pid.as_u32(),
+        started_at,
+        command: process.cmd().join(" "),
+        managed: managed == Some(pid.as_u32()),
+    }
+}
+
+#[tauri::command]
+pub async fn list_core_processes(app: AppHandle) -> Result<Vec<CoreProcess>, AppError> {
+    let found = tauri::async_runtime::spawn_blocking(move || {
+        let managed = app.state::<CoreState>().child_pid();
+        let system = processes();
+        let mut found: Vec<_> = system
+            .processes()
+            .iter()
+            .filter(|(_, process)| is_core(process))
+            .map(|(&pid, process)| describe(pid, process, managed))
+            .collect();
+        found.sort_by_key(|process| process.pid);
+        found
+    })
+    .await
+    .map_err(|e| format!("Listing core processes failed: {}", e))?;
+    Ok(found)
+}
+
+// The command line is checked again at kill time, so a pid that has since
+// been reused by some other program is refused.
+#[tauri::command]
+pub async fn kill_core_process(app: AppHandle, pid: u32) -> Result<(), AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let system = processes();
+        let process = system
+            .process(Pid::from_u32(pid))
+            .ok_or_else(|| format!("No process with pid {}", pid))?;
+        if !is_core(process) {
+            return Err(format!(
+                "Process {} isn't a Smartbot core; refusing to kill it",
+                pid
+            ));
+        }
+        let command = process.cmd().join(" ");
+        if !process.kill() {
+            return Err(format!("Failed to kill process {}", pid));
+        }
+        app.state::<AuditState>().record(
+            AuditAction::KillProcess,
+            serde_json::json!({ "pid": pid, "command": command }),
+        );
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("Killing process {} failed: {}", pid, e))??;
+    Ok(())
+}
@@ -0,0 +1,552 @@
+// Separate profiles for shared computers. Each profile gets its own
+// directory holding its database, audit log and settings file, so switching
+// profiles swaps every per-user store at once. The original single-user data
+// stays where it was as the `default` profile; every other profile's
+// database is encrypted from the start, under a passphrase chosen when it is
+// created.
+//
+// Settings that belong to the person rather than the computer, such as
+// reminders (`ProfileSettings`), live in the profile's settings file; the
+// app config holds the rest and is shared.
+
+use crate::audit::{self, AuditState};
+use crate::encryption;
+use crate::error::AppError;
+use crate::guest::GuestState;
+use crate::paths;
+use crate::reminders::{self, Reminder, ReminderAdaptation};
+use crate::storage::{self, Storage, StorageError, StorageState};
+use crate::validate;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+pub const PROFILES_FILE_NAME: &str = "profiles.json";
+pub const SETTINGS_FILE_NAME: &str = "settings.json";
+pub const DEFAULT_PROFILE_ID: &str = "default";
+
+const MAX_NAME_LEN: usize = 64;
+const MAX_ID_LEN: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileList {
+    pub active: String,
+    pub profiles: Vec<Profile>,
+}
+
+impl Default for ProfileList {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_PROFILE_ID.to_string(),
+            profiles: vec![Profile {
+                id: DEFAULT_PROFILE_ID.to_string(),
+                name: "Default".to_string(),
+                created_at: chrono::Utc::now().to_rfc3339(),
+            }],
+        }
+    }
+}
+
+// Named as they were in the app config, which held them before profiles.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileSettings {
+    /// Scheduled reminders. One-off ones are removed once they fire, any one
+    /// once it is cancelled.
+    pub reminders: Vec<Reminder>,
+    /// When and how far repeating reminders back off after dismissals.
+    pub reminder_adaptation: ReminderAdaptation,
+    /// Reminders that come due while paused are dropped. The pause lifts by
+    /// itself at `reminders_paused_until` (RFC 3339) if set.
+    pub reminders_paused: bool,
+    pub reminders_paused_until: Option<String>,
+    /// Set when the user turns down the startup offer to encrypt a plaintext
+    /// store, so it isn't made again.
+    pub storage_encryption_declined: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeleteReport {
+    pub profile: Profile,
+    pub data_dir: String,
+    pub files_removed: Vec<String>,
+    pub bytes_removed: u64,
+    // Nothing was deleted; the report shows what would be.
+    pub dry_run: bool,
+}
+
+#[derive(Default)]
+pub struct ProfileState {
+    // Data directory root; `None` if it couldn't be resolved at startup.
+    root: Option<PathBuf>,
+    list: Mutex<ProfileList>,
+    // The active profile's.
+    settings: Mutex<ProfileSettings>,
+}
+
+pub fn profile_dir(root: &Path, id: &str) -> PathBuf {
+    if id == DEFAULT_PROFILE_ID {
+        root.to_path_buf()
+    } else {
+        root.join("profiles").join(id)
+    }
+}
+
+// A missing file means nothing has been set yet. An unreadable one is moved
+// aside, as an invalid config is, so the next save doesn't overwrite it.
+fn load_settings(dir: &Path) -> ProfileSettings {
+    let path = dir.join(SETTINGS_FILE_NAME);
+    let Ok(contents) = std::fs::read_to_string(&path) else {
+        return ProfileSettings::default();
+    };
+    match serde_json::from_str(&contents) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Ignoring invalid profile settings {}: {}", path.display(), e);
+            let mut name = path.file_name().unwrap_or_default().to_os_string();
+            name.push(format!(".invalid-{}", chrono::Utc::now().format("%Y%m%dT%H%M%SZ")));
+            if let Err(e) = std::fs::rename(&path, path.with_file_name(name)) {
+                eprintln!("Failed to move invalid profile settings aside: {}", e);
+            }
+            ProfileSettings::default()
+        }
+    }
+}
+
+fn save_settings(dir: &Path, settings: &ProfileSettings) -> Result<(), String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create profile directory {}: {}", dir.display(), e))?;
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize profile settings: {}", e))?;
+    std::fs::write(dir.join(SETTINGS_FILE_NAME), json)
+        .map_err(|e| format!("Failed to save profile settings: {}", e))
+}
+
+impl ProfileState {
+    // The default profile takes its settings over from the app config at
+    // `config_path` the first time it has no settings file of its own.
+    pub fn load(root: PathBuf, config_path: Option<&Path>) -> Self {
+        let list = std::fs::read_to_string(root.join(PROFILES_FILE_NAME))
+            .ok()
+            .and_then(|contents| serde_json::from_str::<ProfileList>(&contents).ok())
+            .filter(|list| list.profiles.iter().any(|p| p.id == list.active))
+            .unwrap_or_default();
+        let default_dir = profile_dir(&root, DEFAULT_PROFILE_ID);
+        if !default_dir.join(SETTINGS_FILE_NAME).exists() {
+            let legacy = config_path
+                .and_then(|path| std::fs::read_to_string(path).ok())
+                .and_then(|contents| serde_json::from_str::<ProfileSettings>(&contents).ok());
+            if let Some(legacy) = legacy {
+                if let Err(e) = save_settings(&default_dir, &legacy) {
+                    eprintln!("Failed to move settings into the default profile: {}", e);
+                }
+            }
+        }
+        let settings = load_settings(&profile_dir(&root, &list.active));
+        Self {
+            root: Some(root),
+            list: Mutex::new(list),
+            settings: Mutex::new(settings),
+        }
+    }
+
+    fn root(&self) -> Result<&Path, String> {
+        self.root
+            .as_deref()
+            .ok_or_else(|| "Could not resolve the app data directory".to_string())
+    }
+
+    fn list(&self) -> std::sync::MutexGuard<'_, ProfileList> {
+        self.list.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    pub fn active_id(&self) -> String {
+        self.list().active.clone()
+    }
+
+    pub fn active_dir(&self) -> Result<PathBuf, String> {
+        Ok(profile_dir(self.root()?, &self.active_id()))
+    }
+
+    pub fn settings(&self) -> ProfileSettings {
+        self.settings.lock().unwrap_or_else(|p| p.into_inner()).clone()
+    }
+
+    // Apply `change` to the active profile's settings and persist them.
+    pub fn update_settings(
+        &self,
+        change: impl FnOnce(&mut ProfileSettings),
+    ) -> Result<ProfileSettings, String> {
+        let mut settings = self.settings.lock().unwrap_or_else(|p| p.into_inner());
+        let mut updated = settings.clone();
+        change(&mut updated);
+        save_settings(&self.active_dir()?, &updated)?;
+        *settings = updated;
+        Ok(settings.clone())
+    }
+
+    fn save(&self, list: &ProfileList) -> Result<(), String> {
+        let root = self.root()?;
+        std::fs::create_dir_all(root)
+            .map_err(|e| format!("Failed to create data directory {}: {}", root.display(), e))?;
+        let json = serde_json::to_string_pretty(list)
+            .map_err(|e| format!("Failed to serialize profiles: {}", e))?;
+        std::fs::write(root.join(PROFILES_FILE_NAME), json)
+            .map_err(|e| format!("Failed to save profiles: {}", e))
+    }
+
+    fn find(&self, id: &str) -> Result<Profile, AppError> {
+        validate::identifier("Profile id", id, MAX_ID_LEN)?;
+        self.list()
+            .profiles
+            .iter()
+            .find(|p| p.id == id)
+            .cloned()
+            .ok_or_else(|| AppError::InvalidInput(format!("No profile with id {}", id)))
+    }
+
+    fn create(&self, name: &str, passphrase: &str) -> Result<Profile, AppError> {
+        let name = name.trim().to_string();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+            return Err(AppError::InvalidInput(format!(
+                "Profile names must be 1 to {} characters",
+                MAX_NAME_LEN
+            )));
+        }
+        encryption::check_passphrase(passphrase)?;
+        let mut list = self.list();
+        if list.profiles.iter().any(|p| p.name.eq_ignore_ascii_case(&name)) {
+            return Err(AppError::InvalidInput(format!(
+                "A profile named {:?} already exists",
+                name
+            )));
+        }
+        let profile = Profile {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            name,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        let dir = profile_dir(self.root()?, &profile.id);
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            AppError::io(
+                &dir,
+                format!("Failed to create profile directory {}: {}", dir.display(), e),
+            )
+        })?;
+
+        if let Err(e) = Storage::open_encrypted(&dir.join(storage::DB_FILE_NAME), passphrase) {
+            let _ = std::fs::remove_dir_all(&dir);
+            return Err(e.into());
+        }
+
+        let mut updated = list.clone();
+        updated.profiles.push(profile.clone());
+        if let Err(e) = self.save(&updated) {
+            let _ = std::fs::remove_dir_all(&dir);
+            return Err(e.into());
+        }
+        *list = updated;
+        Ok(profile)
+    }
+
+    // `open` closes the current profile's stores and opens those in the
+    // directory it is given. The list is saved first; if the stores then
+    // can't be opened, the previous profile is saved and opened again, so
+    // the saved list always names the profile whose data is open.
+    fn switch(
+        &self,
+        id: &str,
+        open: impl Fn(&Path) -> Result<(), String>,
+    ) -> Result<Profile, AppError> {
+        let profile = self.find(id)?;
+        let root = self.root()?;
+        let previous = self.list().clone();
+        if previous.active == id {
+            return Ok(profile);
+        }
+
+        let mut updated = previous.clone();
+        updated.active = id.to_string();
+        self.save(&updated)?;
+        if let Err(e) = open(&profile_dir(root, id)) {
+            let reverted = self
+                .save(&previous)
+                .and_then(|()| open(&profile_dir(root, &previous.active)));
+            if let Err(revert) = reverted {
+                eprintln!("Failed to return to the previous profile: {}", revert);
+            }
+            return Err(e.into());
+        }
+        *self.settings.lock().unwrap_or_else(|p| p.into_inner()) =
+            load_settings(&profile_dir(root, id));
+        *self.list() = updated;
+        Ok(profile)
+    }
+
+    fn delete(&self, id: &str, dry_run: bool) -> Result<DeleteReport, AppError> {
+        validate::identifier("Profile id", id, MAX_ID_LEN)?;
+        if id == DEFAULT_PROFILE_ID {
+            return Err(AppError::InvalidInput(
+                "The default profile can't be deleted".to_string(),
+            ));
+        }
+        let mut list = self.list();
+        if list.active == id {
+            return Err(AppError::InvalidInput(
+                "Switch to another profile before deleting this one".to_string(),
+            ));
+        }
+        let profile = list
+            .profiles
+            .iter()
+            .find(|p| p.id == id)
+            .cloned()
+            .ok_or_else(|| AppError::InvalidInput(format!("No profile with id {}", id)))?;
+
+        let dir = profile_dir(self.root()?, id);
+        let files = paths::files_under(&dir)?;
+        let report = DeleteReport {
+            profile,
+            data_dir: dir.display().to_string(),
+            files_removed: files.iter().map(|(path, _)| path.display().to_string()).collect(),
+            bytes_removed: files.iter().map(|(_, size)| size).sum(),
+            dry_run,
+        };
+        if report.dry_run {
+            return Ok(report);
+        }
+
+        let mut updated = list.clone();
+        updated.profiles.retain(|p| p.id != id);
+        self.save(&updated)?;
+        *list = updated;
+
+        match std::fs::remove_dir_all(&dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(AppError::io(
+                    &dir,
+                    format!("Failed to delete profile data at {}: {}", dir.display(), e),
+                ))
+            }
+            _ => Ok(report),
+        }
+    }
+}
+
+// Close the current profile's stores and open `dir`'s. The old connection is
+// dropped before the new one opens so its WAL is checkpointed and released.
+pub fn open_stores(app: &AppHandle, dir: &Path) -> Result<(), String> {
+    let storage_state = app.state::<StorageState>();
+    let mut storage = storage_state.0.lock().unwrap_or_else(|p| p.into_inner());
+    *storage = None;
+    *storage = match Storage::open(&dir.join(storage::DB_FILE_NAME)) {
+        Ok(opened) => Some(opened),
+        // An encrypted store stays closed until `unlock_storage`.
+        Err(StorageError::Locked(_)) => None,
+        Err(e) => return Err(e.into()),
+    };
+    app.state::<AuditState>()
+        .reopen(dir.join(audit::AUDIT_FILE_NAME))
+}
+
+#[tauri::command]
+pub fn list_profiles(state: tauri::State<ProfileState>) -> ProfileList {
+    state.list().clone()
+}
+
+// `passphrase` encrypts the new profile's database; `unlock_storage` asks
+// for it after switching to the profile.
+#[tauri::command]
+pub fn create_profile(
+    state: tauri::State<ProfileState>,
+    guest: tauri::State<GuestState>,
+    name: String,
+    passphrase: String,
+) -> Result<Profile, AppError> {
+    guest.refuse("Creating a profile")?;
+    state.create(&name, &passphrase)
+}
+
+// Locks the app as part of the switch so the next person has to unlock the
+// profile they are moving into. Reminders the previous profile left pending
+// are forgotten with it.
+#[tauri::command]
+pub fn switch_profile(app: AppHandle, id: String) -> Result<Profile, AppError> {
+    app.state::<GuestState>().refuse("Switching profiles")?;
+    let state = app.state::<ProfileState>();
+    let switching = state.active_id() != id;
+    let profile = state.switch(&id, |dir| {
+        crate::autolock::lock(&app);
+        open_stores(&app, dir)
+    })?;
+    if switching {
+        reminders::forget_pending(&app);
+        let _ = app.emit_all("profile-switched", &profile);
+    }
+    Ok(profile)
+}
+
+#[tauri::command]
+pub fn delete_profile(
+    state: tauri::State<ProfileState>,
+    guest: tauri::State<GuestState>,
+    id: String,
+    dry_run: Option<bool>,
+) -> Result<DeleteReport, AppError> {
+    guest.refuse("Deleting a profile")?;
+    state.delete(&id, dry_run.unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSPHRASE: &str = "correct horse battery";
+
+    fn temp_state() -> ProfileState {
+        let root = std::env::temp_dir().join(format!("smartbot-profiles-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        ProfileState::load(root, None)
+    }
+
+    fn reminder(title: &str) -> Reminder {
+        Reminder {
+            id: title.to_string(),
+            time: "2026-06-01T09:00:00+00:00".to_string(),
+            timezone: None,
+            title: title.to_string(),
+            body: String::new(),
+            urgent: false,
+            repeat_minutes: None,
+            dismissals: 0,
+            backoff: 0,
+        }
+    }
+
+    // Stands in for `open_stores`, opening the profile's database as it
+    // would and keeping it in `storage`.
+    fn opener(storage: &Mutex<Option<Storage>>) -> impl Fn(&Path) -> Result<(), String> + '_ {
+        move |dir| {
+            let mut open = storage.lock().unwrap();
+            *open = None;
+            *open = match Storage::open(&dir.join(storage::DB_FILE_NAME)) {
+                Ok(opened) => Some(opened),
+                Err(StorageError::Locked(_)) => None,
+                Err(e) => return Err(e.into()),
+            };
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn profiles_keep_their_own_data_and_settings() {
+        let state = temp_state();
+        let storage = Mutex::new(None);
+        opener(&storage)(&state.active_dir().unwrap()).unwrap();
+        let mut default_store = storage.lock().unwrap().take().unwrap();
+        default_store.add_entry("journal", "default's entry", None).unwrap();
+        drop(default_store);
+        state
+            .update_settings(|settings| settings.reminders.push(reminder("default's")))
+            .unwrap();
+
+        let profile = state.create("Sam", PASSPHRASE).unwrap();
+        let db = profile_dir(state.root().unwrap(), &profile.id).join(storage::DB_FILE_NAME);
+        assert!(storage::is_encrypted(&db).unwrap());
+
+        state.switch(&profile.id, opener(&storage)).unwrap();
+        assert_eq!(state.active_id(), profile.id);
+        // Encrypted, so it waits for the passphrase.
+        assert!(storage.lock().unwrap().is_none());
+        let store = Storage::open_encrypted(&db, PASSPHRASE).unwrap();
+        assert_eq!(store.entry_count().unwrap(), 0);
+        assert!(state.settings().reminders.is_empty());
+        state
+            .update_settings(|settings| {
+                settings.reminders.push(reminder("Sam's"));
+                settings.reminders_paused = true;
+            })
+            .unwrap();
+
+        state.switch(DEFAULT_PROFILE_ID, opener(&storage)).unwrap();
+        let settings = state.settings();
+        let titles: Vec<_> = settings.reminders.iter().map(|r| r.title.as_str()).collect();
+        assert_eq!(titles, ["default's"]);
+        assert!(!settings.reminders_paused);
+        assert_eq!(storage.lock().unwrap().as_ref().unwrap().entry_count().unwrap(), 1);
+
+        // What was saved agrees with what is open.
+        let reloaded = ProfileState::load(state.root().unwrap().to_path_buf(), None);
+        assert_eq!(reloaded.active_id(), DEFAULT_PROFILE_ID);
+        assert_eq!(reloaded.list().profiles.len(), 2);
+    }
+
+    #[test]
+    fn a_failed_switch_stays_on_the_current_profile() {
+        let state = temp_state();
+        let profile = state.create("Sam", PASSPHRASE).unwrap();
+        let result = state.switch(&profile.id, |dir| {
+            if dir.ends_with(&profile.id) {
+                Err("can't open".to_string())
+            } else {
+                Ok(())
+            }
+        });
+        assert!(result.is_err());
+        assert_eq!(state.active_id(), DEFAULT_PROFILE_ID);
+        let reloaded = ProfileState::load(state.root().unwrap().to_path_buf(), None);
+        assert_eq!(reloaded.active_id(), DEFAULT_PROFILE_ID);
+    }
+
+    #[test]
+    fn the_active_and_default_profiles_cant_be_deleted() {
+        let state = temp_state();
+        let storage = Mutex::new(None);
+        let profile = state.create("Sam", PASSPHRASE).unwrap();
+        let dir = profile_dir(state.root().unwrap(), &profile.id);
+
+        assert!(matches!(
+            state.delete(DEFAULT_PROFILE_ID, false),
+            Err(AppError::InvalidInput(_))
+        ));
+        state.switch(&profile.id, opener(&storage)).unwrap();
+        assert!(matches!(state.delete(&profile.id, false), Err(AppError::InvalidInput(_))));
+        assert!(dir.exists());
+
+        state.switch(DEFAULT_PROFILE_ID, opener(&storage)).unwrap();
+        let report = state.delete(&profile.id, true).unwrap();
+        assert!(!report.files_removed.is_empty());
+        assert!(dir.exists());
+        state.delete(&profile.id, false).unwrap();
+        assert!(!dir.exists());
+        assert!(state.find(&profile.id).is_err());
+    }
+
+    #[test]
+    fn the_default_profile_takes_over_settings_from_the_config() {
+        let root = std::env::temp_dir().join(format!("smartbot-profiles-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let config = root.join("config.json");
+        std::fs::write(
+            &config,
+            serde_json::json!({ "zoom_level": 1.25, "reminders": [reminder("Old")] }).to_string(),
+        )
+        .unwrap();
+
+        let state = ProfileState::load(root.clone(), Some(&config));
+        assert_eq!(state.settings().reminders.len(), 1);
+        assert!(root.join(SETTINGS_FILE_NAME).exists());
+        // Only once: later config contents don't overwrite the profile's.
+        state.update_settings(|settings| settings.reminders.clear()).unwrap();
+        let state = ProfileState::load(root, Some(&config));
+        assert!(state.settings().reminders.is_empty());
+    }
+}
@@ -0,0 +1,273 @@
+// Programs a counselor shares: a set of worksheets and a plan, as a signed
+// `.smartprog` bundle. A bundle is only installed once its signature verifies
+// against one of `trusted_program_keys`, and the program is kept exactly as
+// signed, apart from the user's entries: it can be listed and removed, but
+// nothing edits it. Answers to its worksheets are ordinary entries that name
+// the program.
+//
+// A bundle is JSON: `program` holds the base64 of the program's JSON, and
+// `signature` a minisign signature of those bytes, base64-wrapped the same
+// way as the update manifest's.
+
+use crate::config::ConfigState;
+use crate::guest::GuestState;
+use crate::storage::{ProgramRecord, Storage, StorageError, StorageState};
+use crate::updates::decode_base64;
+use crate::validate;
+use crate::worksheets::{self, Worksheet};
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+const MAX_BUNDLE_LEN: u64 = 1024 * 1024;
+const MAX_ID_LEN: usize = 64;
+const MAX_NAME_LEN: usize = 200;
+const MAX_WORKSHEETS: usize = 50;
+const MAX_PLAN_STEPS: usize = 100;
+const MAX_STEP_LEN: usize = 2000;
+
+#[derive(Debug, Deserialize)]
+struct Bundle {
+    program: String,
+    signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanStep {
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Program {
+    pub id: String,
+    pub name: String,
+    // Who signed it off, e.g. the clinic; shown so the user can tell the
+    // program's worksheets from their own.
+    pub provider: String,
+    // An installed program is only replaced by the same or a later version.
+    pub version: u32,
+    #[serde(default)]
+    pub worksheets: Vec<Worksheet>,
+    #[serde(default)]
+    pub plan: Vec<PlanStep>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstalledProgram {
+    #[serde(flatten)]
+    pub program: Program,
+    pub installed_at: String,
+}
+
+// For config validation: a key that can't be read would fail every import.
+pub fn check_key(key: &str) -> Result<(), String> {
+    PublicKey::decode(&decode_base64(key, "program key")?)
+        .map(|_| ())
+        .map_err(|e| format!("Invalid program key: {}", e))
+}
+
+fn verifies(key: &str, data: &[u8], signature: &Signature) -> bool {
+    decode_base64(key, "program key")
+        .ok()
+        .and_then(|key| PublicKey::decode(&key).ok())
+        .is_some_and(|key| key.verify(data, signature, false).is_ok())
+}
+
+fn check_program(program: &Program) -> Result<(), String> {
+    validate::identifier("Program", &program.id, MAX_ID_LEN)?;
+    validate::non_empty("Program name", &program.name, MAX_NAME_LEN)?;
+    validate::non_empty("Provider", &program.provider, MAX_NAME_LEN)?;
+    if program.worksheets.is_empty() && program.plan.is_empty() {
+        return Err(format!("{} has no worksheets or plan", program.name));
+    }
+    if program.worksheets.len() > MAX_WORKSHEETS || program.plan.len() > MAX_PLAN_STEPS {
+        return Err(format!("{} is too large", program.name));
+    }
+    for (i, worksheet) in program.worksheets.iter().enumerate() {
+        worksheets::check_template(worksheet)?;
+        if program.worksheets[..i]
+            .iter()
+            .any(|other| other.id == worksheet.id)
+        {
+            return Err(format!(
+                "{} has worksheet {:?} twice",
+                program.name, worksheet.id
+            ));
+        }
+    }
+    for step in &program.plan {
+        validate::non_empty("Plan step", &step.title, MAX_NAME_LEN)?;
+        if let Some(details) = &step.details {
+            validate::text("Plan step details", details, MAX_STEP_LEN)?;
+        }
+    }
+    Ok(())
+}
+
+// Nothing in the program is looked at until the signature verifies. Returns
+// the program, its signed JSON and the key that signed it.
+fn open_bundle(bytes: &[u8], trusted_keys: &[String]) -> Result<(Program, String, String), String> {
+    let bundle: Bundle =
+        serde_json::from_slice(bytes).map_err(|e| format!("This isn't a program bundle: {}", e))?;
+    let content = decode_base64(&bundle.program, "program")?;
+    let signature = Signature::decode(&decode_base64(&bundle.signature, "program signature")?)
+        .map_err(|e| format!("The program's signature is unreadable: {}", e))?;
+    let signed_by = trusted_keys
+        .iter()
+        .find(|key| verifies(key, content.as_bytes(), &signature))
+        .ok_or("The program's signature doesn't match any trusted provider key, so it may have been altered")?;
+    let program: Program =
+        serde_json::from_str(&content).map_err(|e| format!("The program is malformed: {}", e))?;
+    check_program(&program)?;
+    Ok((program, content, signed_by.clone()))
+}
+
+fn installed(record: &ProgramRecord) -> Result<InstalledProgram, StorageError> {
+    let program = serde_json::from_str(&record.content).map_err(|e| {
+        StorageError::Corrupt(format!(
+            "Installed program {:?} is unreadable: {}",
+            record.id, e
+        ))
+    })?;
+    Ok(InstalledProgram {
+        program,
+        installed_at: record.installed_at.clone(),
+    })
+}
+
+pub fn worksheet(storage: &Storage, program: &str, id: &str) -> Result<Worksheet, StorageError> {
+    validate::identifier("Program", program, MAX_ID_LEN)?;
+    let record = storage
+        .program(program)?
+        .ok_or_else(|| StorageError::InvalidInput(format!("No program with id {:?}", program)))?;
+    installed(&record)?
+        .program
+        .worksheets
+        .into_iter()
+        .find(|worksheet| worksheet.id == id)
+        .ok_or_else(|| {
+            StorageError::InvalidInput(format!("{} has no worksheet {:?}", record.name, id))
+        })
+}
+
+// Reinstalling the same or a later version replaces what is installed; an
+// older one is refused so a stale copy can't undo a counselor's update.
+#[tauri::command]
+pub fn import_program(
+    app: AppHandle,
+    state: tauri::State<StorageState>,
+    guest: tauri::State<GuestState>,
+    config: tauri::State<ConfigState>,
+    src_path: String,
+) -> Result<InstalledProgram, StorageError> {
+    guest.refuse("Importing programs")?;
+    let path = validate::user_path(&app, "Program", &src_path)?;
+    let trusted_keys = config.get().trusted_program_keys;
+    if trusted_keys.is_empty() {
+        return Err(StorageError::InvalidInput(
+            "No provider keys are trusted yet; add your counselor's key to `trusted_program_keys`"
+                .to_string(),
+        ));
+    }
+    let size = std::fs::metadata(&path)
+        .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?
+        .len();
+    if size > MAX_BUNDLE_LEN {
+        return Err(StorageError::InvalidInput(format!(
+            "{} is too large to be a program bundle",
+            path.display()
+        )));
+    }
+    let bytes =
+        std::fs::read(&path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+    let (program, content, signed_by) =
+        open_bundle(&bytes, &trusted_keys).map_err(StorageError::InvalidInput)?;
+
+    let record = ProgramRecord {
+        id: program.id.clone(),
+        name: program.name.clone(),
+        provider: program.provider.clone(),
+        version: program.version.into(),
+        content,
+        signed_by,
+        installed_at: String::new(),
+    };
+    let record = state.with(|storage| {
+        if let Some(existing) = storage.program(&record.id)? {
+            if existing.version > record.version {
+                return Err(StorageError::InvalidInput(format!(
+                    "Version {} of {} is already installed, which is newer than this one",
+                    existing.version, existing.name
+                )));
+            }
+        }
+        storage.install_program(&record)
+    })?;
+    Ok(InstalledProgram {
+        program,
+        installed_at: record.installed_at,
+    })
+}
+
+#[tauri::command]
+pub fn list_programs(
+    state: tauri::State<StorageState>,
+) -> Result<Vec<InstalledProgram>, StorageError> {
+    state.with(|storage| storage.programs()?.iter().map(installed).collect())
+}
+
+// Answers already saved from its worksheets are kept.
+#[tauri::command]
+pub fn remove_program(
+    state: tauri::State<StorageState>,
+    guest: tauri::State<GuestState>,
+    id: String,
+) -> Result<(), StorageError> {
+    guest.refuse("Removing programs")?;
+    validate::identifier("Program", &id, MAX_ID_LEN)?;
+    state.with(|storage| storage.remove_program(&id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    // Signed with a throwaway minisign key made for these tests.
+    const KEY: &str = "dW50cnVzdGVkIGNvbW1lbnQ6IG1pbmlzaWduIHB1YmxpYyBrZXkgMDgwNzA2MDUwNDAzMDIwMQpSV1FCQWdNRUJRWUhDQU9oQjcvenpoQytIWERkR09kTHdKbG41Tll3bTZVTlh4M2NobVFTVlRHNAo=";
+    const PROGRAM: &str = r#"{"id":"harbor-week-1","name":"Harbor Clinic week 1","provider":"Harbor Clinic","version":1,"worksheets":[{"id":"urge_log","version":1,"title":"Urge log","description":"Notice urges without acting on them.","fields":[{"id":"trigger","label":"What set it off?","type":"text","required":true},{"id":"strength","label":"How strong, 1-10?","type":"scale","min":1,"max":10}]}],"plan":[{"title":"Fill in an urge log each evening"}]}"#;
+    const SIGNATURE: &str = "dW50cnVzdGVkIGNvbW1lbnQ6IHNpZ25hdHVyZSBmcm9tIG1pbmlzaWduIHNlY3JldCBrZXkKUlVRQkFnTUVCUVlIQ0lhbDkzMUprOTlweVZOWkZ4ZnlhclJkUzBOR0JtT0lpcEJwVXpTOE1WWTBQVWRzMlFWVG51VUg2NTJFV2R4YkxabXZIQ2lwYVAxRXQ1Y3RJYlNnY2dFPQp0cnVzdGVkIGNvbW1lbnQ6IHRpbWVzdGFtcDoxNzYwMDAwMDAwCWZpbGU6cHJvZ3JhbS5qc29uCldWNjVET2ZidkF2U2Z3YjNWcHZnd1RRVzFSV21ZYngxalNReXNkWlFMNDM4U2ZEQXNvL1VGVkw5N0E5SXg4bGhKR3J1anRwbDA1OCtwZUdoTE9LVEF3PT0K";
+
+    fn bundle(program: &str, signature: &str) -> Vec<u8> {
+        serde_json::json!({
+            "program": base64::engine::general_purpose::STANDARD.encode(program),
+            "signature": signature,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    #[test]
+    fn signed_bundle_opens_with_a_trusted_key() {
+        let keys = [KEY.to_string()];
+        let (program, content, signed_by) =
+            open_bundle(&bundle(PROGRAM, SIGNATURE), &keys).unwrap();
+        assert_eq!(program.id, "harbor-week-1");
+        assert_eq!(program.worksheets[0].fields.len(), 2);
+        assert_eq!(content, PROGRAM);
+        assert_eq!(signed_by, KEY);
+        check_key(KEY).unwrap();
+    }
+
+    #[test]
+    fn unsigned_tampered_and_untrusted_bundles_are_rejected() {
+        let keys = [KEY.to_string()];
+        let tampered = PROGRAM.replace("\"max\":10", "\"max\":11");
+        assert!(open_bundle(&bundle(&tampered, SIGNATURE), &keys).is_err());
+        assert!(open_bundle(&bundle(PROGRAM, ""), &keys).is_err());
+        assert!(open_bundle(PROGRAM.as_bytes(), &keys).is_err());
+        assert!(open_bundle(&bundle(PROGRAM, SIGNATURE), &[]).is_err());
+    }
+}
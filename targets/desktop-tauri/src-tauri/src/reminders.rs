@@ -0,0 +1,723 @@
+// One-off reminders scheduled by the frontend and fired by the shell as
+// native notifications, so check-in nudges arrive even when the webview is in
+// the background and the core is asleep. They can be paused as a whole,
+// e.g. through a rough week, without losing the schedule.
+//
+// Each reminder keeps the IANA zone it was set in and fires at that zone's
+// wall-clock time, so a trip or a change of system time zone doesn't move
+// it.
+//
+// A reminder that has fired stays pending until the frontend acknowledges
+// it, and the number pending is shown as a badge on the app icon.
+//
+// A repeating reminder is moved on by its interval each time it comes due
+// instead of being removed. In adaptive mode (`ReminderAdaptation`) the
+// interval doubles after enough dismissals in a row, up to a limit, and
+// halves again with each time the user engages with it. A reminder still
+// pending when it comes round again counts as dismissed. The state is kept
+// with the reminder in the profile's settings, so it survives restarts.
+//
+// Reminders, their pause and adaptive mode belong to the active profile
+// (`ProfileSettings`); another profile's don't fire until it is switched to.
+
+use crate::background::Workers;
+use crate::badge::{self, BadgeStatus};
+use crate::config::ConfigState;
+use crate::error::AppError;
+use crate::guest::GuestState;
+use crate::notifications;
+use crate::profiles::{ProfileSettings, ProfileState};
+use crate::validate;
+use chrono::{DateTime, FixedOffset, LocalResult, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const TICK: Duration = Duration::from_secs(5);
+// A reminder this late (the machine was asleep) is dropped rather than fired.
+const MAX_LATENESS: chrono::Duration = chrono::Duration::minutes(10);
+const MAX_TITLE_LEN: usize = 200;
+const MAX_BODY_LEN: usize = 2000;
+const MAX_TIMEZONE_LEN: usize = 64;
+// Further back than any DST change is long, to find the offset in effect
+// before a gap.
+const GAP_LOOKBACK: chrono::Duration = chrono::Duration::hours(3);
+
+pub const REPEAT_MINUTES_RANGE: std::ops::RangeInclusive<u32> = 15..=30 * 24 * 60;
+pub const DISMISSALS_TO_BACK_OFF_RANGE: std::ops::RangeInclusive<u32> = 1..=20;
+pub const MAX_BACKOFF_RANGE: std::ops::RangeInclusive<u32> = 1..=64;
+
+pub const PAUSED_CHANGED_EVENT: &str = "reminders-paused-changed";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reminder {
+    pub id: String,
+    // RFC 3339. With a `timezone`, written in that zone's offset, and only
+    // its wall-clock part decides when the reminder fires.
+    pub time: String,
+    // Reminders saved before zones were stored have none and fire at the
+    // instant in `time`.
+    #[serde(default)]
+    pub timezone: Option<String>,
+    pub title: String,
+    pub body: String,
+    // Crisis reminders are never held back by the notification limiter.
+    #[serde(default)]
+    pub urgent: bool,
+    // Minutes between occurrences, within `REPEAT_MINUTES_RANGE`; `None` for
+    // a one-off reminder.
+    #[serde(default)]
+    pub repeat_minutes: Option<u32>,
+    // Adaptive mode's state: dismissals in a row since it last doubled the
+    // interval or the user engaged, and how many times it is doubled now.
+    #[serde(default)]
+    pub dismissals: u32,
+    #[serde(default)]
+    pub backoff: u32,
+}
+
+// Thresholds for adaptive mode; off by default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReminderAdaptation {
+    pub enabled: bool,
+    // Dismissals in a row before a repeating reminder's interval doubles.
+    pub dismissals_to_back_off: u32,
+    // The interval grows to at most this many times the one asked for.
+    pub max_backoff: u32,
+}
+
+impl Default for ReminderAdaptation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dismissals_to_back_off: 3,
+            max_backoff: 8,
+        }
+    }
+}
+
+impl ReminderAdaptation {
+    // Hand-edited settings may be out of range, so clamp rather than trust.
+    fn threshold(&self) -> u32 {
+        self.dismissals_to_back_off.clamp(
+            *DISMISSALS_TO_BACK_OFF_RANGE.start(),
+            *DISMISSALS_TO_BACK_OFF_RANGE.end(),
+        )
+    }
+
+    // The most doublings that stay within `max_backoff`.
+    fn max_doublings(&self) -> u32 {
+        self.max_backoff
+            .clamp(*MAX_BACKOFF_RANGE.start(), *MAX_BACKOFF_RANGE.end())
+            .ilog2()
+    }
+}
+
+impl Reminder {
+    fn due_at(&self) -> Option<DateTime<Utc>> {
+        let time = DateTime::parse_from_rfc3339(&self.time).ok()?;
+        match &self.timezone {
+            Some(zone) => resolve_local(time.naive_local(), zone.parse().ok()?),
+            None => Some(time.with_timezone(&Utc)),
+        }
+    }
+
+    // The interval the reminder repeats at now; only differs from
+    // `repeat_minutes` in adaptive mode.
+    pub fn effective_repeat_minutes(&self, adaptation: &ReminderAdaptation) -> Option<u32> {
+        let doublings = if adaptation.enabled {
+            self.backoff.min(adaptation.max_doublings())
+        } else {
+            0
+        };
+        self.repeat_minutes.map(|minutes| minutes.saturating_mul(1 << doublings))
+    }
+
+    fn record_dismissal(&mut self, adaptation: &ReminderAdaptation) {
+        if !adaptation.enabled || self.repeat_minutes.is_none() {
+            return;
+        }
+        self.dismissals += 1;
+        if self.dismissals >= adaptation.threshold() {
+            self.dismissals = 0;
+            self.backoff = (self.backoff + 1).min(adaptation.max_doublings());
+        }
+    }
+
+    // One step back towards the interval asked for per engagement, so a
+    // single tap after weeks of silence doesn't bring back the full rate.
+    fn record_engagement(&mut self, adaptation: &ReminderAdaptation) {
+        if !adaptation.enabled || self.repeat_minutes.is_none() {
+            return;
+        }
+        self.dismissals = 0;
+        self.backoff = self.backoff.min(adaptation.max_doublings()).saturating_sub(1);
+    }
+
+    // The first occurrence after `now`, keeping the wall-clock time in the
+    // reminder's zone like `due_at`. Written with the offset in effect then.
+    fn next_time(&self, now: DateTime<Utc>, minutes: u32) -> Option<String> {
+        let time = DateTime::parse_from_rfc3339(&self.time).ok()?;
+        let zone: Option<Tz> = match &self.timezone {
+            Some(zone) => Some(zone.parse().ok()?),
+            None => None,
+        };
+        let due = |local: NaiveDateTime| match zone {
+            Some(zone) => resolve_local(local, zone),
+            None => Some(time.offset().from_local_datetime(&local).single()?.with_timezone(&Utc)),
+        };
+        let step = chrono::Duration::minutes(minutes.max(1).into());
+        let mut local = time.naive_local() + step;
+        let behind = (now - due(local)?).num_minutes();
+        if behind >= 0 {
+            local += step * i32::try_from(behind / step.num_minutes() + 1).ok()?;
+        }
+        // A clock change can leave it just short of `now`.
+        let mut at = due(local)?;
+        while at <= now {
+            local += step;
+            at = due(local)?;
+        }
+        let offset: FixedOffset = match zone {
+            Some(zone) => at.with_timezone(&zone).offset().fix(),
+            None => *time.offset(),
+        };
+        Some(offset.from_local_datetime(&local).single()?.to_rfc3339())
+    }
+}
+
+// What becomes of a reminder once it has come due: a repeating one moves on
+// to its next occurrence, counting as dismissed if the last one is still
+// pending; a one-off, or one whose time can't be read, is dropped.
+fn roll_forward(
+    mut reminder: Reminder,
+    now: DateTime<Utc>,
+    adaptation: &ReminderAdaptation,
+    ignored: bool,
+) -> Option<Reminder> {
+    if ignored {
+        reminder.record_dismissal(adaptation);
+    }
+    let minutes = reminder.effective_repeat_minutes(adaptation)?;
+    reminder.time = reminder.next_time(now, minutes)?;
+    Some(reminder)
+}
+
+// A wall-clock time skipped by a spring-forward change is moved on by the
+// length of the gap (2:30 becomes 3:30), and one repeated by a fall-back
+// change fires at its first occurrence. Reminders are removed once they
+// fire, so neither case can fire twice.
+fn resolve_local(local: NaiveDateTime, zone: Tz) -> Option<DateTime<Utc>> {
+    match zone.from_local_datetime(&local) {
+        LocalResult::Single(at) | LocalResult::Ambiguous(at, _) => Some(at.with_timezone(&Utc)),
+        LocalResult::None => {
+            let before = zone.from_local_datetime(&(local - GAP_LOOKBACK)).earliest()?;
+            let offset = before.offset().fix().local_minus_utc();
+            let utc = local - chrono::Duration::seconds(offset.into());
+            Some(Utc.from_utc_datetime(&utc))
+        }
+    }
+}
+
+pub fn parse_timezone(timezone: &str) -> Result<Tz, String> {
+    validate::text("Time zone", timezone, MAX_TIMEZONE_LEN)?;
+    timezone
+        .parse()
+        .map_err(|_| format!("Unknown time zone {:?}", timezone))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PausedState {
+    pub paused: bool,
+    // `None` while paused means until `resume_reminders`.
+    pub until: Option<String>,
+}
+
+fn paused_state(settings: &ProfileSettings) -> PausedState {
+    PausedState {
+        paused: settings.reminders_paused,
+        until: settings.reminders_paused_until.clone(),
+    }
+}
+
+// An unreadable `until` in a hand-edited settings file counts as expired, so
+// a typo can't silence reminders forever.
+fn pause_expired(settings: &ProfileSettings, now: DateTime<Utc>) -> bool {
+    settings.reminders_paused_until.as_ref().is_some_and(|until| {
+        DateTime::parse_from_rfc3339(until).map_or(true, |until| until <= now)
+    })
+}
+
+fn set_paused(app: &AppHandle, paused: bool, until: Option<String>) -> Result<PausedState, String> {
+    let settings = app.state::<ProfileState>().update_settings(|settings| {
+        settings.reminders_paused = paused;
+        settings.reminders_paused_until = until;
+    })?;
+    let state = paused_state(&settings);
+    let _ = app.emit_all(PAUSED_CHANGED_EVENT, &state);
+    Ok(state)
+}
+
+// Ids of reminders that have fired but not been acknowledged. Not saved: the
+// badge doesn't outlive the app either.
+#[derive(Default)]
+pub struct PendingReminders(Mutex<HashSet<String>>);
+
+impl PendingReminders {
+    fn ids(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.0.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+fn update_badge(app: &AppHandle) -> BadgeStatus {
+    let count = app.state::<PendingReminders>().ids().len();
+    badge::set(app, u32::try_from(count).unwrap_or(u32::MAX))
+}
+
+// On a profile switch: what the previous profile left pending isn't the next
+// one's to acknowledge.
+pub fn forget_pending(app: &AppHandle) {
+    app.state::<PendingReminders>().ids().clear();
+    update_badge(app);
+}
+
+fn notify(app: &AppHandle, reminder: &Reminder) {
+    let result = notifications::notify(app, &reminder.title, &reminder.body, reminder.urgent);
+    if let Err(e) = result {
+        eprintln!("Failed to show reminder {}: {}", reminder.id, e);
+    }
+}
+
+// Held while a guest session runs, so the regular user's reminders aren't
+// shown to the guest; any that come due meanwhile are dropped as too late.
+fn tick(app: &AppHandle) {
+    if app.state::<GuestState>().active() {
+        return;
+    }
+    let now = Utc::now();
+    let profiles = app.state::<ProfileState>();
+    let settings = profiles.settings();
+    let mut paused = settings.reminders_paused;
+    if paused && pause_expired(&settings, now) {
+        match set_paused(app, false, None) {
+            Ok(_) => paused = false,
+            Err(e) => eprintln!("Failed to resume reminders: {}", e),
+        }
+    }
+    let is_due = |reminder: &Reminder| reminder.due_at().is_none_or(|at| at <= now);
+    if !settings.reminders.iter().any(is_due) {
+        return;
+    }
+
+    let ignored = app.state::<PendingReminders>().ids().clone();
+    let mut due = Vec::new();
+    let result = profiles.update_settings(|settings| {
+        let (fired, pending): (Vec<Reminder>, Vec<Reminder>) =
+            std::mem::take(&mut settings.reminders).into_iter().partition(is_due);
+        settings.reminders = pending;
+        for reminder in &fired {
+            let ignored = ignored.contains(&reminder.id);
+            let next = roll_forward(reminder.clone(), now, &settings.reminder_adaptation, ignored);
+            settings.reminders.extend(next);
+        }
+        due = fired;
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to update reminders: {}", e);
+        return;
+    }
+    // Skipped rather than saved up, so resuming doesn't set off a burst.
+    // Turning notifications off stops them the same way.
+    if paused || !app.state::<ConfigState>().get().features.notifications {
+        return;
+    }
+    let fired: Vec<Reminder> = due
+        .into_iter()
+        .filter(|reminder| reminder.due_at().is_some_and(|at| now - at <= MAX_LATENESS))
+        .collect();
+    if fired.is_empty() {
+        return;
+    }
+    for reminder in &fired {
+        notify(app, reminder);
+    }
+    app.state::<PendingReminders>()
+        .ids()
+        .extend(fired.into_iter().map(|reminder| reminder.id));
+    update_badge(app);
+}
+
+// Reminders whose time passed while the app was closed are dropped, not
+// fired all at once on launch; repeating ones move on to their next
+// occurrence.
+pub fn start(app: &AppHandle) {
+    let now = Utc::now();
+    let profiles = app.state::<ProfileState>();
+    let has_missed = profiles
+        .settings()
+        .reminders
+        .iter()
+        .any(|reminder| reminder.due_at().is_none_or(|at| at <= now));
+    if has_missed {
+        let result = profiles.update_settings(|settings| {
+            let adaptation = settings.reminder_adaptation.clone();
+            settings.reminders = std::mem::take(&mut settings.reminders)
+                .into_iter()
+                .filter_map(|reminder| match reminder.due_at() {
+                    Some(at) if at > now => Some(reminder),
+                    _ => roll_forward(reminder, now, &adaptation, false),
+                })
+                .collect();
+        });
+        if let Err(e) = result {
+            eprintln!("Failed to drop missed reminders: {}", e);
+        }
+    }
+    let handle = app.clone();
+    app.state::<Workers>().spawn(TICK, move || tick(&handle));
+}
+
+// Returns the reminder's id, for `cancel_reminder`. `timezone` (IANA, e.g.
+// "Europe/Madrid") defaults to the system's zone. With `repeat_minutes` the
+// reminder comes back at that interval from `time` until cancelled.
+#[tauri::command]
+pub fn schedule_reminder(
+    app: AppHandle,
+    time: String,
+    title: String,
+    body: String,
+    urgent: Option<bool>,
+    timezone: Option<String>,
+    repeat_minutes: Option<u32>,
+) -> Result<String, AppError> {
+    app.state::<GuestState>().refuse("Reminders")?;
+    let at = DateTime::parse_from_rfc3339(&time)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid reminder time {:?}: {}", time, e)))?;
+    if at <= Utc::now() {
+        return Err(AppError::InvalidInput(
+            "Reminder times must be in the future".to_string(),
+        ));
+    }
+    validate::non_empty("Reminder title", &title, MAX_TITLE_LEN)?;
+    validate::text("Reminder body", &body, MAX_BODY_LEN)?;
+    if let Some(minutes) = repeat_minutes {
+        validate::number("The repeat interval (minutes)", minutes, REPEAT_MINUTES_RANGE)?;
+    }
+    let timezone = match timezone {
+        Some(timezone) => Some(timezone),
+        None => iana_time_zone::get_timezone()
+            .inspect_err(|e| eprintln!("Couldn't read the system time zone: {}", e))
+            .ok(),
+    };
+    let zone = timezone.as_deref().map(parse_timezone).transpose()?;
+
+    let reminder = Reminder {
+        id: uuid::Uuid::new_v4().simple().to_string(),
+        time: match zone {
+            Some(zone) => at.with_timezone(&zone).to_rfc3339(),
+            None => at.to_rfc3339(),
+        },
+        timezone,
+        title,
+        body,
+        urgent: urgent.unwrap_or(false),
+        repeat_minutes,
+        dismissals: 0,
+        backoff: 0,
+    };
+    let id = reminder.id.clone();
+    app.state::<ProfileState>()
+        .update_settings(|settings| settings.reminders.push(reminder))?;
+    Ok(id)
+}
+
+// A cancelled reminder that has fired stops counting towards the badge.
+#[tauri::command]
+pub fn cancel_reminder(app: AppHandle, id: String) -> Result<(), AppError> {
+    let mut found = false;
+    app.state::<ProfileState>().update_settings(|settings| {
+        let before = settings.reminders.len();
+        settings.reminders.retain(|reminder| reminder.id != id);
+        found = settings.reminders.len() != before;
+    })?;
+    if app.state::<PendingReminders>().ids().remove(&id) {
+        update_badge(&app);
+    }
+    if !found {
+        return Err(AppError::InvalidInput(format!("No reminder with id {}", id)));
+    }
+    Ok(())
+}
+
+// `until` is RFC 3339; without it the pause lasts until `resume_reminders`.
+#[tauri::command]
+pub fn pause_reminders(app: AppHandle, until: Option<String>) -> Result<PausedState, AppError> {
+    let until = match until {
+        Some(until) => {
+            let at = DateTime::parse_from_rfc3339(&until)
+                .map_err(|e| AppError::InvalidInput(format!("Invalid pause end {:?}: {}", until, e)))?;
+            if at <= Utc::now() {
+                return Err(AppError::InvalidInput(
+                    "A pause must end in the future".to_string(),
+                ));
+            }
+            Some(at.to_rfc3339())
+        }
+        None => None,
+    };
+    Ok(set_paused(&app, true, until)?)
+}
+
+#[tauri::command]
+pub fn resume_reminders(app: AppHandle) -> Result<PausedState, AppError> {
+    Ok(set_paused(&app, false, None)?)
+}
+
+#[tauri::command]
+pub fn reminders_paused_state(profiles: tauri::State<ProfileState>) -> PausedState {
+    paused_state(&profiles.settings())
+}
+
+// Marks fired reminders as handled, or all of them without `ids`, and
+// updates the badge; it is cleared once nothing is pending. In adaptive mode
+// `dismissed` says the user waved them away rather than engaged with them.
+#[tauri::command]
+pub async fn acknowledge_reminders(
+    app: AppHandle,
+    ids: Option<Vec<String>>,
+    dismissed: Option<bool>,
+) -> Result<BadgeStatus, AppError> {
+    let handled: HashSet<String> = {
+        let state = app.state::<PendingReminders>();
+        let mut pending = state.ids();
+        match ids {
+            Some(ids) => ids.into_iter().filter(|id| pending.remove(id)).collect(),
+            None => pending.drain().collect(),
+        }
+    };
+    let profiles = app.state::<ProfileState>();
+    let settings = profiles.settings();
+    let adapts = settings.reminder_adaptation.enabled
+        && settings
+            .reminders
+            .iter()
+            .any(|reminder| reminder.repeat_minutes.is_some() && handled.contains(&reminder.id));
+    if adapts {
+        profiles.update_settings(|settings| {
+            let adaptation = settings.reminder_adaptation.clone();
+            let responded = settings.reminders.iter_mut();
+            for reminder in responded.filter(|reminder| handled.contains(&reminder.id)) {
+                if dismissed.unwrap_or(false) {
+                    reminder.record_dismissal(&adaptation);
+                } else {
+                    reminder.record_engagement(&adaptation);
+                }
+            }
+        })?;
+    }
+    Ok(update_badge(&app))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReminderListing {
+    #[serde(flatten)]
+    pub reminder: Reminder,
+    // The interval adaptive mode has it repeating at; `repeat_minutes` until
+    // it backs off.
+    pub effective_repeat_minutes: Option<u32>,
+}
+
+// Scheduled reminders, soonest first.
+#[tauri::command]
+pub fn list_reminders(profiles: tauri::State<ProfileState>) -> Vec<ReminderListing> {
+    let settings = profiles.settings();
+    let mut listings: Vec<ReminderListing> = settings
+        .reminders
+        .into_iter()
+        .map(|reminder| {
+            let adaptation = &settings.reminder_adaptation;
+            ReminderListing {
+                effective_repeat_minutes: reminder.effective_repeat_minutes(adaptation),
+                reminder,
+            }
+        })
+        .collect();
+    listings.sort_by_key(|listing| listing.reminder.due_at());
+    listings
+}
+
+// Turning adaptive mode off keeps each reminder's state, so turning it back
+// on picks up where it was.
+#[tauri::command]
+pub fn set_reminder_adaptation(
+    app: AppHandle,
+    adaptation: ReminderAdaptation,
+) -> Result<ReminderAdaptation, AppError> {
+    validate::number(
+        "Dismissals before backing off",
+        adaptation.dismissals_to_back_off,
+        DISMISSALS_TO_BACK_OFF_RANGE,
+    )?;
+    validate::number("The maximum backoff", adaptation.max_backoff, MAX_BACKOFF_RANGE)?;
+    let settings = app
+        .state::<ProfileState>()
+        .update_settings(|settings| settings.reminder_adaptation = adaptation.clone())?;
+    Ok(settings.reminder_adaptation)
+}
+
+#[tauri::command]
+pub fn pending_reminders(pending: tauri::State<PendingReminders>) -> Vec<String> {
+    let mut ids: Vec<String> = pending.ids().iter().cloned().collect();
+    ids.sort();
+    ids
+}
+
+// Keeps the reminder's wall-clock time and reads it in `timezone` from now
+// on. Returns the updated reminder.
+#[tauri::command]
+pub fn set_reminder_timezone(app: AppHandle, id: String, timezone: String) -> Result<Reminder, AppError> {
+    let zone = parse_timezone(&timezone)?;
+    let reminder = app
+        .state::<ProfileState>()
+        .settings()
+        .reminders
+        .into_iter()
+        .find(|reminder| reminder.id == id)
+        .ok_or_else(|| AppError::InvalidInput(format!("No reminder with id {}", id)))?;
+    let local = DateTime::parse_from_rfc3339(&reminder.time)
+        .map_err(|e| AppError::Failed(format!("Reminder {} has an invalid time: {}", id, e)))?
+        .naive_local();
+    let due = resolve_local(local, zone)
+        .ok_or_else(|| AppError::InvalidInput(format!("{} doesn't exist in {}", local, timezone)))?;
+    if due <= Utc::now() {
+        return Err(AppError::InvalidInput(format!(
+            "In {} that time has already passed",
+            timezone
+        )));
+    }
+
+    let updated = Reminder {
+        time: due.with_timezone(&zone).to_rfc3339(),
+        timezone: Some(timezone),
+        ..reminder
+    };
+    let mut found = false;
+    app.state::<ProfileState>().update_settings(|settings| {
+        if let Some(stored) = settings.reminders.iter_mut().find(|stored| stored.id == id) {
+            *stored = updated.clone();
+            found = true;
+        }
+    })?;
+    if !found {
+        return Err(AppError::InvalidInput(format!("No reminder with id {}", id)));
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reminder(time: &str, timezone: Option<&str>) -> Reminder {
+        Reminder {
+            id: "test".to_string(),
+            time: time.to_string(),
+            timezone: timezone.map(str::to_string),
+            title: "Check in".to_string(),
+            body: String::new(),
+            urgent: false,
+            repeat_minutes: None,
+            dismissals: 0,
+            backoff: 0,
+        }
+    }
+
+    fn adaptive() -> ReminderAdaptation {
+        ReminderAdaptation {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    fn utc(time: &str) -> Option<DateTime<Utc>> {
+        Some(DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc))
+    }
+
+    #[test]
+    fn wall_clock_time_is_read_in_the_stored_zone() {
+        // Written with another offset, e.g. by a hand edit: only 09:00 counts.
+        let r = reminder("2026-06-01T09:00:00+02:00", Some("America/New_York"));
+        assert_eq!(r.due_at(), utc("2026-06-01T13:00:00Z"));
+        let r = reminder("2026-06-01T09:00:00+02:00", None);
+        assert_eq!(r.due_at(), utc("2026-06-01T07:00:00Z"));
+        let r = reminder("2026-06-01T09:00:00+02:00", Some("Not/A_Zone"));
+        assert_eq!(r.due_at(), None);
+    }
+
+    #[test]
+    fn time_skipped_by_spring_forward_moves_past_the_gap() {
+        // 02:30 doesn't exist in New York on 8 March 2026; it fires at 03:30 EDT.
+        let r = reminder("2026-03-08T02:30:00-05:00", Some("America/New_York"));
+        assert_eq!(r.due_at(), utc("2026-03-08T07:30:00Z"));
+    }
+
+    #[test]
+    fn spring_forward_gap_is_found_east_of_utc_too() {
+        // Sydney skips 02:00-03:00 on 4 October 2026.
+        let r = reminder("2026-10-04T02:30:00+10:00", Some("Australia/Sydney"));
+        assert_eq!(r.due_at(), utc("2026-10-03T16:30:00Z"));
+    }
+
+    #[test]
+    fn time_repeated_by_fall_back_fires_once_at_the_first() {
+        // 01:30 happens twice in New York on 1 November 2026: EDT, then EST.
+        let r = reminder("2026-11-01T01:30:00-05:00", Some("America/New_York"));
+        assert_eq!(r.due_at(), utc("2026-11-01T05:30:00Z"));
+    }
+
+    #[test]
+    fn dismissals_stretch_the_interval_and_engagement_brings_it_back() {
+        let adaptation = adaptive();
+        let mut r = reminder("2026-06-01T09:00:00+00:00", None);
+        r.repeat_minutes = Some(60);
+        for _ in 0..2 {
+            r.record_dismissal(&adaptation);
+        }
+        assert_eq!(r.effective_repeat_minutes(&adaptation), Some(60));
+        r.record_dismissal(&adaptation);
+        assert_eq!(r.effective_repeat_minutes(&adaptation), Some(120));
+        // Capped at `max_backoff` times the interval asked for.
+        for _ in 0..12 {
+            r.record_dismissal(&adaptation);
+        }
+        assert_eq!(r.effective_repeat_minutes(&adaptation), Some(480));
+
+        r.record_engagement(&adaptation);
+        assert_eq!(r.effective_repeat_minutes(&adaptation), Some(240));
+        assert_eq!(r.dismissals, 0);
+        let off = ReminderAdaptation::default();
+        assert_eq!(r.effective_repeat_minutes(&off), Some(60));
+    }
+
+    #[test]
+    fn repeating_reminders_move_past_now_on_the_same_wall_clock() {
+        let adaptation = adaptive();
+        let mut r = reminder("2026-03-07T09:00:00-05:00", Some("America/New_York"));
+        r.repeat_minutes = Some(24 * 60);
+        // Two days late, across the spring-forward change: still 09:00, now EDT.
+        let now = utc("2026-03-09T15:00:00Z").unwrap();
+        let next = roll_forward(r.clone(), now, &adaptation, false).unwrap();
+        assert_eq!(next.time, "2026-03-10T09:00:00-04:00");
+        assert_eq!(next.due_at(), utc("2026-03-10T13:00:00Z"));
+
+        // Still pending from last time counts as a dismissal.
+        let next = roll_forward(r.clone(), now, &adaptation, true).unwrap();
+        assert_eq!(next.dismissals, 1);
+        r.repeat_minutes = None;
+        assert!(roll_forward(r, now, &adaptation, false).is_none());
+    }
+}
@@ -0,0 +1,136 @@
+// Checks the files bundled with the app (`bundle.resources` in
+// `tauri.conf.json`) against checksums `build.rs` takes of them. A damaged or
+// altered install could otherwise show wrong text, such as a changed hotline
+// number, with nothing looking amiss. Worksheets and the English strings are
+// compiled into the binary rather than read from the bundle, so they aren't
+// covered here; the binary is the updater's and the OS's to check.
+
+use crate::error::AppError;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Runtime, Window};
+
+include!(concat!(env!("OUT_DIR"), "/resource_checksums.rs"));
+
+pub const RESOURCES_CORRUPT_EVENT: &str = "resources-corrupt";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceReport {
+    pub ok: bool,
+    pub checked: usize,
+    // Paths relative to the resource directory, e.g. "resources/locales/es.json".
+    pub missing: Vec<String>,
+    pub altered: Vec<String>,
+    // RFC 3339.
+    pub checked_at: String,
+}
+
+// The last report, kept while it found a problem so each page load can warn
+// again.
+#[derive(Default)]
+pub struct ResourceState(Mutex<Option<ResourceReport>>);
+
+fn verify_files(
+    expected: &[(&str, &str)],
+    resolve: impl Fn(&str) -> Option<PathBuf>,
+) -> ResourceReport {
+    let mut missing = Vec::new();
+    let mut altered = Vec::new();
+    for &(name, sha256) in expected {
+        match resolve(name).map(std::fs::read) {
+            Some(Ok(bytes)) => {
+                if hex::encode(Sha256::digest(&bytes)) != sha256 {
+                    altered.push(name.to_string());
+                }
+            }
+            _ => missing.push(name.to_string()),
+        }
+    }
+    ResourceReport {
+        ok: missing.is_empty() && altered.is_empty(),
+        checked: expected.len(),
+        missing,
+        altered,
+        checked_at: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+fn verify(app: &AppHandle) -> ResourceReport {
+    let report = verify_files(RESOURCE_CHECKSUMS, |name| {
+        app.path_resolver().resolve_resource(name)
+    });
+    let corrupt = (!report.ok).then(|| report.clone());
+    *app.state::<ResourceState>()
+        .0
+        .lock()
+        .unwrap_or_else(|p| p.into_inner()) = corrupt;
+    if !report.ok {
+        eprintln!(
+            "Bundled resources are damaged: missing {:?}, altered {:?}",
+            report.missing, report.altered
+        );
+        let _ = app.emit_all(RESOURCES_CORRUPT_EVENT, &report);
+    }
+    report
+}
+
+// Called from `setup`; the event sent here goes unheard, so `on_page_load`
+// repeats it.
+pub fn check(app: &AppHandle) {
+    verify(app);
+}
+
+pub fn on_page_load<R: Runtime>(window: &Window<R>) {
+    let report = window
+        .state::<ResourceState>()
+        .0
+        .lock()
+        .unwrap_or_else(|p| p.into_inner())
+        .clone();
+    if let Some(report) = report {
+        let _ = window.emit(RESOURCES_CORRUPT_EVENT, &report);
+    }
+}
+
+#[tauri::command]
+pub async fn verify_resources(app: AppHandle) -> Result<ResourceReport, AppError> {
+    let report = tauri::async_runtime::spawn_blocking(move || verify(&app))
+        .await
+        .map_err(|e| format!("Resource check failed: {}", e))?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_and_altered_files_are_reported() {
+        let dir = std::env::temp_dir().join(format!("smartbot-resources-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("en.json"), "{}").unwrap();
+        std::fs::write(dir.join("es.json"), r#"{"crisis":"999"}"#).unwrap();
+        let intact = hex::encode(Sha256::digest(b"{}"));
+        let expected = [
+            ("en.json", intact.as_str()),
+            ("es.json", intact.as_str()),
+            ("fr.json", intact.as_str()),
+        ];
+
+        let report = verify_files(&expected, |name| Some(dir.join(name)));
+        assert!(!report.ok);
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.altered, ["es.json"]);
+        assert_eq!(report.missing, ["fr.json"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checksums_cover_the_bundled_locales() {
+        assert!(RESOURCE_CHECKSUMS
+            .iter()
+            .any(|(name, _)| *name == "resources/locales/en.json"));
+    }
+}
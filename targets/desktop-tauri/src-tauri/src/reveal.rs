@@ -0,0 +1,76 @@
+// Showing a file in the system file manager, e.g. a backup right after it
+// was written.
+
+use crate::error::AppError;
+use crate::validate;
+use std::path::Path;
+use std::process::Command;
+use tauri::AppHandle;
+
+#[cfg(target_os = "macos")]
+fn reveal(path: &Path) -> Result<(), String> {
+    let status = Command::new("open")
+        .arg("-R")
+        .arg(path)
+        .status()
+        .map_err(|e| format!("Failed to run open: {}", e))?;
+    if !status.success() {
+        return Err(format!("Finder could not show {}", path.display()));
+    }
+    Ok(())
+}
+
+// Explorer exits non-zero even when it succeeds, so only a failure to launch
+// it is reported.
+#[cfg(windows)]
+fn reveal(path: &Path) -> Result<(), String> {
+    Command::new("explorer")
+        .arg(format!("/select,{}", path.display()))
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to run explorer: {}", e))
+}
+
+// Most desktops implement the FileManager1 D-Bus interface, which selects the
+// file; otherwise the containing folder is opened without a selection.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn reveal(path: &Path) -> Result<(), String> {
+    let uri = tauri::Url::from_file_path(path)
+        .map_err(|_| format!("{} is not an absolute path", path.display()))?;
+    let selected = Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", uri))
+        .arg("string:")
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if selected {
+        return Ok(());
+    }
+    let folder = path.parent().unwrap_or(path);
+    let status = Command::new("xdg-open")
+        .arg(folder)
+        .status()
+        .map_err(|e| format!("Failed to run xdg-open: {}", e))?;
+    if !status.success() {
+        return Err(format!("Could not open {}", folder.display()));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn reveal_in_explorer(app: AppHandle, path: String) -> Result<(), AppError> {
+    let path = validate::user_path(&app, "Path", &path)?;
+    if !path.exists() {
+        return Err(AppError::InvalidInput(format!(
+            "{} does not exist",
+            path.display()
+        )));
+    }
+    Ok(reveal(&path)?)
+}
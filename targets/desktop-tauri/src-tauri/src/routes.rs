@@ -0,0 +1,191 @@
+// What the running core can do, read from the OpenAPI spec FastAPI serves,
+// so the frontend can hide features an older core doesn't have instead of
+// hardcoding endpoints per version.
+
+use crate::config::ConfigState;
+use crate::core::{self, CoreState, CoreStatus};
+use crate::error;
+use serde::Serialize;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const SPEC_PATH: &str = "/openapi.json";
+// Routes only change when the core does, which also resets the cache.
+const ROUTES_TTL: Duration = Duration::from_secs(10 * 60);
+const SPEC_TIMEOUT: Duration = Duration::from_secs(10);
+const METHODS: [&str; 7] = ["get", "head", "post", "put", "patch", "delete", "options"];
+
+#[derive(Debug)]
+pub enum RoutesError {
+    // The core isn't running, or didn't answer.
+    CoreUnavailable(String),
+    // The core answered but serves no spec, e.g. docs disabled in a build.
+    SpecUnavailable(String),
+    InvalidSpec(String),
+}
+
+impl fmt::Display for RoutesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoutesError::CoreUnavailable(msg)
+            | RoutesError::SpecUnavailable(msg)
+            | RoutesError::InvalidSpec(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl RoutesError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RoutesError::CoreUnavailable(_) => "CoreUnavailable",
+            RoutesError::SpecUnavailable(_) => "SpecUnavailable",
+            RoutesError::InvalidSpec(_) => "InvalidSpec",
+        }
+    }
+}
+
+impl Serialize for RoutesError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        error::serialize(serializer, self.code(), &self.to_string(), None)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Route {
+    // As templated in the spec, e.g. "/entries/{entry_id}".
+    pub path: String,
+    // Uppercase and sorted.
+    pub methods: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CoreRoutes {
+    pub core_version: Option<String>,
+    pub routes: Vec<Route>,
+}
+
+struct Cached {
+    routes: CoreRoutes,
+    fetched_at: Instant,
+}
+
+#[derive(Default)]
+pub struct RoutesCache(Mutex<Option<Cached>>);
+
+impl RoutesCache {
+    fn get(&self, core_version: &Option<String>) -> Option<CoreRoutes> {
+        let cached = self.0.lock().unwrap_or_else(|p| p.into_inner());
+        cached
+            .as_ref()
+            .filter(|c| c.fetched_at.elapsed() < ROUTES_TTL && &c.routes.core_version == core_version)
+            .map(|c| c.routes.clone())
+    }
+
+    fn insert(&self, routes: CoreRoutes) {
+        *self.0.lock().unwrap_or_else(|p| p.into_inner()) = Some(Cached {
+            routes,
+            fetched_at: Instant::now(),
+        });
+    }
+}
+
+fn simplify(spec: &serde_json::Value) -> Result<Vec<Route>, RoutesError> {
+    let paths = spec
+        .get("paths")
+        .and_then(|paths| paths.as_object())
+        .ok_or_else(|| RoutesError::InvalidSpec("The core's OpenAPI spec lists no paths".to_string()))?;
+    let mut routes: Vec<Route> = paths
+        .iter()
+        .filter_map(|(path, operations)| {
+            let operations = operations.as_object()?;
+            let mut methods: Vec<String> = METHODS
+                .iter()
+                .filter(|method| operations.contains_key(**method))
+                .map(|method| method.to_uppercase())
+                .collect();
+            methods.sort();
+            (!methods.is_empty()).then(|| Route {
+                path: path.clone(),
+                methods,
+            })
+        })
+        .collect();
+    routes.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(routes)
+}
+
+fn fetch(app: &AppHandle) -> Result<CoreRoutes, RoutesError> {
+    let state = app.state::<CoreState>();
+    let startup_timeout = app.state::<ConfigState>().get().startup_timeout();
+    // A core outside the supported version range still describes itself,
+    // which is exactly when feature detection matters.
+    let status = state.wait_while_starting(startup_timeout);
+    if matches!(status, CoreStatus::Stopped | CoreStatus::Starting) {
+        return Err(RoutesError::CoreUnavailable("The core isn't running".to_string()));
+    }
+    let response = core::request(app, "GET", SPEC_PATH, None, SPEC_TIMEOUT, None)
+        .map_err(RoutesError::CoreUnavailable)?;
+    if !(200..300).contains(&response.status) {
+        return Err(RoutesError::SpecUnavailable(format!(
+            "The core answered {} with status {}",
+            SPEC_PATH, response.status
+        )));
+    }
+    Ok(CoreRoutes {
+        core_version: state.version(),
+        routes: simplify(&response.body)?,
+    })
+}
+
+// Cached per core version; `refresh` skips the cache.
+#[tauri::command]
+pub async fn core_routes(app: AppHandle, refresh: Option<bool>) -> Result<CoreRoutes, RoutesError> {
+    let version = app.state::<CoreState>().version();
+    if !refresh.unwrap_or(false) {
+        if let Some(routes) = app.state::<RoutesCache>().get(&version) {
+            return Ok(routes);
+        }
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let routes = fetch(&app)?;
+        app.state::<RoutesCache>().insert(routes.clone());
+        Ok(routes)
+    })
+    .await
+    .map_err(|e| RoutesError::CoreUnavailable(format!("Reading the core's routes failed: {}", e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simplify_keeps_http_methods_and_sorts() {
+        let spec = serde_json::json!({
+            "paths": {
+                "/health": { "get": {} },
+                "/entries/{entry_id}": { "delete": {}, "get": {}, "parameters": [] },
+                "/empty": { "summary": "no operations" },
+            }
+        });
+        assert_eq!(
+            simplify(&spec).unwrap(),
+            vec![
+                Route {
+                    path: "/entries/{entry_id}".to_string(),
+                    methods: vec!["DELETE".to_string(), "GET".to_string()],
+                },
+                Route {
+                    path: "/health".to_string(),
+                    methods: vec!["GET".to_string()],
+                },
+            ]
+        );
+        assert!(matches!(
+            simplify(&serde_json::json!({ "openapi": "3.1.0" })),
+            Err(RoutesError::InvalidSpec(_))
+        ));
+    }
+}
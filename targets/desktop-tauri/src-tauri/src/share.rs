@@ -0,0 +1,390 @@
+// Progress summaries for a sponsor. Unlike `export_entries` this is lossy on
+// purpose: the summary is counts, streaks and an average mood, and no entry's
+// text goes in unless the user picked that entry as a highlight. `REDACTION`
+// spells the rules out, and a dry run returns the summary without writing it,
+// so the user sees exactly what would be shared.
+//
+// The file is Markdown, or with a password the summary as JSON in the backup
+// container, which `open_share_summary` reads back.
+
+use crate::audit::{AuditAction, AuditState};
+use crate::backup::{self, BackupError};
+use crate::config::ConfigState;
+use crate::disk;
+use crate::guest::GuestState;
+use crate::i18n;
+use crate::storage::{Entry, StorageState};
+use crate::validate;
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use tauri::{AppHandle, Manager};
+
+const DAYS_RANGE: std::ops::RangeInclusive<u32> = 1..=365;
+const DEFAULT_DAYS: u32 = 30;
+const MAX_HIGHLIGHTS: usize = 20;
+const MAX_SHARE_LEN: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RedactionRule {
+    pub data: &'static str,
+    pub shared: &'static str,
+}
+
+pub const REDACTION: &[RedactionRule] = &[
+    RedactionRule {
+        data: "entry text",
+        shared: "never, except for entries chosen as highlights",
+    },
+    RedactionRule {
+        data: "worksheet answers",
+        shared: "never, except for worksheets chosen as highlights",
+    },
+    RedactionRule {
+        data: "entries",
+        shared: "as a count per kind over the period",
+    },
+    RedactionRule {
+        data: "days with entries",
+        shared: "as a count and as streaks; no dates",
+    },
+    RedactionRule {
+        data: "mood scores",
+        shared: "as one average over the period, unless turned off",
+    },
+    RedactionRule {
+        data: "highlights",
+        shared: "with their date and kind, in full",
+    },
+];
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShareOptions {
+    // How far back the summary looks, ending today.
+    pub days: u32,
+    pub include_mood: bool,
+    // Entries whose text is shared in full.
+    pub highlight_ids: Vec<i64>,
+    // Encrypts the file; the sponsor needs it and Smartbot to read it.
+    pub password: Option<String>,
+    pub dry_run: bool,
+}
+
+impl Default for ShareOptions {
+    fn default() -> Self {
+        Self {
+            days: DEFAULT_DAYS,
+            include_mood: true,
+            highlight_ids: Vec::new(),
+            password: None,
+            dry_run: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Highlight {
+    pub date: String,
+    pub kind: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareSummary {
+    pub generated_on: String,
+    pub period_days: u32,
+    pub current_streak_days: u32,
+    pub longest_streak_days: u32,
+    pub active_days: u32,
+    pub entry_counts: BTreeMap<String, u32>,
+    pub average_mood: Option<f64>,
+    pub highlights: Vec<Highlight>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShareReport {
+    // `None` for a dry run.
+    pub path: Option<String>,
+    pub encrypted: bool,
+    pub summary: ShareSummary,
+    pub redaction: &'static [RedactionRule],
+}
+
+fn local_date(timestamp: &str) -> Option<NaiveDate> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|at| at.with_timezone(&Local).date_naive())
+}
+
+// A streak still counts as current when today has nothing yet, so it doesn't
+// read as broken until the day is over.
+fn streaks(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> (u32, u32) {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in days {
+        run = match previous {
+            Some(previous) if day - previous == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(day);
+    }
+    let mut current = 0;
+    let mut day = if days.contains(&today) {
+        today
+    } else {
+        today - Duration::days(1)
+    };
+    while days.contains(&day) {
+        current += 1;
+        day -= Duration::days(1);
+    }
+    (current, longest)
+}
+
+fn summarize(
+    entries: &[Entry],
+    options: &ShareOptions,
+    today: NaiveDate,
+) -> Result<ShareSummary, String> {
+    let first = today - Duration::days(i64::from(options.days) - 1);
+    let in_period: Vec<(NaiveDate, &Entry)> = entries
+        .iter()
+        .filter_map(|entry| local_date(&entry.created_at).map(|date| (date, entry)))
+        .filter(|(date, _)| (first..=today).contains(date))
+        .collect();
+
+    let days: BTreeSet<NaiveDate> = in_period.iter().map(|(date, _)| *date).collect();
+    let (current_streak_days, longest_streak_days) = streaks(&days, today);
+    let mut entry_counts = BTreeMap::new();
+    for (_, entry) in &in_period {
+        *entry_counts.entry(entry.kind.clone()).or_insert(0) += 1;
+    }
+    let moods: Vec<f64> = in_period
+        .iter()
+        .filter_map(|(_, entry)| entry.mood)
+        .collect();
+    let average_mood = (options.include_mood && !moods.is_empty())
+        .then(|| (moods.iter().sum::<f64>() / moods.len() as f64 * 10.0).round() / 10.0);
+
+    let mut highlights = Vec::new();
+    for id in &options.highlight_ids {
+        let entry = entries
+            .iter()
+            .find(|entry| entry.id == *id)
+            .ok_or_else(|| format!("No entry with id {}", id))?;
+        highlights.push(Highlight {
+            date: local_date(&entry.created_at)
+                .map(|date| date.to_string())
+                .unwrap_or_default(),
+            kind: entry.kind.clone(),
+            text: entry.content.clone(),
+        });
+    }
+
+    Ok(ShareSummary {
+        generated_on: today.to_string(),
+        period_days: options.days,
+        current_streak_days,
+        longest_streak_days,
+        active_days: days.len() as u32,
+        entry_counts,
+        average_mood,
+        highlights,
+    })
+}
+
+fn render_markdown(app: &AppHandle, summary: &ShareSummary) -> String {
+    let t = |key: &str| i18n::t(app, &format!("share_summary.{}", key));
+    let mut out = format!("# {}\n\n", t("title"));
+    out.push_str(
+        &t("period")
+            .replace("{days}", &summary.period_days.to_string())
+            .replace("{date}", &summary.generated_on),
+    );
+    out.push_str("\n\n");
+    let stats = [
+        (t("current_streak"), summary.current_streak_days.to_string()),
+        (t("longest_streak"), summary.longest_streak_days.to_string()),
+        (t("active_days"), summary.active_days.to_string()),
+    ];
+    for (label, value) in stats {
+        out.push_str(&format!("- {}: {}\n", label, value));
+    }
+    if let Some(mood) = summary.average_mood {
+        out.push_str(&format!("- {}: {}\n", t("average_mood"), mood));
+    }
+    if !summary.entry_counts.is_empty() {
+        out.push_str(&format!("\n## {}\n\n", t("entries")));
+        for (kind, count) in &summary.entry_counts {
+            out.push_str(&format!("- {}: {}\n", kind, count));
+        }
+    }
+    if !summary.highlights.is_empty() {
+        out.push_str(&format!("\n## {}\n", t("highlights")));
+        for highlight in &summary.highlights {
+            out.push_str(&format!(
+                "\n### {} ({})\n\n{}\n",
+                highlight.date, highlight.kind, highlight.text
+            ));
+        }
+    }
+    out
+}
+
+#[tauri::command]
+pub fn export_share_summary(
+    app: AppHandle,
+    state: tauri::State<StorageState>,
+    guest: tauri::State<GuestState>,
+    dest_path: String,
+    options: Option<ShareOptions>,
+) -> Result<ShareReport, BackupError> {
+    guest.refuse("Sharing a summary")?;
+    let options = options.unwrap_or_default();
+    validate::number("Days", options.days, DAYS_RANGE)?;
+    if options.highlight_ids.len() > MAX_HIGHLIGHTS {
+        return Err(BackupError::InvalidInput(format!(
+            "At most {} highlights can be shared",
+            MAX_HIGHLIGHTS
+        )));
+    }
+    if let Some(password) = &options.password {
+        validate::text("Password", password, backup::MAX_PASSWORD_LEN)?;
+        backup::check_password(password)?;
+    }
+    let dest = validate::user_path(&app, "Destination", &dest_path)?;
+
+    let entries = state.with(|storage| storage.all_entries())?;
+    let summary = summarize(&entries, &options, Local::now().date_naive())
+        .map_err(BackupError::InvalidInput)?;
+    let encrypted = options.password.is_some();
+    if options.dry_run {
+        return Ok(ShareReport {
+            path: None,
+            encrypted,
+            summary,
+            redaction: REDACTION,
+        });
+    }
+
+    let bytes = match &options.password {
+        Some(password) => {
+            let json = serde_json::to_vec(&summary)
+                .map_err(|e| format!("Failed to serialize summary: {}", e))?;
+            backup::seal(&json, password)?
+        }
+        None => render_markdown(&app, &summary).into_bytes(),
+    };
+    let reserve = disk::reserve_bytes(&app.state::<ConfigState>());
+    disk::ensure_space(&dest, bytes.len() as u64, reserve)?;
+    backup::write_atomically(&dest, &bytes)?;
+    app.state::<AuditState>().record(
+        AuditAction::Export,
+        serde_json::json!({
+            "kind": "share_summary",
+            "path": dest.display().to_string(),
+            "highlights": summary.highlights.len(),
+            "encrypted": encrypted,
+        }),
+    );
+    Ok(ShareReport {
+        path: Some(dest.display().to_string()),
+        encrypted,
+        summary,
+        redaction: REDACTION,
+    })
+}
+
+// For the sponsor's side of a password-protected summary.
+#[tauri::command]
+pub fn open_share_summary(
+    app: AppHandle,
+    src_path: String,
+    password: String,
+) -> Result<ShareSummary, BackupError> {
+    validate::text("Password", &password, backup::MAX_PASSWORD_LEN)?;
+    let src = validate::user_path(&app, "Summary", &src_path)?;
+    let read_failed = |e: std::io::Error| format!("Failed to read {}: {}", src.display(), e);
+    if std::fs::metadata(&src).map_err(read_failed)?.len() > MAX_SHARE_LEN {
+        return Err(BackupError::InvalidBackup(format!(
+            "{} is too large to be a shared summary",
+            src.display()
+        )));
+    }
+    let bytes = std::fs::read(&src).map_err(read_failed)?;
+    let json = backup::unseal(&bytes, &password)?;
+    serde_json::from_slice(&json).map_err(|e| {
+        BackupError::InvalidBackup(format!("{} isn't a shared summary: {}", src.display(), e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: i64, kind: &str, content: &str, mood: Option<f64>, created_at: &str) -> Entry {
+        Entry {
+            id,
+            kind: kind.to_string(),
+            content: content.to_string(),
+            mood,
+            created_at: created_at.to_string(),
+            updated_at: created_at.to_string(),
+        }
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn streaks_run_up_to_yesterday_or_today() {
+        let days: BTreeSet<_> = [
+            "2026-03-01",
+            "2026-03-02",
+            "2026-03-03",
+            "2026-03-06",
+            "2026-03-07",
+        ]
+        .into_iter()
+        .map(date)
+        .collect();
+        assert_eq!(streaks(&days, date("2026-03-07")), (2, 3));
+        assert_eq!(streaks(&days, date("2026-03-08")), (2, 3));
+        assert_eq!(streaks(&days, date("2026-03-09")), (0, 3));
+    }
+
+    #[test]
+    fn text_is_only_shared_for_highlights() {
+        let now = Local::now();
+        let at = |days_ago: i64| (now - Duration::days(days_ago)).to_rfc3339();
+        let entries = [
+            entry(1, "journal", "private thoughts", Some(4.0), &at(0)),
+            entry(2, "urge", "resisted at the party", Some(6.0), &at(1)),
+            entry(3, "journal", "long ago", None, &at(90)),
+        ];
+        let options = ShareOptions::default();
+        let summary = summarize(&entries, &options, now.date_naive()).unwrap();
+        assert_eq!(summary.entry_counts.values().sum::<u32>(), 2);
+        assert_eq!(summary.average_mood, Some(5.0));
+        let shared = serde_json::to_string(&summary).unwrap();
+        assert!(!shared.contains("private thoughts") && !shared.contains("resisted"));
+
+        let options = ShareOptions {
+            highlight_ids: vec![2],
+            include_mood: false,
+            ..ShareOptions::default()
+        };
+        let summary = summarize(&entries, &options, now.date_naive()).unwrap();
+        assert_eq!(summary.highlights[0].text, "resisted at the party");
+        assert_eq!(summary.average_mood, None);
+        let missing = ShareOptions {
+            highlight_ids: vec![99],
+            ..ShareOptions::default()
+        };
+        assert!(summarize(&entries, &missing, now.date_naive()).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+// Coalesces identical concurrent core reads: the first caller makes the
+// upstream request and everyone who asked for the same key meanwhile gets
+// its result, error included.
+
+use crate::http::Response;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+
+type Outcome = Result<Response, String>;
+
+#[derive(Default)]
+struct Flight {
+    outcome: Mutex<Option<Outcome>>,
+    done: Condvar,
+}
+
+#[derive(Default)]
+pub struct InFlight(Mutex<HashMap<String, Arc<Flight>>>);
+
+impl InFlight {
+    pub fn run(&self, key: &str, request: impl FnOnce() -> Outcome) -> Outcome {
+        let (flight, leader) = {
+            let mut flights = self.0.lock().unwrap_or_else(|p| p.into_inner());
+            match flights.get(key) {
+                Some(flight) => (flight.clone(), false),
+                None => {
+                    let flight = Arc::new(Flight::default());
+                    flights.insert(key.to_string(), flight.clone());
+                    (flight, true)
+                }
+            }
+        };
+
+        if !leader {
+            let mut outcome = flight.outcome.lock().unwrap_or_else(|p| p.into_inner());
+            while outcome.is_none() {
+                outcome = flight.done.wait(outcome).unwrap_or_else(|p| p.into_inner());
+            }
+            return outcome.clone().unwrap_or_else(|| Err("Core request was abandoned".to_string()));
+        }
+
+        // A panicking leader must still release its followers.
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(request))
+            .unwrap_or_else(|_| Err("Core request panicked".to_string()));
+        // Unregister before publishing so later callers start a fresh request
+        // instead of reading this one's result.
+        self.0.lock().unwrap_or_else(|p| p.into_inner()).remove(key);
+        *flight.outcome.lock().unwrap_or_else(|p| p.into_inner()) = Some(outcome.clone());
+        flight.done.notify_all();
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+    use std::time::Duration;
+
+    // Runs a caller per key at once. Each request takes long enough for the
+    // others to arrive while it is still in flight; returns the outcomes in
+    // key order and how many requests actually ran.
+    fn run_together(
+        keys: &[&str],
+        outcome: impl Fn(&str) -> Outcome + Sync,
+    ) -> (Vec<Outcome>, usize) {
+        let in_flight = InFlight::default();
+        let executions = AtomicUsize::new(0);
+        let start = Barrier::new(keys.len());
+        let outcomes = std::thread::scope(|scope| {
+            let callers: Vec<_> = keys
+                .iter()
+                .map(|key| {
+                    let (in_flight, executions, start, outcome) =
+                        (&in_flight, &executions, &start, &outcome);
+                    scope.spawn(move || {
+                        start.wait();
+                        in_flight.run(key, || {
+                            executions.fetch_add(1, Ordering::SeqCst);
+                            std::thread::sleep(Duration::from_millis(200));
+                            outcome(key)
+                        })
+                    })
+                })
+                .collect();
+            callers.into_iter().map(|caller| caller.join().unwrap()).collect()
+        });
+        (outcomes, executions.into_inner())
+    }
+
+    fn ok(body: &str) -> Outcome {
+        Ok(Response {
+            status: 200,
+            body: body.to_string(),
+        })
+    }
+
+    #[test]
+    fn callers_with_the_same_key_share_one_request() {
+        let (outcomes, executions) = run_together(&["/stats"; 4], ok);
+        assert_eq!(executions, 1);
+        for outcome in outcomes {
+            assert_eq!(outcome.unwrap().body, "/stats");
+        }
+    }
+
+    #[test]
+    fn an_error_reaches_every_follower() {
+        let (outcomes, executions) =
+            run_together(&["/stats"; 3], |_| Err("Core is not running".to_string()));
+        assert_eq!(executions, 1);
+        for outcome in outcomes {
+            assert_eq!(outcome.unwrap_err(), "Core is not running");
+        }
+    }
+
+    #[test]
+    fn different_keys_are_not_merged() {
+        let (outcomes, executions) = run_together(&["/stats", "/mood"], ok);
+        assert_eq!(executions, 2);
+        let bodies: Vec<_> = outcomes.into_iter().map(|outcome| outcome.unwrap().body).collect();
+        assert_eq!(bodies, ["/stats", "/mood"]);
+    }
+}
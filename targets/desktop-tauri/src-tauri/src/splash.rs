@@ -0,0 +1,182 @@
+// Small loading window shown while the core starts, so the main window never
+// appears half-working. The page (`splash.html` in the UI's public folder)
+// has no IPC: the shell drives it with `eval`, and its buttons are links
+// caught by the navigation filter.
+//
+// The main window is revealed once the core is ready (or the user chose to
+// continue offline) and its frontend has called `frontend_ready`, so it
+// doesn't appear before it has rendered.
+
+use crate::config::ConfigState;
+use crate::core::{self, CoreState};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{App, AppHandle, Manager, Url, WindowBuilder, WindowUrl};
+
+pub const SPLASH_LABEL: &str = "splash";
+pub const CORE_READY_EVENT: &str = "core-ready";
+
+// Links on the splash page point here to trigger an action.
+const ACTION_HOST: &str = "splash.smartbot";
+// After this the splash offers to continue offline while the core keeps
+// starting.
+const SLOW_STARTUP: Duration = Duration::from_secs(15);
+// A frontend that never calls `frontend_ready` is shown anyway after this.
+const RENDER_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CoreReady {
+    // The user gave up on the core and continued with local features only.
+    pub offline: bool,
+}
+
+#[derive(Default)]
+struct Reveal {
+    // The main window is being held back behind the splash.
+    waiting: bool,
+    core: Option<CoreReady>,
+    rendered: bool,
+}
+
+#[derive(Default)]
+pub struct RevealState(Mutex<Reveal>);
+
+impl RevealState {
+    fn get(&self) -> std::sync::MutexGuard<'_, Reveal> {
+        self.0.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+fn eval(app: &AppHandle, script: String) {
+    if let Some(splash) = app.get_window(SPLASH_LABEL) {
+        let _ = splash.eval(&script);
+    }
+}
+
+fn js_string(text: &str) -> String {
+    serde_json::Value::String(text.to_string()).to_string()
+}
+
+pub fn create(app: &App, internal_origins: Vec<String>) -> tauri::Result<()> {
+    let handle = app.handle();
+    WindowBuilder::new(app, SPLASH_LABEL, WindowUrl::App("splash.html".into()))
+        .title("Smartbot Desktop")
+        .inner_size(420.0, 280.0)
+        .resizable(false)
+        .decorations(false)
+        .center()
+        .on_navigation(move |url| {
+            if url.host_str() == Some(ACTION_HOST) {
+                on_action(&handle, &url);
+                return false;
+            }
+            crate::navigation::is_internal(&url, &internal_origins)
+        })
+        .build()?;
+    app.state::<RevealState>().get().waiting = true;
+
+    let handle = app.handle();
+    std::thread::spawn(move || {
+        std::thread::sleep(SLOW_STARTUP);
+        if handle.state::<RevealState>().get().core.is_none() {
+            eval(&handle, "window.splash.slow()".to_string());
+        }
+    });
+    Ok(())
+}
+
+fn on_action(app: &AppHandle, url: &Url) {
+    match url.path() {
+        "/retry" => {
+            let app = app.clone();
+            std::thread::spawn(move || {
+                let result = core::ensure(&app);
+                on_startup_finished(&app, &result);
+            });
+        }
+        "/logs" => {
+            let output = app.state::<CoreState>().output();
+            eval(app, format!("window.splash.showLog({})", js_string(&output)));
+        }
+        // The app still works for local journaling without the core.
+        "/continue" => finish(app, CoreReady { offline: true }),
+        _ => {}
+    }
+}
+
+// A failure is shown by `on_startup_finished`, with the error and the retry
+// buttons.
+pub fn on_progress(app: &AppHandle, progress: &core::StartupProgress) {
+    if progress.stage == "failed" {
+        return;
+    }
+    eval(
+        app,
+        format!(
+            "window.splash.progress({}, {})",
+            js_string(&progress.message),
+            progress.percent
+        ),
+    );
+}
+
+fn finish(app: &AppHandle, ready: CoreReady) {
+    let rendered = {
+        let state = app.state::<RevealState>();
+        let mut reveal = state.get();
+        reveal.core = Some(ready);
+        reveal.rendered
+    };
+    let _ = app.emit_all(CORE_READY_EVENT, ready);
+    if rendered {
+        reveal(app);
+    } else {
+        let app = app.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(RENDER_TIMEOUT);
+            reveal(&app);
+        });
+    }
+}
+
+// Close the splash and show the main window, once, unless the biometric gate
+// is still holding it back.
+fn reveal(app: &AppHandle) {
+    if !std::mem::take(&mut app.state::<RevealState>().get().waiting) {
+        return;
+    }
+    if let Some(splash) = app.get_window(SPLASH_LABEL) {
+        let _ = splash.close();
+    }
+    if !app.state::<ConfigState>().get().biometric_unlock_enabled {
+        if let Some(window) = app.get_window("main") {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+    }
+}
+
+pub fn on_startup_finished(app: &AppHandle, result: &Result<String, core::CoreError>) {
+    match result {
+        Ok(_) => finish(app, CoreReady { offline: false }),
+        Err(e) => eval(app, format!("window.splash.failed({})", js_string(&e.to_string()))),
+    }
+}
+
+// Called by the main window's frontend once it has rendered. Returns how the
+// core settled, or `None` while it is still starting, for a frontend that
+// loaded after `core-ready` went out.
+#[tauri::command]
+pub fn frontend_ready(app: AppHandle) -> Option<CoreReady> {
+    let core = {
+        let state = app.state::<RevealState>();
+        let mut reveal = state.get();
+        reveal.rendered = true;
+        reveal.core
+    };
+    if core.is_some() {
+        reveal(&app);
+    }
+    core
+}
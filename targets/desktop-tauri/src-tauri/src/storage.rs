@@ -0,0 +1,1811 @@
+// Local SQLite data store for journal entries, check-ins and urge logs.
+//
+// Every write refreshes a SHA-256 checksum of the entry data stored in the
+// `meta` table, so `verify_storage` can detect silent corruption that
+// SQLite's own integrity check would not notice (e.g. a torn page that still
+// parses).
+//
+// Editing an entry keeps the text it replaced in `entry_history`, up to
+// `MAX_HISTORY_PER_ENTRY` versions per entry, so a revision never loses what
+// was written before. Versions a merge brought in are marked `merged` and
+// never pruned, since they are the only copy of the merged entries' text.
+//
+// Messages to the user's future self wait in `future_messages` until their
+// delivery time; `delivered_at` is set once they have been shown.
+//
+// Programs a counselor shared (see `programs`) live in their own table,
+// apart from entries, so nothing that edits or deletes entries reaches them.
+//
+// Stores are plaintext until `encrypt_in_place` converts them with SQLCipher.
+// An encrypted store can't be opened without its passphrase, so until
+// `open_encrypted` is called the app runs with no store, as it does when
+// opening fails.
+
+use crate::config::ConfigState;
+use crate::disk::{self, DiskError};
+use crate::error::{self, AppError};
+use crate::guest::GuestState;
+use crate::profiles::ProfileState;
+use crate::validate;
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+pub const DB_FILE_NAME: &str = "smartbot.db";
+
+// Every plaintext SQLite file starts with this. SQLCipher encrypts the first
+// page along with the rest, so an encrypted store never does.
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+// How long SQLite itself waits on another connection's lock before giving up
+// with SQLITE_BUSY.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+// Busy errors SQLite can't wait out (e.g. a read transaction that needs to
+// become a write one) are retried this many times with a growing pause.
+const BUSY_RETRIES: u32 = 3;
+const BUSY_RETRY_PAUSE: Duration = Duration::from_millis(200);
+
+// Schema migrations, applied in order. `PRAGMA user_version` records how many
+// have run, so only append to this list.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE entries (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        kind TEXT NOT NULL,
+        content TEXT NOT NULL,
+        mood REAL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE INDEX idx_entries_kind_created ON entries(kind, created_at);
+    CREATE TABLE meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );",
+    "CREATE TABLE entry_history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        entry_id INTEGER NOT NULL REFERENCES entries(id) ON DELETE CASCADE,
+        content TEXT NOT NULL,
+        written_at TEXT NOT NULL,
+        replaced_at TEXT NOT NULL
+    );
+    CREATE INDEX idx_entry_history_entry ON entry_history(entry_id, id);",
+    "CREATE TABLE future_messages (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        content TEXT NOT NULL,
+        deliver_at TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        delivered_at TEXT
+    );
+    CREATE INDEX idx_future_messages_due ON future_messages(delivered_at, deliver_at);",
+    "CREATE TABLE programs (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        provider TEXT NOT NULL,
+        version INTEGER NOT NULL,
+        content TEXT NOT NULL,
+        signed_by TEXT NOT NULL,
+        installed_at TEXT NOT NULL
+    );",
+    "ALTER TABLE entry_history ADD COLUMN merged INTEGER NOT NULL DEFAULT 0;",
+];
+
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+// Older versions beyond this are dropped, oldest first; merged ones don't
+// count.
+pub const MAX_HISTORY_PER_ENTRY: i64 = 20;
+
+pub struct Storage {
+    conn: Connection,
+    path: PathBuf,
+    // The passphrase of an encrypted store, for reopening it after a repair
+    // or unlock.
+    key: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: i64,
+    pub kind: String,
+    pub content: String,
+    pub mood: Option<f64>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+// A version of an entry that a later edit replaced.
+#[derive(Debug, Serialize)]
+pub struct EntryVersion {
+    pub content: String,
+    pub written_at: String,
+    pub replaced_at: String,
+}
+
+// A row of `entry_history` as backups carry it, with the entry it belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub entry_id: i64,
+    pub content: String,
+    pub written_at: String,
+    pub replaced_at: String,
+    #[serde(default)]
+    pub merged: bool,
+}
+
+// `deliver_at` is UTC with a `Z` suffix, so times compare as text.
+#[derive(Debug, Clone, Serialize)]
+pub struct FutureMessage {
+    pub id: i64,
+    pub content: String,
+    pub deliver_at: String,
+    pub created_at: String,
+    pub delivered_at: Option<String>,
+}
+
+// `content` is the program exactly as it was signed.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgramRecord {
+    pub id: String,
+    pub name: String,
+    pub provider: String,
+    pub version: i64,
+    pub content: String,
+    // The trusted key it verified against.
+    pub signed_by: String,
+    pub installed_at: String,
+}
+
+fn program_record(row: &rusqlite::Row<'_>) -> rusqlite::Result<ProgramRecord> {
+    Ok(ProgramRecord {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        provider: row.get(2)?,
+        version: row.get(3)?,
+        content: row.get(4)?,
+        signed_by: row.get(5)?,
+        installed_at: row.get(6)?,
+    })
+}
+
+fn future_message(row: &rusqlite::Row<'_>) -> rusqlite::Result<FutureMessage> {
+    Ok(FutureMessage {
+        id: row.get(0)?,
+        content: row.get(1)?,
+        deliver_at: row.get(2)?,
+        created_at: row.get(3)?,
+        delivered_at: row.get(4)?,
+    })
+}
+
+// Mood scores grouped by local day or week; `bucket` is the local date the
+// group starts on (YYYY-MM-DD).
+#[derive(Debug)]
+pub struct MoodStats {
+    pub bucket: String,
+    pub average: f64,
+    pub min: f64,
+    pub max: f64,
+    pub count: u32,
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    // Another connection holds a lock; retrying later may succeed.
+    Busy(String),
+    // The store is encrypted and needs its passphrase, or the one given was
+    // wrong.
+    Locked(String),
+    // The file isn't a readable database; retrying won't help, repairing or
+    // restoring a backup might.
+    Corrupt(String),
+    InvalidInput(String),
+    Failed(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Busy(msg)
+            | StorageError::Locked(msg)
+            | StorageError::Corrupt(msg)
+            | StorageError::InvalidInput(msg)
+            | StorageError::Failed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl StorageError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            StorageError::Busy(_) => "Busy",
+            StorageError::Locked(_) => "Locked",
+            StorageError::Corrupt(_) => "Corrupt",
+            StorageError::InvalidInput(_) => "InvalidInput",
+            StorageError::Failed(_) => "Failed",
+        }
+    }
+}
+
+impl Serialize for StorageError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        error::serialize(serializer, self.code(), &self.to_string(), None)
+    }
+}
+
+impl From<String> for StorageError {
+    fn from(msg: String) -> Self {
+        StorageError::Failed(msg)
+    }
+}
+
+impl From<&str> for StorageError {
+    fn from(msg: &str) -> Self {
+        StorageError::Failed(msg.to_string())
+    }
+}
+
+impl From<validate::ValidationError> for StorageError {
+    fn from(e: validate::ValidationError) -> Self {
+        StorageError::InvalidInput(e.to_string())
+    }
+}
+
+impl From<DiskError> for StorageError {
+    fn from(e: DiskError) -> Self {
+        match e {
+            DiskError::InvalidInput(msg) => StorageError::InvalidInput(msg),
+            e => StorageError::Failed(e.to_string()),
+        }
+    }
+}
+
+impl From<StorageError> for String {
+    fn from(e: StorageError) -> Self {
+        e.to_string()
+    }
+}
+
+// What `replace_all` does to the store, with entries matched by id. Computed
+// the same way for a dry run as for the real thing.
+#[derive(Debug, Serialize)]
+pub struct ReplacePlan {
+    pub entries_added: usize,
+    pub entries_overwritten: usize,
+    pub entries_removed: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnlockReport {
+    pub path: String,
+    pub removed: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyReport {
+    pub path: String,
+    pub checked_at: String,
+    pub ok: bool,
+    pub integrity_check: Vec<String>,
+    pub schema_version: Option<i64>,
+    pub entry_count: Option<i64>,
+    pub stored_checksum: Option<String>,
+    pub computed_checksum: Option<String>,
+    pub checksum_ok: bool,
+    pub problems: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TableRepair {
+    pub table: String,
+    pub rows_recovered: i64,
+    pub rows_lost: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RepairReport {
+    pub path: String,
+    pub backup_path: String,
+    pub tables: Vec<TableRepair>,
+    pub integrity_after: Vec<String>,
+    pub replaced: bool,
+    pub actions: Vec<String>,
+}
+
+impl Storage {
+    pub fn open(path: &Path) -> Result<Self, StorageError> {
+        Self::open_with(path, BUSY_TIMEOUT)
+    }
+
+    pub fn open_encrypted(path: &Path, passphrase: &str) -> Result<Self, StorageError> {
+        Self::open_keyed(path, BUSY_TIMEOUT, Some(passphrase))
+    }
+
+    fn open_with(path: &Path, busy_timeout: Duration) -> Result<Self, StorageError> {
+        Self::open_keyed(path, busy_timeout, None)
+    }
+
+    fn open_keyed(path: &Path, busy_timeout: Duration, key: Option<&str>) -> Result<Self, StorageError> {
+        if key.is_none() && is_encrypted(path)? {
+            return Err(StorageError::Locked(
+                "The data store is encrypted; enter your passphrase to unlock it".to_string(),
+            ));
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create data directory {}: {}", parent.display(), e))?;
+        }
+        let conn = Connection::open(path).map_err(db_err)?;
+        if let Some(key) = key {
+            conn.pragma_update(None, "key", key).map_err(db_err)?;
+        }
+        conn.busy_timeout(busy_timeout).map_err(db_err)?;
+        // With a key, an unreadable first page almost always means the wrong
+        // one rather than damage.
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;")
+            .map_err(|e| match db_err(e) {
+                StorageError::Corrupt(_) if key.is_some() => {
+                    StorageError::Locked("Incorrect passphrase".to_string())
+                }
+                other => other,
+            })?;
+        migrate(&conn)?;
+        Ok(Self {
+            conn,
+            path: path.to_path_buf(),
+            key: key.map(str::to_string),
+        })
+    }
+
+    fn reopen(path: &Path, key: Option<&str>) -> Result<Self, StorageError> {
+        Self::open_keyed(path, BUSY_TIMEOUT, key)
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+
+    // For guest sessions: nothing is written to disk, and everything is gone
+    // once it is dropped.
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        let conn = Connection::open_in_memory().map_err(db_err)?;
+        conn.execute_batch("PRAGMA foreign_keys = ON;").map_err(db_err)?;
+        migrate(&conn)?;
+        Ok(Self {
+            conn,
+            path: PathBuf::from(":memory:"),
+            key: None,
+        })
+    }
+
+    pub fn add_entry(
+        &mut self,
+        kind: &str,
+        content: &str,
+        mood: Option<f64>,
+    ) -> Result<i64, StorageError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let tx = self.conn.transaction().map_err(db_err)?;
+        tx.execute(
+            "INSERT INTO entries (kind, content, mood, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?4)",
+            params![kind, content, mood, now],
+        )
+        .map_err(db_err)?;
+        let id = tx.last_insert_rowid();
+        store_checksum(&tx)?;
+        tx.commit().map_err(db_err)?;
+        Ok(id)
+    }
+
+    pub fn update_entry(&mut self, id: i64, content: &str) -> Result<Entry, StorageError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let tx = self.conn.transaction().map_err(db_err)?;
+        let previous: Option<(String, String)> = tx
+            .query_row(
+                "SELECT content, updated_at FROM entries WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(db_err)?;
+        let Some((previous_content, written_at)) = previous else {
+            return Err(StorageError::InvalidInput(format!("No entry with id {}", id)));
+        };
+        if previous_content != content {
+            tx.execute(
+                "INSERT INTO entry_history (entry_id, content, written_at, replaced_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![id, previous_content, written_at, now],
+            )
+            .map_err(db_err)?;
+            prune_history(&tx, id)?;
+            tx.execute(
+                "UPDATE entries SET content = ?2, updated_at = ?3 WHERE id = ?1",
+                params![id, content, now],
+            )
+            .map_err(db_err)?;
+            store_checksum(&tx)?;
+        }
+        let entry = tx
+            .query_row(
+                "SELECT id, kind, content, mood, created_at, updated_at FROM entries WHERE id = ?1",
+                params![id],
+                entry_from_row,
+            )
+            .map_err(db_err)?;
+        tx.commit().map_err(db_err)?;
+        Ok(entry)
+    }
+
+    // Folds `others` into `keep`: their text joins `keep`'s history, as do
+    // their own histories, and then they are deleted.
+    pub fn merge_entries(&mut self, keep: i64, others: &[i64]) -> Result<Entry, StorageError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let tx = self.conn.transaction().map_err(db_err)?;
+        for &id in std::iter::once(&keep).chain(others) {
+            let exists: bool = tx
+                .query_row("SELECT EXISTS(SELECT 1 FROM entries WHERE id = ?1)", params![id], |row| {
+                    row.get(0)
+                })
+                .map_err(db_err)?;
+            if !exists {
+                return Err(StorageError::InvalidInput(format!("No entry with id {}", id)));
+            }
+        }
+        for &id in others {
+            tx.execute(
+                "UPDATE entry_history SET entry_id = ?1, merged = 1 WHERE entry_id = ?2",
+                params![keep, id],
+            )
+            .map_err(db_err)?;
+            tx.execute(
+                "INSERT INTO entry_history (entry_id, content, written_at, replaced_at, merged)
+                 SELECT ?1, content, updated_at, ?3, 1 FROM entries WHERE id = ?2",
+                params![keep, id, now],
+            )
+            .map_err(db_err)?;
+            tx.execute("DELETE FROM entries WHERE id = ?1", params![id])
+                .map_err(db_err)?;
+        }
+        store_checksum(&tx)?;
+        let entry = tx
+            .query_row(
+                "SELECT id, kind, content, mood, created_at, updated_at FROM entries WHERE id = ?1",
+                params![keep],
+                entry_from_row,
+            )
+            .map_err(db_err)?;
+        tx.commit().map_err(db_err)?;
+        Ok(entry)
+    }
+
+    // Newest first.
+    pub fn entry_history(&self, id: i64) -> Result<Vec<EntryVersion>, StorageError> {
+        let exists: bool = self
+            .conn
+            .query_row("SELECT EXISTS(SELECT 1 FROM entries WHERE id = ?1)", params![id], |row| {
+                row.get(0)
+            })
+            .map_err(db_err)?;
+        if !exists {
+            return Err(StorageError::InvalidInput(format!("No entry with id {}", id)));
+        }
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT content, written_at, replaced_at FROM entry_history
+                 WHERE entry_id = ?1 ORDER BY id DESC",
+            )
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map(params![id], |row| {
+                Ok(EntryVersion {
+                    content: row.get(0)?,
+                    written_at: row.get(1)?,
+                    replaced_at: row.get(2)?,
+                })
+            })
+            .map_err(db_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_err)
+    }
+
+    pub fn add_future_message(
+        &mut self,
+        content: &str,
+        deliver_at: &str,
+    ) -> Result<FutureMessage, StorageError> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.conn
+            .execute(
+                "INSERT INTO future_messages (content, deliver_at, created_at) VALUES (?1, ?2, ?3)",
+                params![content, deliver_at, now],
+            )
+            .map_err(db_err)?;
+        Ok(FutureMessage {
+            id: self.conn.last_insert_rowid(),
+            content: content.to_string(),
+            deliver_at: deliver_at.to_string(),
+            created_at: now,
+            delivered_at: None,
+        })
+    }
+
+    // Pending messages soonest first, or delivered ones most recent first.
+    pub fn future_messages(&self, delivered: bool) -> Result<Vec<FutureMessage>, StorageError> {
+        let sql = if delivered {
+            "SELECT id, content, deliver_at, created_at, delivered_at FROM future_messages
+             WHERE delivered_at IS NOT NULL ORDER BY delivered_at DESC, id DESC"
+        } else {
+            "SELECT id, content, deliver_at, created_at, delivered_at FROM future_messages
+             WHERE delivered_at IS NULL ORDER BY deliver_at, id"
+        };
+        let mut stmt = self.conn.prepare(sql).map_err(db_err)?;
+        let rows = stmt.query_map([], future_message).map_err(db_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_err)
+    }
+
+    // Only pending messages can be cancelled.
+    pub fn cancel_future_message(&mut self, id: i64) -> Result<(), StorageError> {
+        let removed = self
+            .conn
+            .execute(
+                "DELETE FROM future_messages WHERE id = ?1 AND delivered_at IS NULL",
+                params![id],
+            )
+            .map_err(db_err)?;
+        if removed == 0 {
+            return Err(StorageError::InvalidInput(format!("No pending message with id {}", id)));
+        }
+        Ok(())
+    }
+
+    // Marks every message due by `now` as delivered and returns them.
+    pub fn deliver_future_messages(&mut self, now: &str) -> Result<Vec<FutureMessage>, StorageError> {
+        let tx = self.conn.transaction().map_err(db_err)?;
+        let due = {
+            let mut stmt = tx
+                .prepare(
+                    "UPDATE future_messages SET delivered_at = ?1
+                     WHERE delivered_at IS NULL AND deliver_at <= ?1
+                     RETURNING id, content, deliver_at, created_at, delivered_at",
+                )
+                .map_err(db_err)?;
+            let rows = stmt.query_map(params![now], future_message).map_err(db_err)?;
+            rows.collect::<Result<Vec<_>, _>>().map_err(db_err)?
+        };
+        tx.commit().map_err(db_err)?;
+        Ok(due)
+    }
+
+    // Replaces any program with the same id; `installed_at` is set here.
+    pub fn install_program(&mut self, program: &ProgramRecord) -> Result<ProgramRecord, StorageError> {
+        let installed_at = chrono::Utc::now().to_rfc3339();
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO programs
+                 (id, name, provider, version, content, signed_by, installed_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    program.id,
+                    program.name,
+                    program.provider,
+                    program.version,
+                    program.content,
+                    program.signed_by,
+                    installed_at
+                ],
+            )
+            .map_err(db_err)?;
+        Ok(ProgramRecord {
+            installed_at,
+            ..program.clone()
+        })
+    }
+
+    pub fn programs(&self) -> Result<Vec<ProgramRecord>, StorageError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, name, provider, version, content, signed_by, installed_at
+                 FROM programs ORDER BY name, id",
+            )
+            .map_err(db_err)?;
+        let rows = stmt.query_map([], program_record).map_err(db_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_err)
+    }
+
+    pub fn program(&self, id: &str) -> Result<Option<ProgramRecord>, StorageError> {
+        self.conn
+            .query_row(
+                "SELECT id, name, provider, version, content, signed_by, installed_at
+                 FROM programs WHERE id = ?1",
+                params![id],
+                program_record,
+            )
+            .optional()
+            .map_err(db_err)
+    }
+
+    pub fn remove_program(&mut self, id: &str) -> Result<(), StorageError> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM programs WHERE id = ?1", params![id])
+            .map_err(db_err)?;
+        if removed == 0 {
+            return Err(StorageError::InvalidInput(format!("No program with id {:?}", id)));
+        }
+        Ok(())
+    }
+
+    pub fn list_entries(&self, kind: Option<&str>, limit: u32) -> Result<Vec<Entry>, StorageError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, kind, content, mood, created_at, updated_at FROM entries
+                 WHERE ?1 IS NULL OR kind = ?1
+                 ORDER BY created_at DESC LIMIT ?2",
+            )
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map(params![kind, limit], entry_from_row)
+            .map_err(db_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_err)
+    }
+
+    // Timestamps are stored in UTC; SQLite's 'localtime' modifier converts
+    // them with the system time zone so an evening entry lands on the right
+    // day. Weeks start on Monday ('weekday 0' moves to the next Sunday).
+    pub fn mood_stats(&self, weekly: bool, since: &str) -> Result<Vec<MoodStats>, StorageError> {
+        let bucket = if weekly {
+            "date(created_at, 'localtime', 'weekday 0', '-6 days')"
+        } else {
+            "date(created_at, 'localtime')"
+        };
+        let sql = format!(
+            "SELECT {bucket} AS bucket, AVG(mood), MIN(mood), MAX(mood), COUNT(mood) FROM entries
+             WHERE mood IS NOT NULL AND bucket >= ?1
+             GROUP BY bucket ORDER BY bucket"
+        );
+        let mut stmt = self.conn.prepare(&sql).map_err(db_err)?;
+        let rows = stmt
+            .query_map(params![since], |row| {
+                Ok(MoodStats {
+                    bucket: row.get(0)?,
+                    average: row.get(1)?,
+                    min: row.get(2)?,
+                    max: row.get(3)?,
+                    count: row.get(4)?,
+                })
+            })
+            .map_err(db_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_err)
+    }
+
+    pub fn entry_count(&self) -> Result<i64, StorageError> {
+        self.conn
+            .query_row("SELECT COUNT(*) FROM entries", [], |row| row.get(0))
+            .map_err(db_err)
+    }
+
+    pub fn all_entries(&self) -> Result<Vec<Entry>, StorageError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, kind, content, mood, created_at, updated_at FROM entries ORDER BY id")
+            .map_err(db_err)?;
+        let rows = stmt.query_map([], entry_from_row).map_err(db_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_err)
+    }
+
+    // Oldest first, so inserting them in order keeps each entry's order.
+    pub fn all_history(&self) -> Result<Vec<HistoryRecord>, StorageError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT entry_id, content, written_at, replaced_at, merged FROM entry_history
+                 ORDER BY id",
+            )
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(HistoryRecord {
+                    entry_id: row.get(0)?,
+                    content: row.get(1)?,
+                    written_at: row.get(2)?,
+                    replaced_at: row.get(3)?,
+                    merged: row.get(4)?,
+                })
+            })
+            .map_err(db_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(db_err)
+    }
+
+    pub fn plan_replace(&self, entries: &[Entry]) -> Result<ReplacePlan, StorageError> {
+        let current: HashSet<i64> = {
+            let mut stmt = self.conn.prepare("SELECT id FROM entries").map_err(db_err)?;
+            let ids = stmt.query_map([], |row| row.get(0)).map_err(db_err)?;
+            ids.collect::<Result<_, _>>().map_err(db_err)?
+        };
+        let incoming: HashSet<i64> = entries.iter().map(|entry| entry.id).collect();
+        let overwritten = incoming.intersection(&current).count();
+        Ok(ReplacePlan {
+            entries_added: incoming.len() - overwritten,
+            entries_overwritten: overwritten,
+            entries_removed: current.len() - overwritten,
+        })
+    }
+
+    // Swap the whole store for `entries` and their `history` in one
+    // transaction, keeping their original ids and timestamps. Deleting the
+    // entries takes the current history with them; versions of entries that
+    // aren't in `entries` are dropped.
+    pub fn replace_all(
+        &mut self,
+        entries: &[Entry],
+        history: &[HistoryRecord],
+    ) -> Result<(), StorageError> {
+        let tx = self.conn.transaction().map_err(db_err)?;
+        tx.execute("DELETE FROM entries", []).map_err(db_err)?;
+        for entry in entries {
+            tx.execute(
+                "INSERT INTO entries (id, kind, content, mood, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    entry.id,
+                    entry.kind,
+                    entry.content,
+                    entry.mood,
+                    entry.created_at,
+                    entry.updated_at
+                ],
+            )
+            .map_err(db_err)?;
+        }
+        let ids: HashSet<i64> = entries.iter().map(|entry| entry.id).collect();
+        for version in history.iter().filter(|version| ids.contains(&version.entry_id)) {
+            tx.execute(
+                "INSERT INTO entry_history (entry_id, content, written_at, replaced_at, merged)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    version.entry_id,
+                    version.content,
+                    version.written_at,
+                    version.replaced_at,
+                    version.merged
+                ],
+            )
+            .map_err(db_err)?;
+        }
+        for id in &ids {
+            prune_history(&tx, *id)?;
+        }
+        store_checksum(&tx)?;
+        tx.commit().map_err(db_err)
+    }
+
+    pub fn verify(&self) -> VerifyReport {
+        verify_connection(&self.conn, &self.path)
+    }
+}
+
+fn entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<Entry> {
+    Ok(Entry {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        content: row.get(2)?,
+        mood: row.get(3)?,
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
+
+fn prune_history(conn: &Connection, entry_id: i64) -> Result<(), StorageError> {
+    conn.execute(
+        "DELETE FROM entry_history WHERE entry_id = ?1 AND merged = 0 AND id NOT IN (
+             SELECT id FROM entry_history WHERE entry_id = ?1 AND merged = 0
+             ORDER BY id DESC LIMIT ?2
+         )",
+        params![entry_id, MAX_HISTORY_PER_ENTRY],
+    )
+    .map_err(db_err)?;
+    Ok(())
+}
+
+fn db_err(e: rusqlite::Error) -> StorageError {
+    match e.sqlite_error_code() {
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => StorageError::Busy(format!(
+            "The database is in use by another task or app; try again in a moment ({})",
+            e
+        )),
+        Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase) => {
+            StorageError::Corrupt(format!("The database file is damaged: {}", e))
+        }
+        _ => StorageError::Failed(format!("Database error: {}", e)),
+    }
+}
+
+fn migrate(conn: &Connection) -> Result<(), String> {
+    let version: i64 = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(db_err)?;
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "Database schema version {} is newer than this app supports ({}). Please update Smartbot.",
+            version, SCHEMA_VERSION
+        ));
+    }
+    for (index, sql) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        conn.execute_batch(&format!("BEGIN; {} PRAGMA user_version = {}; COMMIT;", sql, index + 1))
+            .map_err(|e| format!("Failed to apply migration {}: {}", index + 1, e))?;
+    }
+    Ok(())
+}
+
+// Hash every entry column in id order. Any change to the data, including a
+// change SQLite can still read back cleanly, changes the digest.
+fn compute_checksum(conn: &Connection) -> Result<String, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT id, kind, content, mood, created_at, updated_at FROM entries ORDER BY id",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut hasher = Sha256::new();
+    while let Some(row) = rows.next()? {
+        let id: i64 = row.get(0)?;
+        let kind: String = row.get(1)?;
+        let content: String = row.get(2)?;
+        let mood: Option<f64> = row.get(3)?;
+        let created_at: String = row.get(4)?;
+        let updated_at: String = row.get(5)?;
+        hasher.update(id.to_le_bytes());
+        for field in [kind.as_str(), content.as_str(), created_at.as_str(), updated_at.as_str()] {
+            hasher.update((field.len() as u64).to_le_bytes());
+            hasher.update(field.as_bytes());
+        }
+        match mood {
+            Some(m) => hasher.update(m.to_le_bytes()),
+            None => hasher.update([0xff; 8]),
+        }
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn store_checksum(conn: &Connection) -> Result<(), String> {
+    let checksum = compute_checksum(conn).map_err(db_err)?;
+    conn.execute(
+        "INSERT INTO meta (key, value) VALUES ('checksum', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![checksum],
+    )
+    .map_err(db_err)?;
+    Ok(())
+}
+
+fn integrity_check(conn: &Connection) -> Result<Vec<String>, rusqlite::Error> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+    rows.collect()
+}
+
+fn verify_connection(conn: &Connection, path: &Path) -> VerifyReport {
+    let mut problems = Vec::new();
+
+    let integrity = match integrity_check(conn) {
+        Ok(lines) => lines,
+        Err(e) => vec![format!("integrity_check could not run: {}", e)],
+    };
+    let integrity_ok = integrity.len() == 1 && integrity[0] == "ok";
+    if !integrity_ok {
+        problems.extend(integrity.iter().cloned());
+    }
+
+    let schema_version = conn
+        .pragma_query_value(None, "user_version", |row| row.get::<_, i64>(0))
+        .map_err(|e| problems.push(format!("Could not read schema version: {}", e)))
+        .ok();
+    if let Some(version) = schema_version {
+        if version != SCHEMA_VERSION {
+            problems.push(format!(
+                "Schema version is {} but {} was expected",
+                version, SCHEMA_VERSION
+            ));
+        }
+    }
+
+    let entry_count = conn
+        .query_row("SELECT COUNT(*) FROM entries", [], |row| row.get::<_, i64>(0))
+        .map_err(|e| problems.push(format!("Could not count entries: {}", e)))
+        .ok();
+
+    let stored_checksum = conn
+        .query_row("SELECT value FROM meta WHERE key = 'checksum'", [], |row| {
+            row.get::<_, String>(0)
+        })
+        .optional()
+        .map_err(|e| problems.push(format!("Could not read stored checksum: {}", e)))
+        .ok()
+        .flatten();
+    let computed_checksum = compute_checksum(conn)
+        .map_err(|e| problems.push(format!("Could not recompute checksum: {}", e)))
+        .ok();
+
+    // A store that has never been written to has no checksum yet.
+    let checksum_ok = match (&stored_checksum, &computed_checksum) {
+        (Some(stored), Some(computed)) => stored == computed,
+        (None, Some(_)) => entry_count == Some(0),
+        _ => false,
+    };
+    if !checksum_ok && computed_checksum.is_some() {
+        problems.push("Stored checksum does not match the entry data".to_string());
+    }
+
+    VerifyReport {
+        path: path.display().to_string(),
+        checked_at: chrono::Utc::now().to_rfc3339(),
+        ok: problems.is_empty(),
+        integrity_check: integrity,
+        schema_version,
+        entry_count,
+        stored_checksum,
+        computed_checksum,
+        checksum_ok,
+        problems,
+    }
+}
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+// Copy the database plus any WAL/shared-memory files so the backup reflects
+// exactly what was on disk.
+fn backup_files(path: &Path, suffix: &str) -> Result<PathBuf, String> {
+    let backup = sibling_path(path, suffix);
+    for extra in ["", "-wal", "-shm"] {
+        let src = sibling_path(path, extra);
+        if src.exists() {
+            std::fs::copy(&src, sibling_path(&backup, extra))
+                .map_err(|e| format!("Failed to back up {}: {}", src.display(), e))?;
+        }
+    }
+    Ok(backup)
+}
+
+fn remove_files(path: &Path) {
+    for extra in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(sibling_path(path, extra));
+    }
+}
+
+// Renames `fresh` over the live database, which is atomic on one volume, so
+// `path` holds a whole database throughout. The WAL files beside it belong to
+// the old one and go first, before they could be applied to the new one.
+fn swap_in(fresh: &Path, path: &Path) -> Result<(), String> {
+    for extra in ["-wal", "-shm"] {
+        let stale = sibling_path(path, extra);
+        match std::fs::remove_file(&stale) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(format!("Failed to remove {}: {}", stale.display(), e));
+            }
+            _ => {}
+        }
+    }
+    std::fs::rename(fresh, path)
+        .map_err(|e| format!("Failed to move repaired database into place: {}", e))
+}
+
+// Puts the copy `backup_files` took back in place of the database at `path`.
+fn put_back(backup: &Path, path: &Path) -> Result<(), String> {
+    for extra in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(sibling_path(path, extra));
+    }
+    move_files(backup, path)
+}
+
+fn move_files(from: &Path, to: &Path) -> Result<(), String> {
+    for extra in ["", "-wal", "-shm"] {
+        let src = sibling_path(from, extra);
+        if src.exists() {
+            std::fs::rename(&src, sibling_path(to, extra))
+                .map_err(|e| format!("Failed to move {}: {}", src.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+// Overwrite with zeros and flush before removing. Returns whether every file
+// was overwritten; on SSDs and copy-on-write filesystems the old blocks may
+// survive anyway, which only full-disk encryption rules out.
+fn shred_files(path: &Path) -> bool {
+    let mut overwritten = true;
+    for extra in ["", "-wal", "-shm"] {
+        let file = sibling_path(path, extra);
+        let Ok(len) = std::fs::metadata(&file).map(|metadata| metadata.len()) else {
+            continue;
+        };
+        let zeroed = std::fs::OpenOptions::new().write(true).open(&file).and_then(|mut out| {
+            std::io::copy(&mut std::io::repeat(0).take(len), &mut out)?;
+            out.sync_all()
+        });
+        overwritten &= zeroed.is_ok();
+        let _ = std::fs::remove_file(&file);
+    }
+    overwritten
+}
+
+// Missing files and ones too short to hold a header are plaintext as far as
+// opening goes: SQLite creates the former and rejects the latter as damaged.
+pub fn is_encrypted(path: &Path) -> Result<bool, StorageError> {
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e).into()),
+    };
+    let mut header = [0u8; SQLITE_HEADER.len()];
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(&header != SQLITE_HEADER),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e).into()),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EncryptReport {
+    pub path: String,
+    pub entry_count: i64,
+    // Whether the plaintext files were overwritten before being removed.
+    pub plaintext_overwritten: bool,
+}
+
+// SQLCipher's export copies the schema and every row, but not the schema
+// version.
+fn export_encrypted(conn: &Connection, dest: &Path, passphrase: &str) -> Result<(), StorageError> {
+    conn.execute(
+        "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+        params![dest.to_string_lossy().to_string(), passphrase],
+    )
+    .map_err(db_err)?;
+    let exported = conn
+        .query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))
+        .and_then(|()| conn.execute_batch(&format!("PRAGMA encrypted.user_version = {};", SCHEMA_VERSION)));
+    let detached = conn.execute_batch("DETACH DATABASE encrypted");
+    exported.and(detached).map_err(db_err)
+}
+
+// Exports the open store into `fresh` under `passphrase` and checks the copy
+// holds the same data; on failure nothing is left at `fresh`.
+fn export_verified(storage: &Storage, fresh: &Path, passphrase: &str) -> Result<(), StorageError> {
+    remove_files(fresh);
+    let exported = export_encrypted(&storage.conn, fresh, passphrase).and_then(|()| {
+        let copy = Storage::reopen(fresh, Some(passphrase))?;
+        let original = compute_checksum(&storage.conn).map_err(db_err)?;
+        if compute_checksum(&copy.conn).map_err(db_err)? != original {
+            return Err("The encrypted copy doesn't match the original".into());
+        }
+        Ok(())
+    });
+    if exported.is_err() {
+        remove_files(fresh);
+    }
+    exported
+}
+
+// Convert the open plaintext store to an encrypted one: export into a new
+// file, check it holds the same entries, swap it in and open it, then shred
+// the plaintext. Until the swap nothing on disk changes, and a failed swap
+// puts the plaintext back.
+pub fn encrypt_in_place(state: &StorageState, passphrase: &str) -> Result<EncryptReport, StorageError> {
+    let mut guard = state.0.lock().map_err(|_| "Storage lock poisoned")?;
+    let storage = guard.as_ref().ok_or("Storage is not open")?;
+    if storage.is_encrypted() {
+        return Err(StorageError::InvalidInput("The data store is already encrypted".to_string()));
+    }
+    let path = storage.path.clone();
+    let entry_count = storage.entry_count()?;
+    let fresh = sibling_path(&path, ".encrypting");
+    export_verified(storage, &fresh, passphrase)?;
+
+    if let Some(storage) = guard.take() {
+        let _ = storage.conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);");
+    }
+    let plaintext = sibling_path(&path, ".plaintext");
+    remove_files(&plaintext);
+    let swapped = move_files(&path, &plaintext)
+        .map_err(StorageError::from)
+        .and_then(|()| move_files(&fresh, &path).map_err(StorageError::from))
+        .and_then(|()| Storage::reopen(&path, Some(passphrase)));
+    match swapped {
+        Ok(encrypted) => *guard = Some(encrypted),
+        Err(e) => {
+            remove_files(&path);
+            let _ = move_files(&plaintext, &path);
+            remove_files(&fresh);
+            *guard = Storage::open(&path).ok();
+            return Err(e);
+        }
+    }
+    Ok(EncryptReport {
+        path: path.display().to_string(),
+        entry_count,
+        plaintext_overwritten: shred_files(&plaintext),
+    })
+}
+
+// Re-encrypt the open store under a new passphrase the same way
+// `encrypt_in_place` encrypts it: export a copy under the new key (SQLCipher
+// gives it a fresh salt), check it, swap it in and open it. The old file is
+// kept aside until the new one opens, and put back if it doesn't.
+pub fn rotate_key(state: &StorageState, old: &str, new: &str) -> Result<i64, StorageError> {
+    let mut guard = state.0.lock().map_err(|_| "Storage lock poisoned")?;
+    let storage = guard.as_ref().ok_or("Storage is not open")?;
+    if !storage.is_encrypted() {
+        return Err(StorageError::InvalidInput("The data store isn't encrypted".to_string()));
+    }
+    if storage.key.as_deref() != Some(old) {
+        return Err(StorageError::Locked("Incorrect passphrase".to_string()));
+    }
+    let path = storage.path.clone();
+    let entry_count = storage.entry_count()?;
+    let fresh = sibling_path(&path, ".rekeying");
+    export_verified(storage, &fresh, new)?;
+
+    if let Some(storage) = guard.take() {
+        let _ = storage.conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);");
+    }
+    let previous = sibling_path(&path, ".pre-rekey");
+    remove_files(&previous);
+    let swapped = move_files(&path, &previous)
+        .map_err(StorageError::from)
+        .and_then(|()| move_files(&fresh, &path).map_err(StorageError::from))
+        .and_then(|()| Storage::reopen(&path, Some(new)));
+    match swapped {
+        Ok(rekeyed) => *guard = Some(rekeyed),
+        Err(e) => {
+            remove_files(&path);
+            let _ = move_files(&previous, &path);
+            remove_files(&fresh);
+            *guard = Storage::reopen(&path, Some(old)).ok();
+            return Err(e);
+        }
+    }
+    remove_files(&previous);
+    Ok(entry_count)
+}
+
+// Rebuild the store into a fresh file: create the current schema, then copy
+// every readable row across from the damaged file. Tables that fail a bulk
+// copy are salvaged row by row so one bad page doesn't lose the whole table.
+fn rebuild_into(damaged: &Path, fresh: &Path, actions: &mut Vec<String>) -> Result<Vec<TableRepair>, String> {
+    remove_files(fresh);
+    let conn = Connection::open(fresh)
+        .map_err(|e| format!("Failed to create repaired database {}: {}", fresh.display(), e))?;
+    migrate(&conn)?;
+    conn.execute("ATTACH DATABASE ?1 AS damaged", params![damaged.to_string_lossy().to_string()])
+        .map_err(|e| format!("Failed to open damaged database for reading: {}", e))?;
+
+    let tables: Vec<String> = {
+        let mut stmt = conn
+            .prepare("SELECT name FROM main.sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'")
+            .map_err(db_err)?;
+        let names = stmt.query_map([], |row| row.get(0)).map_err(db_err)?;
+        names.collect::<Result<_, _>>().map_err(db_err)?
+    };
+
+    let mut report = Vec::new();
+    for table in tables {
+        let bulk = format!("INSERT INTO main.\"{0}\" SELECT * FROM damaged.\"{0}\"", table);
+        match conn.execute(&bulk, []) {
+            Ok(rows) => {
+                actions.push(format!("Copied {} rows from {}", rows, table));
+                report.push(TableRepair {
+                    table,
+                    rows_recovered: rows as i64,
+                    rows_lost: 0,
+                });
+            }
+            Err(e) => {
+                actions.push(format!("Bulk copy of {} failed ({}); salvaging row by row", table, e));
+                let _ = conn.execute(&format!("DELETE FROM main.\"{}\"", table), []);
+                let rowids: Vec<i64> = conn
+                    .prepare(&format!("SELECT rowid FROM damaged.\"{}\"", table))
+                    .and_then(|mut stmt| {
+                        let ids = stmt.query_map([], |row| row.get(0))?;
+                        Ok(ids.filter_map(Result::ok).collect())
+                    })
+                    .unwrap_or_default();
+                let single = format!(
+                    "INSERT INTO main.\"{0}\" SELECT * FROM damaged.\"{0}\" WHERE rowid = ?1",
+                    table
+                );
+                let (mut recovered, mut lost) = (0, 0);
+                for rowid in rowids {
+                    match conn.execute(&single, params![rowid]) {
+                        Ok(_) => recovered += 1,
+                        Err(_) => lost += 1,
+                    }
+                }
+                report.push(TableRepair {
+                    table,
+                    rows_recovered: recovered,
+                    rows_lost: lost,
+                });
+            }
+        }
+    }
+
+    conn.execute_batch("DETACH DATABASE damaged").map_err(db_err)?;
+    store_checksum(&conn)?;
+    actions.push("Recomputed data checksum".to_string());
+    Ok(report)
+}
+
+pub fn repair(state: &StorageState) -> Result<RepairReport, String> {
+    let mut guard = state.0.lock().map_err(|_| "Storage lock poisoned".to_string())?;
+    if guard.as_ref().is_some_and(Storage::is_encrypted) {
+        return Err("Encrypted stores can't be repaired in place; restore a backup instead".to_string());
+    }
+    let storage = guard.take().ok_or("Storage is not open")?;
+    let path = storage.path.clone();
+    // Checkpoint what we can so the copy sees committed WAL content, then
+    // release the connection so the files can be swapped.
+    let _ = storage.conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);");
+    drop(storage);
+
+    let mut actions = Vec::new();
+    let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+    let backup = match backup_files(&path, &format!(".corrupt-{}", timestamp)) {
+        Ok(backup) => backup,
+        Err(e) => {
+            // Nothing has been touched yet; reopen and bail.
+            *guard = Storage::open(&path).ok();
+            return Err(e);
+        }
+    };
+    actions.push(format!("Backed up damaged database to {}", backup.display()));
+
+    let fresh = sibling_path(&path, ".repair");
+    let tables = match rebuild_into(&path, &fresh, &mut actions) {
+        Ok(tables) => tables,
+        Err(e) => {
+            remove_files(&fresh);
+            *guard = Storage::open(&path).ok();
+            return Err(format!("Repair failed, original database left in place: {}", e));
+        }
+    };
+
+    let integrity_after = Connection::open(&fresh)
+        .and_then(|conn| integrity_check(&conn))
+        .unwrap_or_else(|e| vec![format!("integrity_check could not run: {}", e)]);
+    let replaced = integrity_after.len() == 1 && integrity_after[0] == "ok";
+
+    if replaced {
+        if let Err(e) = swap_in(&fresh, &path) {
+            remove_files(&fresh);
+            // A failed rename leaves the original, but the WAL may be gone.
+            let restored = put_back(&backup, &path);
+            *guard = Storage::open(&path).ok();
+            return Err(match restored {
+                Ok(()) => format!("Repair failed, original database left in place: {}", e),
+                Err(restore) => format!("{}; restoring the original also failed: {}", e, restore),
+            });
+        }
+        actions.push("Replaced damaged database with repaired copy".to_string());
+    } else {
+        remove_files(&fresh);
+        actions.push("Repaired copy still failed the integrity check; original left in place".to_string());
+    }
+
+    match Storage::open(&path) {
+        Ok(storage) => *guard = Some(storage),
+        Err(e) => {
+            let e = String::from(e);
+            if !replaced {
+                return Err(e);
+            }
+            // The repaired copy won't open; go back to the damaged one, which
+            // at least opened before.
+            let restored = put_back(&backup, &path);
+            *guard = Storage::open(&path).ok();
+            return Err(match restored {
+                Ok(()) => format!("The repaired database wouldn't open, so the original was put back: {}", e),
+                Err(restore) => format!("{}; restoring the original also failed: {}", e, restore),
+            });
+        }
+    }
+
+    Ok(RepairReport {
+        path: path.display().to_string(),
+        backup_path: backup.display().to_string(),
+        tables,
+        integrity_after,
+        replaced,
+        actions,
+    })
+}
+
+pub struct StorageState(pub Mutex<Option<Storage>>);
+
+impl StorageState {
+    pub fn with<T>(
+        &self,
+        mut f: impl FnMut(&mut Storage) -> Result<T, StorageError>,
+    ) -> Result<T, StorageError> {
+        let mut guard = self.0.lock().map_err(|_| "Storage lock poisoned")?;
+        let storage = guard.as_mut().ok_or("Storage is not open")?;
+        let mut attempt = 0;
+        loop {
+            match f(storage) {
+                Err(StorageError::Busy(_)) if attempt < BUSY_RETRIES => {
+                    attempt += 1;
+                    std::thread::sleep(BUSY_RETRY_PAUSE * attempt);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Fold the WAL back into the database file and close it, so nothing is
+    // left half-applied when the process goes away.
+    pub fn close(&self) {
+        let mut guard = self.0.lock().unwrap_or_else(|p| p.into_inner());
+        if let Some(storage) = guard.take() {
+            let _ = storage.conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);");
+        }
+    }
+}
+
+// Probe for locks held elsewhere the same way SQLite decides it is the last
+// connection: an exclusive lock on the database file is only granted once
+// every other connection, in this process or another, has closed. Getting it
+// means whatever remains next to the file is stale.
+fn release_stale_locks(path: &Path, key: Option<&str>) -> Result<UnlockReport, StorageError> {
+    let conn = Connection::open(path).map_err(db_err)?;
+    if let Some(key) = key {
+        conn.pragma_update(None, "key", key).map_err(db_err)?;
+    }
+    conn.busy_timeout(Duration::ZERO).map_err(db_err)?;
+    conn.execute_batch("PRAGMA locking_mode = EXCLUSIVE; BEGIN EXCLUSIVE; COMMIT;")
+        .map_err(|e| match db_err(e) {
+            StorageError::Busy(_) => StorageError::Busy(
+                "The database is still in use by another process; close any other Smartbot windows and try again"
+                    .to_string(),
+            ),
+            other => other,
+        })?;
+    // The WAL can hold committed writes, so it is folded back into the
+    // database before anything is removed.
+    let blocked: i64 = conn
+        .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))
+        .map_err(db_err)?;
+    if blocked != 0 {
+        return Err(StorageError::Busy(
+            "The database's pending changes couldn't be saved yet; try again in a moment".to_string(),
+        ));
+    }
+    // Closing the last connection also deletes the now-empty WAL where the
+    // platform allows; whatever is still there afterwards is left over.
+    conn.close().map_err(|(_, e)| db_err(e))?;
+
+    let mut removed = Vec::new();
+    for extra in ["-wal", "-shm"] {
+        let leftover = sibling_path(path, extra);
+        if leftover.exists() {
+            std::fs::remove_file(&leftover)
+                .map_err(|e| format!("Failed to remove {}: {}", leftover.display(), e))?;
+            removed.push(leftover.display().to_string());
+        }
+    }
+    Ok(UnlockReport {
+        path: path.display().to_string(),
+        removed,
+    })
+}
+
+// `path` is used when the store couldn't be opened at all, which is the usual
+// reason to call this.
+pub fn force_unlock(state: &StorageState, path: &Path) -> Result<UnlockReport, StorageError> {
+    let mut guard = state.0.lock().map_err(|_| "Storage lock poisoned")?;
+    let path = guard.as_ref().map_or_else(|| path.to_path_buf(), |storage| storage.path.clone());
+    let key = guard.as_ref().and_then(|storage| storage.key.clone());
+    // Our own connection would otherwise count as the live owner.
+    if let Some(storage) = guard.take() {
+        let _ = storage.conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);");
+    }
+
+    let report = release_stale_locks(&path, key.as_deref());
+    // Reopen either way, so a refused unlock leaves the store as it was. A
+    // locked encrypted store stays closed until its passphrase is entered.
+    match Storage::reopen(&path, key.as_deref()) {
+        Ok(storage) => *guard = Some(storage),
+        Err(StorageError::Locked(_)) => {}
+        Err(e) if report.is_ok() => return Err(e),
+        Err(_) => {}
+    }
+    report
+}
+
+const MAX_KIND_LEN: usize = 64;
+const MAX_CONTENT_LEN: usize = 1024 * 1024;
+const MAX_LIST_LIMIT: u32 = 1000;
+
+#[tauri::command]
+pub fn add_entry(
+    state: tauri::State<StorageState>,
+    config: tauri::State<ConfigState>,
+    kind: String,
+    content: String,
+    mood: Option<f64>,
+) -> Result<i64, StorageError> {
+    validate::identifier("Entry kind", &kind, MAX_KIND_LEN)?;
+    validate::text("Entry content", &content, MAX_CONTENT_LEN)?;
+    if let Some(mood) = mood {
+        validate::finite("Mood", mood)?;
+    }
+    state.with(|storage| {
+        disk::ensure_space(&storage.path, content.len() as u64, disk::reserve_bytes(&config))?;
+        storage.add_entry(&kind, &content, mood)
+    })
+}
+
+#[tauri::command]
+pub fn list_entries(
+    state: tauri::State<StorageState>,
+    kind: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<Entry>, StorageError> {
+    if let Some(kind) = &kind {
+        validate::identifier("Entry kind", kind, MAX_KIND_LEN)?;
+    }
+    let limit = validate::number("Limit", limit.unwrap_or(100), 1..=MAX_LIST_LIMIT)?;
+    state.with(|storage| storage.list_entries(kind.as_deref(), limit))
+}
+
+// The replaced text goes to the entry's history; editing to the same text
+// changes nothing.
+#[tauri::command]
+pub fn update_entry(
+    state: tauri::State<StorageState>,
+    config: tauri::State<ConfigState>,
+    id: i64,
+    new_content: String,
+) -> Result<Entry, StorageError> {
+    validate::text("Entry content", &new_content, MAX_CONTENT_LEN)?;
+    state.with(|storage| {
+        disk::ensure_space(&storage.path, new_content.len() as u64, disk::reserve_bytes(&config))?;
+        storage.update_entry(id, &new_content)
+    })
+}
+
+#[tauri::command]
+pub fn get_entry_history(
+    state: tauri::State<StorageState>,
+    id: i64,
+) -> Result<Vec<EntryVersion>, StorageError> {
+    state.with(|storage| storage.entry_history(id))
+}
+
+// Run SQLite's integrity check and compare the stored checksum with the data.
+#[tauri::command]
+pub fn verify_storage(state: tauri::State<StorageState>) -> Result<VerifyReport, StorageError> {
+    state.with(|storage| Ok(storage.verify()))
+}
+
+// Back up the damaged file and rebuild the store from whatever is readable.
+#[tauri::command]
+pub async fn repair_storage(
+    state: tauri::State<'_, StorageState>,
+    guest: tauri::State<'_, GuestState>,
+) -> Result<RepairReport, AppError> {
+    guest.refuse("Repairing storage")?;
+    Ok(repair(&state)?)
+}
+
+// For a store left locked by a crashed process: refuses while any live
+// connection still holds it.
+#[tauri::command]
+pub async fn force_unlock_storage(app: AppHandle) -> Result<UnlockReport, StorageError> {
+    let path = app.state::<ProfileState>().active_dir()?.join(DB_FILE_NAME);
+    force_unlock(&app.state::<StorageState>(), &path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_db() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("smartbot-storage-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(DB_FILE_NAME)
+    }
+
+    #[test]
+    fn write_lock_held_elsewhere_is_reported_as_busy() {
+        let path = temp_db();
+        let mut storage = Storage::open_with(&path, Duration::from_millis(50)).unwrap();
+        let other = Connection::open(&path).unwrap();
+        other.execute_batch("BEGIN IMMEDIATE;").unwrap();
+
+        assert!(matches!(storage.add_entry("journal", "held", None), Err(StorageError::Busy(_))));
+
+        other.execute_batch("COMMIT;").unwrap();
+        assert!(storage.add_entry("journal", "released", None).is_ok());
+    }
+
+    #[test]
+    fn force_unlock_refuses_while_the_database_is_in_use() {
+        let path = temp_db();
+        let state = StorageState(Mutex::new(Some(Storage::open(&path).unwrap())));
+        state.with(|storage| storage.add_entry("journal", "before", Some(3.0))).unwrap();
+        let other = Connection::open(&path).unwrap();
+        other.execute_batch("BEGIN IMMEDIATE;").unwrap();
+
+        assert!(matches!(force_unlock(&state, &path), Err(StorageError::Busy(_))));
+        // The refused unlock reopened the store rather than leaving it closed.
+        assert!(state.0.lock().unwrap().is_some());
+
+        other.execute_batch("COMMIT;").unwrap();
+        other.close().map_err(|(_, e)| e).unwrap();
+        // A crashed process leaves its shared-memory file behind.
+        std::fs::write(sibling_path(&path, "-shm"), b"stale").unwrap();
+        force_unlock(&state, &path).unwrap();
+        let entries = state.with(|storage| storage.all_entries()).unwrap();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn plan_replace_matches_entries_by_id() {
+        let mut storage = Storage::open(&temp_db()).unwrap();
+        storage.add_entry("journal", "first", None).unwrap();
+        storage.add_entry("journal", "second", None).unwrap();
+        let mut incoming = storage.all_entries().unwrap();
+        incoming.remove(0);
+        incoming.push(Entry {
+            id: 10,
+            kind: "journal".to_string(),
+            content: "restored".to_string(),
+            mood: None,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        });
+
+        let plan = storage.plan_replace(&incoming).unwrap();
+        assert_eq!(
+            (plan.entries_added, plan.entries_overwritten, plan.entries_removed),
+            (1, 1, 1)
+        );
+        // Planning leaves the store alone.
+        assert_eq!(storage.entry_count().unwrap(), 2);
+    }
+
+    #[test]
+    fn edits_keep_a_capped_history() {
+        let mut storage = Storage::open(&temp_db()).unwrap();
+        let id = storage.add_entry("journal", "v0", None).unwrap();
+        for version in 1..=MAX_HISTORY_PER_ENTRY + 2 {
+            storage.update_entry(id, &format!("v{}", version)).unwrap();
+        }
+
+        let history = storage.entry_history(id).unwrap();
+        assert_eq!(history.len() as i64, MAX_HISTORY_PER_ENTRY);
+        assert_eq!(history[0].content, format!("v{}", MAX_HISTORY_PER_ENTRY + 1));
+        assert_eq!(history.last().unwrap().content, "v2");
+        assert!(storage.verify().checksum_ok);
+        // History goes with the entry when the store is replaced.
+        storage.replace_all(&[], &[]).unwrap();
+        assert!(storage.entry_history(id).is_err());
+    }
+
+    #[test]
+    fn merges_keep_every_text_past_the_history_cap() {
+        let mut storage = Storage::open(&temp_db()).unwrap();
+        let keep = storage.add_entry("journal", "keep v0", None).unwrap();
+        let other = storage.add_entry("journal", "other v0", None).unwrap();
+        let mut texts = Vec::new();
+        for version in 1..=MAX_HISTORY_PER_ENTRY {
+            texts.push(format!("other v{}", version - 1));
+            storage.update_entry(other, &format!("other v{}", version)).unwrap();
+            storage.update_entry(keep, &format!("keep v{}", version)).unwrap();
+        }
+        texts.push(format!("other v{}", MAX_HISTORY_PER_ENTRY));
+
+        storage.merge_entries(keep, &[other]).unwrap();
+        // A later edit prunes the entry's own versions but not merged ones.
+        storage.update_entry(keep, "keep final").unwrap();
+
+        let history = storage.entry_history(keep).unwrap();
+        for text in &texts {
+            assert!(history.iter().any(|v| &v.content == text), "lost {}", text);
+        }
+        let own = history.iter().filter(|v| v.content.starts_with("keep")).count();
+        assert_eq!(own as i64, MAX_HISTORY_PER_ENTRY);
+        assert!(storage.verify().checksum_ok);
+    }
+
+    #[test]
+    fn history_survives_a_restore() {
+        let mut storage = Storage::open(&temp_db()).unwrap();
+        let id = storage.add_entry("journal", "first draft", None).unwrap();
+        storage.update_entry(id, "second draft").unwrap();
+        storage.update_entry(id, "final").unwrap();
+        let entries = storage.all_entries().unwrap();
+        let history = storage.all_history().unwrap();
+        assert_eq!(history.len(), 2);
+
+        let mut restored = Storage::open(&temp_db()).unwrap();
+        restored.add_entry("journal", "replaced by the restore", None).unwrap();
+        restored.replace_all(&entries, &history).unwrap();
+        let versions = restored.entry_history(id).unwrap();
+        let contents: Vec<_> = versions.iter().map(|v| v.content.as_str()).collect();
+        assert_eq!(contents, ["second draft", "first draft"]);
+        assert!(restored.verify().checksum_ok);
+    }
+
+    #[test]
+    fn future_messages_are_delivered_once_when_due() {
+        let mut storage = Storage::open(&temp_db()).unwrap();
+        let soon = storage.add_future_message("soon", "2026-05-01T12:00:00Z").unwrap();
+        let later = storage.add_future_message("later", "2026-06-01T12:00:00Z").unwrap();
+
+        let delivered = storage.deliver_future_messages("2026-05-01T12:00:00Z").unwrap();
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(delivered[0].id, soon.id);
+        assert!(storage.deliver_future_messages("2026-05-02T00:00:00Z").unwrap().is_empty());
+
+        let pending = storage.future_messages(false).unwrap();
+        assert_eq!(pending.iter().map(|m| m.id).collect::<Vec<_>>(), [later.id]);
+        // Delivered messages can't be cancelled, pending ones can.
+        assert!(storage.cancel_future_message(soon.id).is_err());
+        storage.cancel_future_message(later.id).unwrap();
+        assert!(storage.future_messages(false).unwrap().is_empty());
+        assert_eq!(storage.future_messages(true).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn unreadable_file_is_reported_as_corrupt() {
+        let path = temp_db();
+        let mut bytes = SQLITE_HEADER.to_vec();
+        bytes.resize(4096, 0x5a);
+        std::fs::write(&path, bytes).unwrap();
+        assert!(matches!(Storage::open(&path), Err(StorageError::Corrupt(_))));
+        // Without SQLite's header it could be an encrypted store.
+        std::fs::write(&path, vec![0x5a; 4096]).unwrap();
+        assert!(matches!(Storage::open(&path), Err(StorageError::Locked(_))));
+    }
+
+    #[test]
+    fn encrypted_store_keeps_entries_and_needs_the_passphrase() {
+        let path = temp_db();
+        let state = StorageState(Mutex::new(Some(Storage::open(&path).unwrap())));
+        state.with(|storage| storage.add_entry("note", "hello", None)).unwrap();
+
+        let report = encrypt_in_place(&state, "correct horse battery").unwrap();
+        assert_eq!(report.entry_count, 1);
+        assert!(is_encrypted(&path).unwrap());
+        assert!(!sibling_path(&path, ".plaintext").exists());
+        state.close();
+
+        assert!(matches!(Storage::open(&path), Err(StorageError::Locked(_))));
+        assert!(matches!(
+            Storage::open_encrypted(&path, "wrong passphrase"),
+            Err(StorageError::Locked(_))
+        ));
+        let storage = Storage::open_encrypted(&path, "correct horse battery").unwrap();
+        assert_eq!(storage.all_entries().unwrap()[0].content, "hello");
+    }
+
+    #[test]
+    fn rotated_key_replaces_the_old_one() {
+        let path = temp_db();
+        let state = StorageState(Mutex::new(Some(Storage::open(&path).unwrap())));
+        state.with(|storage| storage.add_entry("note", "hello", None)).unwrap();
+        encrypt_in_place(&state, "correct horse battery").unwrap();
+
+        assert!(matches!(
+            rotate_key(&state, "wrong passphrase", "new passphrase here"),
+            Err(StorageError::Locked(_))
+        ));
+        assert_eq!(rotate_key(&state, "correct horse battery", "new passphrase here").unwrap(), 1);
+        assert!(!sibling_path(&path, ".rekeying").exists());
+        assert!(!sibling_path(&path, ".pre-rekey").exists());
+        assert_eq!(state.with(|storage| storage.entry_count()).unwrap(), 1);
+        state.close();
+
+        assert!(matches!(
+            Storage::open_encrypted(&path, "correct horse battery"),
+            Err(StorageError::Locked(_))
+        ));
+        let storage = Storage::open_encrypted(&path, "new passphrase here").unwrap();
+        assert_eq!(storage.all_entries().unwrap()[0].content, "hello");
+    }
+
+    #[test]
+    fn repair_salvages_readable_rows_and_keeps_the_damaged_copy() {
+        let path = temp_db();
+        let mut storage = Storage::open(&path).unwrap();
+        for i in 0..200 {
+            let content = format!("entry-{:03} {}", i, "x".repeat(100));
+            storage.add_entry("journal", &content, None).unwrap();
+        }
+        let page_size: usize = storage
+            .conn
+            .pragma_query_value(None, "page_size", |row| row.get(0))
+            .unwrap();
+        // Closing checkpoints the WAL, so every row is in the main file.
+        drop(storage);
+
+        // Break the header of the page holding entry 150, so reading the
+        // table fails partway through.
+        let mut bytes = std::fs::read(&path).unwrap();
+        let at = bytes.windows(9).position(|window| window == b"entry-150").unwrap();
+        let page = at / page_size * page_size;
+        bytes[page..page + 16].fill(0xff);
+        std::fs::write(&path, &bytes).unwrap();
+        let state = StorageState(Mutex::new(Some(Storage::open(&path).unwrap())));
+        assert!(state.with(|storage| storage.all_entries()).is_err());
+
+        let report = repair(&state).unwrap();
+        assert!(report.replaced);
+        let entries = report.tables.iter().find(|t| t.table == "entries").unwrap();
+        assert!(entries.rows_recovered > 0 && entries.rows_recovered < 200);
+
+        // The reopened store holds what was salvaged, and takes writes.
+        let recovered = state.with(|storage| storage.all_entries()).unwrap();
+        assert_eq!(recovered.len() as i64, entries.rows_recovered);
+        assert!(recovered[0].content.starts_with("entry-000"));
+        assert!(!recovered.iter().any(|entry| entry.content.starts_with("entry-150")));
+        state.with(|storage| storage.add_entry("journal", "after the repair", None)).unwrap();
+        assert!(state.with(|storage| Ok(storage.verify())).unwrap().checksum_ok);
+
+        // The copy set aside is still the damaged database.
+        let damaged = Connection::open(&report.backup_path).unwrap();
+        let read = damaged.query_row("SELECT sum(length(content)) FROM entries", [], |row| {
+            row.get::<_, i64>(0)
+        });
+        assert!(read.is_err());
+    }
+
+    #[test]
+    fn weekly_mood_stats_group_monday_to_sunday_in_local_time() {
+        use chrono::TimeZone;
+
+        let mut storage = Storage::open(&temp_db()).unwrap();
+        let mut add = |day: u32, hour: u32, minute: u32, mood: f64| {
+            let local = chrono::NaiveDate::from_ymd_opt(2026, 5, day)
+                .and_then(|date| date.and_hms_opt(hour, minute, 0))
+                .unwrap();
+            let created_at = chrono::Local
+                .from_local_datetime(&local)
+                .single()
+                .unwrap()
+                .with_timezone(&chrono::Utc)
+                .to_rfc3339();
+            let id = storage.add_entry("journal", "mood", Some(mood)).unwrap();
+            let sql = "UPDATE entries SET created_at = ?1 WHERE id = ?2";
+            storage.conn.execute(sql, params![created_at, id]).unwrap();
+        };
+        // Sunday 3 May belongs to the week before `since`.
+        add(3, 12, 0, 9.0);
+        // Monday 4 May to late on Sunday 10 May is one week...
+        add(4, 8, 0, 2.0);
+        add(10, 23, 30, 4.0);
+        // ...and just after midnight starts the next.
+        add(11, 0, 30, 8.0);
+
+        let weeks = storage.mood_stats(true, "2026-05-04").unwrap();
+        let weeks: Vec<_> = weeks
+            .iter()
+            .map(|s| (s.bucket.as_str(), s.average, s.min, s.max, s.count))
+            .collect();
+        assert_eq!(weeks, [("2026-05-04", 3.0, 2.0, 4.0, 2), ("2026-05-11", 8.0, 8.0, 8.0, 1)]);
+
+        let days = storage.mood_stats(false, "2026-05-10").unwrap();
+        let days: Vec<_> = days.iter().map(|s| (s.bucket.as_str(), s.count)).collect();
+        assert_eq!(days, [("2026-05-10", 1), ("2026-05-11", 1)]);
+    }
+}
@@ -0,0 +1,480 @@
+// Mirrors the active profile's entries to a folder the user picks, usually
+// inside a Dropbox or iCloud Drive folder. The snapshot there is a regular
+// `.smartbak` file, so the provider only ever sees ciphertext, and as with
+// scheduled backups the password is held in memory only: after a restart
+// nothing syncs until it is entered again.
+//
+// Each tick compares both sides with what was last synced. Whichever side
+// changed is copied over the other; if both did, the more recent write wins
+// and the losing side is kept next to the snapshot as a conflict copy.
+
+use crate::audit::{AuditAction, AuditState};
+use crate::autobackup;
+use crate::background::Workers;
+use crate::backup::{self, BackupError, BackupPayload, BACKUP_EXTENSION};
+use crate::config::{self, ConfigState};
+use crate::disk;
+use crate::guest::GuestState;
+use crate::profiles::ProfileState;
+use crate::storage::StorageState;
+use crate::validate;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+
+const TICK: Duration = Duration::from_secs(60);
+
+const SNAPSHOT_PREFIX: &str = "smartbot-sync-";
+const CONFLICT_PREFIX: &str = "smartbot-sync-conflict-";
+
+// Kept in each profile's directory: what was last synced, so a restart can
+// still tell which side changed.
+pub const SYNC_STATE_FILE_NAME: &str = "sync-state.json";
+
+pub const SYNC_STATUS_EVENT: &str = "sync-status";
+// Sent after entries were replaced by the synced snapshot, so the frontend
+// reloads them.
+pub const SYNC_PULLED_EVENT: &str = "sync-pulled";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPhase {
+    Off,
+    NeedsPassword,
+    Synced,
+    // The folder is missing, e.g. an unmounted drive or a signed-out cloud
+    // client. Retried every tick.
+    Unavailable,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SyncStatus {
+    pub phase: SyncPhase,
+    pub folder: Option<String>,
+    pub last_synced_at: Option<String>,
+    // Where the losing side of the most recent conflict was kept.
+    pub conflict_copy: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncBase {
+    sha256: Option<String>,
+    synced_at: Option<String>,
+}
+
+#[derive(Default)]
+pub struct SyncState {
+    password: Mutex<Option<String>>,
+    // Modification time and size of the snapshot as last read or written,
+    // so an unchanged one isn't decrypted again every tick.
+    seen: Mutex<Option<(SystemTime, u64)>>,
+    conflict_copy: Mutex<Option<String>>,
+    last: Mutex<Option<SyncStatus>>,
+    // Held for a whole sync, so a tick and a command never run one at once.
+    running: Mutex<()>,
+}
+
+impl SyncState {
+    fn password(&self) -> Option<String> {
+        self.password
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .clone()
+    }
+
+    fn set_password(&self, password: Option<String>) {
+        *self.password.lock().unwrap_or_else(|p| p.into_inner()) = password;
+    }
+
+    fn seen(&self) -> Option<(SystemTime, u64)> {
+        *self.seen.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    fn set_seen(&self, seen: Option<(SystemTime, u64)>) {
+        *self.seen.lock().unwrap_or_else(|p| p.into_inner()) = seen;
+    }
+}
+
+enum Remote {
+    Missing,
+    Unchanged,
+    Changed(BackupPayload, String),
+}
+
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn read_base(profile_dir: &Path) -> SyncBase {
+    std::fs::read_to_string(profile_dir.join(SYNC_STATE_FILE_NAME))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn write_base(profile_dir: &Path, base: &SyncBase) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(base)
+        .map_err(|e| format!("Failed to serialize sync state: {}", e))?;
+    std::fs::write(profile_dir.join(SYNC_STATE_FILE_NAME), json)
+        .map_err(|e| format!("Failed to save sync state: {}", e))
+}
+
+fn snapshot_path(folder: &Path, profile_id: &str) -> PathBuf {
+    folder.join(format!(
+        "{}{}.{}",
+        SNAPSHOT_PREFIX, profile_id, BACKUP_EXTENSION
+    ))
+}
+
+fn conflict_path(folder: &Path, profile_id: &str) -> PathBuf {
+    folder.join(format!(
+        "{}{}-{}.{}",
+        CONFLICT_PREFIX,
+        profile_id,
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        BACKUP_EXTENSION
+    ))
+}
+
+fn parse_time(at: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(at)
+        .ok()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+// When the local data last changed, as far as entries record it. A snapshot
+// records when it was written, which is never before its own changes.
+fn local_written_at(payload: &BackupPayload) -> Option<DateTime<Utc>> {
+    payload
+        .entries
+        .iter()
+        .filter_map(|entry| parse_time(&entry.updated_at))
+        .max()
+}
+
+// Which side wins a conflict; ties go to the local data.
+fn remote_wins(local: &BackupPayload, remote: &BackupPayload) -> bool {
+    match (local_written_at(local), parse_time(&remote.created_at)) {
+        (Some(local), Some(remote)) => remote > local,
+        (None, Some(_)) => true,
+        (_, None) => false,
+    }
+}
+
+fn read_remote(
+    state: &SyncState,
+    path: &Path,
+    password: &str,
+    base: &SyncBase,
+) -> Result<Remote, BackupError> {
+    let Some(stamp) = file_stamp(path) else {
+        return Ok(Remote::Missing);
+    };
+    if base.sha256.is_some() && state.seen() == Some(stamp) {
+        return Ok(Remote::Unchanged);
+    }
+    let bytes = std::fs::read(path)
+        .map_err(|e| BackupError::Failed(format!("Failed to read {}: {}", path.display(), e)))?;
+    let (payload, _) = backup::decrypt(&bytes, password)?;
+    let hash = autobackup::content_hash(&payload)?;
+    state.set_seen(Some(stamp));
+    if base.sha256.as_deref() == Some(hash.as_str()) {
+        return Ok(Remote::Unchanged);
+    }
+    Ok(Remote::Changed(payload, hash))
+}
+
+fn push(
+    app: &AppHandle,
+    state: &SyncState,
+    payload: &BackupPayload,
+    path: &Path,
+    password: &str,
+) -> Result<(), BackupError> {
+    let reserve = disk::reserve_bytes(&app.state::<ConfigState>());
+    backup::write(payload, path, password, reserve)?;
+    state.set_seen(file_stamp(path));
+    Ok(())
+}
+
+fn pull(app: &AppHandle, payload: &BackupPayload) -> Result<(), BackupError> {
+    app.state::<StorageState>()
+        .with(|storage| storage.replace_all(&payload.entries, &payload.history))?;
+    app.state::<AuditState>().record(
+        AuditAction::Import,
+        serde_json::json!({ "source": "sync", "entries": payload.entries.len() }),
+    );
+    let _ = app.emit_all(SYNC_PULLED_EVENT, payload.entries.len());
+    Ok(())
+}
+
+// One round trip between the store and the folder. Returns when it finished.
+fn run(app: &AppHandle, folder: &Path, password: &str) -> Result<String, BackupError> {
+    let state = app.state::<SyncState>();
+    let _running = state.running.lock().unwrap_or_else(|p| p.into_inner());
+    let profiles = app.state::<ProfileState>();
+    let profile_id = profiles.active_id();
+    let profile_dir = profiles.active_dir()?;
+    let remote_path = snapshot_path(folder, &profile_id);
+
+    let base = read_base(&profile_dir);
+    let local = backup::snapshot(&app.state::<StorageState>())?;
+    let local_hash = autobackup::content_hash(&local)?;
+    let local_changed = base.sha256.as_deref() != Some(local_hash.as_str());
+
+    let synced_hash = match read_remote(&state, &remote_path, password, &base)? {
+        Remote::Missing => {
+            push(app, &state, &local, &remote_path, password)?;
+            local_hash
+        }
+        Remote::Unchanged => {
+            if local_changed {
+                push(app, &state, &local, &remote_path, password)?;
+            }
+            local_hash
+        }
+        Remote::Changed(_, remote_hash) if remote_hash == local_hash => local_hash,
+        // A first sync into an empty store just takes the snapshot.
+        Remote::Changed(remote, remote_hash)
+            if !local_changed || (base.sha256.is_none() && local.entries.is_empty()) =>
+        {
+            pull(app, &remote)?;
+            remote_hash
+        }
+        Remote::Changed(remote, remote_hash) => {
+            let copy = conflict_path(folder, &profile_id);
+            let winner = if remote_wins(&local, &remote) {
+                let reserve = disk::reserve_bytes(&app.state::<ConfigState>());
+                backup::write(&local, &copy, password, reserve)?;
+                pull(app, &remote)?;
+                remote_hash
+            } else {
+                std::fs::rename(&remote_path, &copy).map_err(|e| {
+                    format!(
+                        "Failed to keep the synced copy at {}: {}",
+                        copy.display(),
+                        e
+                    )
+                })?;
+                push(app, &state, &local, &remote_path, password)?;
+                local_hash
+            };
+            *state
+                .conflict_copy
+                .lock()
+                .unwrap_or_else(|p| p.into_inner()) = Some(copy.display().to_string());
+            winner
+        }
+    };
+
+    let now = Utc::now().to_rfc3339();
+    write_base(
+        &profile_dir,
+        &SyncBase {
+            sha256: Some(synced_hash),
+            synced_at: Some(now.clone()),
+        },
+    )?;
+    Ok(now)
+}
+
+fn status(app: &AppHandle, phase: SyncPhase, message: Option<String>) -> SyncStatus {
+    let folder = app.state::<ConfigState>().get().sync_dir;
+    let last_synced_at = app
+        .state::<ProfileState>()
+        .active_dir()
+        .ok()
+        .and_then(|dir| read_base(&dir).synced_at);
+    SyncStatus {
+        phase,
+        folder,
+        last_synced_at,
+        conflict_copy: app
+            .state::<SyncState>()
+            .conflict_copy
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .clone(),
+        message,
+    }
+}
+
+// Emits the status only when it changed, so a steady state stays quiet.
+fn report(app: &AppHandle, status: SyncStatus) -> SyncStatus {
+    let state = app.state::<SyncState>();
+    let mut last = state.last.lock().unwrap_or_else(|p| p.into_inner());
+    if last.as_ref() != Some(&status) {
+        let _ = app.emit_all(SYNC_STATUS_EVENT, &status);
+        *last = Some(status.clone());
+    }
+    status
+}
+
+fn sync_now(app: &AppHandle) -> SyncStatus {
+    let config = app.state::<ConfigState>().get();
+    let Some(folder) = config.sync_dir.filter(|_| config.features.sync) else {
+        return report(app, status(app, SyncPhase::Off, None));
+    };
+    let Some(password) = app.state::<SyncState>().password() else {
+        return report(app, status(app, SyncPhase::NeedsPassword, None));
+    };
+    let folder = PathBuf::from(folder);
+    if !folder.is_dir() {
+        let message = format!("{} isn't available", folder.display());
+        return report(app, status(app, SyncPhase::Unavailable, Some(message)));
+    }
+    match run(app, &folder, &password) {
+        Ok(_) => report(app, status(app, SyncPhase::Synced, None)),
+        Err(e) => {
+            eprintln!("Sync failed: {}", e);
+            report(app, status(app, SyncPhase::Failed, Some(e.to_string())))
+        }
+    }
+}
+
+fn tick(app: &AppHandle) {
+    if app.state::<GuestState>().active() {
+        return;
+    }
+    sync_now(app);
+}
+
+pub fn start(app: &AppHandle) {
+    let handle = app.clone();
+    app.state::<Workers>().spawn(TICK, move || tick(&handle));
+}
+
+#[tauri::command]
+pub fn get_sync_status(app: AppHandle) -> SyncStatus {
+    let config = app.state::<ConfigState>().get();
+    let state = app.state::<SyncState>();
+    if config.sync_dir.is_none() || !config.features.sync {
+        return status(&app, SyncPhase::Off, None);
+    }
+    if state.password().is_none() {
+        return status(&app, SyncPhase::NeedsPassword, None);
+    }
+    let last = state.last.lock().unwrap_or_else(|p| p.into_inner()).clone();
+    last.unwrap_or_else(|| status(&app, SyncPhase::Synced, None))
+}
+
+// After the store's passphrase changes: re-encrypts this profile's snapshot
+// if it opened with the old one, and moves the sync password over if it was
+// the same. Returns whether the snapshot was re-encrypted.
+pub fn rekey(app: &AppHandle, old: &str, new: &str) -> Result<bool, BackupError> {
+    let state = app.state::<SyncState>();
+    let _running = state.running.lock().unwrap_or_else(|p| p.into_inner());
+    if state.password().as_deref() == Some(old) {
+        state.set_password(Some(new.to_string()));
+    }
+    let Some(folder) = app.state::<ConfigState>().get().sync_dir else {
+        return Ok(false);
+    };
+    let path = snapshot_path(Path::new(&folder), &app.state::<ProfileState>().active_id());
+    if file_stamp(&path).is_none() || !backup::reseal(&path, old, new)? {
+        return Ok(false);
+    }
+    state.set_seen(file_stamp(&path));
+    Ok(true)
+}
+
+// `None` turns sync off; the snapshot already in the folder is left there.
+// Otherwise the password is required every time, including to resume after
+// a restart, and must match the one an existing snapshot was written with.
+// Syncs straight away.
+#[tauri::command]
+pub async fn set_sync_folder(
+    app: AppHandle,
+    path: Option<String>,
+    password: Option<String>,
+) -> Result<SyncStatus, BackupError> {
+    app.state::<GuestState>().refuse("Sync")?;
+    let state = app.state::<SyncState>();
+    let Some(path) = path else {
+        state.set_password(None);
+        state.set_seen(None);
+        config::update(&app, |config| config.sync_dir = None)?;
+        return Ok(sync_now(&app));
+    };
+
+    if !app.state::<ConfigState>().get().features.sync {
+        return Err(BackupError::InvalidInput(
+            "Sync is turned off; turn the sync feature on first".to_string(),
+        ));
+    }
+    let folder = validate::user_path(&app, "Sync folder", &path)?;
+    if !folder.is_dir() {
+        return Err(BackupError::InvalidInput(format!(
+            "{} is not a folder",
+            folder.display()
+        )));
+    }
+    let password = password.unwrap_or_default();
+    validate::text("Password", &password, backup::MAX_PASSWORD_LEN)?;
+    backup::check_password(&password)?;
+    let existing = snapshot_path(&folder, &app.state::<ProfileState>().active_id());
+    if let Ok(bytes) = std::fs::read(&existing) {
+        backup::decrypt(&bytes, &password)?;
+    }
+
+    state.set_password(Some(password));
+    state.set_seen(None);
+    config::update(&app, |config| {
+        config.sync_dir = Some(folder.display().to_string())
+    })?;
+    Ok(sync_now(&app))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(created_at: &str, updated_at: &[&str]) -> BackupPayload {
+        BackupPayload {
+            schema_version: crate::storage::SCHEMA_VERSION,
+            app_version: "test".to_string(),
+            created_at: created_at.to_string(),
+            entries: updated_at
+                .iter()
+                .enumerate()
+                .map(|(i, at)| crate::storage::Entry {
+                    id: i as i64 + 1,
+                    kind: "note".to_string(),
+                    content: String::new(),
+                    mood: None,
+                    created_at: at.to_string(),
+                    updated_at: at.to_string(),
+                })
+                .collect(),
+            history: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn latest_write_wins_conflicts() {
+        let local = payload(
+            "2024-05-02T12:00:00+00:00",
+            &["2024-05-01T09:00:00+00:00", "2024-05-01T18:00:00+00:00"],
+        );
+        assert!(remote_wins(
+            &local,
+            &payload("2024-05-01T20:00:00+00:00", &[])
+        ));
+        assert!(!remote_wins(
+            &local,
+            &payload("2024-05-01T10:00:00+00:00", &[])
+        ));
+        // Local edits are compared, not when the local snapshot was taken.
+        assert!(remote_wins(&local, &payload("2024-05-01T19:00:00Z", &[])));
+        assert!(remote_wins(
+            &payload("2024-05-02T12:00:00+00:00", &[]),
+            &local
+        ));
+    }
+}
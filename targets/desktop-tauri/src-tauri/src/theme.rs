@@ -0,0 +1,23 @@
+// System appearance, forwarded so the frontend can follow dark/light mode.
+
+use tauri::{Manager, Runtime, Theme, Window};
+
+pub const THEME_CHANGED_EVENT: &str = "theme-changed";
+
+// `Theme` is non-exhaustive and some platforms can't report it at all.
+pub fn name(theme: Option<Theme>) -> &'static str {
+    match theme {
+        Some(Theme::Dark) => "dark",
+        Some(Theme::Light) => "light",
+        _ => "unknown",
+    }
+}
+
+pub fn on_theme_changed<R: Runtime>(window: &Window<R>, theme: &Theme) {
+    let _ = window.emit_all(THEME_CHANGED_EVENT, name(Some(*theme)));
+}
+
+#[tauri::command]
+pub fn get_system_theme<R: Runtime>(window: Window<R>) -> &'static str {
+    name(window.theme().ok())
+}
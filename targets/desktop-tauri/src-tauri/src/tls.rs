@@ -0,0 +1,173 @@
+// Optional TLS for the loopback connection to the core, so other users on a
+// shared machine can't read the traffic. The certificate is self-signed,
+// generated with the system `openssl`, and is the only one the shell trusts
+// when talking to the core.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const TLS_DIR: &str = "tls";
+const CERT_FILE: &str = "core-cert.pem";
+const KEY_FILE: &str = "core-key.pem";
+const VALID_DAYS: &str = "365";
+// Regenerate a little before expiry so a long session doesn't outlive it.
+const RENEW_BEFORE_SECS: &str = "86400";
+
+#[derive(Debug, Clone)]
+pub struct CoreCert {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl CoreCert {
+    pub fn in_dir(data_dir: &Path) -> Self {
+        let dir = data_dir.join(TLS_DIR);
+        Self {
+            cert: dir.join(CERT_FILE),
+            key: dir.join(KEY_FILE),
+        }
+    }
+
+    fn is_current(&self) -> bool {
+        self.key.is_file()
+            && Command::new("openssl")
+                .args(["x509", "-noout", "-checkend", RENEW_BEFORE_SECS, "-in"])
+                .arg(&self.cert)
+                .output()
+                .is_ok_and(|output| output.status.success())
+    }
+
+    // Both loopback addresses are in the SAN since the core may be reached
+    // over either.
+    fn generate(&self) -> Result<(), String> {
+        if let Some(dir) = self.cert.parent() {
+            create_private_dir(dir)?;
+        }
+        // openssl writes the key into the file as it finds it, so it has to
+        // be private before there's anything in it.
+        create_private_file(&self.key)?;
+        let output = Command::new("openssl")
+            .args(["req", "-x509", "-newkey", "rsa:2048", "-nodes", "-sha256"])
+            .args(["-days", VALID_DAYS, "-subj", "/CN=localhost"])
+            .args(["-addext", "subjectAltName=IP:127.0.0.1,IP:::1,DNS:localhost"])
+            .arg("-keyout")
+            .arg(&self.key)
+            .arg("-out")
+            .arg(&self.cert)
+            .output()
+            .map_err(|e| format!("Failed to run openssl: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to generate the core certificate: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    // Called before spawning the core; a missing or expiring pair is replaced.
+    pub fn ensure(&self) -> Result<(), String> {
+        if self.is_current() {
+            return Ok(());
+        }
+        self.generate()
+    }
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> Result<(), String> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .map_err(|e| format!("Failed to create TLS directory: {}", e))?;
+    // An existing directory keeps the mode it was created with.
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+        .map_err(|e| format!("Failed to restrict the TLS directory: {}", e))
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create TLS directory: {}", e))
+}
+
+// An empty file only its owner can read. The old key is removed first, since
+// an existing file keeps its permissions.
+#[cfg(unix)]
+fn create_private_file(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::OpenOptionsExt;
+    remove_old(path)?;
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to create the core key file: {}", e))
+}
+
+// Drops the inherited ACL and grants the current user alone full control.
+#[cfg(windows)]
+fn create_private_file(path: &Path) -> Result<(), String> {
+    remove_old(path)?;
+    std::fs::File::create(path)
+        .map_err(|e| format!("Failed to create the core key file: {}", e))?;
+    let user = std::env::var("USERNAME")
+        .map_err(|_| "Failed to restrict the core key: USERNAME is not set".to_string())?;
+    let output = Command::new("icacls")
+        .arg(path)
+        .args(["/inheritance:r", "/grant:r"])
+        .arg(format!("{}:F", user))
+        .output()
+        .map_err(|e| format!("Failed to run icacls: {}", e))?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(path);
+        return Err(format!(
+            "Failed to restrict the core key: {}",
+            String::from_utf8_lossy(&output.stdout).trim()
+        ));
+    }
+    Ok(())
+}
+
+fn remove_old(path: &Path) -> Result<(), String> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("Failed to remove the old core key: {}", e))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn mode(path: &Path) -> u32 {
+        std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn key_file_is_private_before_anything_is_written() {
+        let dir = std::env::temp_dir().join(format!("smartbot-tls-{}", uuid::Uuid::new_v4()));
+        let cert = CoreCert::in_dir(&dir);
+        let tls_dir = cert.key.parent().unwrap();
+        std::fs::create_dir_all(tls_dir).unwrap();
+        std::fs::set_permissions(tls_dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        // A key left readable by an earlier version.
+        std::fs::write(&cert.key, "old key").unwrap();
+        std::fs::set_permissions(&cert.key, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        create_private_dir(tls_dir).unwrap();
+        create_private_file(&cert.key).unwrap();
+        assert_eq!(mode(tls_dir), 0o700);
+        assert_eq!(mode(&cert.key), 0o600);
+        assert_eq!(std::fs::read_to_string(&cert.key).unwrap(), "");
+        // Written over the way openssl does, it stays private.
+        std::fs::write(&cert.key, "new key").unwrap();
+        assert_eq!(mode(&cert.key), 0o600);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
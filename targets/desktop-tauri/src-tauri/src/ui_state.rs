@@ -0,0 +1,129 @@
+// Where the user left off (open screen, selected tab), so the next launch can
+// pick up there. The blob is opaque to the shell; it only checks size and
+// that it is JSON. Closing the main window first asks the frontend for a
+// final snapshot: `save-ui-state` is emitted, and the close goes ahead once
+// `save_ui_state` answers or `SAVE_TIMEOUT` passes. `restart_app` asks the
+// same way, since a restart never closes the window.
+
+use crate::error::AppError;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, CloseRequestApi, Manager, Window};
+
+pub const UI_STATE_FILE_NAME: &str = "ui_state.json";
+pub const SAVE_UI_STATE_EVENT: &str = "save-ui-state";
+
+const MAX_UI_STATE_LEN: usize = 256 * 1024;
+// A frontend that never answers mustn't keep the window open.
+const SAVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum Close {
+    #[default]
+    Open,
+    // Waiting for the frontend's snapshot.
+    Saving,
+    // Snapshot saved (or given up on); the next close request goes through.
+    Ready,
+}
+
+#[derive(Default)]
+pub struct UiState {
+    close: Mutex<Close>,
+    // Counts answers to `save-ui-state`, so a restart can tell when one came.
+    saves: AtomicU64,
+}
+
+impl UiState {
+    // Returns the previous stage.
+    fn set(&self, close: Close) -> Close {
+        std::mem::replace(&mut *self.close.lock().unwrap_or_else(|p| p.into_inner()), close)
+    }
+
+    // Whether a close was pending. Saves made along the way don't count.
+    fn finish(&self) -> bool {
+        let mut close = self.close.lock().unwrap_or_else(|p| p.into_inner());
+        let pending = *close == Close::Saving;
+        if pending {
+            *close = Close::Ready;
+        }
+        pending
+    }
+}
+
+fn ui_state_path(app: &AppHandle) -> Option<PathBuf> {
+    crate::paths::config_dir(app).map(|dir| dir.join(UI_STATE_FILE_NAME))
+}
+
+fn finish_close(window: &Window) {
+    if window.state::<UiState>().finish() {
+        let _ = window.close();
+    }
+}
+
+pub fn on_close_requested(window: &Window, api: &CloseRequestApi) {
+    let state = window.state::<UiState>();
+    match state.set(Close::Saving) {
+        Close::Ready => return,
+        Close::Saving => {}
+        Close::Open => {
+            let _ = window.emit(SAVE_UI_STATE_EVENT, ());
+            let window = window.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(SAVE_TIMEOUT);
+                finish_close(&window);
+            });
+        }
+    }
+    api.prevent_close();
+}
+
+// Waits up to `SAVE_TIMEOUT` for the frontend's snapshot. Called off the main
+// thread, which `save_ui_state` runs on.
+pub fn save_before_restart(app: &AppHandle) {
+    let Some(window) = app.get_window("main") else {
+        return;
+    };
+    let state = app.state::<UiState>();
+    let before = state.saves.load(Ordering::SeqCst);
+    if window.emit(SAVE_UI_STATE_EVENT, ()).is_err() {
+        return;
+    }
+    let started = Instant::now();
+    while state.saves.load(Ordering::SeqCst) == before && started.elapsed() < SAVE_TIMEOUT {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[tauri::command]
+pub fn save_ui_state(app: AppHandle, json: String) -> Result<(), AppError> {
+    crate::validate::text("UI state", &json, MAX_UI_STATE_LEN)?;
+    serde_json::from_str::<serde_json::Value>(&json)
+        .map_err(|e| AppError::InvalidInput(format!("UI state is not valid JSON: {}", e)))?;
+
+    let path = ui_state_path(&app).ok_or("Could not resolve the app config directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    // Written via a temporary file so a close mid-write can't leave a
+    // truncated blob to restore next time.
+    let tmp = path.with_extension("json.tmp");
+    let written = std::fs::write(&tmp, &json).and_then(|_| std::fs::rename(&tmp, &path));
+    app.state::<UiState>().saves.fetch_add(1, Ordering::SeqCst);
+    if let Some(window) = app.get_window("main") {
+        finish_close(&window);
+    }
+    written.map_err(|e| AppError::io(&path, format!("Failed to save UI state: {}", e)))
+}
+
+// A missing, oversized or corrupt file just means starting fresh.
+#[tauri::command]
+pub fn load_ui_state(app: AppHandle) -> Option<String> {
+    let json = std::fs::read_to_string(ui_state_path(&app)?).ok()?;
+    let valid = json.len() <= MAX_UI_STATE_LEN
+        && serde_json::from_str::<serde_json::Value>(&json).is_ok();
+    valid.then_some(json)
+}
@@ -0,0 +1,202 @@
+// Update checks against a signed release manifest, and opt-in installs
+// through Tauri's updater. The updater is configured `"active": false` in
+// tauri.conf.json: with it active Tauri listens globally for
+// `tauri://update` and `tauri://update-install`, and any page could start an
+// install past `install_update`'s checks. The builder API used here reads the
+// endpoints and key from the config either way.
+//
+// The manifest (`latest.json`) is signed with the same minisign key as the
+// Tauri updater artifacts, and its detached signature is published next to it
+// as `latest.json.sig`. Version info is only trusted once that verifies.
+
+use crate::config::ConfigState;
+use crate::core::{self, CoreState};
+use crate::error::AppError;
+use crate::http;
+use crate::i18n;
+use base64::Engine;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    version: String,
+    #[serde(default)]
+    notes_url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdateCheck {
+    pub update_available: bool,
+    pub latest_version: Option<String>,
+    pub notes_url: Option<String>,
+}
+
+fn fetch(url: &str, timeout: Duration) -> Result<String, String> {
+    let response = http::request("GET", url, None, timeout)?;
+    if response.status != 200 {
+        return Err(format!("Release feed returned HTTP {} for {}", response.status, url));
+    }
+    Ok(response.body)
+}
+
+pub fn decode_base64(value: &str, what: &str) -> Result<String, String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(value.trim())
+        .map_err(|e| format!("Failed to decode {}: {}", what, e))?;
+    String::from_utf8(bytes).map_err(|e| format!("Failed to decode {}: {}", what, e))
+}
+
+// Same encoding as the Tauri updater: both the key and the signature are
+// base64-wrapped minisign text.
+fn verify_signature(data: &[u8], signature: &str, pubkey: &str) -> Result<(), String> {
+    let key = minisign_verify::PublicKey::decode(&decode_base64(pubkey, "update public key")?)
+        .map_err(|e| format!("Invalid update public key: {}", e))?;
+    let signature = minisign_verify::Signature::decode(&decode_base64(signature, "manifest signature")?)
+        .map_err(|e| format!("Invalid manifest signature: {}", e))?;
+    key.verify(data, &signature, true)
+        .map_err(|e| format!("Release manifest signature did not verify: {}", e))
+}
+
+// The key is baked into the build through tauri.conf.json, so a tampered
+// feed can't also swap the key it is checked against.
+fn public_key(app: &AppHandle) -> Result<String, String> {
+    let pubkey = app.config().tauri.updater.pubkey.trim().to_string();
+    if pubkey.is_empty() {
+        return Err("Update checks are unavailable: this build has no update signing key".to_string());
+    }
+    Ok(pubkey)
+}
+
+fn check(app: &AppHandle) -> Result<UpdateCheck, String> {
+    let config = app.state::<ConfigState>().get();
+    let pubkey = public_key(app)?;
+    let timeout = config.startup_timeout();
+
+    let manifest = fetch(&config.update_manifest_url, timeout)?;
+    let signature = fetch(&format!("{}.sig", config.update_manifest_url), timeout)?;
+    verify_signature(manifest.as_bytes(), &signature, &pubkey)?;
+
+    let manifest: Manifest = serde_json::from_str(&manifest)
+        .map_err(|e| format!("Failed to parse release manifest: {}", e))?;
+    // Tags are often published as "v1.2.3".
+    let latest = Version::parse(manifest.version.trim_start_matches('v'))
+        .map_err(|e| format!("Release manifest has an invalid version: {}", e))?;
+    let current = Version::parse(env!("CARGO_PKG_VERSION"))
+        .map_err(|e| format!("Invalid app version: {}", e))?;
+
+    Ok(UpdateCheck {
+        update_available: latest > current,
+        latest_version: Some(latest.to_string()),
+        notes_url: manifest.notes_url,
+    })
+}
+
+// With checks turned off nothing leaves the machine; the result just reports
+// no update.
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle) -> Result<UpdateCheck, AppError> {
+    if !app.state::<ConfigState>().get().features.update_checks {
+        return Ok(UpdateCheck {
+            update_available: false,
+            latest_version: None,
+            notes_url: None,
+        });
+    }
+    let check = tauri::async_runtime::spawn_blocking(move || check(&app))
+        .await
+        .map_err(|e| format!("Update check failed: {}", e))??;
+    Ok(check)
+}
+
+fn confirm_install(app: &AppHandle, version: &str) -> bool {
+    let window = app.get_window("main");
+    tauri::api::dialog::blocking::confirm(
+        window.as_ref(),
+        i18n::t(app, "install_update.title"),
+        i18n::t(app, "install_update.body").replace("{version}", version),
+    )
+}
+
+// Downloads, verifies and applies the latest release once the user agrees,
+// then restarts. Tauri reports download progress on
+// `tauri://update-download-progress` and its status on
+// `tauri://update-status`. Returns false when there was nothing to install or
+// the user declined.
+#[tauri::command]
+pub async fn install_update(app: AppHandle) -> Result<bool, AppError> {
+    let config = app.state::<ConfigState>().get();
+    if !config.features.update_checks || !config.auto_update_enabled {
+        return Err("Automatic updates are turned off in settings".into());
+    }
+    public_key(&app)?;
+
+    // Without events nothing is announced on `tauri://update-available` and
+    // no `tauri://update-install` listener is left waiting; this command is
+    // the only way to install.
+    let update = app
+        .updater()
+        .endpoints(&[config.update_manifest_url])
+        .skip_events()
+        .check()
+        .await
+        .map_err(|e| format!("Failed to check for updates: {}", e))?;
+    if !update.is_update_available() {
+        return Ok(false);
+    }
+
+    let version = update.latest_version().to_string();
+    let handle = app.clone();
+    let confirmed = tauri::async_runtime::spawn_blocking(move || confirm_install(&handle, &version))
+        .await
+        .map_err(|e| format!("Update confirmation failed: {}", e))?;
+    if !confirmed {
+        return Ok(false);
+    }
+
+    // On Windows the installer replaces the running app and the process ends
+    // inside `download_and_install`, so the core has to be stopped first.
+    core::shutdown(&app.state::<CoreState>());
+    if let Err(e) = update.download_and_install().await {
+        let handle = app.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = core::ensure(&handle) {
+                eprintln!("Core restart after failed update failed: {}", e);
+            }
+        });
+        return Err(format!("Failed to install update: {}", e).into());
+    }
+    app.restart();
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The throwaway minisign key `programs`' tests use, and another one.
+    const KEY: &str = "dW50cnVzdGVkIGNvbW1lbnQ6IG1pbmlzaWduIHB1YmxpYyBrZXkgMDgwNzA2MDUwNDAzMDIwMQpSV1FCQWdNRUJRWUhDQU9oQjcvenpoQytIWERkR09kTHdKbG41Tll3bTZVTlh4M2NobVFTVlRHNAo=";
+    const OTHER_KEY: &str = "dW50cnVzdGVkIGNvbW1lbnQ6IG1pbmlzaWduIHB1YmxpYyBrZXkgMTAwRjBFMEQwQzBCMEEwOQpSV1FKQ2dzTURRNFBFQ21zdXVGQnZNcndzaTRhbE5OTkM4YzJIbEp0Qy80U3lKZVV2Sk1pbG0zWAo=";
+    const MANIFEST: &str = r#"{"version":"9.9.9","notes_url":"https://github.com/abandini/smartbot/releases/tag/v9.9.9"}"#;
+    const SIGNATURE: &str = "dW50cnVzdGVkIGNvbW1lbnQ6IHNpZ25hdHVyZSBmcm9tIG1pbmlzaWduIHNlY3JldCBrZXkKUlVRQkFnTUVCUVlIQ1ArV1ZuWDMwN05LbzJxdG9qd1l6VmxDVWJjanFqcGtqOS9scng2cUprQTV4ZG1acXcwRjh2dVlsbmlhTDJITThEbWFtalg2U1lFR2lGN2FJVGVieEFVPQp0cnVzdGVkIGNvbW1lbnQ6IHRpbWVzdGFtcDoxNzYwMDAwMDAwCWZpbGU6bGF0ZXN0Lmpzb24KbHhvY0FZQ3hJU2NrQlFHS2MzMDd5U0QzeWZkUHZhRTBDSHprVmhOdUNzT0pYeHNlcjFONkN6VDY0dkRPWkNMamdySmRBeWNzRERhK0NQTHF4dFlqQXc9PQo=";
+
+    #[test]
+    fn signed_manifest_verifies() {
+        verify_signature(MANIFEST.as_bytes(), SIGNATURE, KEY).unwrap();
+    }
+
+    #[test]
+    fn tampered_manifest_is_rejected() {
+        let tampered = MANIFEST.replace("9.9.9", "9.9.10");
+        assert!(verify_signature(tampered.as_bytes(), SIGNATURE, KEY).is_err());
+        assert!(verify_signature(MANIFEST.as_bytes(), "", KEY).is_err());
+    }
+
+    #[test]
+    fn manifest_signed_with_another_key_is_rejected() {
+        assert!(verify_signature(MANIFEST.as_bytes(), SIGNATURE, OTHER_KEY).is_err());
+        assert!(verify_signature(MANIFEST.as_bytes(), SIGNATURE, "not a key").is_err());
+    }
+}
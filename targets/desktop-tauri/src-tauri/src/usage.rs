@@ -0,0 +1,97 @@
+// How much space the app's data takes up, for the "manage storage" screen.
+// Sizes come from file metadata only; nothing is opened or read.
+
+use crate::audit::{self, AUDIT_FILE_NAME};
+use crate::autobackup;
+use crate::config::ConfigState;
+use crate::disk;
+use crate::error::AppError;
+use crate::paths;
+use crate::profiles::ProfileState;
+use crate::storage::{StorageState, DB_FILE_NAME};
+use serde::Serialize;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+// SQLite keeps recent writes in these alongside the database.
+const DB_SIDE_FILES: [&str; 2] = ["-wal", "-shm"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Size {
+    pub bytes: u64,
+    pub display: String,
+}
+
+impl Size {
+    fn of(bytes: u64) -> Self {
+        Size {
+            bytes,
+            display: disk::format_bytes(bytes),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct StorageStats {
+    // `None` when the store couldn't be opened; the sizes are still real.
+    pub entry_count: Option<i64>,
+    pub database: Size,
+    // Scheduled backups in the backup folder. Manual backups can be saved
+    // anywhere, so they aren't counted.
+    pub backups: Size,
+    // Log and crash report files, plus the active profile's audit log.
+    pub logs: Size,
+    pub total: Size,
+}
+
+// Missing files count as empty.
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map_or(0, |metadata| metadata.len())
+}
+
+#[tauri::command]
+pub async fn get_storage_stats(app: AppHandle) -> Result<StorageStats, AppError> {
+    let profile_dir = app.state::<ProfileState>().active_dir()?;
+    let db_path = profile_dir.join(DB_FILE_NAME);
+    let database = file_size(&db_path)
+        + DB_SIDE_FILES
+            .iter()
+            .map(|suffix| {
+                let mut name = db_path.clone().into_os_string();
+                name.push(suffix);
+                file_size(Path::new(&name))
+            })
+            .sum::<u64>();
+
+    // An unplugged backup drive counts as nothing rather than an error.
+    let backups = match app.state::<ConfigState>().get().auto_backup_dir {
+        Some(dir) => autobackup::auto_backups(Path::new(&dir))
+            .unwrap_or_default()
+            .iter()
+            .map(|path| file_size(path))
+            .sum(),
+        None => 0,
+    };
+
+    let log_files = match paths::log_dir(&app) {
+        Some(dir) => paths::files_under(&dir)?,
+        None => Vec::new(),
+    };
+    let logs = log_files.iter().map(|(_, size)| size).sum::<u64>()
+        + audit::log_files(&profile_dir.join(AUDIT_FILE_NAME))
+            .iter()
+            .map(|path| file_size(path))
+            .sum::<u64>();
+
+    let entry_count = app
+        .state::<StorageState>()
+        .with(|storage| storage.entry_count())
+        .ok();
+    Ok(StorageStats {
+        entry_count,
+        database: Size::of(database),
+        backups: Size::of(backups),
+        logs: Size::of(logs),
+        total: Size::of(database + backups + logs),
+    })
+}
@@ -0,0 +1,223 @@
+// Input checks shared by the IPC commands. Everything the webview sends is
+// treated as untrusted: strings are length-capped, numbers bounded and paths
+// confined to directories the user would reasonably pick.
+
+use crate::error;
+use serde::Serialize;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::{Component, Path, PathBuf};
+use tauri::AppHandle;
+
+const MAX_PATH_LEN: usize = 4096;
+
+// Where removable drives are mounted; backups to a USB stick are expected.
+#[cfg(target_os = "macos")]
+const MOUNT_ROOTS: &[&str] = &["/Volumes"];
+#[cfg(all(unix, not(target_os = "macos")))]
+const MOUNT_ROOTS: &[&str] = &["/media", "/mnt", "/run/media"];
+#[cfg(not(unix))]
+const MOUNT_ROOTS: &[&str] = &[];
+
+#[derive(Debug)]
+pub enum ValidationError {
+    TooLong(String),
+    OutOfRange(String),
+    InvalidPath(String),
+    Invalid(String),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::TooLong(msg)
+            | ValidationError::OutOfRange(msg)
+            | ValidationError::InvalidPath(msg)
+            | ValidationError::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl ValidationError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ValidationError::TooLong(_) => "TooLong",
+            ValidationError::OutOfRange(_) => "OutOfRange",
+            ValidationError::InvalidPath(_) => "InvalidPath",
+            ValidationError::Invalid(_) => "Invalid",
+        }
+    }
+}
+
+impl Serialize for ValidationError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        error::serialize(serializer, self.code(), &self.to_string(), None)
+    }
+}
+
+impl From<ValidationError> for String {
+    fn from(e: ValidationError) -> Self {
+        e.to_string()
+    }
+}
+
+// Lengths are in bytes, which is what ends up in memory, on disk and on the
+// wire.
+pub fn text<'a>(field: &str, value: &'a str, max_len: usize) -> Result<&'a str, ValidationError> {
+    if value.len() > max_len {
+        return Err(ValidationError::TooLong(format!(
+            "{} is too long ({} bytes, at most {})",
+            field,
+            value.len(),
+            max_len
+        )));
+    }
+    Ok(value)
+}
+
+pub fn non_empty<'a>(field: &str, value: &'a str, max_len: usize) -> Result<&'a str, ValidationError> {
+    if value.trim().is_empty() {
+        return Err(ValidationError::Invalid(format!("{} can't be empty", field)));
+    }
+    text(field, value, max_len)
+}
+
+pub fn number<T: PartialOrd + fmt::Display + Copy>(
+    field: &str,
+    value: T,
+    range: RangeInclusive<T>,
+) -> Result<T, ValidationError> {
+    if !range.contains(&value) {
+        return Err(ValidationError::OutOfRange(format!(
+            "{} must be between {} and {}",
+            field,
+            range.start(),
+            range.end()
+        )));
+    }
+    Ok(value)
+}
+
+// NaN and infinities slip through clamping and don't survive a JSON round trip.
+pub fn finite(field: &str, value: f64) -> Result<f64, ValidationError> {
+    if !value.is_finite() {
+        return Err(ValidationError::OutOfRange(format!("{} must be a number", field)));
+    }
+    Ok(value)
+}
+
+// Identifiers that end up in file or resource paths (locales, profile ids).
+pub fn identifier<'a>(field: &str, value: &'a str, max_len: usize) -> Result<&'a str, ValidationError> {
+    non_empty(field, value, max_len)?;
+    if !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(ValidationError::Invalid(format!(
+            "{} may only contain letters, digits, '-' and '_'",
+            field
+        )));
+    }
+    Ok(value)
+}
+
+// A bare file name, e.g. a suggested export name: no directories at all.
+pub fn file_name<'a>(field: &str, value: &'a str, max_len: usize) -> Result<&'a str, ValidationError> {
+    non_empty(field, value, max_len)?;
+    let mut components = Path::new(value).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) if !value.contains(['/', '\\', '\0']) => Ok(value),
+        _ => Err(ValidationError::InvalidPath(format!("{} must be a file name, not a path", field))),
+    }
+}
+
+// Directories a user-supplied path may point into.
+pub fn allowed_roots(app: &AppHandle) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = MOUNT_ROOTS.iter().map(PathBuf::from).collect();
+    roots.extend(tauri::api::path::home_dir());
+    roots.extend(crate::paths::data_dir(app).ok());
+    roots
+}
+
+// Resolved against the filesystem so symlinks can't point a path outside the
+// allowed roots. The file itself may not exist yet (a backup destination),
+// so the nearest existing ancestor is canonicalized instead.
+pub fn path_within(field: &str, value: &str, roots: &[PathBuf]) -> Result<PathBuf, ValidationError> {
+    text(field, value, MAX_PATH_LEN)?;
+    let invalid = |why: &str| ValidationError::InvalidPath(format!("{} {}", field, why));
+    if value.is_empty() || value.contains('\0') {
+        return Err(invalid("is not a valid path"));
+    }
+    let path = Path::new(value);
+    if !path.is_absolute() {
+        return Err(invalid("must be an absolute path"));
+    }
+    if path.components().any(|c| c == Component::ParentDir) {
+        return Err(invalid("must not contain '..'"));
+    }
+
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| invalid("is not on an existing drive"))?;
+    let resolved = existing
+        .canonicalize()
+        .map_err(|e| ValidationError::InvalidPath(format!("{} could not be resolved: {}", field, e)))?
+        .join(path.strip_prefix(existing).unwrap_or(Path::new("")));
+
+    let allowed = roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| resolved.starts_with(root));
+    if !allowed {
+        return Err(invalid("is outside the folders Smartbot may use"));
+    }
+    Ok(resolved)
+}
+
+pub fn user_path(app: &AppHandle, field: &str, value: &str) -> Result<PathBuf, ValidationError> {
+    path_within(field, value, &allowed_roots(app))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root() -> PathBuf {
+        std::env::temp_dir().canonicalize().unwrap()
+    }
+
+    #[test]
+    fn rejects_traversal_and_paths_outside_the_roots() {
+        let roots = [root()];
+        let escaped = format!("{}/../../etc/passwd", root().display());
+
+        for malicious in ["../../etc/passwd", escaped.as_str(), "/etc/passwd", ""] {
+            assert!(
+                matches!(path_within("path", malicious, &roots), Err(ValidationError::InvalidPath(_))),
+                "{:?} was accepted",
+                malicious
+            );
+        }
+        let inside = root().join("backup.smartbak");
+        assert_eq!(path_within("path", inside.to_str().unwrap(), &roots).unwrap(), inside);
+    }
+
+    #[test]
+    fn rejects_huge_strings_and_out_of_range_numbers() {
+        let huge = "a".repeat(10 * 1024 * 1024);
+        assert!(matches!(text("content", &huge, 1024), Err(ValidationError::TooLong(_))));
+        assert!(matches!(
+            path_within("path", &format!("/{}", huge), &[root()]),
+            Err(ValidationError::TooLong(_))
+        ));
+        assert!(matches!(number("port", 0u16, 1..=u16::MAX), Err(ValidationError::OutOfRange(_))));
+        assert!(matches!(finite("zoom", f64::NAN), Err(ValidationError::OutOfRange(_))));
+    }
+
+    #[test]
+    fn identifiers_and_file_names_cannot_carry_paths() {
+        assert!(identifier("locale", "../../etc/passwd", 35).is_err());
+        assert!(identifier("locale", "pt-BR", 35).is_ok());
+        assert!(file_name("name", "../secrets.md", 255).is_err());
+        assert!(file_name("name", "/etc/passwd", 255).is_err());
+        assert!(file_name("name", "session.md", 255).is_ok());
+    }
+}
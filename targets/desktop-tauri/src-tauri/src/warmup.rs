@@ -0,0 +1,195 @@
+// Warming the core: a core that loads something heavy on first use (e.g. an
+// embedding model) makes the user's first real request slow. `warm_core`
+// sends the request from `core_warmup` instead, once the core is up, so that
+// loading happens before anyone is waiting on it. With
+// `core_warmup_on_startup` it also runs after every launch. The last run is
+// kept for `get_core_status`.
+
+use crate::config::ConfigState;
+use crate::core::{self, CoreState, CoreStatus};
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+pub const WARMUP_PROGRESS_EVENT: &str = "core-warmup-progress";
+
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WarmupRequest {
+    pub path: String,
+    // Sent with POST; without a body the path is fetched with GET.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<serde_json::Value>,
+    // Within `core::REQUEST_TIMEOUT_RANGE`.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+impl WarmupRequest {
+    fn method(&self) -> &'static str {
+        if self.body.is_some() {
+            "POST"
+        } else {
+            "GET"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmupReport {
+    pub path: String,
+    // Just the warmup request, not the wait for the core to come up.
+    pub duration_ms: u128,
+    // RFC 3339.
+    pub finished_at: String,
+    // `None` when the core answered with a 2xx.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct WarmupProgress {
+    // "waiting_for_core", "warming", "done" or "failed".
+    stage: &'static str,
+    elapsed_ms: u128,
+}
+
+// Holds the last report, and is held for the whole of a run so a second
+// caller waits for the first instead of warming alongside it.
+#[derive(Default)]
+pub struct WarmupState {
+    last: Mutex<Option<WarmupReport>>,
+    running: Mutex<()>,
+}
+
+impl WarmupState {
+    pub fn last(&self) -> Option<WarmupReport> {
+        self.last.lock().unwrap_or_else(|p| p.into_inner()).clone()
+    }
+}
+
+// For config validation.
+pub fn check(request: &WarmupRequest) -> Result<(), String> {
+    core::validate_proxy_request(request.method(), &request.path, request.body.as_ref())
+        .map_err(|e| e.to_string())?;
+    if !core::REQUEST_TIMEOUT_RANGE.contains(&request.timeout_secs) {
+        return Err(format!(
+            "timeout_secs is outside {} to {}",
+            core::REQUEST_TIMEOUT_RANGE.start(),
+            core::REQUEST_TIMEOUT_RANGE.end()
+        ));
+    }
+    Ok(())
+}
+
+fn emit_progress(app: &AppHandle, stage: &'static str, started: Instant) {
+    let _ = app.emit_all(
+        WARMUP_PROGRESS_EVENT,
+        WarmupProgress {
+            stage,
+            elapsed_ms: started.elapsed().as_millis(),
+        },
+    );
+}
+
+fn warm(app: &AppHandle) -> Result<WarmupReport, String> {
+    let config = app.state::<ConfigState>().get();
+    let startup_timeout = config.startup_timeout();
+    let request = config
+        .core_warmup
+        .ok_or("Set `core_warmup` in the config to warm the core")?;
+    let state = app.state::<WarmupState>();
+    let _running = state.running.lock().unwrap_or_else(|p| p.into_inner());
+
+    let started = Instant::now();
+    emit_progress(app, "waiting_for_core", started);
+    let core = app.state::<CoreState>();
+    let status = core.wait_while_starting(startup_timeout);
+    if !matches!(status, CoreStatus::Running | CoreStatus::Sleeping) {
+        emit_progress(app, "failed", started);
+        return Err("The core isn't running, so it can't be warmed".to_string());
+    }
+
+    emit_progress(app, "warming", started);
+    let warming = Instant::now();
+    let response = core::request(
+        app,
+        request.method(),
+        &request.path,
+        request.body.as_ref(),
+        Duration::from_secs(request.timeout_secs),
+        None,
+    );
+    let duration_ms = warming.elapsed().as_millis();
+    let error = match response {
+        Ok(response) if (200..300).contains(&response.status) => None,
+        Ok(response) => Some(format!("The core answered {}", response.status)),
+        Err(e) => Some(e),
+    };
+    let stage = if error.is_some() { "failed" } else { "done" };
+    emit_progress(app, stage, started);
+    let report = WarmupReport {
+        path: request.path,
+        duration_ms,
+        finished_at: chrono::Utc::now().to_rfc3339(),
+        error,
+    };
+    *state.last.lock().unwrap_or_else(|p| p.into_inner()) = Some(report.clone());
+    Ok(report)
+}
+
+// Called from the startup thread once the core is up.
+pub fn on_startup(app: &AppHandle) {
+    let config = app.state::<ConfigState>().get();
+    if !config.core_warmup_on_startup || config.core_warmup.is_none() {
+        return;
+    }
+    match warm(app) {
+        Ok(WarmupReport { error: Some(e), .. }) | Err(e) => {
+            eprintln!("Core warmup failed: {}", e)
+        }
+        Ok(_) => {}
+    }
+}
+
+// Returns once the warmup request has been answered. A failed warmup is
+// still a report, with `error` set; `Err` means it never ran.
+#[tauri::command]
+pub async fn warm_core(app: AppHandle) -> Result<WarmupReport, AppError> {
+    let report = tauri::async_runtime::spawn_blocking(move || warm(&app))
+        .await
+        .map_err(|e| format!("Core warmup failed: {}", e))??;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warmup_request_is_held_to_the_proxy_rules() {
+        let request: WarmupRequest =
+            serde_json::from_str(r#"{"path":"/embed","body":{"text":"warm up"}}"#).unwrap();
+        assert_eq!(request.method(), "POST");
+        assert_eq!(request.timeout_secs, DEFAULT_TIMEOUT_SECS);
+        check(&request).unwrap();
+
+        let outside = WarmupRequest {
+            path: "//elsewhere.example/".to_string(),
+            ..request.clone()
+        };
+        assert!(check(&outside).is_err());
+        let too_slow = WarmupRequest {
+            timeout_secs: 3600,
+            ..request
+        };
+        assert!(check(&too_slow).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+// Compact "today" window pinned in a corner. Like the splash page it has no
+// IPC of its own: the shell pushes the streak into it with `eval`, and its
+// buttons are links caught by the navigation filter. It lives in the same
+// process, so it shares the running core rather than starting another.
+
+use crate::config::{self, ConfigState};
+use crate::error::AppError;
+use crate::i18n;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{
+    AppHandle, LogicalPosition, LogicalSize, Manager, Runtime, Url, Window, WindowBuilder,
+    WindowUrl,
+};
+
+pub const WIDGET_LABEL: &str = "widget";
+
+// Links on the widget page point here to trigger an action.
+const ACTION_HOST: &str = "widget.smartbot";
+
+const DEFAULT_WIDTH: f64 = 240.0;
+const DEFAULT_HEIGHT: f64 = 130.0;
+const CORNER_MARGIN: f64 = 24.0;
+
+pub const QUICK_LOG_EVENT: &str = "widget-quick-log";
+
+// Logical pixels, so a widget moved between displays keeps its size.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WidgetGeometry {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+// Last streak the main window reported, for when the widget (re)opens.
+#[derive(Default)]
+pub struct WidgetState(Mutex<Option<u32>>);
+
+fn js_string(text: &str) -> String {
+    serde_json::Value::String(text.to_string()).to_string()
+}
+
+fn push_streak<R: Runtime>(widget: &Window<R>, days: Option<u32>) {
+    let app = widget.app_handle();
+    let label = match days {
+        Some(days) => i18n::t(&app, "widget.streak").replace("{days}", &days.to_string()),
+        None => String::new(),
+    };
+    let _ = widget.eval(&format!(
+        "window.widget.update({}, {})",
+        days.map_or("null".to_string(), |days| days.to_string()),
+        js_string(&label)
+    ));
+}
+
+pub fn on_page_load<R: Runtime>(widget: &Window<R>) {
+    let app = widget.app_handle();
+    let _ = widget.eval(&format!(
+        "window.widget.labels({}, {})",
+        js_string(&i18n::t(&app, "widget.quick_log")),
+        js_string(&i18n::t(&app, "widget.open"))
+    ));
+    let days = *app.state::<WidgetState>().0.lock().unwrap_or_else(|p| p.into_inner());
+    push_streak(widget, days);
+}
+
+fn show_main(app: &AppHandle) {
+    if let Some(window) = app.get_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn on_action(app: &AppHandle, url: &Url) {
+    match url.path() {
+        "/open" => show_main(app),
+        "/quick-log" => {
+            show_main(app);
+            let _ = app.emit_to("main", QUICK_LOG_EVENT, ());
+        }
+        _ => {}
+    }
+}
+
+fn current_geometry<R: Runtime>(widget: &Window<R>) -> Option<WidgetGeometry> {
+    let scale = widget.scale_factor().ok()?;
+    let position = widget.outer_position().ok()?.to_logical::<f64>(scale);
+    let size = widget.inner_size().ok()?.to_logical::<f64>(scale);
+    Some(WidgetGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    })
+}
+
+// Called before the widget closes, whichever way it is closed.
+pub fn save_geometry(widget: &Window) {
+    let Some(geometry) = current_geometry(widget) else {
+        return;
+    };
+    let app = widget.app_handle();
+    if let Err(e) = config::update(&app, |config| config.widget_geometry = Some(geometry)) {
+        eprintln!("Failed to save widget position: {}", e);
+    }
+}
+
+// First open: the top-right corner of the display the main window is on.
+fn default_geometry(app: &AppHandle) -> WidgetGeometry {
+    let monitor = app
+        .get_window("main")
+        .and_then(|window| window.current_monitor().ok().flatten());
+    let (right, top) = match monitor {
+        Some(monitor) => {
+            let scale = monitor.scale_factor();
+            let position = monitor.position().to_logical::<f64>(scale);
+            let size = monitor.size().to_logical::<f64>(scale);
+            (position.x + size.width, position.y)
+        }
+        None => (DEFAULT_WIDTH + 2.0 * CORNER_MARGIN, 0.0),
+    };
+    WidgetGeometry {
+        x: right - DEFAULT_WIDTH - CORNER_MARGIN,
+        y: top + CORNER_MARGIN,
+        width: DEFAULT_WIDTH,
+        height: DEFAULT_HEIGHT,
+    }
+}
+
+fn open(app: &AppHandle) -> tauri::Result<()> {
+    let config = app.state::<ConfigState>().get();
+    let geometry = config.widget_geometry.unwrap_or_else(|| default_geometry(app));
+    let handle = app.clone();
+    let internal_origins = config.internal_origins;
+    let widget = WindowBuilder::new(app, WIDGET_LABEL, WindowUrl::App("widget.html".into()))
+        .title("Smartbot")
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .min_inner_size(180.0, 100.0)
+        .on_navigation(move |url| {
+            if url.host_str() == Some(ACTION_HOST) {
+                on_action(&handle, &url);
+                return false;
+            }
+            crate::navigation::is_internal(&url, &internal_origins)
+        })
+        .build()?;
+    // Applied after building so the saved values are taken as logical units.
+    let _ = widget.set_size(LogicalSize::new(geometry.width, geometry.height));
+    let _ = widget.set_position(LogicalPosition::new(geometry.x, geometry.y));
+    Ok(())
+}
+
+// Returns whether the widget is open afterwards.
+#[tauri::command]
+pub async fn toggle_widget_mode(app: AppHandle) -> Result<bool, AppError> {
+    if let Some(widget) = app.get_window(WIDGET_LABEL) {
+        save_geometry(&widget);
+        widget.close().map_err(|e| format!("Failed to close the widget: {}", e))?;
+        return Ok(false);
+    }
+    open(&app).map_err(|e| format!("Failed to open the widget: {}", e))?;
+    Ok(true)
+}
+
+// The main window reports the streak whenever it changes; `None` hides it.
+#[tauri::command]
+pub fn update_widget(app: AppHandle, streak_days: Option<u32>) {
+    *app.state::<WidgetState>().0.lock().unwrap_or_else(|p| p.into_inner()) = streak_days;
+    if let Some(widget) = app.get_window(WIDGET_LABEL) {
+        push_streak(&widget, streak_days);
+    }
+}
@@ -0,0 +1,293 @@
+// SMART Recovery worksheets (cost-benefit analysis, ABC and the rest),
+// filled in locally and saved as entries of kind "worksheet". The templates
+// are compiled in rather than read from the bundle's resources, so they work
+// with the core down and can't go missing from a broken install. Programs a
+// counselor shared add their own worksheets, looked up by program id.
+
+use crate::programs;
+use crate::storage::{StorageError, StorageState};
+use crate::validate;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::OnceLock;
+
+const TEMPLATES: &[&str] = &[
+    include_str!("../resources/worksheets/cost_benefit.json"),
+    include_str!("../resources/worksheets/abc.json"),
+    include_str!("../resources/worksheets/change_plan.json"),
+    include_str!("../resources/worksheets/vaci.json"),
+];
+
+pub const WORKSHEET_ENTRY_KIND: &str = "worksheet";
+const MAX_ID_LEN: usize = 64;
+const MAX_TEXT_LEN: usize = 10_000;
+const MAX_LIST_ITEMS: usize = 50;
+const MAX_TITLE_LEN: usize = 200;
+const MAX_FIELDS: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FieldKind {
+    Text,
+    Scale { min: i64, max: i64 },
+    // Any number of short answers, such as the four boxes of a cost-benefit
+    // analysis.
+    List,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorksheetField {
+    pub id: String,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    #[serde(flatten)]
+    pub kind: FieldKind,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Worksheet {
+    pub id: String,
+    // Saved with each response, so old responses can be read against the
+    // fields they were written for.
+    pub version: u32,
+    pub title: String,
+    pub description: String,
+    pub fields: Vec<WorksheetField>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorksheetSummary {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+}
+
+fn templates() -> &'static [Worksheet] {
+    static PARSED: OnceLock<Vec<Worksheet>> = OnceLock::new();
+    PARSED.get_or_init(|| {
+        TEMPLATES
+            .iter()
+            .map(|json| serde_json::from_str(json).expect("bundled worksheet template is invalid"))
+            .collect()
+    })
+}
+
+fn find(id: &str) -> Result<&'static Worksheet, String> {
+    validate::identifier("Worksheet", id, MAX_ID_LEN)?;
+    templates()
+        .iter()
+        .find(|worksheet| worksheet.id == id)
+        .ok_or_else(|| format!("Unknown worksheet {:?}", id))
+}
+
+// For templates that didn't ship with the app, such as a program's.
+pub fn check_template(worksheet: &Worksheet) -> Result<(), String> {
+    validate::identifier("Worksheet", &worksheet.id, MAX_ID_LEN)?;
+    validate::non_empty("Worksheet title", &worksheet.title, MAX_TITLE_LEN)?;
+    validate::text("Worksheet description", &worksheet.description, MAX_TEXT_LEN)?;
+    if worksheet.fields.is_empty() || worksheet.fields.len() > MAX_FIELDS {
+        return Err(format!(
+            "{} must have between 1 and {} fields",
+            worksheet.title, MAX_FIELDS
+        ));
+    }
+    for (i, field) in worksheet.fields.iter().enumerate() {
+        validate::identifier("Field", &field.id, MAX_ID_LEN)?;
+        validate::non_empty("Field label", &field.label, MAX_TITLE_LEN)?;
+        if let Some(prompt) = &field.prompt {
+            validate::text("Field prompt", prompt, MAX_TEXT_LEN)?;
+        }
+        if worksheet.fields[..i].iter().any(|other| other.id == field.id) {
+            return Err(format!("{} has field {:?} twice", worksheet.title, field.id));
+        }
+        if let FieldKind::Scale { min, max } = field.kind {
+            if min >= max {
+                return Err(format!("{} has an empty scale", field.label));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_field(field: &WorksheetField, value: &Value) -> Result<(), String> {
+    let label = &field.label;
+    match (&field.kind, value) {
+        (FieldKind::Text, Value::String(text)) => {
+            validate::text(label, text, MAX_TEXT_LEN)?;
+        }
+        (FieldKind::Scale { min, max }, Value::Number(n)) => {
+            let n = n
+                .as_i64()
+                .ok_or_else(|| format!("{} must be a whole number", label))?;
+            validate::number(label, n, *min..=*max)?;
+        }
+        (FieldKind::List, Value::Array(items)) => {
+            if items.len() > MAX_LIST_ITEMS {
+                return Err(format!(
+                    "{} has too many items (at most {})",
+                    label, MAX_LIST_ITEMS
+                ));
+            }
+            for item in items {
+                let item = item
+                    .as_str()
+                    .ok_or_else(|| format!("{} items must be text", label))?;
+                validate::text(label, item, MAX_TEXT_LEN)?;
+            }
+        }
+        _ => return Err(format!("{} has the wrong kind of answer", label)),
+    }
+    Ok(())
+}
+
+fn is_blank(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(text) => text.trim().is_empty(),
+        Value::Array(items) => items.iter().all(is_blank),
+        _ => false,
+    }
+}
+
+// Blank answers are dropped, so a saved response only holds what the user
+// actually wrote.
+fn check_responses(
+    worksheet: &Worksheet,
+    mut responses: Map<String, Value>,
+) -> Result<Map<String, Value>, String> {
+    if let Some(unknown) = responses
+        .keys()
+        .find(|key| !worksheet.fields.iter().any(|field| &field.id == *key))
+    {
+        return Err(format!("{} has no field {:?}", worksheet.title, unknown));
+    }
+    responses.retain(|_, value| !is_blank(value));
+    for field in &worksheet.fields {
+        match responses.get(&field.id) {
+            Some(value) => check_field(field, value)?,
+            None if field.required => return Err(format!("{} is required", field.label)),
+            None => {}
+        }
+    }
+    Ok(responses)
+}
+
+#[tauri::command]
+pub fn list_worksheets() -> Vec<WorksheetSummary> {
+    templates()
+        .iter()
+        .map(|worksheet| WorksheetSummary {
+            id: worksheet.id.clone(),
+            title: worksheet.title.clone(),
+            description: worksheet.description.clone(),
+        })
+        .collect()
+}
+
+fn resolve(
+    state: &StorageState,
+    id: &str,
+    program: Option<&str>,
+) -> Result<Worksheet, StorageError> {
+    match program {
+        Some(program) => state.with(|storage| programs::worksheet(storage, program, id)),
+        None => Ok(find(id)?.clone()),
+    }
+}
+
+// `program` picks a worksheet from an installed program instead of the
+// bundled ones.
+#[tauri::command]
+pub fn get_worksheet(
+    state: tauri::State<StorageState>,
+    id: String,
+    program: Option<String>,
+) -> Result<Worksheet, StorageError> {
+    resolve(&state, &id, program.as_deref())
+}
+
+// `responses` maps field ids to answers: text for text fields, a whole number
+// for scales and a list of text for lists. Returns the new entry's id.
+#[tauri::command]
+pub fn save_worksheet_response(
+    state: tauri::State<StorageState>,
+    id: String,
+    responses: Map<String, Value>,
+    program: Option<String>,
+) -> Result<i64, StorageError> {
+    let worksheet = resolve(&state, &id, program.as_deref())?;
+    let responses = check_responses(&worksheet, responses)?;
+    let mut content = serde_json::json!({
+        "worksheet": worksheet.id,
+        "version": worksheet.version,
+        "responses": responses,
+    });
+    if let Some(program) = program {
+        content["program"] = program.into();
+    }
+    let content = content.to_string();
+    state.with(|storage| storage.add_entry(WORKSHEET_ENTRY_KIND, &content, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn templates_parse_with_unique_ids() {
+        let mut ids = HashSet::new();
+        for worksheet in templates() {
+            assert!(
+                ids.insert(&worksheet.id),
+                "duplicate worksheet {}",
+                worksheet.id
+            );
+            let mut fields = HashSet::new();
+            for field in &worksheet.fields {
+                assert!(
+                    fields.insert(&field.id),
+                    "duplicate field {} in {}",
+                    field.id,
+                    worksheet.id
+                );
+            }
+        }
+        assert_eq!(ids.len(), TEMPLATES.len());
+        for worksheet in templates() {
+            check_template(worksheet).unwrap();
+        }
+    }
+
+    #[test]
+    fn responses_are_checked_against_the_template() {
+        let worksheet = find("abc").unwrap();
+        let responses = |value: Value| value.as_object().unwrap().clone();
+        let complete = serde_json::json!({
+            "activating_event": "Payday",
+            "beliefs": "I deserve it",
+            "consequences": "Cravings",
+            "disputation": "I deserve to feel well tomorrow too",
+            "effective_belief": " ",
+            "suds_before": 8,
+        });
+
+        let saved = check_responses(worksheet, responses(complete.clone())).unwrap();
+        assert!(!saved.contains_key("effective_belief"));
+
+        let mut missing = responses(complete.clone());
+        missing.remove("beliefs");
+        assert!(check_responses(worksheet, missing).is_err());
+
+        let mut out_of_range = responses(complete.clone());
+        out_of_range.insert("suds_before".into(), Value::from(11));
+        assert!(check_responses(worksheet, out_of_range).is_err());
+
+        let mut unknown = responses(complete);
+        unknown.insert("mood".into(), Value::from("fine"));
+        assert!(check_responses(worksheet, unknown).is_err());
+    }
+}
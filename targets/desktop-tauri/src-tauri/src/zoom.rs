@@ -0,0 +1,48 @@
+// Webview zoom for low-vision users. Tauri 1 has no native zoom API, so the
+// level is applied as CSS zoom and re-applied on every page load.
+
+use crate::config::{self, ConfigState};
+use crate::error::AppError;
+use crate::validate;
+use tauri::{AppHandle, Manager, Runtime, Window};
+
+pub const MIN_ZOOM: f64 = 0.5;
+pub const MAX_ZOOM: f64 = 3.0;
+
+// Round to whole percent so repeated zoom in/out steps don't drift.
+fn normalize(level: f64) -> f64 {
+    if !level.is_finite() {
+        return 1.0;
+    }
+    (level.clamp(MIN_ZOOM, MAX_ZOOM) * 100.0).round() / 100.0
+}
+
+pub fn apply<R: Runtime>(window: &Window<R>, level: f64) {
+    let _ = window.eval(&format!(
+        "document.documentElement.style.zoom = '{}';",
+        normalize(level)
+    ));
+}
+
+// Also covers startup: the first page load restores the saved level.
+pub fn on_page_load<R: Runtime>(window: &Window<R>) {
+    if let Some(config) = window.try_state::<ConfigState>() {
+        apply(window, config.get().zoom_level);
+    }
+}
+
+#[tauri::command]
+pub fn get_zoom(state: tauri::State<ConfigState>) -> f64 {
+    normalize(state.get().zoom_level)
+}
+
+// Returns the level actually applied after clamping.
+#[tauri::command]
+pub fn set_zoom(app: AppHandle, level: f64) -> Result<f64, AppError> {
+    let level = normalize(validate::finite("Zoom level", level)?);
+    config::update(&app, |config| config.zoom_level = level)?;
+    if let Some(window) = app.get_window("main") {
+        apply(&window, level);
+    }
+    Ok(level)
+}
@@ -4,6 +4,7 @@ Implements contextual bandit for SMART Recovery tool recommendations
 """
 from fastapi import FastAPI
 from fastapi.middleware.cors import CORSMiddleware
+from fastapi.responses import JSONResponse
 from pydantic import BaseModel
 from typing import List, Optional, Literal
 import numpy as np
@@ -176,7 +177,10 @@ class LinUCBBandit:
         return dict(zip(self.feature_names, importance_scores))
 
 # Global bandit instance  
-BANDIT_STATE_FILE = Path("bandit_state.json")
+# The desktop shell passes SMARTBOT_DATA_DIR when the user has redirected
+# app data; otherwise state stays in the working directory as before.
+DATA_DIR = Path(os.environ.get("SMARTBOT_DATA_DIR") or ".")
+BANDIT_STATE_FILE = DATA_DIR / "bandit_state.json"
 bandit = LinUCBBandit(n_actions=7, n_features=16, alpha=1.0)
 
 def save_bandit_state():
@@ -206,6 +210,15 @@ def load_bandit_state():
 # Load state on startup
 load_bandit_state()
 
+# Flipped once startup has finished; /health reports 503 until then so the
+# desktop shell doesn't send real requests to a core that can't serve them.
+core_ready = False
+
+@app.on_event("startup")
+async def mark_ready():
+    global core_ready
+    core_ready = bandit is not None and len(bandit.A) == bandit.n_actions
+
 def determine_ui_mode(features: np.ndarray) -> UIModeType:
     """
     Determine appropriate UI mode based on user context.
@@ -235,6 +248,18 @@ async def root():
     """Health check endpoint."""
     return {"message": "Smartbot Core API", "status": "running"}
 
+@app.get("/version")
+async def version():
+    """Core version, checked by the desktop shell for compatibility."""
+    return {"version": app.version}
+
+@app.get("/health")
+async def health():
+    """Readiness endpoint: 200 only once the model state is loaded."""
+    if not core_ready:
+        return JSONResponse(status_code=503, content={"ready": False})
+    return {"ready": True}
+
 @app.post("/choose", response_model=ChooseResponse)
 async def choose_action(request: ChooseRequest) -> ChooseResponse:
     """